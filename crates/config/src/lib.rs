@@ -19,7 +19,7 @@ pub mod example;
 
 use std::fs::{self, create_dir_all, File};
 use std::io::{Read, Write};
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use std::time::{Duration, SystemTime};
 
 use anyhow::{Context, Result};
@@ -41,6 +41,12 @@ pub struct Config<T: Configuration> {
     pub cleanup_backups_after_days: u32,
 }
 
+impl<T: Configuration> Default for Config<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T: Configuration> Config<T> {
     /// Creates a new Config instance with default settings
     pub fn new() -> Self {
@@ -183,18 +189,16 @@ impl<T: Configuration> Config<T> {
             let entry = entry?;
             let path = entry.path();
             
-            if path.is_file() && path.file_name().unwrap().to_string_lossy().contains(T::crate_name()) {
-                if let Ok(metadata) = fs::metadata(&path) {
-                    if let Ok(modified) = metadata.modified() {
-                        if let Ok(age) = now.duration_since(modified) {
-                            if age > max_age {
-                                debug!("Removing old backup: {}", path.display());
-                                if let Err(e) = fs::remove_file(&path) {
-                                    warn!("Failed to remove old backup {}: {}", path.display(), e);
-                                }
-                            }
-                        }
-                    }
+            if path.is_file()
+                && path.file_name().unwrap().to_string_lossy().contains(T::crate_name())
+                && let Ok(metadata) = fs::metadata(&path)
+                && let Ok(modified) = metadata.modified()
+                && let Ok(age) = now.duration_since(modified)
+                && age > max_age
+            {
+                debug!("Removing old backup: {}", path.display());
+                if let Err(e) = fs::remove_file(&path) {
+                    warn!("Failed to remove old backup {}: {}", path.display(), e);
                 }
             }
         }
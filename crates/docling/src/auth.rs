@@ -0,0 +1,492 @@
+//! Credentials for crawling sites behind a login or an API gateway
+//!
+//! Credentials are kept out of `entries.toml`, in a separate `secrets.toml`
+//! in the inputs directory that is only readable by its owner. Values may
+//! reference environment variables as `env:NAME` so nothing secret has to be
+//! stored at all. Before a crawl the credentials of the entry are resolved
+//! into a [`RequestAuth`], which the fetcher applies to every request:
+//! custom headers and basic or bearer auth are only sent to the entry's own
+//! origin (scheme, host and port), cookies from a Netscape `cookies.txt` jar
+//! only to the domains they belong to.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::create_dir_all;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use chrono::Utc;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, COOKIE};
+use reqwest::RequestBuilder;
+use serde::{Deserialize, Serialize};
+use url::{Origin, Url};
+
+use crate::{DoclingError, IoContext, Result};
+
+/// File name of the secret store inside the inputs directory
+pub const SECRETS_FILE_NAME: &str = "secrets.toml";
+
+/// Prefix of values that are read from an environment variable
+const ENV_PREFIX: &str = "env:";
+
+/// Username and password for HTTP basic auth
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BasicCredentials {
+    /// Username
+    pub username: String,
+    /// Password, if any
+    pub password: Option<String>,
+}
+
+/// Credentials of a single entry as stored in `secrets.toml`
+///
+/// Every value may be given as `env:NAME` to read it from the environment
+/// when the entry is crawled.
+#[derive(Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EntryCredentials {
+    /// Extra headers sent with every request to the entry's origin
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// HTTP basic auth
+    #[serde(default)]
+    pub basic: Option<BasicCredentials>,
+    /// Bearer token for the `Authorization` header
+    #[serde(default)]
+    pub bearer_token: Option<String>,
+    /// Netscape `cookies.txt` file, relative paths are resolved against the inputs directory
+    #[serde(default)]
+    pub cookies_file: Option<PathBuf>,
+}
+
+impl EntryCredentials {
+    /// Whether no credentials are set
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty() && self.basic.is_none() && self.bearer_token.is_none() && self.cookies_file.is_none()
+    }
+}
+
+// Values are never printed, only which kinds of credentials are set
+impl fmt::Debug for EntryCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EntryCredentials")
+            .field("headers", &self.headers.keys().collect::<Vec<_>>())
+            .field("basic", &self.basic.as_ref().map(|basic| &basic.username))
+            .field("bearer_token", &self.bearer_token.as_ref().map(|_| "<redacted>"))
+            .field("cookies_file", &self.cookies_file)
+            .finish()
+    }
+}
+
+/// Contents of `secrets.toml`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SecretsFile {
+    /// Credentials by entry name
+    #[serde(default)]
+    entries: BTreeMap<String, EntryCredentials>,
+}
+
+/// Store of entry credentials backed by `secrets.toml`
+#[derive(Debug)]
+pub struct SecretStore {
+    /// Path of the `secrets.toml` file
+    path: PathBuf,
+    /// Serialises read-modify-write cycles on the file
+    lock: Mutex<()>,
+}
+
+impl SecretStore {
+    /// Create a store for the `secrets.toml` file in the given inputs directory
+    pub fn new(inputs_path: impl AsRef<Path>) -> Self {
+        Self {
+            path: inputs_path.as_ref().join(SECRETS_FILE_NAME),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Path of the backing `secrets.toml` file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Credentials of an entry, if any are stored
+    pub fn get(&self, name: &str) -> Result<Option<EntryCredentials>> {
+        let _guard = self.guard();
+        Ok(self.read()?.entries.remove(name))
+    }
+
+    /// Apply `f` to the credentials of an entry and save the result
+    ///
+    /// Entries left without credentials are removed from the file.
+    pub fn update<R>(&self, name: &str, f: impl FnOnce(&mut EntryCredentials) -> R) -> Result<R> {
+        let _guard = self.guard();
+        let mut secrets = self.read()?;
+        let credentials = secrets.entries.entry(name.to_string()).or_default();
+        let result = f(credentials);
+        if credentials.is_empty() {
+            secrets.entries.remove(name);
+        }
+        self.write(&secrets)?;
+        Ok(result)
+    }
+
+    /// Remove the credentials of an entry
+    pub fn remove(&self, name: &str) -> Result<()> {
+        let _guard = self.guard();
+        let mut secrets = self.read()?;
+        if secrets.entries.remove(name).is_some() {
+            self.write(&secrets)?;
+        }
+        Ok(())
+    }
+
+    fn guard(&self) -> MutexGuard<'_, ()> {
+        self.lock.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn read(&self) -> Result<SecretsFile> {
+        if !self.path.exists() {
+            return Ok(SecretsFile::default());
+        }
+
+        let toml_string = std::fs::read_to_string(&self.path)
+            .io_path_context("Failed to read secrets from file", &self.path)?;
+
+        toml::from_str(&toml_string)
+            .map_err(|e| DoclingError::config_with("Failed to parse secrets from TOML", e))
+    }
+
+    fn write(&self, secrets: &SecretsFile) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            create_dir_all(parent).io_path_context("Failed to create inputs directory", parent)?;
+        }
+
+        let toml_string = toml::to_string(secrets)
+            .map_err(|e| DoclingError::config_with("Failed to serialize secrets to TOML", e))?;
+
+        write_private(&self.path, toml_string.as_bytes())
+            .io_path_context("Failed to write secrets to file", &self.path)
+    }
+}
+
+/// Write a file that only its owner can read
+#[cfg(unix)]
+fn write_private(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    // The mode only applies to new files
+    file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    file.write_all(content)
+}
+
+#[cfg(not(unix))]
+fn write_private(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    std::fs::write(path, content)
+}
+
+/// Resolve an `env:NAME` reference, or return the value as is
+pub(crate) fn resolve_value(value: &str) -> Result<String> {
+    match value.strip_prefix(ENV_PREFIX) {
+        Some(name) => std::env::var(name)
+            .map_err(|e| DoclingError::config_with(format!("Environment variable {} is not available", name), e)),
+        None => Ok(value.to_string()),
+    }
+}
+
+/// A cookie from a Netscape `cookies.txt` file
+#[derive(Clone, PartialEq, Eq)]
+pub struct Cookie {
+    /// Domain the cookie belongs to, without a leading dot
+    pub domain: String,
+    /// Whether subdomains of `domain` receive the cookie too
+    pub include_subdomains: bool,
+    /// Path prefix the cookie applies to
+    pub path: String,
+    /// Whether the cookie is only sent over HTTPS
+    pub secure: bool,
+    /// Expiry as a Unix timestamp, `None` for session cookies
+    pub expires: Option<i64>,
+    /// Name of the cookie
+    pub name: String,
+    /// Value of the cookie
+    pub value: String,
+}
+
+impl fmt::Debug for Cookie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cookie")
+            .field("domain", &self.domain)
+            .field("path", &self.path)
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Cookie {
+    /// Whether the cookie is sent with a request to `url`
+    pub fn matches(&self, url: &Url, now: i64) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        let domain_matches = host.eq_ignore_ascii_case(&self.domain)
+            || (self.include_subdomains
+                && host.len() > self.domain.len()
+                && host.to_ascii_lowercase().ends_with(&format!(".{}", self.domain.to_ascii_lowercase())));
+        let path = url.path();
+        let path_matches = path == self.path
+            || (path.starts_with(&self.path)
+                && (self.path.ends_with('/') || path[self.path.len()..].starts_with('/')));
+
+        domain_matches
+            && path_matches
+            && (!self.secure || url.scheme() == "https")
+            && self.expires.is_none_or(|expires| expires > now)
+    }
+}
+
+/// Parse a Netscape `cookies.txt` file as written by browsers and curl
+///
+/// # Errors
+/// Returns an error for lines that do not have the seven tab-separated fields
+pub fn parse_cookies_txt(content: &str) -> Result<Vec<Cookie>> {
+    let mut cookies = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        // curl marks HttpOnly cookies with a prefix that looks like a comment
+        let line = line.strip_prefix("#HttpOnly_").unwrap_or(line);
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [domain, include_subdomains, path, secure, expires, name, value] = fields[..] else {
+            return Err(DoclingError::config(format!(
+                "Invalid cookies.txt line {}: expected 7 tab-separated fields",
+                number + 1
+            )));
+        };
+        let expires = expires.trim().parse::<i64>().unwrap_or(0);
+        cookies.push(Cookie {
+            domain: domain.trim_start_matches('.').to_string(),
+            include_subdomains: include_subdomains.eq_ignore_ascii_case("TRUE") || domain.starts_with('.'),
+            path: if path.is_empty() { "/".to_string() } else { path.to_string() },
+            secure: secure.eq_ignore_ascii_case("TRUE"),
+            expires: (expires > 0).then_some(expires),
+            name: name.to_string(),
+            value: value.to_string(),
+        });
+    }
+    Ok(cookies)
+}
+
+/// Resolved credentials applied to the requests of one entry
+#[derive(Clone)]
+pub struct RequestAuth {
+    /// Origin that receives the headers and basic or bearer auth
+    origin: Origin,
+    /// Extra headers
+    headers: HeaderMap,
+    /// Basic auth username and password
+    basic: Option<(String, Option<String>)>,
+    /// Bearer token
+    bearer_token: Option<String>,
+    /// Cookies from the cookie jar
+    cookies: Vec<Cookie>,
+}
+
+impl fmt::Debug for RequestAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestAuth")
+            .field("origin", &self.origin.ascii_serialization())
+            .field("headers", &self.headers.keys().collect::<Vec<_>>())
+            .field("basic", &self.basic.is_some())
+            .field("bearer_token", &self.bearer_token.is_some())
+            .field("cookies", &self.cookies.len())
+            .finish()
+    }
+}
+
+impl RequestAuth {
+    /// Resolve the stored credentials of an entry
+    ///
+    /// # Arguments
+    /// * `credentials` - Credentials from the secret store
+    /// * `root` - Entry URL; headers and basic or bearer auth are only sent to its origin
+    /// * `inputs_path` - Directory relative cookie files are resolved against
+    ///
+    /// # Errors
+    /// Returns an error if a referenced environment variable is missing, a
+    /// header is invalid, more than one credential sets the `Authorization`
+    /// header or the cookie file cannot be read
+    pub fn resolve(credentials: &EntryCredentials, root: &Url, inputs_path: &Path) -> Result<Self> {
+        if root.host_str().is_none() {
+            return Err(DoclingError::config(format!("URL has no host: {}", root)));
+        }
+        let origin = root.origin();
+
+        let authorization_sources = [
+            credentials.basic.is_some(),
+            credentials.bearer_token.is_some(),
+            credentials
+                .headers
+                .keys()
+                .any(|name| name.eq_ignore_ascii_case(AUTHORIZATION.as_str())),
+        ];
+        if authorization_sources.iter().filter(|set| **set).count() > 1 {
+            return Err(DoclingError::config(
+                "Basic auth, a bearer token and an Authorization header exclude each other, keep only one",
+            ));
+        }
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &credentials.headers {
+            let header_name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| DoclingError::config_with(format!("Invalid header name: {}", name), e))?;
+            let mut header_value = HeaderValue::from_str(&resolve_value(value)?)
+                .map_err(|e| DoclingError::config_with(format!("Invalid value for header {}", name), e))?;
+            header_value.set_sensitive(true);
+            headers.insert(header_name, header_value);
+        }
+
+        let basic = credentials
+            .basic
+            .as_ref()
+            .map(|basic| -> Result<_> {
+                let password = basic.password.as_deref().map(resolve_value).transpose()?;
+                Ok((resolve_value(&basic.username)?, password))
+            })
+            .transpose()?;
+        let bearer_token = credentials.bearer_token.as_deref().map(resolve_value).transpose()?;
+
+        let cookies = match &credentials.cookies_file {
+            Some(file) => {
+                let path = inputs_path.join(file);
+                let content = std::fs::read_to_string(&path).io_path_context("Failed to read cookie file", &path)?;
+                parse_cookies_txt(&content)?
+            }
+            None => Vec::new(),
+        };
+
+        Ok(Self {
+            origin,
+            headers,
+            basic,
+            bearer_token,
+            cookies,
+        })
+    }
+
+    /// Add the credentials that apply to `url` to a request
+    ///
+    /// Headers and basic or bearer auth are only added for the entry's
+    /// origin, so they are not sent over plain HTTP after a redirect from
+    /// HTTPS or to another port of the same host.
+    pub fn apply(&self, mut request: RequestBuilder, url: &Url) -> RequestBuilder {
+        if url.origin() == self.origin {
+            request = request.headers(self.headers.clone());
+            if let Some((username, password)) = &self.basic {
+                request = request.basic_auth(username, password.as_ref());
+            }
+            if let Some(token) = &self.bearer_token {
+                request = request.bearer_auth(token);
+            }
+        }
+
+        if let Some(cookie) = self.cookie_header(url) {
+            request = request.header(COOKIE, cookie);
+        }
+        request
+    }
+
+    /// Value of the `Cookie` header for a request to `url`, if any cookie applies
+    fn cookie_header(&self, url: &Url) -> Option<HeaderValue> {
+        let now = Utc::now().timestamp();
+        let pairs: Vec<String> = self
+            .cookies
+            .iter()
+            .filter(|cookie| cookie.matches(url, now))
+            .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+            .collect();
+        if pairs.is_empty() {
+            return None;
+        }
+        let mut value = HeaderValue::from_str(&pairs.join("; ")).ok()?;
+        value.set_sensitive(true);
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_cookies_txt_and_matches_domains() {
+        let content = "# Netscape HTTP Cookie File\n\
+            .example.com\tTRUE\t/\tTRUE\t0\tsession\tabc\n\
+            #HttpOnly_docs.example.com\tFALSE\t/api\tFALSE\t4102444800\ttoken\txyz\n\
+            old.example.com\tFALSE\t/\tFALSE\t1\texpired\t1\n";
+        let cookies = parse_cookies_txt(content).unwrap();
+        assert_eq!(cookies.len(), 3);
+        let now = 1_700_000_000;
+
+        let url = Url::parse("https://docs.example.com/api/v1").unwrap();
+        let matching: Vec<&str> = cookies
+            .iter()
+            .filter(|cookie| cookie.matches(&url, now))
+            .map(|cookie| cookie.name.as_str())
+            .collect();
+        assert_eq!(matching, ["session", "token"]);
+
+        // Secure cookies stay off plain HTTP, paths match on segment boundaries
+        let url = Url::parse("http://docs.example.com/apis").unwrap();
+        assert!(cookies.iter().all(|cookie| !cookie.matches(&url, now)));
+        let url = Url::parse("http://old.example.com/").unwrap();
+        assert!(cookies.iter().all(|cookie| !cookie.matches(&url, now)));
+
+        assert!(parse_cookies_txt("example.com\tTRUE\t/\n").is_err());
+    }
+
+    #[test]
+    fn credentials_are_only_sent_to_the_entry_origin() {
+        let mut credentials = EntryCredentials::default();
+        credentials.headers.insert("X-Api-Key".to_string(), "secret".to_string());
+        credentials.bearer_token = Some("token".to_string());
+        let root = Url::parse("https://docs.example.com/guide/").unwrap();
+        let auth = RequestAuth::resolve(&credentials, &root, Path::new(".")).unwrap();
+
+        let client = reqwest::Client::new();
+        let own = Url::parse("https://docs.example.com/guide/a").unwrap();
+        let request = auth.apply(client.get(own.as_str()), &own).build().unwrap();
+        assert_eq!(request.headers()["x-api-key"], "secret");
+        assert_eq!(request.headers()["authorization"], "Bearer token");
+
+        let explicit_port = Url::parse("https://DOCS.example.com:443/guide/b").unwrap();
+        let request = auth.apply(client.get(explicit_port.as_str()), &explicit_port).build().unwrap();
+        assert_eq!(request.headers()["authorization"], "Bearer token");
+
+        for other in [
+            "https://cdn.example.net/image.png",
+            "http://docs.example.com/guide/a",
+            "https://docs.example.com:8443/guide/a",
+        ] {
+            let other = Url::parse(other).unwrap();
+            let request = auth.apply(client.get(other.as_str()), &other).build().unwrap();
+            assert!(request.headers().is_empty(), "credentials sent to {}", other);
+        }
+
+        credentials.basic = Some(BasicCredentials {
+            username: "user".to_string(),
+            password: None,
+        });
+        assert!(RequestAuth::resolve(&credentials, &root, Path::new(".")).is_err());
+
+        assert!(format!("{:?}", credentials).contains("<redacted>"));
+        assert!(!format!("{:?}", auth).contains("secret"));
+    }
+}
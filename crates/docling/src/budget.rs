@@ -0,0 +1,253 @@
+//! Limits on how much a single crawl may fetch
+//!
+//! A [`CrawlBudget`] caps the number of pages, the bytes of page and document
+//! content, the bytes of media and the wall-clock time of one run of an
+//! entry. Budgets are configured globally and can be overridden per entry.
+//! When a limit is reached the crawler stops scheduling requests, lets the
+//! ones in flight finish and reports the run as truncated; everything
+//! collected so far is still converted. Bytes are counted while bodies
+//! arrive, so a download that would go past the byte limit is cut off.
+
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{DoclingError, Result};
+
+/// Limits of a crawl, `None` meaning unlimited
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CrawlBudget {
+    /// Maximum number of pages and documents requested
+    #[serde(default)]
+    pub max_pages: Option<u64>,
+    /// Maximum bytes of page and document content downloaded
+    #[serde(default)]
+    pub max_bytes: Option<u64>,
+    /// Maximum bytes of media kept, replaces `max_media_bytes_per_entry`
+    #[serde(default)]
+    pub max_media_bytes: Option<u64>,
+    /// Maximum wall-clock time of the crawl in seconds
+    #[serde(default)]
+    pub max_duration_secs: Option<u64>,
+}
+
+impl CrawlBudget {
+    /// Budget for an entry: limits set on the entry replace the global ones
+    pub fn for_entry(&self, entry: Option<&CrawlBudget>) -> CrawlBudget {
+        let Some(entry) = entry else {
+            return self.clone();
+        };
+
+        CrawlBudget {
+            max_pages: entry.max_pages.or(self.max_pages),
+            max_bytes: entry.max_bytes.or(self.max_bytes),
+            max_media_bytes: entry.max_media_bytes.or(self.max_media_bytes),
+            max_duration_secs: entry.max_duration_secs.or(self.max_duration_secs),
+        }
+    }
+}
+
+/// Limit that stopped a crawl early
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetLimit {
+    /// The maximum number of pages was requested
+    Pages,
+    /// The maximum bytes of page content were downloaded
+    Bytes,
+    /// The media budget was used up
+    MediaBytes,
+    /// The maximum crawl time elapsed
+    Duration,
+}
+
+impl fmt::Display for BudgetLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            BudgetLimit::Pages => "page limit reached",
+            BudgetLimit::Bytes => "download size limit reached",
+            BudgetLimit::MediaBytes => "media size limit reached",
+            BudgetLimit::Duration => "time limit reached",
+        };
+        f.write_str(description)
+    }
+}
+
+/// Bytes of page content a crawl may still download
+///
+/// Cheap to clone; clones share the count, so fetches in flight draw from
+/// the same allowance.
+#[derive(Debug, Clone)]
+pub struct ByteAllowance {
+    /// Bytes downloaded so far
+    used: Arc<AtomicU64>,
+    /// Maximum bytes, `None` meaning unlimited
+    max: Option<u64>,
+}
+
+impl ByteAllowance {
+    /// Count `bytes` that arrived against the allowance
+    ///
+    /// # Errors
+    /// Returns [`DoclingError::LimitExceeded`] if the bytes go past the
+    /// maximum; the allowance then counts as used up
+    pub fn take(&self, bytes: u64, what: &str) -> Result<()> {
+        let used = self.used.fetch_add(bytes, Ordering::SeqCst) + bytes;
+        match self.max {
+            Some(max) if used > max => Err(DoclingError::LimitExceeded {
+                what: what.to_string(),
+                limit: max,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Bytes downloaded so far
+    pub fn used(&self) -> u64 {
+        self.used.load(Ordering::SeqCst)
+    }
+}
+
+/// Usage of a budget during a crawl
+#[derive(Debug)]
+pub struct BudgetTracker {
+    /// Limits being enforced
+    budget: CrawlBudget,
+    /// When the crawl started
+    started: Instant,
+    /// Pages requested so far
+    pages: u64,
+    /// Bytes of page content downloaded so far
+    bytes: ByteAllowance,
+}
+
+impl BudgetTracker {
+    /// Start tracking a crawl now
+    pub fn new(budget: CrawlBudget) -> Self {
+        Self::started_at(budget, Instant::now())
+    }
+
+    fn started_at(budget: CrawlBudget, started: Instant) -> Self {
+        let bytes = ByteAllowance {
+            used: Arc::new(AtomicU64::new(0)),
+            max: budget.max_bytes,
+        };
+        Self {
+            budget,
+            started,
+            pages: 0,
+            bytes,
+        }
+    }
+
+    /// Record a page request
+    pub fn add_page(&mut self) {
+        self.pages += 1;
+    }
+
+    /// Record downloaded page content
+    pub fn add_bytes(&mut self, bytes: u64) {
+        self.bytes.used.fetch_add(bytes, Ordering::SeqCst);
+    }
+
+    /// Allowance of page content bytes, to count bodies while they arrive
+    pub fn allowance(&self) -> ByteAllowance {
+        self.bytes.clone()
+    }
+
+    /// The first limit that does not allow another request, if any
+    pub fn exceeded(&self) -> Option<BudgetLimit> {
+        self.exceeded_at(Instant::now())
+    }
+
+    fn exceeded_at(&self, now: Instant) -> Option<BudgetLimit> {
+        let elapsed = now.saturating_duration_since(self.started);
+        if self
+            .budget
+            .max_duration_secs
+            .is_some_and(|secs| elapsed >= Duration::from_secs(secs))
+        {
+            Some(BudgetLimit::Duration)
+        } else if self.budget.max_pages.is_some_and(|max| self.pages >= max) {
+            Some(BudgetLimit::Pages)
+        } else if self.budget.max_bytes.is_some_and(|max| self.bytes.used() >= max) {
+            Some(BudgetLimit::Bytes)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entry_limits_replace_global_ones() {
+        let global = CrawlBudget {
+            max_pages: Some(10_000),
+            max_duration_secs: Some(3600),
+            ..Default::default()
+        };
+        let entry = CrawlBudget {
+            max_pages: Some(50),
+            max_bytes: Some(1024),
+            ..Default::default()
+        };
+        assert_eq!(
+            global.for_entry(Some(&entry)),
+            CrawlBudget {
+                max_pages: Some(50),
+                max_bytes: Some(1024),
+                max_media_bytes: None,
+                max_duration_secs: Some(3600),
+            }
+        );
+    }
+
+    #[test]
+    fn tracker_reports_the_first_exceeded_limit() {
+        let start = Instant::now();
+        let budget = CrawlBudget {
+            max_pages: Some(2),
+            max_bytes: Some(100),
+            max_duration_secs: Some(60),
+            ..Default::default()
+        };
+        let mut tracker = BudgetTracker::started_at(budget, start);
+        tracker.add_page();
+        tracker.add_bytes(60);
+        assert_eq!(tracker.exceeded_at(start), None);
+
+        tracker.add_bytes(40);
+        assert_eq!(tracker.exceeded_at(start), Some(BudgetLimit::Bytes));
+        tracker.add_page();
+        assert_eq!(tracker.exceeded_at(start), Some(BudgetLimit::Pages));
+        assert_eq!(
+            tracker.exceeded_at(start + Duration::from_secs(60)),
+            Some(BudgetLimit::Duration)
+        );
+
+        // Bodies in flight draw from the same allowance
+        let tracker = BudgetTracker::started_at(
+            CrawlBudget {
+                max_bytes: Some(100),
+                ..Default::default()
+            },
+            start,
+        );
+        let allowance = tracker.allowance();
+        allowance.take(80, "page").unwrap();
+        assert!(matches!(
+            tracker.allowance().take(30, "page"),
+            Err(DoclingError::LimitExceeded { limit: 100, .. })
+        ));
+        assert_eq!(tracker.exceeded_at(start), Some(BudgetLimit::Bytes));
+
+        let unlimited = BudgetTracker::started_at(CrawlBudget::default(), start);
+        assert_eq!(unlimited.exceeded_at(start + Duration::from_secs(86_400)), None);
+    }
+}
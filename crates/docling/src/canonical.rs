@@ -0,0 +1,422 @@
+//! Canonical URLs and duplicate pages
+//!
+//! The same page is often linked as `/docs`, `/docs/`, `/docs/index.html`
+//! or `/docs?utm_source=nav`. The crawler normalises every URL it queues
+//! with [`UrlNormalization`] and fetches each normalised page once. After
+//! the crawl, pages that declare another page as their `rel=canonical`, or
+//! whose content is an exact or near duplicate of another page (by the word
+//! shingles of their visible text, see [`shingle_sketch`]), are marked as
+//! duplicates: one copy is kept and converted, and every alias is recorded
+//! in the entry's [`UrlAliases`] file.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{DoclingError, IoContext, Result};
+
+/// Name of the URL alias file in an entry's output directory
+pub const URL_ALIASES_FILE_NAME: &str = "url_aliases.json";
+
+/// Query parameters that only track where a visitor came from
+const TRACKING_PARAMS: [&str; 12] = [
+    "fbclid", "gclid", "dclid", "msclkid", "yclid", "mc_cid", "mc_eid", "_ga", "_gl", "ref", "ref_src", "igshid",
+];
+
+/// Prefixes of tracking query parameters
+const TRACKING_PARAM_PREFIXES: [&str; 2] = ["utm_", "pk_"];
+
+/// Number of words in a shingle
+const SHINGLE_WORDS: usize = 3;
+
+/// Pages with fewer words are only compared exactly; their shingles are too few
+const MIN_SHINGLED_WORDS: usize = 20;
+
+/// Number of smallest shingle hashes kept to estimate the resemblance of pages
+const SKETCH_SIZE: usize = 256;
+
+/// Default index files standing for their directory
+fn default_index_files() -> Vec<String> {
+    vec!["index.html".to_string(), "index.htm".to_string()]
+}
+
+/// Default share of shingles near duplicates have in common
+fn default_near_duplicate_similarity() -> f64 {
+    0.9
+}
+
+/// Helper for `#[serde(default = ...)]` of switches that are on by default
+fn default_true() -> bool {
+    true
+}
+
+/// Rules deciding when two URLs name the same page
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UrlNormalization {
+    /// Treat `/docs` and `/docs/` as the same page
+    #[serde(default = "default_true")]
+    pub ignore_trailing_slash: bool,
+    /// File names that stand for their directory (`/docs/index.html` is `/docs/`)
+    #[serde(default = "default_index_files")]
+    pub index_files: Vec<String>,
+    /// Remove well-known tracking parameters (`utm_*`, `gclid`, `fbclid`, ...)
+    #[serde(default = "default_true")]
+    pub strip_tracking_params: bool,
+    /// Further query parameters to remove
+    #[serde(default)]
+    pub strip_params: Vec<String>,
+    /// Treat a page as an alias of the in-scope page its `rel=canonical` names
+    #[serde(default = "default_true")]
+    pub honour_canonical: bool,
+    /// Share of word shingles (0 to 1) two pages must have in common to
+    /// count as near duplicates; above 1 only exact duplicates are found
+    #[serde(default = "default_near_duplicate_similarity")]
+    pub near_duplicate_similarity: f64,
+}
+
+impl Default for UrlNormalization {
+    fn default() -> Self {
+        Self {
+            ignore_trailing_slash: true,
+            index_files: default_index_files(),
+            strip_tracking_params: true,
+            strip_params: Vec::new(),
+            honour_canonical: true,
+            near_duplicate_similarity: default_near_duplicate_similarity(),
+        }
+    }
+}
+
+impl UrlNormalization {
+    /// The URL to fetch for a link: without fragment, tracking parameters
+    /// and index file name
+    pub fn normalize(&self, url: &Url) -> Url {
+        let mut url = url.clone();
+        url.set_fragment(None);
+
+        if url.query().is_some() {
+            let kept: Vec<(String, String)> = url
+                .query_pairs()
+                .filter(|(name, _)| !self.is_stripped_param(name))
+                .map(|(name, value)| (name.into_owned(), value.into_owned()))
+                .collect();
+            if kept.is_empty() {
+                url.set_query(None);
+            } else {
+                url.query_pairs_mut().clear().extend_pairs(kept);
+            }
+        }
+
+        let index_file = url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .filter(|last| self.index_files.iter().any(|index| index.eq_ignore_ascii_case(last)))
+            .map(str::len);
+        if let Some(length) = index_file {
+            let path = url.path().to_string();
+            url.set_path(&path[..path.len() - length]);
+        }
+        url
+    }
+
+    /// Key identifying the page a URL names; normalised URLs with the same
+    /// key are fetched once
+    pub fn key(&self, url: &Url) -> String {
+        let url = self.normalize(url);
+        let mut key = url.to_string();
+        if self.ignore_trailing_slash && url.path().len() > 1 && url.path().ends_with('/') {
+            let path = url.path().trim_end_matches('/');
+            let mut trimmed = url.clone();
+            trimmed.set_path(path);
+            key = trimmed.to_string();
+        }
+        key
+    }
+
+    /// Whether a query parameter is removed
+    fn is_stripped_param(&self, name: &str) -> bool {
+        let name = name.to_ascii_lowercase();
+        self.strip_params.iter().any(|param| param.eq_ignore_ascii_case(&name))
+            || (self.strip_tracking_params
+                && (TRACKING_PARAMS.contains(&name.as_str())
+                    || TRACKING_PARAM_PREFIXES.iter().any(|prefix| name.starts_with(prefix))))
+    }
+}
+
+/// Why a URL is an alias of another
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AliasReason {
+    /// The URLs are the same after normalisation
+    Normalized,
+    /// The page names the other one as its `rel=canonical`
+    Canonical,
+    /// The pages have identical content
+    Duplicate,
+    /// The pages have nearly identical text
+    NearDuplicate,
+}
+
+/// The page a URL stands for
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UrlAlias {
+    /// URL of the page that is kept
+    pub canonical: String,
+    /// Why the URL is an alias
+    pub reason: AliasReason,
+}
+
+/// Aliases found by the last crawl of an entry, keyed by alias URL
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UrlAliases {
+    /// Alias URLs and the page each stands for
+    pub aliases: BTreeMap<String, UrlAlias>,
+}
+
+impl UrlAliases {
+    /// Location of the alias file inside an entry's output directory
+    pub fn path_in(entry_dir: &Path) -> PathBuf {
+        entry_dir.join(URL_ALIASES_FILE_NAME)
+    }
+
+    /// Record an alias, unless the URL is already known as one
+    pub fn insert(&mut self, alias: &str, canonical: &str, reason: AliasReason) {
+        if alias != canonical {
+            self.aliases.entry(alias.to_string()).or_insert_with(|| UrlAlias {
+                canonical: canonical.to_string(),
+                reason,
+            });
+        }
+    }
+
+    /// Load the aliases of an entry, returning none if none were saved yet
+    pub fn load(entry_dir: &Path) -> Result<Self> {
+        let path = Self::path_in(entry_dir);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let json = std::fs::read_to_string(&path).io_path_context("Failed to read URL aliases", &path)?;
+        serde_json::from_str(&json)
+            .map_err(|e| DoclingError::config_with(format!("Failed to parse URL aliases {}", path.display()), e))
+    }
+
+    /// Save the aliases into an entry's output directory
+    pub fn save(&self, entry_dir: &Path) -> Result<()> {
+        let path = Self::path_in(entry_dir);
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| DoclingError::config_with("Failed to serialize URL aliases", e))?;
+        std::fs::write(&path, json).io_path_context("Failed to write URL aliases", &path)
+    }
+}
+
+/// What duplicate detection needs to know about a saved page
+#[derive(Debug, Clone)]
+pub struct PageFingerprint {
+    /// URL of the page
+    pub url: String,
+    /// Link depth of the page
+    pub depth: u32,
+    /// SHA-256 of the saved content
+    pub content_hash: String,
+    /// Shingle sketch of the visible text, `None` for pages with too little text
+    pub sketch: Option<Vec<u64>>,
+    /// Crawled page the page names as its `rel=canonical`, if any
+    pub canonical: Option<String>,
+}
+
+/// Find the pages that duplicate another page
+///
+/// Pages named as canonical by others are kept first, then pages closer to
+/// the entry URL and with shorter URLs. Every other page that declares a
+/// canonical page, has the same content as a kept page or is within
+/// shares at least `min_similarity` of its shingles with one is returned with
+/// the page it stands for.
+pub fn find_duplicates(pages: &[PageFingerprint], min_similarity: f64) -> BTreeMap<String, UrlAlias> {
+    let present: HashSet<&str> = pages.iter().map(|page| page.url.as_str()).collect();
+    let targets: HashSet<&str> = pages
+        .iter()
+        .filter_map(|page| page.canonical.as_deref())
+        .filter(|canonical| present.contains(canonical))
+        .collect();
+    let mut order: Vec<&PageFingerprint> = pages.iter().collect();
+    order.sort_by(|a, b| {
+        (!targets.contains(a.url.as_str()), a.depth, a.url.len(), &a.url)
+            .cmp(&(!targets.contains(b.url.as_str()), b.depth, b.url.len(), &b.url))
+    });
+
+    let mut kept: Vec<&PageFingerprint> = Vec::new();
+    // Page kept for each content seen so far
+    let mut contents: HashMap<&str, String> = HashMap::new();
+    let mut duplicates: BTreeMap<String, UrlAlias> = BTreeMap::new();
+    for page in order {
+        if let Some(canonical) = page.canonical.as_deref().filter(|canonical| {
+            *canonical != page.url && present.contains(canonical)
+        }) {
+            // The canonical page may itself duplicate another one
+            let canonical = duplicates
+                .get(canonical)
+                .map_or(canonical, |alias| alias.canonical.as_str())
+                .to_string();
+            if canonical != page.url {
+                contents.entry(&page.content_hash).or_insert_with(|| canonical.clone());
+                duplicates.insert(page.url.clone(), UrlAlias {
+                    canonical,
+                    reason: AliasReason::Canonical,
+                });
+                continue;
+            }
+        }
+        let original = contents
+            .get(page.content_hash.as_str())
+            .map(|canonical| (canonical.clone(), AliasReason::Duplicate))
+            .or_else(|| {
+                let sketch = page.sketch.as_deref()?;
+                kept.iter()
+                    .find(|kept| {
+                        kept.sketch
+                            .as_deref()
+                            .is_some_and(|other| resemblance(sketch, other) >= min_similarity)
+                    })
+                    .map(|kept| (kept.url.clone(), AliasReason::NearDuplicate))
+            });
+        match original {
+            Some((canonical, reason)) => {
+                contents.entry(&page.content_hash).or_insert_with(|| canonical.clone());
+                duplicates.insert(page.url.clone(), UrlAlias { canonical, reason });
+            }
+            None => {
+                contents.insert(&page.content_hash, page.url.clone());
+                kept.push(page);
+            }
+        }
+    }
+    duplicates
+}
+
+/// Sketch of the word shingles of a text, `None` if it is too short
+///
+/// The sketch holds the smallest hashes of the distinct three-word shingles,
+/// so [`resemblance`] can compare long pages without keeping every shingle.
+pub fn shingle_sketch(text: &str) -> Option<Vec<u64>> {
+    let words: Vec<String> = text.split_whitespace().map(str::to_lowercase).collect();
+    if words.len() < MIN_SHINGLED_WORDS {
+        return None;
+    }
+
+    let mut hashes: Vec<u64> = words.windows(SHINGLE_WORDS).map(shingle_hash).collect();
+    hashes.sort_unstable();
+    hashes.dedup();
+    hashes.truncate(SKETCH_SIZE);
+    Some(hashes)
+}
+
+/// Estimated share of shingles two texts have in common (their Jaccard index)
+///
+/// Exact for texts with fewer distinct shingles than the sketch holds.
+pub fn resemblance(a: &[u64], b: &[u64]) -> f64 {
+    let (mut i, mut j, mut union, mut shared) = (0, 0, 0, 0);
+    while union < SKETCH_SIZE && (i < a.len() || j < b.len()) {
+        match (a.get(i), b.get(j)) {
+            (Some(x), Some(y)) if x == y => {
+                shared += 1;
+                i += 1;
+                j += 1;
+            }
+            (Some(x), Some(y)) if x < y => i += 1,
+            (Some(_), None) => i += 1,
+            _ => j += 1,
+        }
+        union += 1;
+    }
+    if union == 0 {
+        return 0.0;
+    }
+    shared as f64 / union as f64
+}
+
+/// Well-mixed 64-bit hash of a shingle (FNV-1a of its words joined by spaces,
+/// finished with the SplitMix64 mixer so the smallest hashes are a fair sample)
+fn shingle_hash(words: &[String]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    let mut hash = OFFSET_BASIS;
+    for (i, word) in words.iter().enumerate() {
+        if i > 0 {
+            hash = (hash ^ u64::from(b' ')).wrapping_mul(PRIME);
+        }
+        for byte in word.bytes() {
+            hash = (hash ^ u64::from(byte)).wrapping_mul(PRIME);
+        }
+    }
+    hash ^= hash >> 30;
+    hash = hash.wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash ^= hash >> 27;
+    hash = hash.wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^ hash >> 31
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn urls_are_normalised() {
+        let rules = UrlNormalization::default();
+        let normalize = |url: &str| rules.normalize(&Url::parse(url).unwrap()).to_string();
+        assert_eq!(normalize("https://example.com/docs/index.html#intro"), "https://example.com/docs/");
+        assert_eq!(
+            normalize("https://example.com/docs?utm_source=nav&page=2&ref=home"),
+            "https://example.com/docs?page=2"
+        );
+        assert_eq!(normalize("https://example.com/docs?gclid=1"), "https://example.com/docs");
+        assert_eq!(normalize("https://example.com/reindex.html"), "https://example.com/reindex.html");
+
+        let key = |url: &str| rules.key(&Url::parse(url).unwrap());
+        assert_eq!(key("https://example.com/docs"), key("https://example.com/docs/"));
+        assert_eq!(key("https://example.com/docs/"), key("https://example.com/docs/index.html?utm_medium=x"));
+        assert_ne!(key("https://example.com/docs"), key("https://example.com/docs?page=2"));
+        assert_eq!(key("https://example.com/"), "https://example.com/");
+
+        let strict = UrlNormalization {
+            ignore_trailing_slash: false,
+            index_files: Vec::new(),
+            ..UrlNormalization::default()
+        };
+        assert_ne!(
+            strict.key(&Url::parse("https://example.com/docs").unwrap()),
+            strict.key(&Url::parse("https://example.com/docs/index.html").unwrap())
+        );
+    }
+
+    #[test]
+    fn duplicates_keep_one_copy() {
+        let text = "The quick brown fox jumps over the lazy dog while the cat watches from the warm windowsill and the birds sing in the old oak tree";
+        let page = |url: &str, depth, hash: &str, text: &str, canonical: Option<&str>| PageFingerprint {
+            url: url.to_string(),
+            depth,
+            content_hash: hash.to_string(),
+            sketch: shingle_sketch(text),
+            canonical: canonical.map(str::to_string),
+        };
+        let pages = [
+            page("https://example.com/docs/print", 1, "a", text, None),
+            page("https://example.com/docs/", 0, "b", &format!("{} today", text), None),
+            page("https://example.com/docs/copy", 2, "b", "", None),
+            page("https://example.com/docs/v2", 1, "c", "Something else entirely", Some("https://example.com/docs/print")),
+            page("https://example.com/docs/other", 1, "d", "A completely different page about installing the tool on a new machine with the package manager of your system and then configuring it", None),
+        ];
+        let duplicates = find_duplicates(&pages, 0.9);
+        assert_eq!(duplicates.len(), 3);
+        assert_eq!(duplicates["https://example.com/docs/copy"].reason, AliasReason::Duplicate);
+        // Pages named as canonical are kept first, even over the entry URL
+        assert_eq!(duplicates["https://example.com/docs/"].reason, AliasReason::NearDuplicate);
+        assert_eq!(duplicates["https://example.com/docs/"].canonical, "https://example.com/docs/print");
+        assert_eq!(duplicates["https://example.com/docs/v2"].canonical, "https://example.com/docs/print");
+        assert!(shingle_sketch("too short").is_none());
+        let sketch = shingle_sketch(text).unwrap();
+        assert_eq!(resemblance(&sketch, &sketch), 1.0);
+    }
+}
@@ -0,0 +1,190 @@
+//! Character set detection and transcoding for HTML pages
+//!
+//! Pages are saved as UTF-8 regardless of how they were served. The encoding
+//! is taken from, in order of precedence: a byte order mark, the `charset`
+//! parameter of the `Content-Type` header, a `<meta>` declaration near the
+//! start of the document, and finally statistical detection.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::LazyLock;
+
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_8};
+use regex::bytes::Regex as BytesRegex;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Number of bytes searched for a `<meta>` charset declaration
+const META_PRESCAN_BYTES: usize = 4096;
+
+/// `<meta charset=...>` or `<meta http-equiv="Content-Type" content="...; charset=...">`
+static META_CHARSET: LazyLock<BytesRegex> = LazyLock::new(|| {
+    BytesRegex::new(r#"(?i)<meta\b[^>]*?\bcharset\s*=\s*["']?\s*([a-z0-9_:.+-]+)"#)
+        .expect("valid meta charset regex")
+});
+
+/// The charset value inside a `<meta>` declaration of a decoded document
+static META_CHARSET_VALUE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)(<meta\b[^>]*?\bcharset\s*=\s*["']?\s*)([a-z0-9_:.+-]+)"#)
+        .expect("valid meta charset regex")
+});
+
+/// Where the encoding of a page was determined from
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EncodingSource {
+    /// Byte order mark at the start of the content
+    Bom,
+    /// `charset` parameter of the `Content-Type` header
+    ContentType,
+    /// `<meta>` declaration in the document
+    Meta,
+    /// The content is valid UTF-8
+    Utf8,
+    /// Statistical detection
+    Detected,
+}
+
+impl fmt::Display for EncodingSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            EncodingSource::Bom => "byte order mark",
+            EncodingSource::ContentType => "Content-Type header",
+            EncodingSource::Meta => "meta tag",
+            EncodingSource::Utf8 => "valid UTF-8",
+            EncodingSource::Detected => "detection",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A page decoded to UTF-8
+#[derive(Debug, Clone)]
+pub struct DecodedPage {
+    /// Content of the page as UTF-8, with the `<meta>` charset updated
+    pub text: String,
+    /// Encoding the page was served in
+    pub encoding: &'static Encoding,
+    /// Where the encoding was determined from
+    pub source: EncodingSource,
+    /// Whether malformed sequences had to be replaced
+    pub had_errors: bool,
+}
+
+/// Decode an HTML page to UTF-8
+///
+/// # Arguments
+/// * `body` - Raw content of the response
+/// * `content_type` - Value of the `Content-Type` header, if any
+/// * `tld` - Top-level domain of the page, used as a hint for detection
+pub fn decode_html(body: &[u8], content_type: Option<&str>, tld: Option<&str>) -> DecodedPage {
+    let (encoding, source) = sniff_encoding(body, content_type, tld);
+    let (text, had_errors) = encoding.decode_with_bom_removal(body);
+
+    let text = if encoding == UTF_8 {
+        text.into_owned()
+    } else {
+        // The saved copy is UTF-8, so its declaration must say so too
+        declare_utf8(&text).into_owned()
+    };
+
+    DecodedPage {
+        text,
+        encoding,
+        source,
+        had_errors,
+    }
+}
+
+/// Determine the encoding of an HTML page
+pub fn sniff_encoding(
+    body: &[u8],
+    content_type: Option<&str>,
+    tld: Option<&str>,
+) -> (&'static Encoding, EncodingSource) {
+    if let Some((encoding, _)) = Encoding::for_bom(body) {
+        return (encoding, EncodingSource::Bom);
+    }
+
+    if let Some(encoding) = content_type.and_then(charset_parameter) {
+        return (encoding, EncodingSource::ContentType);
+    }
+
+    if let Some(encoding) = meta_charset(body) {
+        return (encoding, EncodingSource::Meta);
+    }
+
+    if std::str::from_utf8(body).is_ok() {
+        return (UTF_8, EncodingSource::Utf8);
+    }
+
+    let mut detector = EncodingDetector::new();
+    detector.feed(body, true);
+    let tld = tld.map(str::as_bytes);
+    (detector.guess(tld, true), EncodingSource::Detected)
+}
+
+/// Encoding named by the `charset` parameter of a `Content-Type` value
+fn charset_parameter(content_type: &str) -> Option<&'static Encoding> {
+    content_type
+        .split(';')
+        .skip(1)
+        .filter_map(|param| param.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+        .and_then(|(_, value)| Encoding::for_label(value.trim().trim_matches(['"', '\'']).as_bytes()))
+}
+
+/// Encoding declared by a `<meta>` tag near the start of the document
+fn meta_charset(body: &[u8]) -> Option<&'static Encoding> {
+    let head = &body[..body.len().min(META_PRESCAN_BYTES)];
+    let label = META_CHARSET.captures(head)?.get(1)?;
+    let encoding = Encoding::for_label(label.as_bytes())?;
+
+    // A UTF-16 declaration in an ASCII-compatible document means UTF-8 (HTML spec)
+    if encoding.output_encoding() != encoding {
+        Some(UTF_8)
+    } else {
+        Some(encoding)
+    }
+}
+
+/// Replace the charset of `<meta>` declarations with UTF-8
+fn declare_utf8(html: &str) -> Cow<'_, str> {
+    META_CHARSET_VALUE.replace_all(html, "${1}utf-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::{SHIFT_JIS, WINDOWS_1252};
+
+    #[test]
+    fn header_and_meta_charsets_are_honoured() {
+        let (body, _, _) = WINDOWS_1252.encode("<html><body>Café</body></html>");
+        let page = decode_html(&body, Some("text/html; charset=ISO-8859-1"), None);
+        assert_eq!(page.source, EncodingSource::ContentType);
+        assert!(page.text.contains("Café"));
+
+        let html = "<html><head><meta charset=\"shift_jis\"></head><body>日本語</body></html>";
+        let (body, _, _) = SHIFT_JIS.encode(html);
+        let page = decode_html(&body, Some("text/html"), None);
+        assert_eq!(page.encoding, SHIFT_JIS);
+        assert_eq!(page.source, EncodingSource::Meta);
+        assert!(page.text.contains("日本語"));
+        assert!(page.text.contains("<meta charset=\"utf-8\">"));
+    }
+
+    #[test]
+    fn bom_wins_and_utf8_is_kept() {
+        let mut body = vec![0xEF, 0xBB, 0xBF];
+        body.extend_from_slice("<p>ok</p>".as_bytes());
+        let page = decode_html(&body, Some("text/html; charset=windows-1252"), None);
+        assert_eq!(page.source, EncodingSource::Bom);
+        assert_eq!(page.text, "<p>ok</p>");
+
+        let page = decode_html("<p>ü</p>".as_bytes(), None, None);
+        assert_eq!(page.encoding, UTF_8);
+        assert_eq!(page.source, EncodingSource::Utf8);
+    }
+}
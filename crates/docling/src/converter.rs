@@ -1,17 +1,23 @@
 //! Converter for transforming HTML content to Markdown
 //! Supports multiple conversion methods: htmd, fast_html2md, and jina_reader
+//! Linked documents (PDF, DOCX, text) are converted with format-specific extractors
 
 use std::fs::{self, create_dir_all, read_to_string, write};
-use std::path::{Path, PathBuf};
+use std::path::Path;
 use std::time::{Duration, Instant};
 
-use anyhow::{Context, Result};
 #[cfg(not(any(feature = "htmd", feature = "fast-html2md")))]
 use regex::Regex; // Only needed if neither feature is enabled
 use reqwest::{Client, ClientBuilder};
 use tracing::{debug, error, info, warn};
 
-use crate::{DoclingConfig, TransformMethod};
+use crate::auth::RequestAuth;
+use crate::documents::extract_markdown;
+use crate::events::{ProgressEvent, ProgressReporter};
+use crate::extract::{extract_main_content, ContentExtraction};
+use crate::manifest::Manifest;
+use crate::report::{millis, ConversionMethod, ConversionReport};
+use crate::{DoclingConfig, DoclingError, DocumentType, IoContext, Result, TransformMethod};
 
 /// Converter for HTML to Markdown transformation
 pub struct Converter {
@@ -19,29 +25,73 @@ pub struct Converter {
     config: DoclingConfig,
     /// HTTP client for making requests (used by Jina Reader)
     client: Client,
+    /// Receives conversion progress events
+    progress: ProgressReporter,
+    /// Credentials of the entry, if it needs any
+    auth: Option<RequestAuth>,
+    /// Convert every file again, without using the network
+    reconvert: bool,
 }
 
 impl Converter {
     /// Create a new converter with the given configuration
     pub fn new(config: DoclingConfig) -> Result<Self> {
         // Set up HTTP client with proper timeouts to prevent hanging
-        let client = ClientBuilder::new()
+        let builder = ClientBuilder::new()
             .user_agent(&config.user_agent)
             .timeout(Duration::from_secs(60)) // 1 minute timeout for all requests
-            .connect_timeout(Duration::from_secs(30)) // 30 seconds connect timeout
+            .connect_timeout(Duration::from_secs(30)); // 30 seconds connect timeout
+        let client = config
+            .network
+            .configure(builder)?
             .build()
-            .context("Failed to create HTTP client")?;
+            .map_err(|e| DoclingError::config_with("Failed to create HTTP client", e))?;
 
         info!(
             "Converter initialized with {:?} transformation method",
             config.transform_md_using
         );
 
-        Ok(Self { config, client })
+        Ok(Self {
+            config,
+            client,
+            progress: ProgressReporter::noop(),
+            auth: None,
+            reconvert: false,
+        })
     }
 
-    /// Convert HTML files in a directory to Markdown
-    pub async fn convert_directory(&self, entry_name: &str) -> Result<Vec<PathBuf>> {
+    /// Report progress events to the given reporter
+    pub fn with_progress(mut self, progress: ProgressReporter) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Credentials of the entry being converted
+    ///
+    /// Jina Reader fetches pages itself and would need the credentials, so
+    /// it is not used for entries that have any; the saved HTML is converted
+    /// locally with htmd instead.
+    pub fn with_auth(mut self, auth: Option<RequestAuth>) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// Convert every file again, offline
+    ///
+    /// Files whose Markdown is up to date are converted anyway, and Jina
+    /// Reader, which fetches the pages itself, is not used; the saved HTML
+    /// is converted locally with htmd instead.
+    pub fn with_reconvert(mut self, reconvert: bool) -> Self {
+        self.reconvert = reconvert;
+        self
+    }
+
+    /// Convert the HTML files and linked documents of an entry to Markdown
+    ///
+    /// Returns a report for every file found, including the ones that failed
+    /// to convert.
+    pub async fn convert_directory(&self, entry_name: &str) -> Result<Vec<ConversionReport>> {
         let start_time = Instant::now();
         info!(
             "Starting conversion of HTML files for entry '{}'",
@@ -50,19 +100,25 @@ impl Converter {
 
         let base_dir = self.config.outputs_path.join(entry_name);
         let html_dir = base_dir.join(&self.config.output_parts_html_suffix);
+        let docs_dir = base_dir.join(&self.config.output_parts_docs_suffix);
         let md_dir = base_dir.join(&self.config.output_parts_markdown_suffix);
 
         info!("HTML directory: {}", html_dir.display());
         info!("Markdown directory: {}", md_dir.display());
 
+        // Original URLs and content hashes of the saved pages
+        let mut manifest = Manifest::load(&base_dir)?;
+        let files = manifest.file_index();
+
         // Create markdown directory if it doesn't exist
-        create_dir_all(&md_dir).context("Failed to create Markdown output directory")?;
+        create_dir_all(&md_dir)
+            .io_path_context("Failed to create Markdown output directory", &md_dir)?;
 
         // Get all HTML files
         info!("Scanning for HTML files in {}", html_dir.display());
         let html_files = fs::read_dir(&html_dir)
-            .context("Failed to read HTML directory")?
-            .filter_map(Result::ok)
+            .io_path_context("Failed to read HTML directory", &html_dir)?
+            .filter_map(std::result::Result::ok)
             .filter(|entry| {
                 entry.file_type().map(|ft| ft.is_file()).unwrap_or(false)
                     && entry
@@ -71,17 +127,81 @@ impl Converter {
                         .map(|ext| ext == "html")
                         .unwrap_or(false)
             })
-            .map(|entry| entry.path())
+            .map(|entry| (entry.path(), None))
             .collect::<Vec<_>>();
 
-        info!("Found {} HTML files to convert", html_files.len());
+        // Pages that duplicate another page are left out
+        let (html_files, duplicates): (Vec<_>, Vec<_>) = html_files.into_iter().partition(|(html_file, _)| {
+            manifest
+                .find_by_file(&files, html_file)
+                .is_none_or(|record| record.duplicate_of.is_none())
+        });
+        if !duplicates.is_empty() {
+            info!("Skipping {} duplicate pages", duplicates.len());
+        }
 
-        let mut converted_files = Vec::new();
+        // So are pages saved before their language was left out
+        let (html_files, other_languages): (Vec<_>, Vec<_>) = html_files.into_iter().partition(|(html_file, _)| {
+            manifest
+                .find_by_file(&files, html_file)
+                .and_then(|record| record.language.as_deref())
+                .is_none_or(|language| self.config.languages.accepts(language))
+        });
+        if !other_languages.is_empty() {
+            info!("Skipping {} pages in languages that are not kept", other_languages.len());
+        }
+
+        // Linked documents, if the crawler saved any
+        let mut source_files = html_files;
+        if docs_dir.is_dir() {
+            info!("Scanning for documents in {}", docs_dir.display());
+            let documents = fs::read_dir(&docs_dir)
+                .io_path_context("Failed to read document directory", &docs_dir)?
+                .filter_map(std::result::Result::ok)
+                .filter(|entry| entry.file_type().map(|ft| ft.is_file()).unwrap_or(false))
+                .filter_map(|entry| {
+                    let path = entry.path();
+                    let document_type = manifest
+                        .find_by_file(&files, &path)
+                        .and_then(|record| record.document)
+                        .or_else(|| {
+                            path.extension()
+                                .and_then(|ext| DocumentType::from_extension(&ext.to_string_lossy()))
+                        })?;
+                    Some((path, Some(document_type)))
+                })
+                .collect::<Vec<_>>();
+            info!("Found {} documents", documents.len());
+            source_files.extend(documents);
+        }
+
+        // Skip files whose Markdown was already produced from the same content, unless rebuilding
+        let (html_files, up_to_date): (Vec<_>, Vec<_>) = source_files.into_iter().partition(|(html_file, _)| {
+            let md_file = html_file
+                .file_stem()
+                .map(|stem| md_dir.join(format!("{}.md", stem.to_string_lossy())));
+            self.reconvert
+                || !(md_file.is_some_and(|md_file| md_file.exists())
+                    && manifest
+                        .find_by_file(&files, html_file)
+                        .is_some_and(|record| record.is_converted()))
+        });
+        if !up_to_date.is_empty() {
+            info!("Skipping {} unchanged files", up_to_date.len());
+        }
+
+        info!("Found {} files to convert", html_files.len());
+        self.progress.emit(ProgressEvent::ConversionStarted {
+            total: html_files.len(),
+        });
+
+        let mut reports = Vec::new();
+        let mut converted_count = 0;
         let mut conversion_count = 0;
         let total_files = html_files.len();
 
         // Process each HTML file
-        for html_file in html_files {
+        for (html_file, document_type) in html_files {
             conversion_count += 1;
             let filename = html_file.file_stem().unwrap().to_string_lossy();
             let md_file = md_dir.join(format!("{}.md", filename));
@@ -95,14 +215,23 @@ impl Converter {
             );
 
             let file_start_time = Instant::now();
-            match self.convert_file(&html_file, &md_file).await {
-                Ok(_) => {
+            let source_url = manifest.find_by_file(&files, &html_file).map(|record| record.url.clone());
+            let result = match document_type {
+                Some(document_type) => self.convert_document(&html_file, &md_file, document_type),
+                None => self.convert_file(&html_file, &md_file, source_url.as_deref()).await,
+            };
+            match result {
+                Ok(report) => {
                     let duration = file_start_time.elapsed();
                     info!(
                         "Successfully converted {}/{} in {:.2?}",
                         conversion_count, total_files, duration
                     );
-                    converted_files.push(md_file);
+                    converted_count += 1;
+                    if let Some(record) = manifest.find_by_file_mut(&files, &html_file) {
+                        record.converted_hash = Some(record.content_hash.clone());
+                    }
+                    reports.push(report);
                 }
                 Err(e) => {
                     let duration = file_start_time.elapsed();
@@ -110,36 +239,91 @@ impl Converter {
                         "Failed to convert file {}/{} after {:.2?}: {}",
                         conversion_count, total_files, duration, e
                     );
+                    reports.push(ConversionReport {
+                        source: html_file.clone(),
+                        output: None,
+                        requested_method: self.config.transform_md_using.clone(),
+                        method: None,
+                        fallback_reason: None,
+                        input_bytes: fs::metadata(&html_file).map(|m| m.len()).unwrap_or(0),
+                        output_bytes: 0,
+                        duration_ms: millis(duration),
+                        error: Some(e.to_string()),
+                        extraction: None,
+                    });
                 }
             }
         }
 
+        self.progress.emit(ProgressEvent::ConversionFinished {
+            converted: converted_count,
+            failed: total_files - converted_count,
+        });
+
+        // Remember which content the Markdown files were converted from
+        if converted_count > 0 {
+            manifest.save(&base_dir)?;
+        }
+
         let total_duration = start_time.elapsed();
         info!(
-            "Converted {}/{} HTML files to Markdown in {:.2?}",
-            converted_files.len(),
+            "Converted {}/{} files to Markdown in {:.2?}",
+            converted_count,
             total_files,
             total_duration
         );
 
-        Ok(converted_files)
+        Ok(reports)
     }
 
     /// Convert a single HTML file to Markdown
-    pub async fn convert_file(&self, html_file: &Path, md_file: &Path) -> Result<()> {
+    ///
+    /// `source_url` is the URL the file was crawled from, as recorded in the
+    /// entry manifest; it is required by the Jina Reader method. Pages Jina
+    /// Reader cannot or must not fetch are converted with htmd instead, and
+    /// the reason is recorded in the returned report.
+    /// Unless extraction is disabled, only the main content of the page is
+    /// converted; Jina Reader extracts it on its own.
+    /// If the configured method fails, the built-in fallback converter is used
+    /// and the reason is recorded in the returned report.
+    pub async fn convert_file(
+        &self,
+        html_file: &Path,
+        md_file: &Path,
+        source_url: Option<&str>,
+    ) -> Result<ConversionReport> {
         let start_time = Instant::now();
         info!("Starting conversion of file: {}", html_file.display());
 
         // Read HTML content
         debug!("Reading HTML content from: {}", html_file.display());
         let read_start = Instant::now();
-        let html_content = read_to_string(html_file).context("Failed to read HTML file")?;
+        let html_content =
+            read_to_string(html_file).io_path_context("Failed to read HTML file", html_file)?;
         let read_duration = read_start.elapsed();
         info!(
             "Read HTML content ({} bytes) in {:.2?}",
             html_content.len(),
             read_duration
         );
+        let input_bytes = html_content.len() as u64;
+
+        // Pages Jina Reader cannot fetch are converted locally
+        let local_reason = match self.config.transform_md_using {
+            TransformMethod::JinaReader => self.jina_reader_unavailable(source_url),
+            _ => None,
+        };
+        let uses_jina_reader =
+            matches!(self.config.transform_md_using, TransformMethod::JinaReader) && local_reason.is_none();
+
+        let mut extraction = None;
+        let html_content = if self.config.extraction.enabled && !uses_jina_reader {
+            let (content, found) = self.extract_content(&html_content, md_file)?;
+            extraction = Some(found);
+            content
+        } else {
+            html_content
+        };
 
         // Convert to Markdown based on config
         info!(
@@ -156,12 +340,27 @@ impl Converter {
                 info!("Using fast_html2md conversion method");
                 self.convert_with_fast_html2md(&html_content)
             }
-            TransformMethod::JinaReader => {
-                info!("Using Jina Reader conversion method");
-                self.convert_with_jina_reader(html_file).await
-            }
+            TransformMethod::JinaReader => match (&local_reason, source_url) {
+                (None, Some(source_url)) => {
+                    info!("Using Jina Reader conversion method");
+                    self.convert_with_jina_reader(html_file, source_url).await
+                }
+                _ => {
+                    info!(
+                        "Converting {} with htmd instead of Jina Reader: {}",
+                        html_file.display(),
+                        local_reason.as_deref().unwrap_or_default()
+                    );
+                    self.convert_with_htmd(&html_content)
+                }
+            },
         };
 
+        let mut method = match local_reason {
+            Some(_) => ConversionMethod::Htmd,
+            None => ConversionMethod::from(&self.config.transform_md_using),
+        };
+        let mut fallback_reason = local_reason;
         let markdown = match markdown_result {
             Ok(md) => md,
             Err(e) => {
@@ -170,6 +369,8 @@ impl Converter {
                     html_file.display(),
                     e
                 );
+                method = ConversionMethod::SimpleFallback;
+                fallback_reason = Some(e.to_string());
                 // Add title as header even in fallback
                 let title = self.extract_title_from_html(&html_content);
                 let fallback_md = self.simple_html_to_markdown(&html_content);
@@ -191,34 +392,115 @@ impl Converter {
         // Write Markdown content
         debug!("Writing Markdown content to: {}", md_file.display());
         let write_start = Instant::now();
-        write(md_file, markdown).context("Failed to write Markdown file")?;
+        let output_bytes = markdown.len() as u64;
+        write(md_file, markdown).io_path_context("Failed to write Markdown file", md_file)?;
         let write_duration = write_start.elapsed();
         info!("Wrote Markdown content in {:.2?}", write_duration);
 
         let total_duration = start_time.elapsed();
         info!("Total conversion time for file: {:.2?}", total_duration);
 
-        Ok(())
+        Ok(ConversionReport {
+            source: html_file.to_path_buf(),
+            output: Some(md_file.to_path_buf()),
+            requested_method: self.config.transform_md_using.clone(),
+            method: Some(method),
+            fallback_reason,
+            input_bytes,
+            output_bytes,
+            duration_ms: millis(total_duration),
+            error: None,
+            extraction,
+        })
+    }
+
+    /// Isolate the main content of a page, saving it if asked to
+    ///
+    /// The extracted HTML is saved under the name of the Markdown file in the
+    /// extracted-HTML directory of the entry.
+    ///
+    /// # Errors
+    /// Returns an error if the extracted HTML cannot be saved
+    fn extract_content(&self, html: &str, md_file: &Path) -> Result<(String, ContentExtraction)> {
+        let (content, mut extraction) = extract_main_content(html);
+        debug!(
+            "Extracted {} of {} characters of text with {:?} ({})",
+            extraction.text_chars,
+            extraction.page_text_chars,
+            extraction.method,
+            extraction.element.as_deref().unwrap_or("whole page")
+        );
+
+        if self.config.extraction.save_extracted
+            && let Some(entry_dir) = md_file.parent().and_then(Path::parent)
+            && let Some(stem) = md_file.file_stem()
+        {
+            let extracted_dir = entry_dir.join(&self.config.output_parts_extracted_suffix);
+            create_dir_all(&extracted_dir)
+                .io_path_context("Failed to create extracted HTML directory", &extracted_dir)?;
+            let extracted_file = extracted_dir.join(format!("{}.html", stem.to_string_lossy()));
+            write(&extracted_file, &content).io_path_context("Failed to write extracted HTML", &extracted_file)?;
+            extraction.saved_html = Some(extracted_file);
+        }
+        Ok((content, extraction))
+    }
+
+    /// Convert a linked document to Markdown with the extractor for its format
+    ///
+    /// There is no fallback: a document that cannot be extracted is reported
+    /// as failed.
+    pub fn convert_document(
+        &self,
+        doc_file: &Path,
+        md_file: &Path,
+        document_type: DocumentType,
+    ) -> Result<ConversionReport> {
+        let start_time = Instant::now();
+        info!("Extracting {:?} document: {}", document_type, doc_file.display());
+
+        let content = fs::read(doc_file).io_path_context("Failed to read document", doc_file)?;
+        let markdown = extract_markdown(document_type, &content)?;
+
+        let output_bytes = markdown.len() as u64;
+        write(md_file, markdown).io_path_context("Failed to write Markdown file", md_file)?;
+
+        let total_duration = start_time.elapsed();
+        info!("Extracted {} in {:.2?}", doc_file.display(), total_duration);
+
+        Ok(ConversionReport {
+            source: doc_file.to_path_buf(),
+            output: Some(md_file.to_path_buf()),
+            requested_method: self.config.transform_md_using.clone(),
+            method: Some(ConversionMethod::from(document_type)),
+            fallback_reason: None,
+            input_bytes: content.len() as u64,
+            output_bytes,
+            duration_ms: millis(total_duration),
+            error: None,
+            extraction: None,
+        })
     }
 
     /// Convert HTML to Markdown using htmd
+    #[cfg_attr(not(feature = "htmd"), allow(unused_variables))]
     fn convert_with_htmd(&self, html: &str) -> Result<String> {
         #[cfg(feature = "htmd")]
         {
             debug!("Converting HTML to Markdown with htmd feature");
             let md = htmd::HtmlToMarkdown::new()
                 .convert(html)
-                .map_err(|e| anyhow::anyhow!("htmd conversion failed: {}", e))?;
+                .map_err(|e| DoclingError::conversion(format!("htmd conversion failed: {}", e)))?;
             Ok(md)
         }
 
         #[cfg(not(feature = "htmd"))]
         {
-            Err(anyhow::anyhow!("htmd feature is not enabled"))
+            Err(DoclingError::conversion("htmd feature is not enabled"))
         }
     }
 
     /// Convert HTML to Markdown using fast_html2md
+    #[cfg_attr(not(feature = "fast-html2md"), allow(unused_variables))]
     fn convert_with_fast_html2md(&self, html: &str) -> Result<String> {
         #[cfg(feature = "fast-html2md")]
         {
@@ -227,14 +509,17 @@ impl Converter {
             let result = std::panic::catch_unwind(|| fast_html2md::convert_html(html));
             match result {
                 Ok(Ok(md)) => Ok(md),
-                Ok(Err(e)) => Err(anyhow::anyhow!("fast_html2md conversion failed: {}", e)),
-                Err(_) => Err(anyhow::anyhow!("fast_html2md conversion panicked")),
+                Ok(Err(e)) => Err(DoclingError::conversion(format!(
+                    "fast_html2md conversion failed: {}",
+                    e
+                ))),
+                Err(_) => Err(DoclingError::conversion("fast_html2md conversion panicked")),
             }
         }
 
         #[cfg(not(feature = "fast-html2md"))]
         {
-            Err(anyhow::anyhow!("fast-html2md feature is not enabled"))
+            Err(DoclingError::conversion("fast-html2md feature is not enabled"))
         }
     }
 
@@ -244,7 +529,6 @@ impl Converter {
     fn simple_html_to_markdown(&self, html: &str) -> String {
         warn!("Using simple fallback HTML to Markdown converter.");
         // Basic implementation focusing on text extraction and minimal formatting
-        let mut output = String::new();
         let body_content = if let Some(body_start) = html.to_lowercase().find("<body") {
             if let Some(body_content_start) = html[body_start..].find('>') {
                 let real_body_start = body_start + body_content_start + 1;
@@ -283,7 +567,7 @@ impl Converter {
             .filter(|line| !line.is_empty())
             .collect();
 
-        output = lines.join("\n\n"); // Add double newline between potential paragraphs
+        let output = lines.join("\n\n"); // Add double newline between potential paragraphs
 
         output.trim().to_string()
     }
@@ -293,7 +577,6 @@ impl Converter {
     fn simple_html_to_markdown(&self, html: &str) -> String {
         warn!("Using simple fallback HTML to Markdown converter due to primary method failure.");
         // Basic implementation focusing on text extraction and minimal formatting
-        let mut output = String::new();
         let body_content = if let Some(body_start) = html.to_lowercase().find("<body") {
             if let Some(body_content_start) = html[body_start..].find('>') {
                 let real_body_start = body_start + body_content_start + 1;
@@ -334,55 +617,47 @@ impl Converter {
             .filter(|line| !line.is_empty())
             .collect();
 
-        output = lines.join("\n\n"); // Add double newline between potential paragraphs
+        let output = lines.join("\n\n"); // Add double newline between potential paragraphs
 
         output.trim().to_string()
     }
 
     /// Extract title from HTML content
     fn extract_title_from_html(&self, html: &str) -> Option<String> {
-        if let Some(title_start) = html.to_lowercase().find("<title>") {
-            if let Some(title_end) = html.to_lowercase()[title_start..].find("</title>") {
-                let title_content = &html[title_start + 7..title_start + title_end];
-                // Decode HTML entities in title
-                return Some(html_escape::decode_html_entities(title_content.trim()).to_string());
-            }
+        if let Some(title_start) = html.to_lowercase().find("<title>")
+            && let Some(title_end) = html.to_lowercase()[title_start..].find("</title>")
+        {
+            let title_content = &html[title_start + 7..title_start + title_end];
+            // Decode HTML entities in title
+            return Some(html_escape::decode_html_entities(title_content.trim()).to_string());
         }
         None
     }
 
+    /// Why Jina Reader cannot convert a page, if it cannot
+    ///
+    /// Jina fetches the page itself, so it needs the URL recorded by the
+    /// crawler, cannot reach local files, would make rebuilds depend on the
+    /// network and must not be handed the entry's credentials.
+    fn jina_reader_unavailable(&self, source_url: Option<&str>) -> Option<String> {
+        let reason = match source_url {
+            None => "no source URL is recorded in the manifest",
+            Some(url) if url.starts_with("file:") => "Jina Reader cannot fetch local files",
+            Some(_) if self.reconvert => "Jina Reader is not used when converting offline",
+            Some(_) if self.auth.is_some() => "Jina Reader is not used for entries with credentials",
+            Some(_) => return None,
+        };
+        Some(reason.to_string())
+    }
+
     /// Convert HTML to Markdown using Jina Reader
-    async fn convert_with_jina_reader(&self, html_file: &Path) -> Result<String> {
+    async fn convert_with_jina_reader(&self, html_file: &Path, original_url: &str) -> Result<String> {
         let start_time = Instant::now();
         info!(
             "Starting Jina Reader conversion for file: {}",
             html_file.display()
         );
-
-        // Extract original URL from filename (assuming crawler saved it like this)
-        info!("Extracting original URL from filename");
-        let filename = html_file.file_stem().unwrap().to_string_lossy();
-        debug!("File stem: {}", filename);
-
-        // Attempt to reconstruct the original URL from the filename format `host_path_parts`
-        let url_string = filename.replace('_', "/").replace("-slash-", "/"); // Basic reconstruction attempt
-        let original_url = match url::Url::parse(&format!("https://{}", url_string)) {
-            Ok(url) => url.to_string(),
-            Err(_) => {
-                warn!(
-                    "Could not reliably reconstruct URL from filename '{}', using raw HTML content for Jina (might fail)",
-                    filename
-                );
-                // Fallback: Send raw HTML content? Jina might not support this well.
-                // Or return error? Let's try returning an error as it's unlikely to work.
-                return Err(anyhow::anyhow!(
-                    "Cannot reconstruct URL from filename: {}",
-                    filename
-                ));
-            }
-        };
-
-        info!("Reconstructed original URL: {}", original_url);
+        info!("Original URL from manifest: {}", original_url);
 
         // Prefix with Jina Reader URL
         let jina_url = format!("https://r.jina.ai/{}", original_url);
@@ -415,11 +690,11 @@ impl Converter {
                         .await
                         .unwrap_or_else(|_| "Failed to read error body".to_string());
                     error!("Jina Reader error body: {}", error_body);
-                    return Err(anyhow::anyhow!(
-                        "Jina Reader error: HTTP {} - {}",
-                        status,
-                        error_body
-                    ));
+                    return Err(DoclingError::Http {
+                        status: Some(status.as_u16()),
+                        url: jina_url,
+                        source: Some(error_body.into()),
+                    });
                 }
 
                 info!("Reading response body...");
@@ -444,10 +719,7 @@ impl Converter {
                             "Failed to read response body after {:.2?}: {}",
                             body_duration, e
                         );
-                        Err(anyhow::anyhow!(
-                            "Failed to read Jina Reader response: {}",
-                            e
-                        ))
+                        Err(DoclingError::http_transport(jina_url, e))
                     }
                 }
             }
@@ -457,18 +729,84 @@ impl Converter {
                     "Failed to get response from Jina Reader after {:.2?}: {}",
                     req_duration, e
                 );
-                let error_message = if e.is_timeout() {
-                    format!(
+                if e.is_timeout() {
+                    warn!(
                         "Jina Reader request timed out after {:.2?}: {}",
                         req_duration, e
-                    )
+                    );
                 } else if e.is_connect() {
-                    format!("Failed to connect to Jina Reader: {}", e)
-                } else {
-                    format!("Jina Reader request failed: {}", e)
-                };
-                Err(anyhow::anyhow!(error_message))
+                    warn!("Failed to connect to Jina Reader: {}", e);
+                }
+                Err(DoclingError::http_transport(jina_url, e))
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract::ExtractionSettings;
+
+    #[tokio::test]
+    async fn offline_conversions_use_htmd_instead_of_jina_reader() {
+        let dir = std::env::temp_dir().join(format!("docling-convert-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let html_file = dir.join("page.html");
+        let md_file = dir.join("page.md");
+        std::fs::write(&html_file, "<html><body><main><h1>Guide</h1><p>Install it.</p></main></body></html>").unwrap();
+        let config = DoclingConfig {
+            transform_md_using: TransformMethod::JinaReader,
+            ..DoclingConfig::default()
+        };
+        let converter = Converter::new(config).unwrap().with_reconvert(true);
+
+        let report = converter
+            .convert_file(&html_file, &md_file, Some("https://example.com/guide"))
+            .await
+            .unwrap();
+        assert_eq!(report.requested_method, TransformMethod::JinaReader);
+        assert!(report.fallback_reason.is_some());
+        if cfg!(feature = "htmd") {
+            assert_eq!(report.method, Some(ConversionMethod::Htmd));
+            assert!(report.fallback_reason.unwrap().contains("offline"));
+        }
+        assert!(std::fs::read_to_string(&md_file).unwrap().contains("Install it."));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn extracted_html_is_saved_with_the_entry() {
+        let dir = std::env::temp_dir().join(format!("docling-extracted-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let config = DoclingConfig {
+            extraction: ExtractionSettings {
+                enabled: true,
+                save_extracted: true,
+            },
+            ..DoclingConfig::default()
+        };
+        let html_dir = dir.join(&config.output_parts_html_suffix);
+        let md_dir = dir.join(&config.output_parts_markdown_suffix);
+        std::fs::create_dir_all(&html_dir).unwrap();
+        std::fs::create_dir_all(&md_dir).unwrap();
+        let html_file = html_dir.join("guide.html");
+        let md_file = md_dir.join("guide.md");
+        std::fs::write(
+            &html_file,
+            "<html><body><nav><a href=\"/\">Home</a><a href=\"/blog\">Blog</a></nav>\
+             <main><h1>Guide</h1><p>Install the tool with cargo and run it from the workspace.</p></main>\
+             <footer>Copyright</footer></body></html>",
+        )
+        .unwrap();
+        let converter = Converter::new(config.clone()).unwrap();
+
+        let report = converter.convert_file(&html_file, &md_file, None).await.unwrap();
+        let extracted_file = dir.join(&config.output_parts_extracted_suffix).join("guide.html");
+        assert_eq!(report.extraction.unwrap().saved_html, Some(extracted_file.clone()));
+        let extracted = std::fs::read_to_string(&extracted_file).unwrap();
+        assert!(extracted.contains("Install the tool"));
+        assert!(!extracted.contains("Copyright"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,60 +1,205 @@
 //! Web crawler functionality for downloading web pages and extracting links
 //!
 //! This module provides functionality to:
-//! - Crawl websites to a specified depth using the `spider` crate
-//! - Download and save HTML content
-//! - Extract and download media files (images)
-//! - Support configurable parameters like crawl depth, delay, user agent, robots.txt respect
-//! - Handle URL normalization and conversion to filenames
+//! - Crawl websites to a specified depth, breadth first
+//! - Seed the crawl from the site's sitemaps, or crawl only the sitemap pages
+//! - Download and save HTML content, and linked documents of the entry's
+//!   document types
+//! - Extract media files (images, inline SVGs, backgrounds, ...) and save them
+//!   to the shared media store
+//! - Respect robots.txt (including `Crawl-delay`) if configured
+//! - Re-fetch conditionally using validators remembered in the entry manifest
+//! - Fetch every page once whatever form its URL takes, and leave out pages
+//!   that duplicate another one
 //!
 //! The main entry point is the `Crawler` struct, which orchestrates the entire
 //! crawling process for a given URL entry.
 
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::fs::create_dir_all;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::Duration;
 
-use anyhow::{Context, Result};
 use chrono::Utc;
-use reqwest::Client;
-use spider::{
-    configuration::{Configuration, RequestConfig}, // Import RequestConfig
-    website::Website,
-};
-use tokio::fs::File;
-use tokio::io::AsyncWriteExt;
-use tokio::sync::{Semaphore, broadcast, mpsc};
-use tokio::time::sleep;
+use reqwest::header::{self, HeaderMap, HeaderValue};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
 use tracing::{debug, error, info, warn};
 use url::Url;
 
-use crate::{CrawlStatus, DoclingConfig, UrlEntry};
+use crate::auth::RequestAuth;
+use crate::budget::{BudgetLimit, BudgetTracker};
+use crate::canonical::{find_duplicates, shingle_sketch, AliasReason, PageFingerprint, UrlAliases, UrlNormalization};
+use crate::charset::decode_html;
+use crate::events::{ProgressEvent, ProgressReporter};
+use crate::fetch::{FetchResponse, Fetcher, Validators};
+use crate::git::GitSource;
+use crate::html::{
+    declared_version, extract_alternates, extract_canonical, extract_links, extract_media, extract_switcher_options,
+    visible_text, Link, MediaSource,
+};
+use crate::language::{page_language, primary_subtag, url_language, LanguageSettings};
+use crate::links::LinkGraph;
+use crate::local;
+use crate::manifest::{
+    content_hash, is_hashed_filename, relative_to, url_to_filename, Manifest, MediaRecord, PageRecord,
+};
+use crate::media::{media_key, MediaBudget, MediaDownloader, MediaIndex, MediaReference, MediaStore};
+use crate::report::{PageOutcome, PageReport};
+use crate::robots::Robots;
+use crate::plan::{CrawlPlan, Exclusion, PlannedPage};
+use crate::scope::{Scope, ScopeDecision};
+use crate::sitemap;
+use crate::version::{self, DocsVersion, VersionFilter};
+use crate::warc::{WarcIndex, WarcReplay, WarcWriter};
+use crate::{CrawlStatus, DoclingConfig, DoclingError, DocumentType, IoContext, Result, UrlEntry};
 
-/// Represents a web page with its URL and HTML content
-///
-/// This is used for communication between crawler components.
-#[derive(Clone)]
-struct Page {
-    /// The URL of the page
+/// A page waiting in the crawl frontier
+#[derive(Debug, Clone)]
+struct QueuedPage {
+    /// URL of the page
     url: Url,
-    /// The HTML content of the page
+    /// Link depth from the entry URL
+    depth: u32,
+    /// URL of the page the link was found on
+    parent: Option<String>,
+    /// Language the `hreflang` of a link to the page declared, if any
+    language: Option<String>,
+}
+
+/// A page after its fetch finished
+struct FetchedPage {
+    /// The page that was fetched
+    page: QueuedPage,
+    /// Response, or the transport error
+    response: Result<FetchResponse>,
+    /// Validators sent with the request
+    validators: Option<Validators>,
+}
+
+/// HTML of a handled page, to find the pages it leads to
+struct PageHtml {
+    /// URL the page was served from
+    final_url: Url,
+    /// HTML of the page
     body: String,
+    /// Whether the page is in a kept language; only the `hreflang`
+    /// alternates of other pages are followed
+    kept: bool,
+}
+
+/// URLs queued during a crawl, by normalised key
+struct SeenUrls {
+    /// Rules deciding when two URLs name the same page
+    normalization: UrlNormalization,
+    /// Queued URL for every key
+    queued: HashMap<String, Url>,
+    /// URLs that were found in another form than the one queued
+    aliases: UrlAliases,
+    /// Languages `hreflang` alternates declared for pages, by key
+    hreflang: HashMap<String, String>,
 }
 
-/// Crawler handles web crawling and content downloading using the `spider` crate
+impl SeenUrls {
+    fn new(normalization: UrlNormalization) -> Self {
+        Self {
+            normalization,
+            queued: HashMap::new(),
+            aliases: UrlAliases::default(),
+            hreflang: HashMap::new(),
+        }
+    }
+
+    /// Remember a URL queued as it is
+    fn insert(&mut self, url: &Url) {
+        let key = self.normalization.key(url);
+        self.queued.entry(key).or_insert_with(|| url.clone());
+    }
+
+    /// The page to queue for a link, or `None` if its page is already
+    /// queued; other forms of a queued page are recorded as aliases
+    fn admit(&mut self, url: &Url, depth: u32, parent: Option<&str>) -> Option<QueuedPage> {
+        let key = self.normalization.key(url);
+        let (queued, is_new) = match self.queued.get(&key) {
+            Some(queued) => (queued.clone(), false),
+            None => {
+                let queued = self.normalization.normalize(url);
+                self.queued.insert(key.clone(), queued.clone());
+                (queued, true)
+            }
+        };
+        self.aliases.insert(url.as_str(), queued.as_str(), AliasReason::Normalized);
+        is_new.then(|| QueuedPage {
+            url: queued,
+            depth,
+            parent: parent.map(str::to_string),
+            language: self.hreflang.get(&key).cloned(),
+        })
+    }
+
+    /// Remember the language an `hreflang` alternate declares for a page
+    fn set_hreflang(&mut self, url: &Url, tag: &str) {
+        if let Some(language) = primary_subtag(tag) {
+            self.hreflang.entry(self.normalization.key(url)).or_insert(language);
+        }
+    }
+
+    /// Language a page is expected in before it is fetched, from the
+    /// `hreflang` of links to it or from its URL
+    fn expected_language(&self, url: &Url) -> Option<String> {
+        self.hreflang
+            .get(&self.normalization.key(url))
+            .cloned()
+            .or_else(|| url_language(url))
+    }
+
+    /// Whether the page of a URL is already queued
+    fn contains(&self, url: &Url) -> bool {
+        self.queued.contains_key(&self.normalization.key(url))
+    }
+
+    /// The URL a link leads to: the queued form of its page, or its normalised form
+    fn resolve(&self, url: &Url) -> Url {
+        self.queued
+            .get(&self.normalization.key(url))
+            .cloned()
+            .unwrap_or_else(|| self.normalization.normalize(url))
+    }
+}
+
+/// Outcome of crawling an entry
+#[derive(Debug, Default)]
+pub struct CrawlResult {
+    /// A report for every page the crawler requested
+    pub pages: Vec<PageReport>,
+    /// Limit of the crawl budget that stopped the crawl early, if any
+    pub truncated: Option<BudgetLimit>,
+    /// WARC archive of the crawl, if one was written
+    pub warc_file: Option<PathBuf>,
+    /// Whether pages became or stopped being duplicates of other pages, or
+    /// were left out for their language or docs version after being kept before
+    pub selection_changed: bool,
+    /// Documentation version the crawl was confined to, if the entry names one
+    pub docs_version: Option<DocsVersion>,
+}
+
+/// Crawler handles web crawling and content downloading
 ///
 /// This is the main component that orchestrates the crawling process:
-/// 1. Configures and initializes a spider `Website` for a URL entry
-/// 2. Crawls the website to the specified depth, respecting robots.txt if configured
-/// 3. Downloads and saves HTML content for allowed pages
-/// 4. Extracts and downloads media files (images) from downloaded pages
-/// 5. Updates the URL entry with status information
+/// 1. Loads robots.txt for the entry's host, if configured
+/// 2. Crawls the website breadth first to the entry's depth, staying in scope,
+///    starting from the entry URL and the pages listed in its sitemaps
+/// 3. Downloads and saves HTML content, skipping pages that did not change
+/// 4. Extracts and downloads media files of the configured kinds from downloaded pages
+/// 5. Marks pages that duplicate another page and records their URL aliases
+/// 6. Records every page in the entry manifest and the link graph, and updates the entry
 pub struct Crawler {
     /// Configuration for the crawler
     config: DoclingConfig,
-    /// HTTP client for making requests (used for image downloads)
-    client: Client,
+    /// HTTP fetcher for pages, robots.txt and media
+    fetcher: Fetcher,
+    /// Receives progress events for saved pages and media
+    progress: ProgressReporter,
 }
 
 impl Crawler {
@@ -69,14 +214,34 @@ impl Crawler {
     /// # Errors
     /// Returns an error if the HTTP client cannot be created
     pub fn new(config: DoclingConfig) -> Result<Self> {
-        // Create HTTP client with user agent and other settings for image downloads
-        let client = Client::builder()
-            .user_agent(&config.user_agent)
-            .timeout(Duration::from_secs(30)) // Timeout for image downloads
-            .build()
-            .context("Failed to create HTTP client for media")?;
+        let fetcher = Fetcher::new(&config)?;
+
+        Ok(Self {
+            config,
+            fetcher,
+            progress: ProgressReporter::noop(),
+        })
+    }
 
-        Ok(Self { config, client })
+    /// Report progress events to the given reporter
+    pub fn with_progress(mut self, progress: ProgressReporter) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Send the entry's credentials with page, media, robots.txt and sitemap requests
+    pub fn with_auth(mut self, auth: Option<RequestAuth>) -> Self {
+        self.fetcher = self.fetcher.with_auth(auth);
+        self
+    }
+
+    /// Crawl from archived responses instead of the network
+    ///
+    /// The crawl follows the same steps, but every request is answered from
+    /// the archive and no new archive is written.
+    pub fn with_replay(mut self, replay: Option<WarcReplay>) -> Self {
+        self.fetcher = self.fetcher.with_replay(replay);
+        self
     }
 
     /// Processes a URL entry, downloading content and finding links
@@ -84,26 +249,34 @@ impl Crawler {
     /// This is the main entry point for crawling a website. It:
     /// 1. Updates the entry's status
     /// 2. Creates necessary output directories
-    /// 3. Configures and starts the `spider` crate crawler
-    /// 4. Downloads all pages and media files
-    /// 5. Updates the entry with success/failure information
+    /// 3. Crawls the site, sending the validators of the previous run so
+    ///    unchanged pages are answered with `304 Not Modified`
+    /// 4. Downloads all changed pages and media files until the crawl budget
+    ///    runs out; requests in flight are finished when it does, unless
+    ///    their bodies go past the byte limit. If WARC output is enabled,
+    ///    every exchange is archived as well
+    /// 5. Updates the manifest and the entry with success information
+    ///
+    /// Entries with a git source are read from their repository instead.
     ///
     /// # Arguments
     /// * `entry` - The URL entry to process
     ///
     /// # Returns
-    /// Ok(()) if successful
+    /// A report for every page the crawler requested and the budget limit
+    /// that cut the crawl short, if any
     ///
     /// # Errors
-    /// Returns an error if any step in the crawling process fails
-    pub async fn process_entry(&mut self, entry: &mut UrlEntry) -> Result<()> {
+    /// Returns an error if the output directories cannot be created, the
+    /// entry URL is disallowed by robots.txt or the manifest cannot be saved
+    pub async fn process_entry(&mut self, entry: &mut UrlEntry) -> Result<CrawlResult> {
         // Update entry status
         entry.last_try = Some(Utc::now());
 
         // Skip if disabled
         if entry.status == CrawlStatus::Disabled {
             info!("Skipping disabled entry: {}", entry.name);
-            return Ok(());
+            return Ok(CrawlResult::default());
         }
 
         info!("Processing entry: {} ({})", entry.name, entry.url);
@@ -113,380 +286,1748 @@ impl Crawler {
         let html_output_dir = base_output_dir.join(&self.config.output_parts_html_suffix);
         let media_output_dir = base_output_dir.join(&self.config.output_parts_media_suffix);
 
-        create_dir_all(&html_output_dir).context("Failed to create HTML output directory")?;
-        create_dir_all(&media_output_dir).context("Failed to create media output directory")?;
+        create_dir_all(&html_output_dir)
+            .io_path_context("Failed to create HTML output directory", &html_output_dir)?;
+        create_dir_all(&media_output_dir)
+            .io_path_context("Failed to create media output directory", &media_output_dir)?;
+        if !entry.document_types.is_empty() || entry.git.is_some() {
+            let docs_output_dir = base_output_dir.join(&self.config.output_parts_docs_suffix);
+            create_dir_all(&docs_output_dir)
+                .io_path_context("Failed to create document output directory", &docs_output_dir)?;
+        }
 
         // Create error directory if it doesn't exist
         let error_dir = base_output_dir.join("ERRORS");
-        create_dir_all(&error_dir).context("Failed to create error directory")?;
-
-        // Configure the spider Website
-        let request_config = RequestConfig::new()
-            .with_user_agent(Some(&self.config.user_agent))
-            .with_timeout(Some(Duration::from_secs(30)))
-            .build();
-
-        let spider_config = Configuration::new()
-            .with_respect_robots_txt(self.config.respect_robots_txt)
-            .with_delay(self.config.delay_between_request_in_ms)
-            .with_request_config(Some(request_config))
-            .with_max_depth(entry.crawl_depth as usize)
-            .with_max_concurrent_requests(Some(self.config.max_concurrent_requests as usize))
-            .with_subdomains(false) // Only crawl the specified domain
-            .with_tld(false) // Only crawl the specified domain
-            .build();
-
-        let mut website = Website::new(&entry.url)
-            .with_config(spider_config)
-            .build()
-            .context("Failed to build Website crawler")?;
-
-        // Subscribe to receive pages
-        let mut rx = website.subscribe(100)?; // Buffer size 100
-
-        // Channel to send downloaded pages for image processing
-        let (page_proc_tx, mut page_proc_rx) = mpsc::channel::<Page>(100);
-
-        // Task to handle image downloading and saving HTML
-        let html_dir = html_output_dir.clone();
-        let media_dir = media_output_dir.clone();
-        let client = self.client.clone();
-        let config = self.config.clone();
-        let download_task = tokio::spawn(async move {
-            let media_semaphore = Arc::new(Semaphore::new(config.max_concurrent_requests as usize));
-            let mut crawled_urls = std::collections::HashSet::new();
-
-            while let Some(page) = page_proc_rx.recv().await {
-                let url_string = page.url.to_string();
-                let filename_base = url_to_filename(&page.url);
-                let file_path = html_dir.join(format!("{}.html", filename_base));
-
-                // Save HTML content
-                match File::create(&file_path).await {
-                    Ok(mut file) => {
-                        if let Err(e) = file.write_all(page.body.as_bytes()).await {
-                            error!("Failed to write HTML content for {}: {}", url_string, e);
-                            continue;
-                        }
+        create_dir_all(&error_dir).io_path_context("Failed to create error directory", &error_dir)?;
 
-                        debug!("Saved HTML: {}", url_string);
-                        crawled_urls.insert(url_string.clone());
-
-                        // Extract and download images in a separate task
-                        let url_clone = page.url.clone();
-                        let body_clone = page.body.clone();
-                        let media_dir_clone = media_dir.clone();
-                        let client_clone = client.clone();
-                        let semaphore_clone = Arc::clone(&media_semaphore);
-                        let delay = config.delay_between_request_in_ms;
-
-                        tokio::spawn(async move {
-                            // Acquire semaphore permit
-                            let permit = match semaphore_clone.acquire().await {
-                                Ok(p) => p,
-                                Err(_) => {
-                                    error!("Failed to acquire semaphore permit for image download");
-                                    return;
-                                }
-                            };
-
-                            if let Err(e) = download_images(
-                                &url_clone,
-                                &body_clone,
-                                &client_clone,
-                                &media_dir_clone,
-                                delay,
-                            )
-                            .await
-                            {
-                                warn!("Failed to download images for {}: {}", url_clone, e);
-                            }
-                            drop(permit); // Release permit
-                        });
-                    }
-                    Err(e) => {
-                        error!("Failed to create HTML file for {}: {}", url_string, e);
-                    }
-                }
+        // Git sources are read from the repository instead of being crawled
+        if let Some(source) = entry.git.clone() {
+            return self.read_git_source(entry, &source, &base_output_dir).await;
+        }
+
+        let Some(entry_scope) = self.entry_scope(entry).await? else {
+            return Err(DoclingError::Robots {
+                url: entry.url.clone(),
+            });
+        };
+        let scope = &entry_scope.scope;
+        let max_concurrent = self.config.max_concurrent_requests.max(1) as usize;
+
+        // Validators and hashes from the previous run
+        let manifest = Manifest::load(&base_output_dir)?;
+        let max_media_bytes = self
+            .config
+            .budget
+            .max_media_bytes
+            .unwrap_or(self.config.max_media_bytes_per_entry);
+        // Archive the exchanges of web crawls if asked to, unless they come from an archive
+        let warc = if (self.config.write_warc || entry.warc)
+            && matches!(scope.root().scheme(), "http" | "https")
+            && !self.fetcher.is_replaying()
+        {
+            let warc_dir = base_output_dir.join(&self.config.output_parts_warc_suffix);
+            create_dir_all(&warc_dir).io_path_context("Failed to create WARC output directory", &warc_dir)?;
+            let writer = WarcWriter::create(&warc_dir, &base_output_dir, &entry.name, &self.config.user_agent)?;
+            info!("Archiving crawl of {} to {}", entry.name, writer.path().display());
+            Some(writer)
+        } else {
+            None
+        };
+        let warc_index = match &warc {
+            Some(_) => WarcIndex::load(&base_output_dir)?,
+            None => WarcIndex::default(),
+        };
+
+        let downloader = MediaDownloader {
+            fetcher: self.fetcher.clone(),
+            store: MediaStore::new(&self.config.outputs_path),
+            media_dir: media_output_dir.clone(),
+            entry_dir: base_output_dir.clone(),
+            known_media: Arc::new(manifest.media.clone()),
+            budget: Arc::new(MediaBudget::new(self.config.max_media_file_bytes, max_media_bytes)),
+            progress: self.progress.clone(),
+            warc: warc.clone(),
+        };
+        let mut saver = PageSaver {
+            crawler: self,
+            entry_dir: &base_output_dir,
+            document_types: &entry.document_types,
+            scope,
+            manifest,
+            page_reports: Vec::new(),
+            link_graph: LinkGraph::new(scope.root().as_str()),
+            warc: warc.clone(),
+            warc_index,
+            downloader,
+            media_semaphore: Arc::new(Semaphore::new(max_concurrent)),
+            media_references: BTreeMap::new(),
+            media_tasks: JoinSet::new(),
+        };
+        let Discovery {
+            seen,
+            declared,
+            mut truncated,
+        } = self.discover(entry, &entry_scope, &mut saver).await;
+        let PageSaver {
+            mut manifest,
+            mut page_reports,
+            mut link_graph,
+            mut warc_index,
+            downloader,
+            media_references,
+            mut media_tasks,
+            ..
+        } = saver;
+        let EntryScope {
+            versions, docs_version, ..
+        } = entry_scope;
+
+        // Wait for all media downloads and record them in the manifest and media index
+        let mut run_media = Vec::new();
+        while let Some(joined) = media_tasks.join_next().await {
+            match joined {
+                Ok(Ok(records)) => run_media.extend(records),
+                Ok(Err(e)) => warn!("Failed to download media for {}: {}", entry.name, e),
+                Err(e) => error!("Media download task failed for {}: {}", entry.name, e),
             }
+        }
 
-            crawled_urls
-        });
+        if downloader.budget.used() > 0 {
+            debug!(
+                "Media of entry {} uses {} of {} bytes",
+                entry.name,
+                downloader.budget.used(),
+                max_media_bytes
+            );
+        }
+        if truncated.is_none() && downloader.budget.is_exhausted() {
+            warn!("Media budget of {} ran out, some media was not downloaded", entry.name);
+            self.progress.emit(ProgressEvent::CrawlTruncated {
+                limit: BudgetLimit::MediaBytes,
+            });
+            truncated = Some(BudgetLimit::MediaBytes);
+        }
+        MediaIndex::build(&run_media, &media_references).save(&base_output_dir)?;
+        for record in run_media {
+            manifest.media.insert(record.url.clone(), record);
+        }
+        let duplicates_changed =
+            self.mark_duplicates(&base_output_dir, &mut manifest, &mut page_reports, &declared, seen.aliases)?;
+        let languages_changed = self.drop_other_languages(&base_output_dir, &mut manifest, &page_reports)?;
+        let versions_changed = match &versions {
+            Some(versions) => self.drop_other_versions(&base_output_dir, &mut manifest, versions)?,
+            None => false,
+        };
+        let legacy_removed = self.drop_legacy_files(&base_output_dir, &manifest)?;
+        self.drop_unreferenced_media(&base_output_dir, &manifest)?;
+        manifest.save(&base_output_dir)?;
+        link_graph.finish();
+        link_graph.save(&base_output_dir)?;
+        if warc.is_some() {
+            warc_index.link_saved_paths(&manifest);
+            warc_index.save(&base_output_dir)?;
+        }
+
+        info!(
+            "Successfully processed {} URLs for entry: {} ({} unchanged)",
+            page_reports.len(),
+            entry.name,
+            page_reports
+                .iter()
+                .filter(|page| page.outcome == PageOutcome::Unchanged)
+                .count()
+        );
+        entry.last_download = Some(Utc::now());
+        entry.version += 1;
+        entry.status = CrawlStatus::Enabled; // Mark as success if crawl/download finishes
+
+        Ok(CrawlResult {
+            pages: page_reports,
+            truncated,
+            warc_file: warc.map(|writer| writer.path().to_path_buf()),
+            selection_changed: duplicates_changed || languages_changed || versions_changed || legacy_removed,
+            docs_version,
+        })
+    }
 
-        // Start crawling in a separate task
-        let crawl_handle = tokio::spawn(async move {
-            website.crawl().await;
-            website // Return website to get stats later if needed
+    /// Discover the pages of an entry without saving anything
+    ///
+    /// Follows the same steps as [`Crawler::process_entry`] (robots.txt,
+    /// sitemaps, links within scope, depth and budget), fetching pages only
+    /// to find their links. No pages, media, archives or manifest are
+    /// written and the entry is left alone. The files of a git source are
+    /// listed without reading them.
+    ///
+    /// # Arguments
+    /// * `entry` - The URL entry to plan
+    ///
+    /// # Returns
+    /// The URLs a crawl would fetch and the URLs it would leave out
+    ///
+    /// # Errors
+    /// Returns an error if the entry URL or its git source cannot be read
+    pub async fn plan_entry(&mut self, entry: &UrlEntry) -> Result<CrawlPlan> {
+        info!("Planning crawl of entry: {} ({})", entry.name, entry.url);
+        if let Some(source) = &entry.git {
+            return self.plan_git_source(entry, source).await;
+        }
+
+        let Some(entry_scope) = self.entry_scope(entry).await? else {
+            let root = Scope::for_entry(entry)?.root().clone();
+            let mut plan = CrawlPlan::new(&entry.name, root.as_str());
+            plan.exclude(&root, 0, None, Exclusion::Robots);
+            return Ok(plan);
+        };
+        let mut planner = PagePlanner {
+            languages: &self.config.languages,
+            plan: CrawlPlan::new(&entry.name, entry_scope.scope.root().as_str()),
+        };
+        let discovery = self.discover(entry, &entry_scope, &mut planner).await;
+        let mut plan = planner.plan;
+        plan.truncated = discovery.truncated;
+        plan.docs_version = entry_scope.docs_version;
+        info!(
+            "Crawl of {} would fetch {} pages and leave out {} URLs",
+            entry.name,
+            plan.pages.len(),
+            plan.excluded.len()
+        );
+        Ok(plan)
+    }
+
+    /// Load robots.txt for the entry and confine its scope to the docs version it names
+    ///
+    /// # Returns
+    /// The scope and rules of the crawl, or `None` if robots.txt disallows the entry URL
+    ///
+    /// # Errors
+    /// Returns an error if the entry URL is invalid or its version cannot be determined
+    async fn entry_scope(&self, entry: &UrlEntry) -> Result<Option<EntryScope>> {
+        let scope = Scope::for_entry(entry)?;
+        let robots = if self.config.respect_robots_txt {
+            Robots::fetch(&self.fetcher, scope.root(), &self.config.user_agent).await
+        } else {
+            Robots::allow_all()
+        };
+        if !robots.is_allowed(scope.root()) {
+            return Ok(None);
+        }
+
+        // Honour a Crawl-delay longer than the configured delay; the fetcher
+        // spaces out page and media requests to the host
+        if let Some(crawl_delay) = robots.crawl_delay() {
+            self.fetcher.rate_limiter().set_min_interval(scope.root(), crawl_delay);
+        }
+        let (scope, versions, docs_version) = self.confine_version(scope, &robots).await?;
+        Ok(Some(EntryScope {
+            scope,
+            robots,
+            versions,
+            docs_version,
+        }))
+    }
+
+    /// Crawl the pages of an entry breadth first, handing each fetched page to `visitor`
+    ///
+    /// Starts from the entry URL and the in-scope pages of its sitemaps, and
+    /// keeps up to `max_concurrent_requests` fetches in flight while the
+    /// budget lasts. The `hreflang` alternates, links and canonical URL of
+    /// every page the visitor returns are queued if the rules allow them;
+    /// the URLs left out are reported to the visitor, including the ones
+    /// still queued when the budget ran out.
+    async fn discover(&self, entry: &UrlEntry, entry_scope: &EntryScope, visitor: &mut impl PageVisitor) -> Discovery {
+        let scope = &entry_scope.scope;
+        let robots = &entry_scope.robots;
+        let max_concurrent = self.config.max_concurrent_requests.max(1) as usize;
+        let rules = CrawlRules {
+            entry,
+            scope,
+            robots,
+            languages: &self.config.languages,
+            versions: entry_scope.versions.as_ref(),
+        };
+        let mut frontier = VecDeque::new();
+        let mut seen = SeenUrls::new(self.config.normalization.clone());
+        seen.insert(scope.root());
+        // Canonical URLs declared by the crawled pages
+        let mut declared: HashMap<String, String> = HashMap::new();
+        frontier.push_back(QueuedPage {
+            url: scope.root().clone(),
+            depth: 0,
+            parent: None,
+            language: None,
         });
+        self.progress.emit(ProgressEvent::PageDiscovered {
+            url: scope.root().to_string(),
+        });
+
+        // Seed the frontier with the in-scope pages listed in sitemaps; they
+        // count as one level below the entry URL
+        if entry.discovery.uses_sitemaps() {
+            let discovery = sitemap::discover(&self.fetcher, scope.root(), robots).await;
+            if discovery.is_empty() && !entry.discovery.follows_links() {
+                warn!(
+                    "No sitemap pages found for entry {}, only the entry URL will be fetched",
+                    entry.name
+                );
+            }
+            for page in discovery.pages {
+                let sitemap = page.sitemap.as_str();
+                if let Some(exclusion) = rules.exclusion(&seen, &page.url) {
+                    visitor.exclude(&page.url, 1, Some(sitemap), exclusion);
+                } else if let Some(queued) = seen.admit(&page.url, 1, Some(sitemap)) {
+                    self.progress.emit(ProgressEvent::PageDiscovered { url: queued.url.to_string() });
+                    frontier.push_back(queued);
+                }
+            }
+        }
+
+        let mut tracker = BudgetTracker::new(self.config.budget.clone());
+        let mut truncated = None;
+        let mut fetches: JoinSet<FetchedPage> = JoinSet::new();
+
+        loop {
+            // Keep up to `max_concurrent` fetches in flight while the budget lasts
+            while fetches.len() < max_concurrent && truncated.is_none() && !frontier.is_empty() {
+                if let Some(limit) = tracker.exceeded() {
+                    warn!(
+                        "Crawl of {} stopped early ({}), {} pages left in the frontier",
+                        entry.name,
+                        limit,
+                        frontier.len()
+                    );
+                    self.progress.emit(ProgressEvent::CrawlTruncated { limit });
+                    truncated = Some(limit);
+                    break;
+                }
+                let Some(page) = frontier.pop_front() else {
+                    break;
+                };
+                tracker.add_page();
+                let validators = visitor.validators(&page.url);
+                let fetcher = self.fetcher.clone();
+                let allowance = tracker.allowance();
+                fetches.spawn(async move {
+                    let response = fetcher.fetch_within(&page.url, validators.as_ref(), &allowance).await;
+                    FetchedPage {
+                        page,
+                        response,
+                        validators,
+                    }
+                });
+            }
+
+            let Some(joined) = fetches.join_next().await else {
+                break;
+            };
+            let fetched = match joined {
+                Ok(fetched) => fetched,
+                Err(e) => {
+                    error!("Fetch task failed for {}: {}", entry.name, e);
+                    continue;
+                }
+            };
+
+            let depth = fetched.page.depth;
+            let page_url = fetched.page.url.to_string();
+            let Some(PageHtml { final_url, body, kept }) = visitor.visit(fetched) else {
+                continue;
+            };
+
+            // Translations of the page in the kept languages are crawled at the same depth
+            for queued in queue_alternates(&rules, &mut seen, &final_url, &body, depth, &page_url) {
+                self.progress.emit(ProgressEvent::PageDiscovered { url: queued.url.to_string() });
+                frontier.push_back(queued);
+            }
+            if !kept {
+                continue;
+            }
 
-        // Process pages received from the crawler
-        while let Ok(page_data) = rx.recv().await {
-            if let Some(bytes) = page_data.get_bytes() {
-                match String::from_utf8(bytes.to_vec()) {
-                    Ok(body) => {
-                        let page = Page {
-                            url: Url::parse(page_data.get_url())
-                                .context("Invalid URL from spider")?,
-                            body,
-                        };
-                        if let Err(e) = page_proc_tx.send(page).await {
-                            error!("Failed to send page for processing: {}", e);
+            // Queue the links that belong to the entry
+            let mut links = extract_links(&final_url, &body);
+            if entry.discovery.follows_links() {
+                for link in &links {
+                    if let Some(exclusion) = rules.link_exclusion(&seen, &link.url, depth) {
+                        if !seen.contains(&link.url) {
+                            visitor.exclude(&link.url, depth + 1, Some(&page_url), exclusion);
                         }
+                        continue;
                     }
-                    Err(e) => {
-                        warn!(
-                            "Failed to decode page content for {}: {}",
-                            page_data.get_url(),
-                            e
-                        );
+                    if let Some(queued) = seen.admit(&link.url, depth + 1, Some(&page_url)) {
+                        self.progress.emit(ProgressEvent::PageDiscovered { url: queued.url.to_string() });
+                        frontier.push_back(queued);
                     }
                 }
-            } else {
-                warn!("Received page without content: {}", page_data.get_url());
             }
+
+            // The page it names as canonical is crawled at the same depth and
+            // decides later whether this page is a duplicate
+            if self.config.normalization.honour_canonical
+                && let Some(canonical) = extract_canonical(&final_url, &body)
+            {
+                match rules.exclusion(&seen, &canonical) {
+                    Some(exclusion) => {
+                        if !seen.contains(&canonical) {
+                            visitor.exclude(&canonical, depth, Some(&page_url), exclusion);
+                        }
+                    }
+                    None => {
+                        if let Some(queued) = seen.admit(&canonical, depth, Some(&page_url)) {
+                            self.progress.emit(ProgressEvent::PageDiscovered { url: queued.url.to_string() });
+                            frontier.push_back(queued);
+                        }
+                        let canonical = seen.resolve(&canonical);
+                        if canonical.as_str() != page_url {
+                            declared.insert(page_url.clone(), canonical.to_string());
+                        }
+                    }
+                }
+            }
+
+            // Links lead to the pages they were queued as
+            for link in &mut links {
+                link.url = seen.resolve(&link.url);
+            }
+            visitor.links(&page_url, &final_url, &body, &links, truncated);
         }
 
-        // Ensure the sender is dropped so the receiver task can finish
-        drop(page_proc_tx);
+        // Pages still waiting when the budget ran out
+        if let Some(limit) = truncated {
+            for page in frontier {
+                visitor.exclude(&page.url, page.depth, page.parent.as_deref(), Exclusion::Budget { limit });
+            }
+        }
+        Discovery {
+            seen,
+            declared,
+            truncated,
+        }
+    }
 
-        // Wait for crawling and processing to complete
-        let crawl_result = crawl_handle.await;
-        let download_result = download_task.await;
+    /// List the files a crawl of a git source would read
+    async fn plan_git_source(&self, entry: &UrlEntry, source: &GitSource) -> Result<CrawlPlan> {
+        let commit = source.resolve_commit().await?;
+        let base = source.url()?;
+        let mut plan = CrawlPlan::new(&entry.name, base.as_str());
+        let mut tracker = BudgetTracker::new(self.config.budget.clone());
+        for file in source.list_files(&commit).await? {
+            let url = GitSource::file_url(&base, &file.path);
+            if let Some(limit) = tracker.exceeded() {
+                plan.truncated = Some(limit);
+                plan.exclude(&url, 0, None, Exclusion::Budget { limit });
+                continue;
+            }
+            tracker.add_page();
+            plan.pages.push(PlannedPage {
+                url: url.to_string(),
+                depth: 0,
+                found_on: None,
+                status: None,
+                content_type: None,
+                language: None,
+                error: None,
+            });
+        }
+        Ok(plan)
+    }
 
-        if let Err(e) = crawl_result {
-            error!("Crawler task failed for {}: {}", entry.name, e);
-            // Optionally update entry status to Failed here or rely on retry logic
-            return Err(anyhow::anyhow!("Crawler task failed: {}", e));
+    /// Read the documentation files of a git source at its reference
+    ///
+    /// Every file is handled like a fetched page without links: HTML files
+    /// are saved to the HTML directory and the other formats to the document
+    /// directory. The blob id serves as validator, so files that did not
+    /// change since the last run are not read again.
+    async fn read_git_source(
+        &self,
+        entry: &mut UrlEntry,
+        source: &GitSource,
+        entry_dir: &Path,
+    ) -> Result<CrawlResult> {
+        let commit = source.resolve_commit().await?;
+        info!(
+            "Reading {} at {} ({}) for entry {}",
+            source.repository.display(),
+            source.reference,
+            commit,
+            entry.name
+        );
+        let files = source.list_files(&commit).await?;
+        if files.is_empty() {
+            warn!("No documentation files match the paths of entry {}", entry.name);
         }
 
-        match download_result {
-            Ok(crawled_urls) => {
-                info!(
-                    "Successfully processed {} URLs for entry: {}",
-                    crawled_urls.len(),
-                    entry.name
-                );
-                entry.last_download = Some(Utc::now());
-                entry.version += 1;
-                entry.status = CrawlStatus::Enabled; // Mark as success if crawl/download finishes
+        let base = source.url()?;
+        let document_types = [DocumentType::Markdown, DocumentType::Rst, DocumentType::AsciiDoc];
+        let mut manifest = Manifest::load(entry_dir)?;
+        let mut tracker = BudgetTracker::new(self.config.budget.clone());
+        let mut truncated = None;
+        let mut page_reports = Vec::new();
+
+        for file in files {
+            if let Some(limit) = tracker.exceeded() {
+                warn!("Reading {} stopped early ({})", entry.name, limit);
+                self.progress.emit(ProgressEvent::CrawlTruncated { limit });
+                truncated = Some(limit);
+                break;
             }
-            Err(e) => {
-                error!("Download/Processing task failed for {}: {}", entry.name, e);
-                entry.status = CrawlStatus::Failed; // Mark as failed if download task panics
-                return Err(anyhow::anyhow!("Download/Processing task failed: {}", e));
+            tracker.add_page();
+
+            let url = GitSource::file_url(&base, &file.path);
+            self.progress.emit(ProgressEvent::PageDiscovered { url: url.to_string() });
+            let etag = format!("\"{}\"", file.blob);
+            let mut headers = HeaderMap::new();
+            if let Ok(value) = HeaderValue::from_str(&etag) {
+                headers.insert(header::ETAG, value);
+            }
+            if file.document_type.is_none() {
+                headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("text/html"));
+            }
+            let unchanged = self
+                .cached_validators(&manifest, entry_dir, &url)
+                .is_some_and(|validators| validators.etag.as_deref() == Some(etag.as_str()));
+            let body = if unchanged {
+                Ok(Vec::new())
+            } else {
+                source.read_blob(&file.blob).await
+            };
+            let response = body.map(|body| {
+                tracker.add_bytes(body.len() as u64);
+                FetchResponse {
+                    url: url.clone(),
+                    final_url: url.clone(),
+                    status: if unchanged { 304 } else { 200 },
+                    headers,
+                    body,
+                    redirects: Vec::new(),
+                }
+            });
+
+            let fetched = FetchedPage {
+                page: QueuedPage {
+                    url,
+                    depth: 0,
+                    parent: None,
+                    language: None,
+                },
+                response,
+                validators: None,
+            };
+            self.handle_fetched_page(fetched, entry_dir, &document_types, &mut manifest, &mut page_reports);
+        }
+        manifest.save(entry_dir)?;
+
+        info!(
+            "Read {} files for entry: {} ({} unchanged)",
+            page_reports.len(),
+            entry.name,
+            page_reports
+                .iter()
+                .filter(|page| page.outcome == PageOutcome::Unchanged)
+                .count()
+        );
+        entry.revision = Some(commit);
+        entry.last_download = Some(Utc::now());
+        entry.version += 1;
+        entry.status = CrawlStatus::Enabled;
+
+        Ok(CrawlResult {
+            pages: page_reports,
+            truncated,
+            warc_file: None,
+            selection_changed: false,
+            docs_version: None,
+        })
+    }
+
+    /// Confine a crawl to the docs version the entry names, if it names one
+    ///
+    /// Reads the page at the entry URL for the version scheme of the site and
+    /// the versions its switcher offers, and moves the root of the scope to
+    /// the same page in the target version.
+    ///
+    /// # Returns
+    /// The scope to crawl, the filter leaving out other versions if the site
+    /// has versions, and the record of the crawled version
+    ///
+    /// # Errors
+    /// Returns an error if the page at the entry URL or in the target version
+    /// cannot be fetched, or if the site does not offer the target version
+    async fn confine_version(
+        &self,
+        scope: Scope,
+        robots: &Robots,
+    ) -> Result<(Scope, Option<VersionFilter>, Option<DocsVersion>)> {
+        let Some(target) = self.config.versions.target.as_deref() else {
+            return Ok((scope, None, None));
+        };
+        let (final_url, html) = self.fetch_html(scope.root()).await?;
+        let mut links: Vec<Url> = extract_links(&final_url, &html).into_iter().map(|link| link.url).collect();
+        links.extend(extract_switcher_options(&final_url, &html));
+        let resolution = version::resolve(target, &final_url, &links)?;
+
+        let (declared, served) = if resolution.root == final_url {
+            (declared_version(&html), final_url)
+        } else {
+            if !robots.is_allowed(&resolution.root) {
+                return Err(DoclingError::Robots {
+                    url: resolution.root.to_string(),
+                });
             }
+            let (served, html) = self.fetch_html(&resolution.root).await.map_err(|e| {
+                DoclingError::config_with(format!("Docs version '{}' not found at {}", target, resolution.root), e)
+            })?;
+            (declared_version(&html), served)
+        };
+        let docs_version = resolution.docs_version(target, declared, &served);
+        match &resolution.filter {
+            Some(_) => info!(
+                "Crawling docs version {} from {} (site offers {})",
+                docs_version.resolved,
+                resolution.root,
+                resolution.available.join(", ")
+            ),
+            None => info!("No docs versions found at {}, crawling it as is", resolution.root),
         }
+        Ok((scope.with_root(resolution.root), resolution.filter, Some(docs_version)))
+    }
 
-        Ok(())
+    /// Fetch a page and decode its HTML
+    ///
+    /// # Returns
+    /// The URL the page was served from and its HTML
+    ///
+    /// # Errors
+    /// Returns an error if the request fails or the status is unsuccessful
+    async fn fetch_html(&self, url: &Url) -> Result<(Url, String)> {
+        let response = self.fetcher.get(url).await?;
+        if !response.is_success() {
+            return Err(DoclingError::http_response(response.status, url.as_str()));
+        }
+        let tld = url.domain().and_then(|domain| domain.rsplit('.').next());
+        let html = decode_html(&response.body, response.content_type(), tld).text;
+        Ok((response.final_url, html))
     }
-}
 
-/// Download images from HTML content
-///
-/// # Arguments
-/// * `url` - The base URL for resolving relative links
-/// * `html` - The HTML content to extract image URLs from
-/// * `client` - The HTTP client to use
-/// * `media_dir` - Directory to save media files
-/// * `delay` - Delay between requests in milliseconds
-///
-/// # Returns
-/// Ok(()) if successful
-///
-/// # Errors
-/// Returns an error if images cannot be extracted or downloaded
-async fn download_images(
-    url: &Url,
-    html: &str,
-    client: &Client,
-    media_dir: &Path,
-    delay: u64,
-) -> Result<()> {
-    // Extract image URLs from HTML
-    let mut image_urls = Vec::new();
-
-    // Extract img src attributes (better parsing than before)
-    for line in html.lines() {
-        if line.contains("<img") && line.contains("src=") {
-            // Handle src="..." format
-            if let Some(start) = line.find("src=\"") {
-                if let Some(end) = line[start + 5..].find('"') {
-                    let src = &line[start + 5..start + 5 + end];
-                    image_urls.push(src);
-                }
+    /// Remove the saved pages of other docs versions than the one crawled
+    ///
+    /// Earlier crawls of the entry may have saved every version; their pages
+    /// are removed from the manifest together with their HTML and Markdown.
+    ///
+    /// # Returns
+    /// Whether any page was removed
+    ///
+    /// # Errors
+    /// Returns an error if a saved file cannot be removed
+    fn drop_other_versions(&self, entry_dir: &Path, manifest: &mut Manifest, versions: &VersionFilter) -> Result<bool> {
+        let md_dir = entry_dir.join(&self.config.output_parts_markdown_suffix);
+        let other_versions: Vec<String> = manifest
+            .pages
+            .keys()
+            .filter(|url| Url::parse(url).is_ok_and(|url| versions.other_version(&url).is_some()))
+            .cloned()
+            .collect();
+        for url in &other_versions {
+            let Some(record) = manifest.pages.remove(url) else {
+                continue;
+            };
+            let mut files = vec![entry_dir.join(&record.saved_path)];
+            if let Some(stem) = record.saved_path.file_stem() {
+                files.push(md_dir.join(format!("{}.md", stem.to_string_lossy())));
             }
-            // Handle src='...' format
-            else if let Some(start) = line.find("src='") {
-                if let Some(end) = line[start + 5..].find('\'') {
-                    let src = &line[start + 5..start + 5 + end];
-                    image_urls.push(src);
-                }
+            for file in files.iter().filter(|file| file.exists()) {
+                std::fs::remove_file(file).io_path_context("Failed to remove page of another docs version", file)?;
             }
-            // Handle src=... format without quotes
-            else if let Some(start) = line.find("src=") {
-                let src_part = &line[start + 4..];
-                if let Some(end) = src_part.find(|c: char| c.is_whitespace() || c == '>') {
-                    let src = &src_part[..end];
-                    image_urls.push(src);
+        }
+        if !other_versions.is_empty() {
+            info!("Removed {} saved pages of other docs versions", other_versions.len());
+        }
+        Ok(!other_versions.is_empty())
+    }
+
+    /// Remove pages saved under file names without a URL hash
+    ///
+    /// Older versions named saved files after the URL alone. Once the pages
+    /// are saved again under hashed names, the old copies and their Markdown
+    /// would otherwise be converted and combined next to the new ones. Files
+    /// the manifest still points to are kept.
+    ///
+    /// # Returns
+    /// Whether any file was removed
+    ///
+    /// # Errors
+    /// Returns an error if a directory cannot be read or a file cannot be removed
+    fn drop_legacy_files(&self, entry_dir: &Path, manifest: &Manifest) -> Result<bool> {
+        let files = manifest.file_index();
+        let mut removed = 0;
+        for suffix in [
+            &self.config.output_parts_html_suffix,
+            &self.config.output_parts_docs_suffix,
+            &self.config.output_parts_markdown_suffix,
+        ] {
+            let dir = entry_dir.join(suffix);
+            if !dir.is_dir() {
+                continue;
+            }
+            let entries = std::fs::read_dir(&dir).io_path_context("Failed to read saved pages", &dir)?;
+            for path in entries.filter_map(std::result::Result::ok).map(|entry| entry.path()) {
+                let legacy = path.is_file()
+                    && path
+                        .file_stem()
+                        .is_some_and(|stem| !is_hashed_filename(&stem.to_string_lossy()))
+                    && files.url(&path).is_none();
+                if legacy {
+                    debug!("Removing {} saved under an old file name", path.display());
+                    std::fs::remove_file(&path).io_path_context("Failed to remove page with old file name", &path)?;
+                    removed += 1;
                 }
             }
         }
+        if removed > 0 {
+            info!("Removed {} files saved under old file names", removed);
+        }
+        Ok(removed > 0)
     }
 
-    // Download each image
-    for image_url in image_urls {
-        // Resolve relative URLs
-        let full_url = match Url::parse(image_url) {
-            Ok(url) => url,
-            Err(_) => {
-                // Handle relative URLs
-                match url.join(image_url) {
-                    Ok(url) => url,
-                    Err(e) => {
-                        warn!(
-                            "Failed to parse/join image URL '{}' relative to '{}': {}",
-                            image_url, url, e
-                        );
-                        continue;
+    /// Remove media files of the entry that no record points to any more
+    ///
+    /// Media is saved under its content hash, so a changed image lands in a
+    /// new file and the record moves to it. The old file is only left behind
+    /// in the entry's media directory; the shared media store keeps its copy.
+    ///
+    /// # Returns
+    /// How many files were removed
+    ///
+    /// # Errors
+    /// Returns an error if the media directory cannot be read or a file cannot be removed
+    fn drop_unreferenced_media(&self, entry_dir: &Path, manifest: &Manifest) -> Result<usize> {
+        let dir = entry_dir.join(&self.config.output_parts_media_suffix);
+        if !dir.is_dir() {
+            return Ok(0);
+        }
+        let referenced: HashSet<PathBuf> = manifest
+            .media
+            .values()
+            .map(|record| entry_dir.join(&record.saved_path))
+            .collect();
+        let entries = std::fs::read_dir(&dir).io_path_context("Failed to read media directory", &dir)?;
+        let mut removed = 0;
+        for path in entries.filter_map(std::result::Result::ok).map(|entry| entry.path()) {
+            if path.is_file() && !referenced.contains(&path) {
+                debug!("Removing unreferenced media file {}", path.display());
+                std::fs::remove_file(&path).io_path_context("Failed to remove media file", &path)?;
+                removed += 1;
+            }
+        }
+        if removed > 0 {
+            info!("Removed {} media files no longer referenced", removed);
+        }
+        Ok(removed)
+    }
+
+    /// Remove the Markdown of saved pages that were skipped for their language
+    ///
+    /// The saved copies stay so the pages convert again once their language
+    /// is kept.
+    ///
+    /// # Returns
+    /// Whether the Markdown of any page was removed
+    ///
+    /// # Errors
+    /// Returns an error if a Markdown file cannot be removed
+    fn drop_other_languages(
+        &self,
+        entry_dir: &Path,
+        manifest: &mut Manifest,
+        page_reports: &[PageReport],
+    ) -> Result<bool> {
+        let md_dir = entry_dir.join(&self.config.output_parts_markdown_suffix);
+        let mut changed = false;
+        for report in page_reports {
+            if report.outcome != PageOutcome::Skipped
+                || report.language.as_deref().is_none_or(|language| self.config.languages.accepts(language))
+            {
+                continue;
+            }
+            let Some(record) = manifest.pages.get_mut(&report.url) else {
+                continue;
+            };
+            record.converted_hash = None;
+            let Some(stem) = record.saved_path.file_stem() else {
+                continue;
+            };
+            let md_file = md_dir.join(format!("{}.md", stem.to_string_lossy()));
+            if md_file.exists() {
+                debug!("Removing Markdown of {} in language not kept", report.url);
+                std::fs::remove_file(&md_file)
+                    .io_path_context("Failed to remove Markdown of page in another language", &md_file)?;
+                changed = true;
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Mark the saved pages that duplicate another page and save the URL aliases
+    ///
+    /// Pages declaring another crawled page as canonical, and pages with the
+    /// same or nearly the same text as another page, keep their saved copy
+    /// but are not converted; their Markdown files are removed.
+    ///
+    /// # Returns
+    /// Whether any page became or stopped being a duplicate
+    ///
+    /// # Errors
+    /// Returns an error if the alias file cannot be written
+    fn mark_duplicates(
+        &self,
+        entry_dir: &Path,
+        manifest: &mut Manifest,
+        page_reports: &mut [PageReport],
+        declared: &HashMap<String, String>,
+        mut aliases: UrlAliases,
+    ) -> Result<bool> {
+        let mut fingerprints = Vec::new();
+        for report in page_reports.iter() {
+            if !matches!(report.outcome, PageOutcome::Saved | PageOutcome::Unchanged) {
+                continue;
+            }
+            let Some(record) = manifest.get(&report.url).filter(|record| record.document.is_none()) else {
+                continue;
+            };
+            let html = match std::fs::read_to_string(entry_dir.join(&record.saved_path)) {
+                Ok(html) => html,
+                Err(e) => {
+                    warn!("Failed to read {} for duplicate detection: {}", record.saved_path.display(), e);
+                    continue;
+                }
+            };
+            fingerprints.push(PageFingerprint {
+                url: record.url.clone(),
+                depth: record.depth.unwrap_or_default(),
+                content_hash: record.content_hash.clone(),
+                sketch: shingle_sketch(&visible_text(&html)),
+                canonical: declared.get(&record.url).cloned(),
+            });
+        }
+        let duplicates = find_duplicates(&fingerprints, self.config.normalization.near_duplicate_similarity);
+
+        let md_dir = entry_dir.join(&self.config.output_parts_markdown_suffix);
+        let mut changed = false;
+        for fingerprint in &fingerprints {
+            let duplicate_of = duplicates.get(&fingerprint.url).map(|alias| alias.canonical.clone());
+            let Some(record) = manifest.pages.get_mut(&fingerprint.url) else {
+                continue;
+            };
+            if record.duplicate_of != duplicate_of {
+                changed = true;
+                record.duplicate_of = duplicate_of.clone();
+                record.converted_hash = None;
+            }
+            if let Some(canonical) = &duplicate_of {
+                debug!("{} duplicates {}", fingerprint.url, canonical);
+                if let Some(stem) = record.saved_path.file_stem() {
+                    let md_file = md_dir.join(format!("{}.md", stem.to_string_lossy()));
+                    if md_file.exists() {
+                        std::fs::remove_file(&md_file)
+                            .io_path_context("Failed to remove Markdown of duplicate page", &md_file)?;
                     }
                 }
             }
+            if let Some(report) = page_reports.iter_mut().find(|report| report.url == fingerprint.url) {
+                report.duplicate_of = duplicate_of;
+            }
+        }
+        if !duplicates.is_empty() {
+            info!("Left out {} duplicate pages", duplicates.len());
+        }
+
+        // Aliases point at the page that is kept
+        for alias in aliases.aliases.values_mut() {
+            if let Some(duplicate) = duplicates.get(&alias.canonical) {
+                alias.canonical = duplicate.canonical.clone();
+            }
+        }
+        for (url, alias) in duplicates {
+            aliases.aliases.insert(url, alias);
+        }
+        aliases.aliases.retain(|url, alias| *url != alias.canonical);
+        aliases.save(entry_dir)?;
+        Ok(changed)
+    }
+
+    /// Validators to send for a page, if its saved copy is still on disk
+    fn cached_validators(&self, manifest: &Manifest, entry_dir: &Path, url: &Url) -> Option<Validators> {
+        manifest
+            .get(url.as_str())
+            .filter(|record| entry_dir.join(&record.saved_path).exists())
+            .map(|record| record.validators.clone())
+            .filter(|validators| !validators.is_empty())
+    }
+
+    /// Save a fetched page or document and record it in the manifest
+    ///
+    /// HTML pages are transcoded to UTF-8 and saved to the HTML directory;
+    /// responses matching one of `document_types` are saved as served to the
+    /// document directory.
+    ///
+    /// HTML pages in languages the entry does not keep are reported as
+    /// skipped and not saved.
+    ///
+    /// # Returns
+    /// The final URL and HTML of the page if its links or alternates should be followed
+    fn handle_fetched_page(
+        &self,
+        fetched: FetchedPage,
+        entry_dir: &Path,
+        document_types: &[DocumentType],
+        manifest: &mut Manifest,
+        page_reports: &mut Vec<PageReport>,
+    ) -> Option<PageHtml> {
+        let FetchedPage { page, response, .. } = fetched;
+        let url_string = page.url.to_string();
+
+        let response = match response {
+            Ok(response) => response,
+            Err(e) => {
+                warn!("Failed to fetch {}: {}", url_string, e);
+                self.report_failure(page_reports, failed_report(&url_string, None, None, e.to_string()));
+                return None;
+            }
         };
+        let status = Some(response.status);
+        let content_type = response.content_type().map(str::to_string);
+        let previous = manifest.get(&url_string).cloned();
 
-        // Download image file
-        let filename_base = url_to_filename(&full_url);
+        // Not modified: keep the saved copy and follow its links again
+        if response.is_not_modified() {
+            let Some(mut record) = previous else {
+                let reason = "Received 304 Not Modified without a saved copy".to_string();
+                self.report_failure(page_reports, failed_report(&url_string, status, content_type, reason));
+                return None;
+            };
+            let file_path = entry_dir.join(&record.saved_path);
+            if record.document.is_some() {
+                debug!("Document not modified: {}", url_string);
+                let bytes = std::fs::metadata(&file_path).map(|m| m.len()).unwrap_or(0);
+                record.fetched_at = Utc::now();
+                record.depth = Some(page.depth);
+                record.parent = page.parent;
+                manifest.insert(record);
+                page_reports.push(PageReport {
+                    url: url_string,
+                    outcome: PageOutcome::Unchanged,
+                    http_status: status,
+                    content_type,
+                    bytes,
+                    saved_path: Some(file_path),
+                    encoding: None,
+                    encoding_source: None,
+                    error: None,
+                    duplicate_of: None,
+                    language: None,
+                });
+                return None;
+            }
+            return match std::fs::read_to_string(&file_path) {
+                Ok(body) => {
+                    debug!("Not modified: {}", url_string);
+                    let final_url = Url::parse(&record.final_url).unwrap_or_else(|_| page.url.clone());
+                    let encoding = record.encoding.clone();
+                    let language = record.language.clone();
+                    let kept = language.as_deref().is_none_or(|language| self.config.languages.accepts(language));
+                    record.fetched_at = Utc::now();
+                    record.depth = Some(page.depth);
+                    record.parent = page.parent;
+                    manifest.insert(record);
+                    page_reports.push(PageReport {
+                        url: url_string,
+                        outcome: if kept { PageOutcome::Unchanged } else { PageOutcome::Skipped },
+                        http_status: status,
+                        content_type,
+                        bytes: body.len() as u64,
+                        saved_path: Some(file_path),
+                        encoding,
+                        encoding_source: None,
+                        error: None,
+                        duplicate_of: None,
+                        language,
+                    });
+                    Some(PageHtml { final_url, body, kept })
+                }
+                Err(e) => {
+                    let reason = format!("Failed to read saved copy {}: {}", file_path.display(), e);
+                    self.report_failure(page_reports, failed_report(&url_string, status, content_type, reason));
+                    None
+                }
+            };
+        }
 
-        // Determine file extension
-        let extension = full_url
-            .path_segments()
-            .and_then(|segs| segs.last())
-            .and_then(|last_seg| last_seg.split('.').last())
-            .unwrap_or("jpg"); // Default to jpg if no extension found
+        if !response.is_success() {
+            let reason = format!("HTTP status {}", response.status);
+            self.report_failure(page_reports, failed_report(&url_string, status, content_type, reason));
+            return None;
+        }
 
-        let file_path = media_dir.join(format!("{}.{}", filename_base, extension));
+        // Generated listings of local directories only lead to their contents
+        if response.header(local::LISTING_HEADER).is_some() {
+            debug!("Following directory listing {}", url_string);
+            page_reports.push(PageReport {
+                url: url_string,
+                outcome: PageOutcome::Skipped,
+                http_status: status,
+                content_type,
+                bytes: response.body.len() as u64,
+                saved_path: None,
+                encoding: None,
+                encoding_source: None,
+                error: None,
+                duplicate_of: None,
+                language: None,
+            });
+            let final_url = response.final_url;
+            return String::from_utf8(response.body).ok().map(|body| PageHtml {
+                final_url,
+                body,
+                kept: true,
+            });
+        }
 
-        // Skip if already exists
-        if file_path.exists() {
-            debug!("Skipping existing image: {}", full_url);
-            continue;
+        // HTML pages and the entry's document types are saved; other content
+        // is reported as skipped
+        let mime_type = response.mime_type();
+        let document = DocumentType::detect(mime_type.as_deref(), &page.url)
+            .filter(|document_type| document_types.contains(document_type));
+        if document.is_none()
+            && !matches!(
+                mime_type.as_deref(),
+                None | Some("text/html") | Some("application/xhtml+xml")
+            )
+        {
+            debug!("Skipping non-HTML content type {:?} for {}", mime_type, url_string);
+            page_reports.push(PageReport {
+                url: url_string,
+                outcome: PageOutcome::Skipped,
+                http_status: status,
+                content_type,
+                bytes: response.body.len() as u64,
+                saved_path: None,
+                encoding: None,
+                encoding_source: None,
+                error: None,
+                duplicate_of: None,
+                language: None,
+            });
+            return None;
         }
 
-        // Wait before making the request
-        sleep(Duration::from_millis(delay)).await;
+        let validators = response.validators();
+        let final_url = response.final_url.clone();
+        let bytes = response.body.len() as u64;
 
-        // Download image
-        match client.get(full_url.as_str()).send().await {
-            Ok(response) => {
-                if !response.status().is_success() {
+        let (file_path, body, encoding, encoding_source, language) = match document {
+            // Documents are kept byte for byte; the converter decodes them
+            Some(document_type) => {
+                let docs_dir = entry_dir.join(&self.config.output_parts_docs_suffix);
+                let file_name = format!("{}.{}", url_to_filename(&page.url), document_type.extension());
+                (docs_dir.join(file_name), response.body, None, None, None)
+            }
+            None => {
+                // Transcode to UTF-8 so every later stage can rely on it
+                let tld = page.url.domain().and_then(|domain| domain.rsplit('.').next());
+                let decoded = decode_html(&response.body, content_type.as_deref(), tld);
+                if decoded.had_errors {
                     warn!(
-                        "Failed to download image {} - Status: {}",
-                        full_url,
-                        response.status()
+                        "Page {} contained invalid {} sequences, they were replaced",
+                        url_string,
+                        decoded.encoding.name()
                     );
-                    continue;
                 }
-
-                // Check if it's actually an image by content type
-                let content_type = response
-                    .headers()
-                    .get(reqwest::header::CONTENT_TYPE)
-                    .and_then(|v| v.to_str().ok())
-                    .unwrap_or("");
-
-                // Skip if not an image
-                if !content_type.starts_with("image/") {
+                if decoded.encoding != encoding_rs::UTF_8 {
                     debug!(
-                        "Skipping non-image content type '{}' for URL: {}",
-                        content_type, full_url
+                        "Transcoded {} from {} (from {})",
+                        url_string,
+                        decoded.encoding.name(),
+                        decoded.source
                     );
-                    continue;
                 }
+                let language = page_language(
+                    &page.url,
+                    &decoded.text,
+                    page.language.as_deref(),
+                    self.config.languages.detect_text,
+                );
+                if let Some(language) = &language {
+                    debug!("Language of {} is {} (from {})", url_string, language.code, language.source);
+                }
+                let language = language.map(|language| language.code);
 
-                match response.bytes().await {
-                    Ok(bytes) => match File::create(&file_path).await {
-                        Ok(mut file) => {
-                            if let Err(e) = file.write_all(&bytes).await {
-                                warn!("Failed to write image file {}: {}", file_path.display(), e);
-                            } else {
-                                debug!("Downloaded image: {}", full_url);
-                            }
-                        }
-                        Err(e) => {
-                            warn!("Failed to create image file {}: {}", file_path.display(), e);
-                        }
-                    },
-                    Err(e) => {
-                        warn!(
-                            "Failed to read image response bytes for {}: {}",
-                            full_url, e
-                        );
+                // Pages in other languages are only read for their alternates
+                if let Some(code) = language.as_deref().filter(|code| !self.config.languages.accepts(code)) {
+                    debug!("Skipping {} in language '{}'", url_string, code);
+                    if let Some(mut record) = previous {
+                        record.language = language.clone();
+                        manifest.insert(record);
                     }
+                    page_reports.push(PageReport {
+                        url: url_string,
+                        outcome: PageOutcome::Skipped,
+                        http_status: status,
+                        content_type,
+                        bytes,
+                        saved_path: None,
+                        encoding: Some(decoded.encoding.name().to_string()),
+                        encoding_source: Some(decoded.source),
+                        error: None,
+                        duplicate_of: None,
+                        language,
+                    });
+                    return Some(PageHtml {
+                        final_url,
+                        body: decoded.text,
+                        kept: false,
+                    });
                 }
+
+                let html_dir = entry_dir.join(&self.config.output_parts_html_suffix);
+                let file_path = html_dir.join(format!("{}.html", url_to_filename(&page.url)));
+                let encoding = decoded.encoding.name().to_string();
+                (file_path, decoded.text.into_bytes(), Some(encoding), Some(decoded.source), language)
             }
-            Err(e) => {
-                warn!("Failed to download image {}: {}", full_url, e);
+        };
+        let hash = content_hash(&body);
+
+        // Servers without validators still send the same content; keep the file as is
+        let unchanged = previous
+            .as_ref()
+            .is_some_and(|record| record.content_hash == hash)
+            && file_path.exists();
+
+        if unchanged {
+            debug!("Content unchanged: {}", url_string);
+        } else {
+            if let Err(e) = std::fs::write(&file_path, &body) {
+                error!("Failed to write {} for {}: {}", file_path.display(), url_string, e);
+                let reason = format!("Failed to write {}: {}", file_path.display(), e);
+                let mut report = failed_report(&url_string, status, content_type, reason);
+                report.bytes = bytes;
+                self.report_failure(page_reports, report);
+                return None;
             }
+            debug!("Saved {}: {}", file_path.display(), url_string);
+            self.progress.emit(ProgressEvent::PageSaved {
+                url: url_string.clone(),
+                path: file_path.clone(),
+                bytes,
+            });
         }
+
+        manifest.insert(PageRecord {
+            url: url_string.clone(),
+            final_url: final_url.to_string(),
+            depth: Some(page.depth),
+            parent: page.parent,
+            status,
+            content_type: content_type.clone(),
+            fetched_at: Utc::now(),
+            content_hash: hash,
+            saved_path: relative_to(&file_path, entry_dir),
+            validators,
+            encoding: encoding.clone(),
+            // The Markdown stays valid as long as the content did not change
+            converted_hash: previous.as_ref().and_then(|record| record.converted_hash.clone()),
+            document,
+            duplicate_of: previous.and_then(|record| record.duplicate_of),
+            language: language.clone(),
+        });
+        page_reports.push(PageReport {
+            url: url_string,
+            outcome: if unchanged { PageOutcome::Unchanged } else { PageOutcome::Saved },
+            http_status: status,
+            content_type,
+            bytes,
+            saved_path: Some(file_path),
+            encoding,
+            encoding_source,
+            error: None,
+            duplicate_of: None,
+            language,
+        });
+
+        if document.is_some() {
+            return None;
+        }
+        String::from_utf8(body).ok().map(|body| PageHtml {
+            final_url,
+            body,
+            kept: true,
+        })
     }
 
-    Ok(())
+    /// Emit a failure event and add the report
+    fn report_failure(&self, page_reports: &mut Vec<PageReport>, report: PageReport) {
+        self.progress.emit(ProgressEvent::PageFailed {
+            url: report.url.clone(),
+            reason: report.error.clone().unwrap_or_default(),
+        });
+        page_reports.push(report);
+    }
 }
 
-/// Converts a URL to a valid filename, attempting to preserve structure.
-///
-/// # Arguments
-/// * `url` - The URL to convert
+/// Remember the `hreflang` alternates of a page and return the ones to queue
 ///
-/// # Returns
-/// A string that can be used as a filename
-fn url_to_filename(url: &Url) -> String {
-    let host = url.host_str().unwrap_or("unknown_host");
-    // Get path segments, filter out empty ones, join with underscores
-    let path = url
-        .path_segments()
-        .map(|segments| {
-            segments
-                .filter(|s| !s.is_empty())
-                .collect::<Vec<_>>()
-                .join("_")
+/// When some languages are left out, alternates in the kept languages are
+/// queued at the depth of the page they translate.
+fn queue_alternates(
+    rules: &CrawlRules<'_>,
+    seen: &mut SeenUrls,
+    base: &Url,
+    html: &str,
+    depth: u32,
+    found_on: &str,
+) -> Vec<QueuedPage> {
+    let mut queued = Vec::new();
+    for (tag, url) in extract_alternates(base, html) {
+        seen.set_hreflang(&url, &tag);
+        if rules.languages.filters() && !seen.contains(&url) && rules.exclusion(seen, &url).is_none() {
+            queued.extend(seen.admit(&url, depth, Some(found_on)));
+        }
+    }
+    queued
+}
+
+/// Scope and rules of a crawl, after robots.txt and the docs version were taken into account
+struct EntryScope {
+    /// Scope of the entry, rooted at the crawled version
+    scope: Scope,
+    /// robots.txt of the entry's host
+    robots: Robots,
+    /// Filter leaving out other docs versions, if the site has versions
+    versions: Option<VersionFilter>,
+    /// Documentation version the crawl is confined to, if the entry names one
+    docs_version: Option<DocsVersion>,
+}
+
+/// What [`Crawler::discover`] found besides the pages it handed out
+struct Discovery {
+    /// URLs queued during the crawl and their aliases
+    seen: SeenUrls,
+    /// Canonical URLs declared by the crawled pages, by page URL
+    declared: HashMap<String, String>,
+    /// Limit of the crawl budget that stopped the crawl early, if any
+    truncated: Option<BudgetLimit>,
+}
+
+/// What a crawl does with the pages [`Crawler::discover`] fetches
+trait PageVisitor {
+    /// Validators to send when fetching a page
+    fn validators(&self, url: &Url) -> Option<Validators>;
+
+    /// Handle a fetched page
+    ///
+    /// # Returns
+    /// The final URL and HTML of the page if its links or alternates should be followed
+    fn visit(&mut self, fetched: FetchedPage) -> Option<PageHtml>;
+
+    /// Take the links of a kept page, resolved to the pages they were queued as
+    fn links(&mut self, page_url: &str, final_url: &Url, body: &str, links: &[Link], truncated: Option<BudgetLimit>);
+
+    /// Take a URL that is left out of the crawl
+    fn exclude(&mut self, url: &Url, depth: u32, found_on: Option<&str>, exclusion: Exclusion);
+}
+
+/// Saves the pages and media of a crawl and records them in the manifest
+struct PageSaver<'a> {
+    /// Crawler saving the pages
+    crawler: &'a Crawler,
+    /// Output directory of the entry
+    entry_dir: &'a Path,
+    /// Document types of the entry that are saved as served
+    document_types: &'a [DocumentType],
+    /// Scope of the entry; media outside of it is not downloaded
+    scope: &'a Scope,
+    /// Manifest of the entry, updated with every saved page
+    manifest: Manifest,
+    /// A report for every page that was requested
+    page_reports: Vec<PageReport>,
+    /// Links between the crawled pages
+    link_graph: LinkGraph,
+    /// Archive of the crawl, if one is written
+    warc: Option<WarcWriter>,
+    /// Locations of the archived responses
+    warc_index: WarcIndex,
+    /// Downloads the media of the pages
+    downloader: MediaDownloader,
+    /// Limits the media downloads running at once
+    media_semaphore: Arc<Semaphore>,
+    /// Pages referencing each media file, for the media index
+    media_references: BTreeMap<String, Vec<MediaReference>>,
+    /// Media downloads in flight
+    media_tasks: JoinSet<Result<Vec<MediaRecord>>>,
+}
+
+impl PageVisitor for PageSaver<'_> {
+    fn validators(&self, url: &Url) -> Option<Validators> {
+        self.crawler.cached_validators(&self.manifest, self.entry_dir, url)
+    }
+
+    fn visit(&mut self, fetched: FetchedPage) -> Option<PageHtml> {
+        if let Ok(response) = &fetched.response
+            && let Some(warc) = &self.warc
+        {
+            match warc.write_exchange(response, &response.body, fetched.validators.as_ref()) {
+                Ok(location) if response.is_success() => {
+                    self.warc_index.insert(fetched.page.url.as_str(), location, response.status);
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Failed to archive {}: {}", fetched.page.url, e),
+            }
+        }
+        self.link_graph.add_page(
+            fetched.page.url.as_str(),
+            fetched.page.depth,
+            fetched.response.as_ref().map_err(ToString::to_string),
+        );
+        self.crawler.handle_fetched_page(
+            fetched,
+            self.entry_dir,
+            self.document_types,
+            &mut self.manifest,
+            &mut self.page_reports,
+        )
+    }
+
+    fn links(&mut self, page_url: &str, final_url: &Url, body: &str, links: &[Link], truncated: Option<BudgetLimit>) {
+        let scope = self.scope;
+        self.link_graph.add_links(page_url, links, |link| scope.check(&link.url).is_allowed());
+
+        // Download the media of the page in a separate task, each file once per run
+        let mut media = Vec::new();
+        for media_ref in extract_media(final_url, body, &self.crawler.config.media_kinds) {
+            if let MediaSource::Url(url) = &media_ref.source
+                && !scope.check(url).is_allowed()
+            {
+                continue;
+            }
+            let references = self.media_references.entry(media_key(&media_ref)).or_default();
+            if references.is_empty() {
+                media.push(media_ref.clone());
+            }
+            references.push(MediaReference {
+                page: page_url.to_string(),
+                alt: media_ref.alt,
+            });
+        }
+        // Past the time limit, only the pages in flight are still saved
+        if media.is_empty() || truncated == Some(BudgetLimit::Duration) {
+            return;
+        }
+        let downloader = self.downloader.clone();
+        let semaphore = Arc::clone(&self.media_semaphore);
+        self.media_tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await.map_err(|e| {
+                DoclingError::config_with("Failed to acquire permit for media download", e)
+            })?;
+            Ok(downloader.download(media).await)
+        });
+    }
+
+    fn exclude(&mut self, url: &Url, _depth: u32, _found_on: Option<&str>, exclusion: Exclusion) {
+        if matches!(
+            exclusion,
+            Exclusion::Robots | Exclusion::Language { .. } | Exclusion::Version { .. }
+        ) {
+            debug!("Skipping {} ({})", url, exclusion);
+        }
+    }
+}
+
+/// Records the pages of a dry run in a plan without saving anything
+struct PagePlanner<'a> {
+    /// Languages kept from the entry
+    languages: &'a LanguageSettings,
+    /// Plan of the crawl
+    plan: CrawlPlan,
+}
+
+impl PageVisitor for PagePlanner<'_> {
+    fn validators(&self, _url: &Url) -> Option<Validators> {
+        None
+    }
+
+    fn visit(&mut self, fetched: FetchedPage) -> Option<PageHtml> {
+        let FetchedPage { page, response, .. } = fetched;
+        let mut planned = PlannedPage {
+            url: page.url.to_string(),
+            depth: page.depth,
+            found_on: page.parent.clone(),
+            status: None,
+            content_type: None,
+            language: None,
+            error: None,
+        };
+        let response = match response {
+            Ok(response) => response,
+            Err(e) => {
+                planned.error = Some(e.to_string());
+                self.plan.pages.push(planned);
+                return None;
+            }
+        };
+        planned.status = Some(response.status);
+        planned.content_type = response.content_type().map(str::to_string);
+
+        // Only HTML pages and directory listings lead to other pages
+        let is_html = matches!(
+            response.mime_type().as_deref(),
+            None | Some("text/html") | Some("application/xhtml+xml")
+        );
+        if !response.is_success() || !(is_html || response.header(local::LISTING_HEADER).is_some()) {
+            self.plan.pages.push(planned);
+            return None;
+        }
+        let tld = page.url.domain().and_then(|domain| domain.rsplit('.').next());
+        let body = decode_html(&response.body, response.content_type(), tld).text;
+
+        // Pages in other languages are fetched but not kept
+        planned.language = page_language(&page.url, &body, page.language.as_deref(), self.languages.detect_text)
+            .map(|language| language.code);
+        let kept = match planned.language.clone().filter(|language| !self.languages.accepts(language)) {
+            Some(language) => {
+                self.plan
+                    .exclude(&page.url, page.depth, page.parent.as_deref(), Exclusion::Language { language });
+                false
+            }
+            None => {
+                self.plan.pages.push(planned);
+                true
+            }
+        };
+        Some(PageHtml {
+            final_url: response.final_url,
+            body,
+            kept,
         })
-        .unwrap_or_else(|| "".to_string()); // Use empty string if no path
-
-    // Combine host and path
-    let mut filename = if path.is_empty() {
-        host.to_string()
-    } else {
-        format!("{}_{}", host, path)
-    };
-
-    // Replace definitely invalid characters for most filesystems
-    filename = filename.replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|'], "_");
-
-    // Replace common web characters that might be problematic
-    filename = filename
-        .replace('&', "_and_")
-        .replace('=', "_eq_")
-        .replace('+', "_plus_");
-
-    // Handle potential trailing characters or excessive underscores
-    filename = filename.trim_matches('_').to_string();
-    while filename.contains("__") {
-        filename = filename.replace("__", "_");
-    }
-
-    // Limit length (e.g., 200 chars) to avoid filesystem limits
-    let max_len = 200;
-    if filename.len() > max_len {
-        // Simple truncation, could be smarter (e.g., hash suffix)
-        filename = filename.chars().take(max_len).collect();
-        // Ensure it doesn't end with an underscore after truncation
-        filename = filename.trim_end_matches('_').to_string();
-    }
-
-    // Handle case where filename might become empty after cleaning (e.g., URL was just "/")
-    if filename.is_empty() {
-        "index".to_string()
-    } else {
-        filename
+    }
+
+    fn links(&mut self, _page_url: &str, _final_url: &Url, _body: &str, _links: &[Link], _truncated: Option<BudgetLimit>) {}
+
+    fn exclude(&mut self, url: &Url, depth: u32, found_on: Option<&str>, exclusion: Exclusion) {
+        self.plan.exclude(url, depth, found_on, exclusion);
+    }
+}
+
+/// Which URLs a crawl of an entry follows
+struct CrawlRules<'a> {
+    /// The entry being crawled
+    entry: &'a UrlEntry,
+    /// Scope of the entry
+    scope: &'a Scope,
+    /// robots.txt of the entry's host
+    robots: &'a Robots,
+    /// Languages kept from the entry
+    languages: &'a LanguageSettings,
+    /// Documentation version the crawl is confined to, if any
+    versions: Option<&'a VersionFilter>,
+}
+
+impl CrawlRules<'_> {
+    /// Why a URL is left out of the crawl, if it is
+    fn exclusion(&self, seen: &SeenUrls, url: &Url) -> Option<Exclusion> {
+        if let ScopeDecision::Excluded(rule) = self.scope.check(url) {
+            return Some(Exclusion::Scope(rule));
+        }
+        if !self.robots.is_allowed(url) {
+            return Some(Exclusion::Robots);
+        }
+        if let Some(version) = self.versions.and_then(|versions| versions.other_version(url)) {
+            return Some(Exclusion::Version { version });
+        }
+        seen.expected_language(url)
+            .filter(|language| !self.languages.accepts(language))
+            .map(|language| Exclusion::Language { language })
+    }
+
+    /// Why a link found on a page at `depth` is not followed, if it is not
+    ///
+    /// Documents of the entry's types have no links of their own, so they
+    /// are fetched one level beyond the crawl depth.
+    fn link_exclusion(&self, seen: &SeenUrls, url: &Url, depth: u32) -> Option<Exclusion> {
+        let is_document = DocumentType::from_url(url)
+            .is_some_and(|document_type| self.entry.document_types.contains(&document_type));
+        if depth >= self.entry.crawl_depth + u32::from(is_document) {
+            return Some(Exclusion::Depth {
+                max_depth: self.entry.crawl_depth,
+            });
+        }
+        self.exclusion(seen, url)
+    }
+}
+
+/// Report for a page that could not be fetched, decoded or saved
+fn failed_report(
+    url: &str,
+    http_status: Option<u16>,
+    content_type: Option<String>,
+    reason: String,
+) -> PageReport {
+    PageReport {
+        url: url.to_string(),
+        outcome: PageOutcome::Failed,
+        http_status,
+        content_type,
+        bytes: 0,
+        saved_path: None,
+        encoding: None,
+        encoding_source: None,
+        error: Some(reason),
+        duplicate_of: None,
+        language: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{request_path, response, TestServer};
+    use crate::version::VersionSettings;
+
+    /// Configuration writing to a fresh directory, without request delays
+    fn test_config(name: &str) -> DoclingConfig {
+        let outputs_path = std::env::temp_dir().join(format!("docling-crawl-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&outputs_path);
+        DoclingConfig {
+            outputs_path,
+            delay_between_request_in_ms: 0,
+            respect_robots_txt: false,
+            ..DoclingConfig::default()
+        }
+    }
+
+    fn outcomes(result: &CrawlResult) -> HashMap<String, PageOutcome> {
+        result
+            .pages
+            .iter()
+            .map(|page| (page.url.clone(), page.outcome))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn unchanged_pages_are_reused() {
+        // The home page has an ETag, the guide has no validators at all
+        let server = TestServer::start(|head| match request_path(head) {
+            "/" if head.to_ascii_lowercase().contains("if-none-match: \"home\"") => {
+                response("304 Not Modified", &[("ETag", "\"home\"")], b"")
+            }
+            "/" => response(
+                "200 OK",
+                &[("Content-Type", "text/html"), ("ETag", "\"home\"")],
+                b"<html><body><h1>Home</h1><p>Welcome to the manual.</p><a href=\"/guide\">Guide</a></body></html>",
+            ),
+            "/guide" => response(
+                "200 OK",
+                &[("Content-Type", "text/html")],
+                b"<html><body><h1>Guide</h1><p>Install the tool with cargo, then run it.</p></body></html>",
+            ),
+            _ => response("404 Not Found", &[], b""),
+        })
+        .await;
+        let config = test_config("unchanged");
+        let home = server.url("/").to_string();
+        let guide = server.url("/guide").to_string();
+        let mut entry = UrlEntry::new(&home, "site", 1).unwrap();
+        let mut crawler = Crawler::new(config.clone()).unwrap();
+
+        let first = crawler.process_entry(&mut entry).await.unwrap();
+        let first_outcomes = outcomes(&first);
+        assert_eq!(first_outcomes[&home], PageOutcome::Saved);
+        assert_eq!(first_outcomes[&guide], PageOutcome::Saved);
+
+        let second = crawler.process_entry(&mut entry).await.unwrap();
+        let second_outcomes = outcomes(&second);
+        assert_eq!(second_outcomes[&home], PageOutcome::Unchanged);
+        assert_eq!(second_outcomes[&guide], PageOutcome::Unchanged);
+        assert_eq!(second.pages.iter().find(|page| page.url == home).unwrap().http_status, Some(304));
+
+        // The saved copy answered the 304 and still leads to the guide
+        let manifest = Manifest::load(&config.outputs_path.join("site")).unwrap();
+        let saved = config.outputs_path.join("site").join(&manifest.get(&home).unwrap().saved_path);
+        assert!(std::fs::read_to_string(saved).unwrap().contains("Welcome to the manual."));
+        let home_requests: Vec<String> = server
+            .requests()
+            .into_iter()
+            .filter(|head| request_path(head) == "/")
+            .collect();
+        assert_eq!(home_requests.len(), 2);
+        assert!(!home_requests[0].contains("if-none-match"));
+        assert!(home_requests[1].contains("if-none-match: \"home\""));
+        std::fs::remove_dir_all(&config.outputs_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn files_with_old_names_are_removed() {
+        let server = TestServer::start(|head| match request_path(head) {
+            "/" => response("200 OK", &[("Content-Type", "text/html")], b"<html><body><h1>Home</h1></body></html>"),
+            _ => response("404 Not Found", &[], b""),
+        })
+        .await;
+        let config = test_config("legacy");
+        let entry_dir = config.outputs_path.join("site");
+        let html_dir = entry_dir.join(&config.output_parts_html_suffix);
+        let md_dir = entry_dir.join(&config.output_parts_markdown_suffix);
+        std::fs::create_dir_all(&html_dir).unwrap();
+        std::fs::create_dir_all(&md_dir).unwrap();
+        std::fs::write(html_dir.join("127.0.0.1.html"), "<h1>Home</h1>").unwrap();
+        std::fs::write(md_dir.join("127.0.0.1.md"), "# Home").unwrap();
+
+        let home = server.url("/").to_string();
+        let mut entry = UrlEntry::new(&home, "site", 0).unwrap();
+        let result = Crawler::new(config.clone()).unwrap().process_entry(&mut entry).await.unwrap();
+
+        assert!(result.selection_changed);
+        assert!(!html_dir.join("127.0.0.1.html").exists());
+        assert!(!md_dir.join("127.0.0.1.md").exists());
+        let manifest = Manifest::load(&entry_dir).unwrap();
+        assert!(entry_dir.join(&manifest.get(&home).unwrap().saved_path).exists());
+        std::fs::remove_dir_all(&config.outputs_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn dry_runs_plan_the_crawl_without_saving() {
+        let server = TestServer::start(|head| match request_path(head) {
+            "/" => response(
+                "200 OK",
+                &[("Content-Type", "text/html")],
+                b"<html><body><a href=\"/guide\">Guide</a><a href=\"https://other.org/\">Elsewhere</a></body></html>",
+            ),
+            "/guide" => response(
+                "200 OK",
+                &[("Content-Type", "text/html")],
+                b"<html><body><a href=\"/guide/deep\">Deeper</a></body></html>",
+            ),
+            _ => response("404 Not Found", &[], b""),
+        })
+        .await;
+        let config = test_config("plan");
+        let home = server.url("/").to_string();
+        let entry = UrlEntry::new(&home, "site", 1).unwrap();
+
+        let plan = Crawler::new(config.clone()).unwrap().plan_entry(&entry).await.unwrap();
+
+        let pages: Vec<&str> = plan.pages.iter().map(|page| page.url.as_str()).collect();
+        assert_eq!(pages, [home.as_str(), server.url("/guide").as_str()]);
+        assert_eq!(plan.pages[1].found_on.as_deref(), Some(home.as_str()));
+        let exclusion = |url: &str| plan.excluded.iter().find(|excluded| excluded.url == url).map(|excluded| &excluded.exclusion);
+        assert!(matches!(exclusion("https://other.org/"), Some(Exclusion::Scope(_))));
+        assert_eq!(
+            exclusion(server.url("/guide/deep").as_str()),
+            Some(&Exclusion::Depth { max_depth: 1 })
+        );
+        assert!(server.requests().iter().all(|head| request_path(head) != "/guide/deep"));
+        assert!(!config.outputs_path.exists());
+    }
+
+    #[tokio::test]
+    async fn replaced_media_is_removed_from_the_entry() {
+        // The logo has no validators and changes between the two runs
+        let runs = Arc::new(std::sync::atomic::AtomicU8::new(0));
+        let served = Arc::clone(&runs);
+        let server = TestServer::start(move |head| match request_path(head) {
+            "/" => response(
+                "200 OK",
+                &[("Content-Type", "text/html")],
+                b"<html><body><h1>Home</h1><p>Welcome to the manual.</p><img src=\"/logo.png\"></body></html>",
+            ),
+            "/logo.png" => {
+                let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+                png.extend_from_slice(&[served.load(std::sync::atomic::Ordering::Relaxed); 80]);
+                response("200 OK", &[("Content-Type", "image/png")], &png)
+            }
+            _ => response("404 Not Found", &[], b""),
+        })
+        .await;
+        let config = test_config("media");
+        let mut entry = UrlEntry::new(server.url("/").as_str(), "site", 0).unwrap();
+        let mut crawler = Crawler::new(config.clone()).unwrap();
+        let entry_dir = config.outputs_path.join("site");
+        let media_files = || -> Vec<PathBuf> {
+            std::fs::read_dir(entry_dir.join(&config.output_parts_media_suffix))
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .collect()
+        };
+
+        crawler.process_entry(&mut entry).await.unwrap();
+        let first = media_files();
+        assert_eq!(first.len(), 1);
+
+        runs.store(1, std::sync::atomic::Ordering::Relaxed);
+        crawler.process_entry(&mut entry).await.unwrap();
+        let second = media_files();
+        assert_eq!(second.len(), 1);
+        assert_ne!(second, first);
+        let manifest = Manifest::load(&entry_dir).unwrap();
+        let logo = manifest.media.get(server.url("/logo.png").as_str()).unwrap();
+        assert_eq!(entry_dir.join(&logo.saved_path), second[0]);
+        std::fs::remove_dir_all(&config.outputs_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn other_docs_versions_are_left_out() {
+        let server = TestServer::start(|head| {
+            let page: &[u8] = match request_path(head) {
+                "/v1/" => b"<html><body><h1>Manual 1</h1><p>Welcome to the first manual.</p>\
+                    <a href=\"/v1/guide\">Guide</a><a href=\"/v2/\">Version 2</a></body></html>",
+                "/v1/guide" => b"<html><body><h1>Guide 1</h1><p>Install the first release with make.</p></body></html>",
+                "/v2/" => b"<html><head><meta name=\"docsearch:version\" content=\"2.0\"></head>\
+                    <body><h1>Manual 2</h1><p>Welcome to the second manual.</p>\
+                    <a href=\"/v2/guide\">Guide</a><a href=\"/v1/\">Version 1</a></body></html>",
+                "/v2/guide" => b"<html><body><h1>Guide 2</h1><p>Install the second release with cargo.</p></body></html>",
+                _ => return response("404 Not Found", &[], b""),
+            };
+            response("200 OK", &[("Content-Type", "text/html")], page)
+        })
+        .await;
+        let config = test_config("versions");
+        let entry_dir = config.outputs_path.join("site");
+        let mut entry = UrlEntry::new(server.url("/v1/").as_str(), "site", 2).unwrap();
+
+        // Without a target every version is saved
+        let first = Crawler::new(config.clone()).unwrap().process_entry(&mut entry).await.unwrap();
+        assert!(first.docs_version.is_none());
+        let manifest = Manifest::load(&entry_dir).unwrap();
+        let v1_guide = manifest.get(server.url("/v1/guide").as_str()).unwrap().saved_path.clone();
+        assert!(entry_dir.join(&v1_guide).exists());
+        assert!(manifest.get(server.url("/v2/guide").as_str()).is_some());
+
+        let config = DoclingConfig {
+            versions: VersionSettings {
+                target: Some("v2".to_string()),
+            },
+            ..config
+        };
+        let requests_before = server.requests().len();
+        let second = Crawler::new(config.clone()).unwrap().process_entry(&mut entry).await.unwrap();
+
+        let docs_version = second.docs_version.clone().unwrap();
+        assert_eq!(docs_version.requested, "v2");
+        assert_eq!(docs_version.resolved, "2.0");
+        assert_eq!(docs_version.path_segment.as_deref(), Some("v2"));
+        assert_eq!(docs_version.root, server.url("/v2/").as_str());
+        assert_eq!(docs_version.available, ["v2", "v1"]);
+        let second_outcomes = outcomes(&second);
+        assert_eq!(second_outcomes[server.url("/v2/guide").as_str()], PageOutcome::Unchanged);
+        assert!(!second_outcomes.contains_key(server.url("/v1/guide").as_str()));
+        // Only the entry URL is read from the first version, for its version switcher
+        let v1_requests: Vec<String> = server.requests()[requests_before..]
+            .iter()
+            .map(|head| request_path(head).to_string())
+            .filter(|path| path.starts_with("/v1/"))
+            .collect();
+        assert_eq!(v1_requests, ["/v1/"]);
+
+        let manifest = Manifest::load(&entry_dir).unwrap();
+        assert!(manifest.pages.keys().all(|url| !url.contains("/v1/")));
+        assert!(manifest.get(server.url("/v2/guide").as_str()).is_some());
+        assert!(!entry_dir.join(&v1_guide).exists());
+        std::fs::remove_dir_all(&config.outputs_path).unwrap();
     }
 }
@@ -0,0 +1,738 @@
+//! Text extraction for linked documents
+//!
+//! Documents saved by the crawler (PDF, DOCX, Markdown, plain text,
+//! reStructuredText and AsciiDoc) are turned into Markdown so they can be
+//! combined with the converted HTML pages. PDF and DOCX support depend on the
+//! `pdf` and `docx` features; AsciiDoc markup is rewritten to Markdown and the
+//! other text formats are passed through after decoding to UTF-8.
+
+use tracing::debug;
+
+use crate::charset::sniff_encoding;
+use crate::{DoclingError, DocumentType, Result};
+
+/// Extract the content of a saved document as Markdown
+///
+/// # Arguments
+/// * `document_type` - Format of the document
+/// * `content` - Raw content of the document as it was downloaded
+///
+/// # Errors
+/// Returns an error if the document cannot be parsed or support for its
+/// format is not compiled in
+pub fn extract_markdown(document_type: DocumentType, content: &[u8]) -> Result<String> {
+    debug!("Extracting {} bytes of {:?}", content.len(), document_type);
+    match document_type {
+        DocumentType::Pdf => extract_pdf(content),
+        DocumentType::Docx => extract_docx(content),
+        DocumentType::Markdown | DocumentType::Text | DocumentType::Rst => Ok(decode_text(content)),
+        DocumentType::AsciiDoc => Ok(asciidoc_to_markdown(&decode_text(content))),
+    }
+}
+
+/// Decode a text document to UTF-8
+fn decode_text(content: &[u8]) -> String {
+    let (encoding, _) = sniff_encoding(content, None, None);
+    let (text, _) = encoding.decode_with_bom_removal(content);
+    text.into_owned()
+}
+
+/// Extract the text of a PDF
+#[cfg(feature = "pdf")]
+fn extract_pdf(content: &[u8]) -> Result<String> {
+    // pdf-extract panics on some malformed files
+    let result = std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem(content));
+    let text = match result {
+        Ok(Ok(text)) => text,
+        Ok(Err(e)) => return Err(DoclingError::conversion_with("PDF text extraction failed", e)),
+        Err(_) => return Err(DoclingError::conversion("PDF text extraction panicked")),
+    };
+    if text.trim().is_empty() {
+        return Err(DoclingError::conversion(
+            "PDF contains no extractable text (it may be scanned)",
+        ));
+    }
+    Ok(pdf_text_to_markdown(&text))
+}
+
+#[cfg(not(feature = "pdf"))]
+fn extract_pdf(_content: &[u8]) -> Result<String> {
+    Err(DoclingError::conversion("pdf feature is not enabled"))
+}
+
+/// Tidy the text layout produced by PDF extraction
+///
+/// Page breaks become blank lines, trailing spaces are removed and runs of
+/// blank lines are collapsed.
+#[cfg(feature = "pdf")]
+fn pdf_text_to_markdown(text: &str) -> String {
+    let mut markdown = String::with_capacity(text.len());
+    let mut blank_lines = 0;
+    for line in text.replace('\u{c}', "\n\n").lines() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            blank_lines += 1;
+            continue;
+        }
+        if !markdown.is_empty() {
+            markdown.push_str(if blank_lines > 0 { "\n\n" } else { "\n" });
+        }
+        markdown.push_str(line);
+        blank_lines = 0;
+    }
+    markdown.push('\n');
+    markdown
+}
+
+/// Convert the main part of a DOCX package to Markdown
+#[cfg(feature = "docx")]
+fn extract_docx(content: &[u8]) -> Result<String> {
+    use std::io::{Cursor, Read};
+
+    let mut archive = zip::ZipArchive::new(Cursor::new(content))
+        .map_err(|e| DoclingError::conversion_with("DOCX file is not a valid ZIP package", e))?;
+    let mut part = archive
+        .by_name("word/document.xml")
+        .map_err(|e| DoclingError::conversion_with("DOCX package has no word/document.xml", e))?;
+    let mut xml = String::new();
+    part.read_to_string(&mut xml)
+        .map_err(|e| DoclingError::conversion_with("Failed to read word/document.xml", e))?;
+    docx::to_markdown(&xml)
+}
+
+#[cfg(not(feature = "docx"))]
+fn extract_docx(_content: &[u8]) -> Result<String> {
+    Err(DoclingError::conversion("docx feature is not enabled"))
+}
+
+/// WordprocessingML to Markdown
+///
+/// Covers headings, bold and italic runs, list paragraphs, line breaks and
+/// tables; everything else is reduced to its text.
+#[cfg(feature = "docx")]
+mod docx {
+    use quick_xml::events::{BytesStart, Event};
+    use quick_xml::Reader;
+
+    use crate::{DoclingError, Result};
+
+    /// A paragraph being read
+    #[derive(Default)]
+    struct Paragraph {
+        text: String,
+        heading: Option<usize>,
+        list_level: Option<usize>,
+    }
+
+    /// A run of text with uniform formatting
+    #[derive(Default)]
+    struct Run {
+        text: String,
+        bold: bool,
+        italic: bool,
+    }
+
+    /// A finished block of the document
+    struct Block {
+        markdown: String,
+        is_list_item: bool,
+    }
+
+    #[derive(Default)]
+    struct Writer {
+        blocks: Vec<Block>,
+        paragraph: Option<Paragraph>,
+        run: Option<Run>,
+        in_text: bool,
+        in_run_properties: bool,
+        /// Open tables, innermost last; each is a list of rows of cells
+        tables: Vec<Vec<Vec<String>>>,
+    }
+
+    /// Convert the XML of `word/document.xml` to Markdown
+    pub(super) fn to_markdown(xml: &str) -> Result<String> {
+        let mut reader = Reader::from_str(xml);
+        let mut writer = Writer::default();
+
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(element)) => writer.open(&element),
+                Ok(Event::Empty(element)) => {
+                    writer.open(&element);
+                    writer.close(element.local_name().as_ref());
+                }
+                Ok(Event::End(element)) => writer.close(element.local_name().as_ref()),
+                Ok(Event::Text(text)) if writer.in_text => {
+                    let text = text
+                        .unescape()
+                        .map_err(|e| DoclingError::conversion_with("Invalid text in DOCX", e))?;
+                    writer.push_text(&text);
+                }
+                Ok(Event::Eof) => break,
+                Ok(_) => {}
+                Err(e) => return Err(DoclingError::conversion_with("Invalid DOCX XML", e)),
+            }
+        }
+
+        let mut markdown = String::new();
+        let mut previous_is_list_item = false;
+        for block in writer.blocks {
+            if !markdown.is_empty() {
+                markdown.push_str(if previous_is_list_item && block.is_list_item { "\n" } else { "\n\n" });
+            }
+            markdown.push_str(&block.markdown);
+            previous_is_list_item = block.is_list_item;
+        }
+        markdown.push('\n');
+        Ok(markdown)
+    }
+
+    /// Value of the `w:val` attribute of an element
+    fn val(element: &BytesStart<'_>) -> Option<String> {
+        element
+            .attributes()
+            .filter_map(std::result::Result::ok)
+            .find(|attr| attr.key.local_name().as_ref() == b"val")
+            .and_then(|attr| attr.unescape_value().ok())
+            .map(|value| value.into_owned())
+    }
+
+    /// Whether a toggle property such as `<w:b/>` is switched on
+    fn is_on(element: &BytesStart<'_>) -> bool {
+        !matches!(val(element).as_deref(), Some("0" | "false" | "off" | "none"))
+    }
+
+    impl Writer {
+        fn open(&mut self, element: &BytesStart<'_>) {
+            match element.local_name().as_ref() {
+                b"p" => self.paragraph = Some(Paragraph::default()),
+                b"r" => self.run = Some(Run::default()),
+                b"rPr" => self.in_run_properties = true,
+                b"t" => self.in_text = true,
+                b"b" if self.in_run_properties => {
+                    if let Some(run) = &mut self.run {
+                        run.bold = is_on(element);
+                    }
+                }
+                b"i" if self.in_run_properties => {
+                    if let Some(run) = &mut self.run {
+                        run.italic = is_on(element);
+                    }
+                }
+                b"tab" => self.push_text(" "),
+                b"br" => self.push_text(if self.tables.is_empty() { "  \n" } else { " " }),
+                b"pStyle" => {
+                    if let (Some(paragraph), Some(style)) = (&mut self.paragraph, val(element)) {
+                        paragraph.heading = heading_level(&style);
+                        if style.starts_with("List") {
+                            paragraph.list_level.get_or_insert(0);
+                        }
+                    }
+                }
+                b"numPr" => {
+                    if let Some(paragraph) = &mut self.paragraph {
+                        paragraph.list_level.get_or_insert(0);
+                    }
+                }
+                b"ilvl" => {
+                    if let Some(paragraph) = &mut self.paragraph {
+                        paragraph.list_level = val(element).and_then(|level| level.parse().ok()).or(Some(0));
+                    }
+                }
+                b"tbl" => self.tables.push(Vec::new()),
+                b"tr" => {
+                    if let Some(table) = self.tables.last_mut() {
+                        table.push(Vec::new());
+                    }
+                }
+                b"tc" => {
+                    if let Some(row) = self.tables.last_mut().and_then(|table| table.last_mut()) {
+                        row.push(String::new());
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        fn close(&mut self, name: &[u8]) {
+            match name {
+                b"t" => self.in_text = false,
+                b"rPr" => self.in_run_properties = false,
+                b"r" => {
+                    if let Some(run) = self.run.take() {
+                        let text = emphasize(&run);
+                        if let Some(paragraph) = &mut self.paragraph {
+                            paragraph.text.push_str(&text);
+                        }
+                    }
+                }
+                b"p" => {
+                    if let Some(paragraph) = self.paragraph.take() {
+                        self.finish_paragraph(paragraph);
+                    }
+                }
+                b"tbl" => {
+                    if let Some(table) = self.tables.pop() {
+                        self.finish_table(table);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        fn push_text(&mut self, text: &str) {
+            if let Some(run) = &mut self.run {
+                run.text.push_str(text);
+            } else if let Some(paragraph) = &mut self.paragraph {
+                paragraph.text.push_str(text);
+            }
+        }
+
+        fn finish_paragraph(&mut self, paragraph: Paragraph) {
+            let text = paragraph.text.trim();
+            if text.is_empty() {
+                return;
+            }
+
+            // Paragraphs inside a table make up the text of the current cell
+            if let Some(cell) = self
+                .tables
+                .last_mut()
+                .and_then(|table| table.last_mut())
+                .and_then(|row| row.last_mut())
+            {
+                if !cell.is_empty() {
+                    cell.push(' ');
+                }
+                cell.push_str(text);
+                return;
+            }
+
+            let (markdown, is_list_item) = match (paragraph.heading, paragraph.list_level) {
+                (Some(level), _) => (format!("{} {}", "#".repeat(level), text), false),
+                (None, Some(level)) => (format!("{}- {}", "  ".repeat(level), text), true),
+                (None, None) => (text.to_string(), false),
+            };
+            self.blocks.push(Block { markdown, is_list_item });
+        }
+
+        fn finish_table(&mut self, table: Vec<Vec<String>>) {
+            let rows: Vec<Vec<String>> = table.into_iter().filter(|row| !row.is_empty()).collect();
+            if rows.is_empty() {
+                return;
+            }
+
+            // A nested table is flattened into the cell that contains it
+            if let Some(cell) = self
+                .tables
+                .last_mut()
+                .and_then(|table| table.last_mut())
+                .and_then(|row| row.last_mut())
+            {
+                let text: Vec<String> = rows.iter().map(|row| row.join(", ")).collect();
+                if !cell.is_empty() {
+                    cell.push(' ');
+                }
+                cell.push_str(&text.join("; "));
+                return;
+            }
+
+            let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+            let format_row = |row: &Vec<String>| {
+                let cells: Vec<String> = (0..columns)
+                    .map(|i| row.get(i).map(|cell| cell.replace('|', "\\|")).unwrap_or_default())
+                    .collect();
+                format!("| {} |", cells.join(" | "))
+            };
+
+            let mut lines = vec![format_row(&rows[0])];
+            lines.push(format!("|{}", " --- |".repeat(columns)));
+            lines.extend(rows[1..].iter().map(format_row));
+            self.blocks.push(Block {
+                markdown: lines.join("\n"),
+                is_list_item: false,
+            });
+        }
+    }
+
+    /// Heading level for a paragraph style such as `Heading2` or `Title`
+    fn heading_level(style: &str) -> Option<usize> {
+        if style == "Title" {
+            return Some(1);
+        }
+        let level: usize = style.strip_prefix("Heading")?.parse().ok()?;
+        (1..=6).contains(&level).then_some(level)
+    }
+
+    /// Text of a run with Markdown emphasis, keeping surrounding spaces outside
+    fn emphasize(run: &Run) -> String {
+        let marker = match (run.bold, run.italic) {
+            (true, true) => "***",
+            (true, false) => "**",
+            (false, true) => "*",
+            (false, false) => return run.text.clone(),
+        };
+        let trimmed = run.text.trim();
+        if trimmed.is_empty() {
+            return run.text.clone();
+        }
+        let leading = &run.text[..run.text.len() - run.text.trim_start().len()];
+        let trailing = &run.text[run.text.trim_end().len()..];
+        format!("{}{}{}{}{}", leading, marker, trimmed, marker, trailing)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn converts_headings_lists_and_tables() {
+            let xml = r#"<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:body>
+<w:p><w:pPr><w:pStyle w:val="Heading1"/></w:pPr><w:r><w:t>Register map</w:t></w:r></w:p>
+<w:p><w:r><w:t xml:space="preserve">The </w:t></w:r><w:r><w:rPr><w:b/></w:rPr><w:t>CTRL</w:t></w:r><w:r><w:t xml:space="preserve"> register.</w:t></w:r></w:p>
+<w:p><w:pPr><w:numPr><w:ilvl w:val="0"/><w:numId w:val="1"/></w:numPr></w:pPr><w:r><w:t>Enable</w:t></w:r></w:p>
+<w:p><w:pPr><w:numPr><w:ilvl w:val="1"/><w:numId w:val="1"/></w:numPr></w:pPr><w:r><w:t>Reset</w:t></w:r></w:p>
+<w:tbl><w:tr><w:tc><w:p><w:r><w:t>Bit</w:t></w:r></w:p></w:tc><w:tc><w:p><w:r><w:t>Name</w:t></w:r></w:p></w:tc></w:tr>
+<w:tr><w:tc><w:p><w:r><w:t>0</w:t></w:r></w:p></w:tc><w:tc><w:p><w:r><w:t>EN|ABLE</w:t></w:r></w:p></w:tc></w:tr></w:tbl>
+</w:body></w:document>"#;
+            assert_eq!(
+                to_markdown(xml).unwrap(),
+                "# Register map\n\nThe **CTRL** register.\n\n- Enable\n  - Reset\n\n\
+                 | Bit | Name |\n| --- | --- |\n| 0 | EN\\|ABLE |\n"
+            );
+        }
+    }
+}
+
+/// Convert an AsciiDoc document to Markdown
+fn asciidoc_to_markdown(text: &str) -> String {
+    asciidoc::to_markdown(text)
+}
+
+/// AsciiDoc to Markdown
+///
+/// Covers section titles, paragraphs, lists, admonitions, listing, literal
+/// and quote blocks, simple tables, links, images and inline emphasis;
+/// attributes, comments and block options are dropped.
+mod asciidoc {
+    use std::sync::LazyLock;
+
+    use regex::Regex;
+
+    /// `*strong*` with constrained boundaries
+    static STRONG: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"(^|[^\w*])\*([^*\s](?:[^*]*[^*\s])?)\*($|[^\w*])").expect("valid strong regex")
+    });
+
+    /// `_emphasis_` with constrained boundaries
+    static EMPHASIS: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"(^|[^\w_])_([^_\s](?:[^_]*[^_\s])?)_($|[^\w_])").expect("valid emphasis regex")
+    });
+
+    /// `image:path[alt]` and `image::path[alt]`
+    static IMAGE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"image::?([^\s\[]+)\[([^\],]*)[^\]]*\]").expect("valid image regex")
+    });
+
+    /// `https://url[text]` and `link:target[text]`
+    static LINK: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"(?:link:([^\s\[]+)|(https?://[^\s\[]+))\[([^\]]*)\]").expect("valid link regex")
+    });
+
+    /// `xref:target[text]`, `<<id,text>>` and `<<id>>`
+    static XREF: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"xref:([^\s\[]+)\[([^\]]*)\]|<<([^,>]+)(?:,\s*([^>]+))?>>").expect("valid xref regex")
+    });
+
+    /// Admonition labels and their Markdown titles
+    const ADMONITIONS: [(&str, &str); 5] = [
+        ("NOTE: ", "Note"),
+        ("TIP: ", "Tip"),
+        ("IMPORTANT: ", "Important"),
+        ("WARNING: ", "Warning"),
+        ("CAUTION: ", "Caution"),
+    ];
+
+    /// Delimited block being read
+    enum Block {
+        /// Listing or literal block, copied verbatim
+        Code(&'static str),
+        /// Comment block, dropped
+        Comment,
+        /// Table, rows of cells
+        Table(Vec<String>),
+    }
+
+    /// Convert an AsciiDoc document
+    pub(super) fn to_markdown(text: &str) -> String {
+        let mut markdown = String::with_capacity(text.len());
+        let mut block: Option<Block> = None;
+        let mut quote = false;
+        let mut source_language = String::new();
+
+        for line in text.lines() {
+            let line = line.trim_end();
+
+            match &mut block {
+                Some(Block::Code(delimiter)) => {
+                    if line == *delimiter {
+                        markdown.push_str("```\n");
+                        block = None;
+                    } else {
+                        markdown.push_str(line);
+                        markdown.push('\n');
+                    }
+                    continue;
+                }
+                Some(Block::Comment) => {
+                    if line == "////" {
+                        block = None;
+                    }
+                    continue;
+                }
+                Some(Block::Table(cells)) => {
+                    if line == "|===" {
+                        markdown.push_str(&table(&std::mem::take(cells)));
+                        block = None;
+                    } else {
+                        cells.extend(line.split('|').skip(1).map(|cell| cell.trim().to_string()));
+                        if !line.is_empty() && !line.starts_with('|') {
+                            // Continuation of the previous cell
+                            if let Some(last) = cells.last_mut() {
+                                last.push(' ');
+                                last.push_str(line);
+                            }
+                        } else if line.is_empty() {
+                            cells.push(String::new());
+                        }
+                    }
+                    continue;
+                }
+                None => {}
+            }
+
+            match line {
+                "----" | "...." | "```" => {
+                    markdown.push_str("```");
+                    markdown.push_str(&source_language);
+                    markdown.push('\n');
+                    source_language.clear();
+                    block = Some(Block::Code(match line {
+                        "----" => "----",
+                        "...." => "....",
+                        _ => "```",
+                    }));
+                    continue;
+                }
+                "////" => {
+                    block = Some(Block::Comment);
+                    continue;
+                }
+                "|===" => {
+                    block = Some(Block::Table(Vec::new()));
+                    continue;
+                }
+                "____" => {
+                    quote = !quote;
+                    continue;
+                }
+                // Example, sidebar and open blocks only group their content
+                "====" | "****" | "--" | "<<<" => continue,
+                "'''" => {
+                    markdown.push_str("---\n");
+                    continue;
+                }
+                _ => {}
+            }
+
+            if line.starts_with("//") || is_attribute_entry(line) {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') && !line.starts_with("[[") {
+                // Block attributes; `[source,lang]` names the language of the next listing
+                let attributes: Vec<&str> = line[1..line.len() - 1].split(',').map(str::trim).collect();
+                if attributes.first() == Some(&"source") {
+                    source_language = attributes.get(1).copied().unwrap_or_default().to_string();
+                }
+                continue;
+            }
+            if line.starts_with("[[") && line.ends_with("]]") {
+                continue;
+            }
+
+            let converted = convert_line(line);
+            if quote && !converted.is_empty() {
+                markdown.push_str("> ");
+            }
+            markdown.push_str(&converted);
+            markdown.push('\n');
+        }
+
+        // An unterminated table still keeps its cells
+        if let Some(Block::Table(cells)) = block {
+            markdown.push_str(&table(&cells));
+        }
+        markdown
+    }
+
+    /// Whether a line sets a document attribute (`:name: value`)
+    fn is_attribute_entry(line: &str) -> bool {
+        line.strip_prefix(':')
+            .and_then(|rest| rest.split_once(':'))
+            .is_some_and(|(name, _)| !name.is_empty() && !name.contains(' '))
+    }
+
+    /// Convert a line outside delimited blocks
+    fn convert_line(line: &str) -> String {
+        // Section titles
+        let level = line.chars().take_while(|c| *c == '=').count();
+        if (1..=6).contains(&level) && line[level..].starts_with(' ') {
+            return format!("{} {}", "#".repeat(level), inline(line[level..].trim()));
+        }
+
+        // Lists: `*`/`-` for bullets and `.` for numbers, repeated for nesting
+        let marker = line.chars().next().unwrap_or_default();
+        if matches!(marker, '*' | '.' | '-') {
+            let depth = line.chars().take_while(|c| *c == marker).count();
+            if line[depth..].starts_with(' ') && (marker != '-' || depth == 1) {
+                let item = inline(line[depth..].trim());
+                return if marker == '.' {
+                    format!("{}1. {}", "   ".repeat(depth - 1), item)
+                } else {
+                    format!("{}- {}", "  ".repeat(depth - 1), item)
+                };
+            }
+        }
+
+        // Block titles
+        if let Some(title) = line.strip_prefix('.')
+            && title.chars().next().is_some_and(|c| !c.is_whitespace() && c != '.')
+        {
+            return format!("**{}**", inline(title));
+        }
+
+        for (label, title) in ADMONITIONS {
+            if let Some(text) = line.strip_prefix(label) {
+                return format!("> **{}:** {}", title, inline(text));
+            }
+        }
+
+        // A trailing ` +` forces a line break
+        match line.strip_suffix(" +") {
+            Some(text) => format!("{}  ", inline(text)),
+            None => inline(line),
+        }
+    }
+
+    /// Convert inline markup
+    fn inline(text: &str) -> String {
+        let text = IMAGE.replace_all(text, "![$2]($1)");
+        let text = LINK.replace_all(&text, |captures: &regex::Captures| {
+            let target = captures.get(1).or_else(|| captures.get(2)).map_or("", |m| m.as_str());
+            match &captures[3] {
+                "" => format!("<{}>", target),
+                label => format!("[{}]({})", label, target),
+            }
+        });
+        let text = XREF.replace_all(&text, |captures: &regex::Captures| {
+            captures
+                .get(2)
+                .or_else(|| captures.get(4))
+                .filter(|label| !label.as_str().is_empty())
+                .or_else(|| captures.get(1))
+                .or_else(|| captures.get(3))
+                .map_or(String::new(), |m| m.as_str().trim().to_string())
+        });
+        // Strong first, so the single asterisks of emphasis are not doubled
+        let text = STRONG.replace_all(&text, "$1**$2**$3");
+        EMPHASIS.replace_all(&text, "$1*$2*$3").into_owned()
+    }
+
+    /// Markdown table from the cells of an AsciiDoc table
+    ///
+    /// The number of columns is taken from the first row, which is used as
+    /// the header.
+    fn table(cells: &[String]) -> String {
+        let mut rows: Vec<Vec<&str>> = Vec::new();
+        let mut columns = 0;
+        let mut row = Vec::new();
+        for cell in cells {
+            if cell.is_empty() && columns == 0 && !row.is_empty() {
+                // A blank line ends the first row and fixes the column count
+                columns = row.len();
+                rows.push(std::mem::take(&mut row));
+                continue;
+            }
+            if cell.is_empty() {
+                continue;
+            }
+            row.push(cell.as_str());
+            if columns > 0 && row.len() == columns {
+                rows.push(std::mem::take(&mut row));
+            }
+        }
+        if !row.is_empty() {
+            if columns == 0 {
+                columns = row.len();
+            }
+            rows.push(row);
+        }
+        if rows.is_empty() {
+            return String::new();
+        }
+
+        let mut markdown = String::from("\n");
+        for (index, row) in rows.iter().enumerate() {
+            let mut cells: Vec<String> = row.iter().map(|cell| inline(cell).replace('|', "\\|")).collect();
+            cells.resize(columns, String::new());
+            markdown.push_str(&format!("| {} |\n", cells.join(" | ")));
+            if index == 0 {
+                markdown.push_str(&format!("|{}\n", " --- |".repeat(columns)));
+            }
+        }
+        markdown.push('\n');
+        markdown
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn asciidoc_markup_becomes_markdown() {
+            let adoc = "= Guide\n:toc: left\n\n== Install\n\n\
+                        NOTE: Needs *root* access.\n\n\
+                        * Download from https://example.com/dl[the site]\n** See <<setup,Setup>>\n\
+                        . First\n.. Nested\n\n\
+                        [source,rust]\n----\nlet x = *y*;\n----\n\n\
+                        |===\n|Name |Value\n\n|a |_1_\n|===\n";
+            assert_eq!(
+                to_markdown(adoc),
+                "# Guide\n\n## Install\n\n\
+                 > **Note:** Needs **root** access.\n\n\
+                 - Download from [the site](https://example.com/dl)\n  - See Setup\n\
+                 1. First\n   1. Nested\n\n\
+                 ```rust\nlet x = *y*;\n```\n\n\
+                 \n| Name | Value |\n| --- | --- |\n| a | *1* |\n\n"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_documents_are_decoded() {
+        let (latin1, _, _) = encoding_rs::WINDOWS_1252.encode("Überblick\n\nDétails");
+        assert_eq!(
+            extract_markdown(DocumentType::Text, &latin1).unwrap(),
+            "Überblick\n\nDétails"
+        );
+
+        let mut markdown = vec![0xEF, 0xBB, 0xBF];
+        markdown.extend_from_slice(b"# Title\n");
+        assert_eq!(extract_markdown(DocumentType::Markdown, &markdown).unwrap(), "# Title\n");
+    }
+}
@@ -0,0 +1,242 @@
+//! Error types for the docling crate
+//!
+//! All public functions in this crate return [`Result`], whose error type is
+//! [`DoclingError`]. Callers embedding the crate (the CLI, the planned web
+//! service) can match on the variant instead of parsing message text, and
+//! map errors to HTTP status codes with [`DoclingError::http_status_code`].
+
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+/// Boxed error used as the source of wrapped third-party errors
+pub type BoxError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// Result type used throughout the docling crate
+pub type Result<T> = std::result::Result<T, DoclingError>;
+
+/// Errors returned by the docling crate
+#[derive(Debug, Error)]
+pub enum DoclingError {
+    /// No entry with the given name exists
+    #[error("Entry with name '{name}' does not exist")]
+    NotFound {
+        /// Name of the missing entry
+        name: String,
+    },
+
+    /// An entry with the given name already exists
+    #[error("Entry with name '{name}' already exists")]
+    AlreadyExists {
+        /// Name of the existing entry
+        name: String,
+    },
+
+    /// The entry is disabled and cannot be run
+    #[error("Entry '{name}' is disabled")]
+    Disabled {
+        /// Name of the disabled entry
+        name: String,
+    },
+
+    /// Invalid configuration, entry definition or input value
+    #[error("{message}")]
+    Config {
+        /// Description of the problem
+        message: String,
+        /// Underlying error, if any
+        #[source]
+        source: Option<BoxError>,
+    },
+
+    /// An HTTP request failed or returned an unsuccessful status
+    #[error("{}", http_message(.status, .url))]
+    Http {
+        /// HTTP status code, if a response was received
+        status: Option<u16>,
+        /// URL of the request
+        url: String,
+        /// Underlying transport error, if any
+        #[source]
+        source: Option<BoxError>,
+    },
+
+    /// The URL is disallowed by robots.txt
+    #[error("Fetching '{url}' is disallowed by robots.txt")]
+    Robots {
+        /// URL that was disallowed
+        url: String,
+    },
+
+    /// A filesystem operation failed
+    #[error("{context}")]
+    Io {
+        /// What was being done when the error occurred
+        context: String,
+        /// Path involved, if any
+        path: Option<PathBuf>,
+        /// Underlying I/O error
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// A size limit or budget was exceeded
+    #[error("{what} exceeds the limit of {limit} bytes")]
+    LimitExceeded {
+        /// What exceeded the limit
+        what: String,
+        /// The limit in bytes
+        limit: u64,
+    },
+
+    /// Converting or processing a document failed
+    #[error("{message}")]
+    Conversion {
+        /// Description of the problem
+        message: String,
+        /// Underlying error, if any
+        #[source]
+        source: Option<BoxError>,
+    },
+}
+
+fn http_message(status: &Option<u16>, url: &str) -> String {
+    match status {
+        Some(status) => format!("HTTP {} for '{}'", status, url),
+        None => format!("HTTP request to '{}' failed", url),
+    }
+}
+
+impl DoclingError {
+    /// Create a configuration error without an underlying source
+    pub fn config(message: impl Into<String>) -> Self {
+        DoclingError::Config {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    /// Create a configuration error wrapping an underlying source
+    pub fn config_with(message: impl Into<String>, source: impl Into<BoxError>) -> Self {
+        DoclingError::Config {
+            message: message.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    /// Create a conversion error without an underlying source
+    pub fn conversion(message: impl Into<String>) -> Self {
+        DoclingError::Conversion {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    /// Create a conversion error wrapping an underlying source
+    pub fn conversion_with(message: impl Into<String>, source: impl Into<BoxError>) -> Self {
+        DoclingError::Conversion {
+            message: message.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    /// Create an HTTP error for a response with an unsuccessful status
+    pub fn http_response(status: u16, url: impl Into<String>) -> Self {
+        DoclingError::Http {
+            status: Some(status),
+            url: url.into(),
+            source: None,
+        }
+    }
+
+    /// Create an HTTP error for a request that did not produce a response
+    pub fn http_transport(url: impl Into<String>, source: impl Into<BoxError>) -> Self {
+        DoclingError::Http {
+            status: None,
+            url: url.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    /// Create an I/O error with context and the path involved
+    pub fn io(context: impl Into<String>, path: impl AsRef<Path>, source: std::io::Error) -> Self {
+        DoclingError::Io {
+            context: context.into(),
+            path: Some(path.as_ref().to_path_buf()),
+            source,
+        }
+    }
+
+    /// HTTP status code the web service should use when reporting this error
+    pub fn http_status_code(&self) -> u16 {
+        match self {
+            DoclingError::NotFound { .. } => 404,
+            DoclingError::AlreadyExists { .. } => 409,
+            DoclingError::Disabled { .. } => 409,
+            DoclingError::Config { .. } => 400,
+            DoclingError::Http { .. } => 502,
+            DoclingError::Robots { .. } => 403,
+            DoclingError::Io { .. } => 500,
+            DoclingError::LimitExceeded { .. } => 413,
+            DoclingError::Conversion { .. } => 500,
+        }
+    }
+}
+
+/// Extension trait for attaching context to fallible I/O operations
+///
+/// Mirrors `anyhow::Context` so call sites keep their descriptive messages
+/// while producing a [`DoclingError::Io`].
+pub trait IoContext<T> {
+    /// Wrap the error with a context message
+    fn io_context(self, context: impl Into<String>) -> Result<T>;
+
+    /// Wrap the error with a context message and the path involved
+    fn io_path_context(self, context: impl Into<String>, path: impl AsRef<Path>) -> Result<T>;
+}
+
+impl<T> IoContext<T> for std::result::Result<T, std::io::Error> {
+    fn io_context(self, context: impl Into<String>) -> Result<T> {
+        self.map_err(|source| DoclingError::Io {
+            context: context.into(),
+            path: None,
+            source,
+        })
+    }
+
+    fn io_path_context(self, context: impl Into<String>, path: impl AsRef<Path>) -> Result<T> {
+        self.map_err(|source| DoclingError::io(context, path, source))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variants_map_to_status_codes() {
+        let io = std::io::Error::new(std::io::ErrorKind::NotFound, "missing");
+        let cases = [
+            (DoclingError::NotFound { name: "docs".to_string() }, 404),
+            (DoclingError::AlreadyExists { name: "docs".to_string() }, 409),
+            (DoclingError::Disabled { name: "docs".to_string() }, 409),
+            (DoclingError::config("bad depth"), 400),
+            (DoclingError::http_response(500, "https://example.com/"), 502),
+            (DoclingError::http_transport("https://example.com/", std::io::Error::other("reset")), 502),
+            (DoclingError::Robots { url: "https://example.com/".to_string() }, 403),
+            (DoclingError::io("Failed to read", "entries.toml", io), 500),
+            (DoclingError::LimitExceeded { what: "Page".to_string(), limit: 10 }, 413),
+            (DoclingError::conversion("htmd failed"), 500),
+        ];
+        for (error, status) in cases {
+            assert_eq!(error.http_status_code(), status, "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn http_errors_name_the_status_and_url() {
+        let error = DoclingError::http_response(404, "https://example.com/missing");
+        assert_eq!(error.to_string(), "HTTP 404 for 'https://example.com/missing'");
+        assert!(matches!(error, DoclingError::Http { status: Some(404), .. }));
+    }
+}
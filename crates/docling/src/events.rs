@@ -0,0 +1,280 @@
+//! Progress events emitted by the crawl/convert/process pipeline
+//!
+//! Each pipeline stage reports what it is doing through a [`ProgressReporter`].
+//! The reporter keeps running totals and forwards every event, together with
+//! a snapshot of the totals, to a [`ProgressObserver`]. Tokio channel senders
+//! implement the observer trait, so the CLI can render progress from an
+//! `mpsc` receiver and the web service can fan events out with `broadcast`.
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use tokio::sync::{broadcast, mpsc};
+
+use crate::budget::BudgetLimit;
+
+/// A single thing that happened while running an entry
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    /// The crawler received a page
+    PageDiscovered {
+        /// URL of the page
+        url: String,
+    },
+    /// A page was saved to disk
+    PageSaved {
+        /// URL of the page
+        url: String,
+        /// Where the page was saved
+        path: PathBuf,
+        /// Size of the saved content in bytes
+        bytes: u64,
+    },
+    /// A page could not be fetched, decoded or saved
+    PageFailed {
+        /// URL of the page
+        url: String,
+        /// Why it failed
+        reason: String,
+    },
+    /// A media file was saved to disk
+    MediaSaved {
+        /// URL of the media file
+        url: String,
+        /// Where the file was saved
+        path: PathBuf,
+        /// Size of the file in bytes
+        bytes: u64,
+    },
+    /// Conversion to Markdown started
+    ConversionStarted {
+        /// Number of files to convert
+        total: usize,
+    },
+    /// Conversion to Markdown finished
+    ConversionFinished {
+        /// Number of files converted successfully
+        converted: usize,
+        /// Number of files that failed to convert
+        failed: usize,
+    },
+    /// The combined result file was written
+    ProcessingDone {
+        /// Path of the result file
+        output: PathBuf,
+        /// Size of the result file in bytes
+        bytes: u64,
+    },
+    /// A failed run will be retried
+    RetryScheduled {
+        /// Number of the attempt that failed (starting at 1)
+        attempt: u32,
+        /// Delay before the next attempt in seconds
+        delay_secs: u64,
+        /// Error that caused the retry
+        reason: String,
+    },
+    /// The crawl stopped early because a limit of its budget was reached
+    CrawlTruncated {
+        /// Limit that was reached
+        limit: BudgetLimit,
+    },
+}
+
+/// Running totals for a single run of an entry
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProgressTotals {
+    /// Pages received from the crawler
+    pub pages_discovered: usize,
+    /// Pages saved to disk
+    pub pages_saved: usize,
+    /// Pages that failed
+    pub pages_failed: usize,
+    /// Media files saved to disk
+    pub media_saved: usize,
+    /// Bytes of page content saved
+    pub page_bytes: u64,
+    /// Bytes of media saved
+    pub media_bytes: u64,
+    /// Files converted to Markdown
+    pub files_converted: usize,
+    /// Files that failed to convert
+    pub conversion_failures: usize,
+}
+
+impl ProgressTotals {
+    fn apply(&mut self, event: &ProgressEvent) {
+        match event {
+            ProgressEvent::PageDiscovered { .. } => self.pages_discovered += 1,
+            ProgressEvent::PageSaved { bytes, .. } => {
+                self.pages_saved += 1;
+                self.page_bytes += bytes;
+            }
+            ProgressEvent::PageFailed { .. } => self.pages_failed += 1,
+            ProgressEvent::MediaSaved { bytes, .. } => {
+                self.media_saved += 1;
+                self.media_bytes += bytes;
+            }
+            ProgressEvent::ConversionFinished { converted, failed } => {
+                self.files_converted = *converted;
+                self.conversion_failures = *failed;
+            }
+            ProgressEvent::ConversionStarted { .. }
+            | ProgressEvent::ProcessingDone { .. }
+            | ProgressEvent::RetryScheduled { .. }
+            | ProgressEvent::CrawlTruncated { .. } => {}
+        }
+    }
+}
+
+/// An event together with the entry it belongs to and the totals after it
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgressUpdate {
+    /// Name of the entry being run
+    pub entry: String,
+    /// What happened
+    pub event: ProgressEvent,
+    /// Totals including this event
+    pub totals: ProgressTotals,
+}
+
+/// Receiver of progress updates
+pub trait ProgressObserver: Send + Sync {
+    /// Called for every update; must not block
+    fn on_progress(&self, update: &ProgressUpdate);
+}
+
+/// Observer that ignores all updates
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopObserver;
+
+impl ProgressObserver for NoopObserver {
+    fn on_progress(&self, _update: &ProgressUpdate) {}
+}
+
+impl ProgressObserver for mpsc::UnboundedSender<ProgressUpdate> {
+    fn on_progress(&self, update: &ProgressUpdate) {
+        // The receiver going away just means nobody is watching anymore
+        let _ = self.send(update.clone());
+    }
+}
+
+impl ProgressObserver for broadcast::Sender<ProgressUpdate> {
+    fn on_progress(&self, update: &ProgressUpdate) {
+        let _ = self.send(update.clone());
+    }
+}
+
+/// Emits progress events for one entry and keeps running totals
+///
+/// Cheap to clone; all clones share the same totals and observer.
+#[derive(Clone)]
+pub struct ProgressReporter {
+    /// Name of the entry being run
+    entry: Arc<str>,
+    /// Where updates are sent
+    observer: Arc<dyn ProgressObserver>,
+    /// Totals shared between clones
+    totals: Arc<Mutex<ProgressTotals>>,
+}
+
+impl ProgressReporter {
+    /// Create a reporter for the given entry
+    pub fn new(entry: &str, observer: Arc<dyn ProgressObserver>) -> Self {
+        Self {
+            entry: Arc::from(entry),
+            observer,
+            totals: Arc::new(Mutex::new(ProgressTotals::default())),
+        }
+    }
+
+    /// Create a reporter that discards all events
+    pub fn noop() -> Self {
+        Self::new("", Arc::new(NoopObserver))
+    }
+
+    /// Record an event and forward it to the observer
+    pub fn emit(&self, event: ProgressEvent) {
+        let totals = {
+            let mut totals = self.totals.lock().unwrap_or_else(|e| e.into_inner());
+            totals.apply(&event);
+            totals.clone()
+        };
+
+        self.observer.on_progress(&ProgressUpdate {
+            entry: self.entry.to_string(),
+            event,
+            totals,
+        });
+    }
+
+    /// Snapshot of the current totals
+    pub fn totals(&self) -> ProgressTotals {
+        self.totals.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+impl Default for ProgressReporter {
+    fn default() -> Self {
+        Self::noop()
+    }
+}
+
+impl std::fmt::Debug for ProgressReporter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProgressReporter")
+            .field("entry", &self.entry)
+            .field("totals", &self.totals())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_workspace;
+
+    #[tokio::test]
+    async fn subscribers_see_the_stages_of_a_run_in_order() {
+        let (dir, docling) = test_workspace("events");
+        docling
+            .add_url(dir.join("site").to_str().unwrap(), Some("site"), Some(1))
+            .unwrap();
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        docling
+            .run_entry_in_mode("site", crate::RunMode::Crawl, Arc::new(sender))
+            .await
+            .unwrap();
+        let mut updates = Vec::new();
+        while let Ok(update) = receiver.try_recv() {
+            updates.push(update);
+        }
+
+        let stage = |event: &ProgressEvent| match event {
+            ProgressEvent::PageDiscovered { .. } | ProgressEvent::PageSaved { .. } => 0,
+            ProgressEvent::ConversionStarted { .. } => 1,
+            ProgressEvent::ConversionFinished { .. } => 2,
+            ProgressEvent::ProcessingDone { .. } => 3,
+            other => panic!("unexpected event {:?}", other),
+        };
+        let stages: Vec<u8> = updates.iter().map(|update| stage(&update.event)).collect();
+        assert!(stages.is_sorted(), "events out of order: {:?}", stages);
+        assert!(matches!(updates.first().map(|update| &update.event), Some(ProgressEvent::PageDiscovered { .. })));
+        assert!(matches!(
+            updates.iter().find(|update| stage(&update.event) == 1).map(|update| &update.event),
+            Some(ProgressEvent::ConversionStarted { total: 2 })
+        ));
+        assert!(matches!(updates.last().map(|update| &update.event), Some(ProgressEvent::ProcessingDone { .. })));
+
+        // Every update names the entry and carries the totals so far
+        assert!(updates.iter().all(|update| update.entry == "site"));
+        let totals = &updates.last().unwrap().totals;
+        assert_eq!(totals.pages_discovered, 2);
+        assert_eq!(totals.pages_saved, 2);
+        assert_eq!(totals.files_converted, 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,440 @@
+//! Main-content extraction
+//!
+//! Crawled pages wrap the text they are about in headers, navigation
+//! sidebars, cookie banners and footers; converted whole, every page of a
+//! combined result starts with the same navigation. Before conversion the
+//! main content of a page is isolated: its `<main>`, `role="main"` or
+//! `<article>` landmark if that holds a fair share of the page's text,
+//! otherwise the element that readability-style scoring of its paragraphs
+//! picks. Navigation, asides, footers and banners inside the content are
+//! dropped as well. Pages where neither finds convincing content are
+//! converted whole.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use scraper::{ElementRef, Html, Node, Selector};
+use serde::{Deserialize, Serialize};
+
+/// Landmarks holding the main content, in order of preference
+const LANDMARKS: [&str; 5] = ["main", "[role=main]", "article", "#main-content", "#content"];
+
+/// Share of the page's prose (text outside links) a landmark must hold
+const MIN_LANDMARK_SHARE: f64 = 0.25;
+
+/// Elements whose text counts as a paragraph when scoring
+const PARAGRAPHS: &str = "p, pre, td, blockquote, li, dd";
+
+/// Shortest paragraph text, in non-whitespace characters, that is scored
+const MIN_PARAGRAPH_CHARS: usize = 25;
+
+/// Lowest score the best scored element needs to be taken as the content
+const MIN_CANDIDATE_SCORE: f64 = 20.0;
+
+/// Elements that are never content
+const BOILERPLATE_TAGS: [&str; 10] = [
+    "nav", "aside", "footer", "script", "style", "noscript", "template", "form", "button", "dialog",
+];
+
+/// ARIA roles of landmarks around the content
+const BOILERPLATE_ROLES: [&str; 5] = ["navigation", "complementary", "contentinfo", "banner", "search"];
+
+/// Elements without content or end tag
+const VOID_TAGS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source", "track", "wbr",
+];
+
+/// Class or id names of boilerplate blocks, matched as whole words
+static BOILERPLATE_NAMES: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)(^|[-_\s])(cookies?|consent|gdpr|breadcrumbs?|sidebar|skip-?link|navbar|site-?nav|toc|table-of-contents|feedback|edit-?this-?page|pagination|pager|social|share|newsletter|banner|announcement)([-_\s]|$)",
+    )
+    .expect("valid regex")
+});
+
+/// Class or id names that make an element more likely to be the content
+static POSITIVE_NAMES: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)article|body|content|entry|main|markdown|page|post|prose|story|text|doc").expect("valid regex")
+});
+
+/// Class or id names that make an element less likely to be the content
+static NEGATIVE_NAMES: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)comment|footer|footnote|masthead|meta|nav|sidebar|sponsor|share|social|banner|cookie|menu|header|breadcrumb|related")
+        .expect("valid regex")
+});
+
+/// Helper for `#[serde(default = ...)]` of switches that are on by default
+fn default_true() -> bool {
+    true
+}
+
+/// Main-content extraction of an entry
+///
+/// The settings apply when pages are converted. Markdown already converted
+/// from unchanged pages is kept, so turning extraction on (or asking for the
+/// extracted HTML) only affects existing pages once the entry is rebuilt.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExtractionSettings {
+    /// Convert only the main content of pages instead of the whole page
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Save the extracted HTML of every page next to the saved pages, to
+    /// check what the extraction kept
+    #[serde(default)]
+    pub save_extracted: bool,
+}
+
+impl Default for ExtractionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            save_extracted: false,
+        }
+    }
+}
+
+impl ExtractionSettings {
+    /// Settings for an entry: the entry's own settings replace the global ones
+    pub fn for_entry(&self, entry: Option<&ExtractionSettings>) -> ExtractionSettings {
+        entry.unwrap_or(self).clone()
+    }
+}
+
+/// How the content of a page was found
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExtractionMethod {
+    /// A `<main>`, `role="main"` or `<article>` landmark
+    Landmark,
+    /// The best element by readability-style scoring
+    Scored,
+    /// Nothing convincing was found; the whole page was kept
+    FullPage,
+}
+
+/// What the extraction kept of a page
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ContentExtraction {
+    /// How the content was found
+    pub method: ExtractionMethod,
+    /// The element that was kept, like `main` or `div#content`
+    pub element: Option<String>,
+    /// Non-whitespace characters of text that were kept
+    pub text_chars: usize,
+    /// Non-whitespace characters of text on the whole page
+    pub page_text_chars: usize,
+    /// Where the extracted HTML was saved, if it was
+    #[serde(default)]
+    pub saved_html: Option<PathBuf>,
+}
+
+/// Isolate the main content of a page
+///
+/// # Returns
+/// A page holding the title and the main content, and what was kept; the
+/// page as it is if no main content was found
+pub fn extract_main_content(html: &str) -> (String, ContentExtraction) {
+    let document = Html::parse_document(html);
+    let page_text_chars = text_stats(document.root_element()).0;
+    let full_page = ContentExtraction {
+        method: ExtractionMethod::FullPage,
+        element: None,
+        text_chars: page_text_chars,
+        page_text_chars,
+        saved_html: None,
+    };
+
+    let Some((content, method)) = find_landmark(&document)
+        .map(|element| (element, ExtractionMethod::Landmark))
+        .or_else(|| find_best_scored(&document).map(|element| (element, ExtractionMethod::Scored)))
+    else {
+        return (html.to_string(), full_page);
+    };
+
+    let mut body = String::new();
+    // Keep the page heading if the content has none of its own
+    let heading = Selector::parse("h1").expect("valid selector");
+    if content.select(&heading).next().is_none()
+        && let Some(h1) = document.select(&heading).next()
+    {
+        write_element(h1, &mut body, true);
+    }
+    write_element(content, &mut body, true);
+
+    let title = Selector::parse("title").expect("valid selector");
+    let title = document
+        .select(&title)
+        .next()
+        .map(|title| title.text().collect::<String>())
+        .unwrap_or_default();
+    let extracted = format!(
+        "<html><head><title>{}</title></head><body>{}</body></html>",
+        html_escape::encode_text(title.trim()),
+        body
+    );
+    let text_chars = text_stats(Html::parse_document(&extracted).root_element()).0;
+    let extraction = ContentExtraction {
+        method,
+        element: Some(describe(content)),
+        text_chars,
+        page_text_chars,
+        saved_html: None,
+    };
+    (extracted, extraction)
+}
+
+/// The first landmark holding a fair share of the page's prose
+///
+/// Of several `<article>` elements the one with the most prose counts.
+fn find_landmark(document: &Html) -> Option<ElementRef<'_>> {
+    let page_prose = prose_chars(document.root_element());
+    if page_prose == 0 {
+        return None;
+    }
+    LANDMARKS.iter().find_map(|landmark| {
+        let selector = Selector::parse(landmark).expect("valid selector");
+        document
+            .select(&selector)
+            .map(|element| (prose_chars(element), element))
+            .max_by_key(|(prose, _)| *prose)
+            .filter(|(prose, _)| *prose as f64 >= page_prose as f64 * MIN_LANDMARK_SHARE)
+            .map(|(_, element)| element)
+    })
+}
+
+/// The element the paragraphs of the page point to, readability style
+///
+/// Every paragraph adds to the score of its parent and half as much to its
+/// grandparent: one point, one per comma and one per hundred characters (at
+/// most three). Candidates start from a score for their tag and their class
+/// and id names, and lose the share of their text that is in links.
+fn find_best_scored(document: &Html) -> Option<ElementRef<'_>> {
+    let paragraphs = Selector::parse(PARAGRAPHS).expect("valid selector");
+    let mut scores = HashMap::new();
+    let mut candidates = Vec::new();
+    for paragraph in document.select(&paragraphs) {
+        if is_boilerplate(paragraph) || paragraph.ancestors().filter_map(ElementRef::wrap).any(is_boilerplate) {
+            continue;
+        }
+        let text: String = paragraph.text().collect();
+        let chars = text.chars().filter(|c| !c.is_whitespace()).count();
+        if chars < MIN_PARAGRAPH_CHARS {
+            continue;
+        }
+        let score = 1.0 + text.matches(',').count() as f64 + (chars as f64 / 100.0).min(3.0);
+
+        let parent = paragraph.parent().and_then(ElementRef::wrap);
+        let grandparent = parent.and_then(|parent| parent.parent()).and_then(ElementRef::wrap);
+        for (ancestor, share) in [(parent, 1.0), (grandparent, 0.5)] {
+            let Some(ancestor) = ancestor else {
+                continue;
+            };
+            let entry = scores.entry(ancestor.id()).or_insert_with(|| {
+                candidates.push(ancestor);
+                initial_score(ancestor)
+            });
+            *entry += score * share;
+        }
+    }
+
+    candidates
+        .into_iter()
+        .filter(|candidate| !matches!(candidate.value().name(), "html" | "body"))
+        .map(|candidate| {
+            let (text, links) = text_stats(candidate);
+            let link_density = if text == 0 { 1.0 } else { links as f64 / text as f64 };
+            (scores[&candidate.id()] * (1.0 - link_density), candidate)
+        })
+        .filter(|(score, _)| *score >= MIN_CANDIDATE_SCORE)
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, candidate)| candidate)
+}
+
+/// Score of a candidate before its paragraphs are counted
+fn initial_score(element: ElementRef<'_>) -> f64 {
+    let tag = match element.value().name() {
+        "div" | "section" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "form" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+    let names = [element.value().attr("class"), element.value().attr("id")];
+    let weight: f64 = names
+        .into_iter()
+        .flatten()
+        .map(|name| {
+            let mut weight = 0.0;
+            if POSITIVE_NAMES.is_match(name) {
+                weight += 25.0;
+            }
+            if NEGATIVE_NAMES.is_match(name) {
+                weight -= 25.0;
+            }
+            weight
+        })
+        .sum();
+    tag + weight
+}
+
+/// Whether an element surrounds the content rather than being part of it
+///
+/// Headers are kept if they hold a heading, as articles often put their
+/// title in one.
+fn is_boilerplate(element: ElementRef<'_>) -> bool {
+    let value = element.value();
+    let name = value.name();
+    if BOILERPLATE_TAGS.contains(&name) || value.attr("hidden").is_some() || value.attr("aria-hidden") == Some("true") {
+        return true;
+    }
+    if name == "header" {
+        let headings = Selector::parse("h1, h2").expect("valid selector");
+        return element.select(&headings).next().is_none();
+    }
+    if value.attr("role").is_some_and(|role| BOILERPLATE_ROLES.contains(&role.trim())) {
+        return true;
+    }
+    [value.attr("class"), value.attr("id")]
+        .into_iter()
+        .flatten()
+        .any(|name| BOILERPLATE_NAMES.is_match(name))
+}
+
+/// Non-whitespace characters of visible text in an element, and how many of them are in links
+fn text_stats(element: ElementRef<'_>) -> (usize, usize) {
+    let mut text = 0;
+    let mut links = 0;
+    for child in element.children() {
+        match child.value() {
+            Node::Text(content) => text += content.chars().filter(|c| !c.is_whitespace()).count(),
+            Node::Element(child_element) => {
+                if matches!(child_element.name(), "head" | "script" | "style" | "noscript" | "template") {
+                    continue;
+                }
+                let Some(child) = ElementRef::wrap(child) else {
+                    continue;
+                };
+                let (child_text, child_links) = text_stats(child);
+                text += child_text;
+                links += if child_element.name() == "a" { child_text } else { child_links };
+            }
+            _ => {}
+        }
+    }
+    (text, links)
+}
+
+/// Visible text of an element outside links and boilerplate
+fn prose_chars(element: ElementRef<'_>) -> usize {
+    element
+        .children()
+        .filter_map(|child| match child.value() {
+            Node::Text(content) => Some(content.chars().filter(|c| !c.is_whitespace()).count()),
+            Node::Element(child_element) if child_element.name() != "a" && child_element.name() != "head" => {
+                ElementRef::wrap(child)
+                    .filter(|child| !is_boilerplate(*child))
+                    .map(prose_chars)
+            }
+            _ => None,
+        })
+        .sum()
+}
+
+/// Serialize an element as HTML, leaving out boilerplate below it
+fn write_element(element: ElementRef<'_>, out: &mut String, is_root: bool) {
+    if !is_root && is_boilerplate(element) {
+        return;
+    }
+    let name = element.value().name();
+    out.push('<');
+    out.push_str(name);
+    for (attribute, value) in element.value().attrs() {
+        out.push(' ');
+        out.push_str(attribute);
+        out.push_str("=\"");
+        out.push_str(&html_escape::encode_double_quoted_attribute(value));
+        out.push('"');
+    }
+    out.push('>');
+    if VOID_TAGS.contains(&name) {
+        return;
+    }
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => out.push_str(&html_escape::encode_text(&text[..])),
+            Node::Element(_) => {
+                if let Some(child) = ElementRef::wrap(child) {
+                    write_element(child, out, false);
+                }
+            }
+            _ => {}
+        }
+    }
+    out.push_str("</");
+    out.push_str(name);
+    out.push('>');
+}
+
+/// Short description of an element, like `main`, `div#content` or `div.body`
+fn describe(element: ElementRef<'_>) -> String {
+    let value = element.value();
+    let name = value.name();
+    if let Some(id) = value.attr("id").filter(|id| !id.is_empty()) {
+        return format!("{}#{}", name, id);
+    }
+    match value.attr("class").and_then(|class| class.split_whitespace().next()) {
+        Some(class) => format!("{}.{}", name, class),
+        None => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAVIGATION: &str = r#"<header class="site-header"><a href="/">Home</a><a href="/docs/">Docs</a></header>
+<nav class="sidebar"><ul><li><a href="/docs/intro">Introduction to the project</a></li>
+<li><a href="/docs/install">Installing the command line tool</a></li></ul></nav>
+<div id="cookie-banner">We use cookies to improve your experience, accept them all.</div>"#;
+
+    #[test]
+    fn landmarks_are_kept_without_their_navigation() {
+        let html = format!(
+            r##"<html><head><title>Guide</title></head><body>{}
+<main><h1>Guide</h1><nav class="toc"><a href="#a">A</a></nav>
+<p>The crawler downloads every page of a site, converts it and combines the results.</p>
+<p>Pages are saved once and converted again only when they change.</p></main>
+<footer>Copyright, all rights reserved, imprint and privacy policy.</footer></body></html>"##,
+            NAVIGATION
+        );
+        let (extracted, extraction) = extract_main_content(&html);
+        assert_eq!(extraction.method, ExtractionMethod::Landmark);
+        assert_eq!(extraction.element.as_deref(), Some("main"));
+        assert!(extracted.contains("<title>Guide</title>") && extracted.contains("<h1>Guide</h1>"));
+        assert!(extracted.contains("converted again only when they change"));
+        for boilerplate in ["Installing", "cookies", "Copyright", "href=\"#a\""] {
+            assert!(!extracted.contains(boilerplate), "{}", boilerplate);
+        }
+        assert!(extraction.text_chars < extraction.page_text_chars);
+    }
+
+    #[test]
+    fn content_without_landmarks_is_found_by_scoring() {
+        let paragraph = "<p>Configuration lives in one file, with a section per entry, and every setting has a default.</p>";
+        let html = format!(
+            r#"<html><body><h1>Settings</h1>{}<div class="layout"><div class="menu"><ul><li><a href="/a">A page</a></li></ul></div>
+<div class="doc-content">{}{}{}</div></div></body></html>"#,
+            NAVIGATION, paragraph, paragraph, paragraph
+        );
+        let (extracted, extraction) = extract_main_content(&html);
+        assert_eq!(extraction.method, ExtractionMethod::Scored);
+        assert_eq!(extraction.element.as_deref(), Some("div.doc-content"));
+        assert!(extracted.contains("<h1>Settings</h1>") && !extracted.contains("A page"));
+
+        let (unchanged, extraction) = extract_main_content("<p>Short</p>");
+        assert_eq!(extraction.method, ExtractionMethod::FullPage);
+        assert_eq!(unchanged, "<p>Short</p>");
+    }
+}
@@ -0,0 +1,565 @@
+//! HTTP fetching shared by page and media downloads
+//!
+//! [`Fetcher`] wraps a `reqwest` client configured from [`DoclingConfig`].
+//! Redirects are followed manually so every hop is visible to the caller,
+//! and conditional requests (`If-None-Match` / `If-Modified-Since`) are sent
+//! when validators from a previous run are available. Every request waits
+//! for the per-host [`RateLimiter`], and requests answered with 429 or 503
+//! are retried after the pause the server asked for. `file://` URLs are
+//! answered from the local file system by [`crate::local`]. A fetcher
+//! replaying an archive answers every request from a [`WarcReplay`] instead.
+//!
+//! Pages are fetched here rather than through a crawling library so the
+//! crawler sees every request it makes: it needs to send the validators of
+//! the previous run per page, record each redirect hop, and keep one rate
+//! limiter per host for pages, media, robots.txt and sitemaps alike.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::header::{self, HeaderMap};
+use reqwest::{redirect, Client, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncReadExt;
+use tracing::{debug, warn};
+use url::Url;
+
+use crate::auth::RequestAuth;
+use crate::budget::ByteAllowance;
+use crate::local::{self, LocalContent};
+use crate::rate_limit::{is_throttling_status, parse_retry_after, RateLimiter, MAX_RETRY_AFTER};
+use crate::warc::WarcReplay;
+use crate::{DoclingConfig, DoclingError, IoContext, Result};
+
+/// Maximum number of redirects followed for a single request
+const MAX_REDIRECTS: usize = 10;
+
+/// Timeout for a single request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Number of times a request answered with 429 or 503 is retried
+const MAX_THROTTLED_RETRIES: usize = 3;
+
+/// Size of the chunks local files are read in
+const LOCAL_CHUNK_BYTES: usize = 64 * 1024;
+
+/// Largest body read into memory; larger responses fail the request
+pub const MAX_BODY_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Cache validators remembered from a previous response
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Validators {
+    /// Value of the `ETag` header
+    pub etag: Option<String>,
+    /// Value of the `Last-Modified` header
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    /// Whether there is anything to send in a conditional request
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+/// A redirect followed while fetching a URL
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Redirect {
+    /// URL that answered with a redirect
+    pub from: String,
+    /// URL the redirect pointed to
+    pub to: String,
+    /// Status code of the redirect response
+    pub status: u16,
+}
+
+/// Response to a fetch, after following redirects
+#[derive(Debug, Clone)]
+pub struct FetchResponse {
+    /// URL that was requested
+    pub url: Url,
+    /// URL of the final response after redirects
+    pub final_url: Url,
+    /// Status code of the final response
+    pub status: u16,
+    /// Headers of the final response
+    pub headers: HeaderMap,
+    /// Body of the final response
+    pub body: Vec<u8>,
+    /// Redirects followed, in order
+    pub redirects: Vec<Redirect>,
+}
+
+impl FetchResponse {
+    /// Whether the final response has a 2xx status
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Whether the server confirmed the cached copy is still current (304)
+    pub fn is_not_modified(&self) -> bool {
+        self.status == StatusCode::NOT_MODIFIED.as_u16()
+    }
+
+    /// Value of a response header as a string
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+
+    /// Full `Content-Type` header of the response
+    pub fn content_type(&self) -> Option<&str> {
+        self.header(header::CONTENT_TYPE.as_str())
+    }
+
+    /// MIME type of the response without parameters, lowercase
+    pub fn mime_type(&self) -> Option<String> {
+        self.content_type()
+            .and_then(|value| value.split(';').next())
+            .map(|mime| mime.trim().to_ascii_lowercase())
+    }
+
+    /// Validators to remember for the next conditional request
+    pub fn validators(&self) -> Validators {
+        Validators {
+            etag: self.header(header::ETAG.as_str()).map(str::to_string),
+            last_modified: self.header(header::LAST_MODIFIED.as_str()).map(str::to_string),
+        }
+    }
+}
+
+/// HTTP fetcher used by the crawler for pages, robots.txt and media
+#[derive(Debug, Clone)]
+pub struct Fetcher {
+    /// HTTP client (with automatic redirects disabled)
+    client: Client,
+    /// Per-host rate limiter shared by all clones of the fetcher
+    rate_limiter: RateLimiter,
+    /// Credentials added to the requests, if any
+    auth: Option<Arc<RequestAuth>>,
+    /// Archive answering the requests instead of the network, if replaying
+    replay: Option<Arc<WarcReplay>>,
+}
+
+impl Fetcher {
+    /// Create a fetcher with the user agent, request delay, proxy and TLS
+    /// settings from the configuration
+    ///
+    /// # Errors
+    /// Returns an error if the HTTP client cannot be created
+    pub fn new(config: &DoclingConfig) -> Result<Self> {
+        let builder = Client::builder()
+            .user_agent(&config.user_agent)
+            .timeout(REQUEST_TIMEOUT)
+            .redirect(redirect::Policy::none());
+        let client = config
+            .network
+            .configure(builder)?
+            .build()
+            .map_err(|e| DoclingError::config_with("Failed to create HTTP client", e))?;
+
+        let rate_limiter = RateLimiter::new(
+            Duration::from_millis(config.delay_between_request_in_ms),
+            config.rate_limit_burst,
+        );
+
+        Ok(Self {
+            client,
+            rate_limiter,
+            auth: None,
+            replay: None,
+        })
+    }
+
+    /// Add credentials to every request of this fetcher
+    ///
+    /// The client and rate limiter stay shared with the original fetcher.
+    pub fn with_auth(mut self, auth: Option<RequestAuth>) -> Self {
+        self.auth = auth.map(Arc::new);
+        self
+    }
+
+    /// Answer every request from archived responses instead of the network
+    ///
+    /// Validators are ignored, the full archived response is always
+    /// returned, and URLs missing from the archive are answered with 404.
+    pub fn with_replay(mut self, replay: Option<WarcReplay>) -> Self {
+        self.replay = replay.map(Arc::new);
+        self
+    }
+
+    /// Whether requests are answered from an archive
+    pub fn is_replaying(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    /// Rate limiter applied to every request of this fetcher and its clones
+    pub fn rate_limiter(&self) -> &RateLimiter {
+        &self.rate_limiter
+    }
+
+    /// Fetch a URL without validators
+    pub async fn get(&self, url: &Url) -> Result<FetchResponse> {
+        self.fetch(url, None).await
+    }
+
+    /// Fetch a URL, following redirects
+    ///
+    /// If `validators` are given they are sent as `If-None-Match` and
+    /// `If-Modified-Since`; a `304 Not Modified` answer is returned as-is with
+    /// an empty body. Unsuccessful statuses are returned too; only transport
+    /// failures, redirect loops and bodies over [`MAX_BODY_BYTES`] are errors.
+    pub async fn fetch(&self, url: &Url, validators: Option<&Validators>) -> Result<FetchResponse> {
+        self.fetch_body(url, validators, None).await
+    }
+
+    /// Fetch a URL like [`Fetcher::fetch`], counting the body against the
+    /// byte allowance of a crawl while it arrives
+    ///
+    /// # Errors
+    /// Also returns an error, and stops reading, once the body goes past what
+    /// is left of the allowance
+    pub async fn fetch_within(
+        &self,
+        url: &Url,
+        validators: Option<&Validators>,
+        allowance: &ByteAllowance,
+    ) -> Result<FetchResponse> {
+        self.fetch_body(url, validators, Some(allowance)).await
+    }
+
+    /// Fetch a URL and read its body chunk by chunk, up to [`MAX_BODY_BYTES`]
+    async fn fetch_body(
+        &self,
+        url: &Url,
+        validators: Option<&Validators>,
+        allowance: Option<&ByteAllowance>,
+    ) -> Result<FetchResponse> {
+        let mut response = self.fetch_streaming(url, validators).await?;
+        let too_large = || DoclingError::LimitExceeded {
+            what: format!("Body of {}", url),
+            limit: MAX_BODY_BYTES,
+        };
+        if response.content_length().is_some_and(|length| length > MAX_BODY_BYTES) {
+            return Err(too_large());
+        }
+
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if (body.len() + chunk.len()) as u64 > MAX_BODY_BYTES {
+                return Err(too_large());
+            }
+            if let Some(allowance) = allowance {
+                allowance.take(chunk.len() as u64, &format!("Crawl download size at {}", url))?;
+            }
+            body.extend_from_slice(&chunk);
+        }
+        response.head.body = body;
+        Ok(response.head)
+    }
+
+    /// Fetch a URL like [`Fetcher::fetch`], but leave the body unread
+    ///
+    /// Used for large files that are written to disk chunk by chunk.
+    pub async fn fetch_streaming(&self, url: &Url, validators: Option<&Validators>) -> Result<StreamingResponse> {
+        if let Some(replay) = &self.replay {
+            return replay_fetch(replay, url);
+        }
+        if local::is_local(url) {
+            let response = local::open(url, validators).await?;
+            let head = FetchResponse {
+                url: url.clone(),
+                final_url: response.final_url,
+                status: response.status,
+                headers: response.headers,
+                body: Vec::new(),
+                redirects: response.redirects,
+            };
+            return Ok(StreamingResponse {
+                head,
+                body: Body::Local(response.content),
+            });
+        }
+
+        let mut current = url.clone();
+        let mut redirects = Vec::new();
+        let mut throttled = 0;
+
+        loop {
+            self.rate_limiter.acquire(&current).await;
+
+            let mut request = self.client.get(current.as_str());
+            if let Some(auth) = &self.auth {
+                request = auth.apply(request, &current);
+            }
+            if let Some(validators) = validators {
+                if let Some(etag) = &validators.etag {
+                    request = request.header(header::IF_NONE_MATCH, etag);
+                }
+                if let Some(last_modified) = &validators.last_modified {
+                    request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+                }
+            }
+
+            let response = request
+                .send()
+                .await
+                .map_err(|e| DoclingError::http_transport(current.as_str(), e))?;
+            let status = response.status();
+
+            let retry_after = response
+                .headers()
+                .get(header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(parse_retry_after);
+            self.rate_limiter.observe(&current, status.as_u16(), retry_after);
+
+            // The rate limiter holds the host back until the pause is over
+            if is_throttling_status(status.as_u16())
+                && throttled < MAX_THROTTLED_RETRIES
+                && retry_after.is_none_or(|wait| wait <= MAX_RETRY_AFTER)
+            {
+                throttled += 1;
+                warn!(
+                    "Retrying {} after status {} (attempt {}/{})",
+                    current,
+                    status.as_u16(),
+                    throttled,
+                    MAX_THROTTLED_RETRIES
+                );
+                continue;
+            }
+
+            if status.is_redirection() && status != StatusCode::NOT_MODIFIED {
+                let location = response
+                    .headers()
+                    .get(header::LOCATION)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|location| current.join(location).ok());
+
+                if let Some(next) = location {
+                    if redirects.len() >= MAX_REDIRECTS {
+                        return Err(DoclingError::Http {
+                            status: Some(status.as_u16()),
+                            url: url.to_string(),
+                            source: Some(format!("more than {} redirects", MAX_REDIRECTS).into()),
+                        });
+                    }
+                    debug!("Redirect {} {} -> {}", status.as_u16(), current, next);
+                    redirects.push(Redirect {
+                        from: current.to_string(),
+                        to: next.to_string(),
+                        status: status.as_u16(),
+                    });
+                    current = next;
+                    continue;
+                }
+            }
+
+            let head = FetchResponse {
+                url: url.clone(),
+                final_url: current,
+                status: status.as_u16(),
+                headers: response.headers().clone(),
+                body: Vec::new(),
+                redirects,
+            };
+            return Ok(StreamingResponse {
+                head,
+                body: Body::Http(response),
+            });
+        }
+    }
+}
+
+/// Answer a request from an archive, following archived redirects
+fn replay_fetch(replay: &WarcReplay, url: &Url) -> Result<StreamingResponse> {
+    let mut current = url.clone();
+    let mut redirects = Vec::new();
+
+    loop {
+        let Some(response) = replay.response(&current)? else {
+            debug!("No archived response for {}", current);
+            let head = FetchResponse {
+                url: url.clone(),
+                final_url: current,
+                status: StatusCode::NOT_FOUND.as_u16(),
+                headers: HeaderMap::new(),
+                body: Vec::new(),
+                redirects,
+            };
+            return Ok(StreamingResponse {
+                head,
+                body: Body::Archived(Vec::new()),
+            });
+        };
+
+        let is_redirect = StatusCode::from_u16(response.status)
+            .is_ok_and(|status| status.is_redirection() && status != StatusCode::NOT_MODIFIED);
+        let location = response
+            .headers
+            .get(header::LOCATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|location| current.join(location).ok());
+        if is_redirect && let Some(next) = location {
+            if redirects.len() >= MAX_REDIRECTS {
+                return Err(DoclingError::Http {
+                    status: Some(response.status),
+                    url: url.to_string(),
+                    source: Some(format!("more than {} redirects", MAX_REDIRECTS).into()),
+                });
+            }
+            redirects.push(Redirect {
+                from: current.to_string(),
+                to: next.to_string(),
+                status: response.status,
+            });
+            current = next;
+            continue;
+        }
+
+        let head = FetchResponse {
+            url: url.clone(),
+            final_url: current,
+            status: response.status,
+            headers: response.headers,
+            body: Vec::new(),
+            redirects,
+        };
+        return Ok(StreamingResponse {
+            head,
+            body: Body::Archived(response.body),
+        });
+    }
+}
+
+/// Response whose body is read incrementally
+#[derive(Debug)]
+pub struct StreamingResponse {
+    /// Status, headers and redirects of the response (with an empty body)
+    pub head: FetchResponse,
+    /// Source the body is read from
+    body: Body,
+}
+
+/// Source of a streamed body
+#[derive(Debug)]
+enum Body {
+    /// Body of an HTTP response
+    Http(Response),
+    /// Local file or generated content
+    Local(LocalContent),
+    /// Body of an archived response
+    Archived(Vec<u8>),
+}
+
+impl StreamingResponse {
+    /// Value of the `Content-Length` header, or the size of a local file or archived body
+    pub fn content_length(&self) -> Option<u64> {
+        match &self.body {
+            Body::Http(response) => response.content_length(),
+            Body::Local(LocalContent::File { length, .. }) => Some(*length),
+            Body::Local(LocalContent::Generated(bytes)) | Body::Archived(bytes) => Some(bytes.len() as u64),
+        }
+    }
+
+    /// Read the next chunk of the body, `None` at the end
+    pub async fn chunk(&mut self) -> Result<Option<Vec<u8>>> {
+        match &mut self.body {
+            Body::Http(response) => response
+                .chunk()
+                .await
+                .map(|chunk| chunk.map(|bytes| bytes.to_vec()))
+                .map_err(|e| DoclingError::http_transport(self.head.final_url.as_str(), e)),
+            Body::Local(LocalContent::File { file, .. }) => {
+                let mut buffer = vec![0; LOCAL_CHUNK_BYTES];
+                let read = file
+                    .read(&mut buffer)
+                    .await
+                    .io_context(format!("Failed to read {}", self.head.final_url))?;
+                buffer.truncate(read);
+                Ok((read > 0).then_some(buffer))
+            }
+            Body::Local(LocalContent::Generated(bytes)) | Body::Archived(bytes) => {
+                Ok((!bytes.is_empty()).then(|| std::mem::take(bytes)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::budget::{BudgetLimit, BudgetTracker, CrawlBudget};
+    use crate::test_server::{request_path, response, TestServer};
+
+    const ETAG: &str = "\"v1\"";
+    const LAST_MODIFIED: &str = "Tue, 01 Oct 2024 10:00:00 GMT";
+
+    fn fetcher() -> Fetcher {
+        let config = DoclingConfig {
+            delay_between_request_in_ms: 0,
+            ..DoclingConfig::default()
+        };
+        Fetcher::new(&config).unwrap()
+    }
+
+    #[tokio::test]
+    async fn conditional_requests_send_validators() {
+        let server = TestServer::start(|head| {
+            if head.to_ascii_lowercase().contains(&format!("if-none-match: {}", ETAG)) {
+                response("304 Not Modified", &[("ETag", ETAG)], b"")
+            } else {
+                response(
+                    "200 OK",
+                    &[("Content-Type", "text/html"), ("ETag", ETAG), ("Last-Modified", LAST_MODIFIED)],
+                    b"<h1>Guide</h1>",
+                )
+            }
+        })
+        .await;
+        let fetcher = fetcher();
+
+        let first = fetcher.get(&server.url("guide")).await.unwrap();
+        assert!(first.is_success());
+        assert_eq!(first.body, b"<h1>Guide</h1>");
+        let validators = first.validators();
+        assert_eq!(validators.etag.as_deref(), Some(ETAG));
+        assert_eq!(validators.last_modified.as_deref(), Some(LAST_MODIFIED));
+
+        let second = fetcher.fetch(&server.url("guide"), Some(&validators)).await.unwrap();
+        assert!(second.is_not_modified());
+        assert!(second.body.is_empty());
+
+        let requests = server.requests();
+        assert!(!requests[0].contains("if-none-match"));
+        assert!(requests[1].contains(&format!("if-none-match: {}", ETAG)));
+        assert!(requests[1].contains(&format!("if-modified-since: {}", LAST_MODIFIED.to_ascii_lowercase())));
+    }
+
+    #[tokio::test]
+    async fn bodies_stop_at_the_size_cap_and_the_byte_budget() {
+        let server = TestServer::start(|head| match request_path(head) {
+            "/huge" => b"HTTP/1.1 200 OK\r\nContent-Length: 1073741824\r\nConnection: close\r\n\r\n".to_vec(),
+            _ => response("200 OK", &[("Content-Type", "text/html")], &[b'a'; 4096]),
+        })
+        .await;
+        let fetcher = fetcher();
+        assert!(matches!(
+            fetcher.get(&server.url("huge")).await,
+            Err(DoclingError::LimitExceeded { limit: MAX_BODY_BYTES, .. })
+        ));
+
+        let tracker = BudgetTracker::new(CrawlBudget {
+            max_bytes: Some(6000),
+            ..Default::default()
+        });
+        let allowance = tracker.allowance();
+        let page = fetcher.fetch_within(&server.url("page"), None, &allowance).await.unwrap();
+        assert_eq!(page.body.len(), 4096);
+        assert!(matches!(
+            fetcher.fetch_within(&server.url("page"), None, &allowance).await,
+            Err(DoclingError::LimitExceeded { limit: 6000, .. })
+        ));
+        assert_eq!(tracker.exceeded(), Some(BudgetLimit::Bytes));
+    }
+}
@@ -0,0 +1,283 @@
+//! Documentation read from local git repositories
+//!
+//! An entry with a [`GitSource`] is not crawled: the crawler lists the files
+//! of the repository at the configured reference, keeps the ones matching
+//! the path globs that are Markdown, reStructuredText, AsciiDoc or HTML, and
+//! reads them straight from the object database with the `git` command. Work
+//! tree changes are never seen, so the result only depends on the commit,
+//! which is recorded as the revision of the entry.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+use tracing::debug;
+use url::Url;
+
+use crate::{DoclingError, DocumentType, Result};
+
+/// Reference read when none is configured
+const DEFAULT_REFERENCE: &str = "HEAD";
+
+/// Reference of a git source when the entry does not set one
+fn default_reference() -> String {
+    DEFAULT_REFERENCE.to_string()
+}
+
+/// Local git repository an entry reads its documentation from
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GitSource {
+    /// Path of the repository (a working tree or a bare repository)
+    pub repository: PathBuf,
+    /// Branch, tag or commit to read
+    #[serde(default = "default_reference")]
+    pub reference: String,
+    /// Globs of the paths to read, relative to the repository root (`*`,
+    /// `?` and `**`; a plain directory includes everything below it); all
+    /// files are read when empty
+    #[serde(default)]
+    pub paths: Vec<String>,
+}
+
+/// A documentation file in the repository
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitFile {
+    /// Path relative to the repository root
+    pub path: String,
+    /// Object id of the content
+    pub blob: String,
+    /// Format of the file, `None` for HTML
+    pub document_type: Option<DocumentType>,
+}
+
+impl GitSource {
+    /// Create a source reading `reference` of the repository at `repository`
+    ///
+    /// # Errors
+    /// Returns an error if the path does not exist
+    pub fn new(repository: &Path, reference: Option<&str>, paths: Vec<String>) -> Result<Self> {
+        let repository = repository.canonicalize().map_err(|e| {
+            DoclingError::config_with(format!("Invalid repository path: {}", repository.display()), e)
+        })?;
+        Ok(Self {
+            repository,
+            reference: reference.map_or_else(default_reference, str::to_string),
+            paths,
+        })
+    }
+
+    /// `file://` URL of the repository, the base of the URLs of its files
+    ///
+    /// # Errors
+    /// Returns an error if the repository path is not absolute
+    pub fn url(&self) -> Result<Url> {
+        Url::from_directory_path(&self.repository).map_err(|()| {
+            DoclingError::config(format!("Invalid repository path: {}", self.repository.display()))
+        })
+    }
+
+    /// URL recorded for a file of the repository
+    pub fn file_url(base: &Url, path: &str) -> Url {
+        let mut url = base.clone();
+        if let Ok(mut segments) = url.path_segments_mut() {
+            segments.pop_if_empty().extend(path.split('/'));
+        }
+        url
+    }
+
+    /// Commit the reference points to
+    ///
+    /// # Errors
+    /// Returns an error if git fails or the reference does not name a commit
+    pub async fn resolve_commit(&self) -> Result<String> {
+        let output = self
+            .git(&["rev-parse", "--verify", "--end-of-options", &format!("{}^{{commit}}", self.reference)])
+            .await?;
+        let commit = String::from_utf8_lossy(&output).trim().to_string();
+        if commit.is_empty() {
+            return Err(DoclingError::config(format!(
+                "Reference {} not found in {}",
+                self.reference,
+                self.repository.display()
+            )));
+        }
+        Ok(commit)
+    }
+
+    /// Documentation files of a commit that match the path globs
+    ///
+    /// # Errors
+    /// Returns an error if git fails
+    pub async fn list_files(&self, commit: &str) -> Result<Vec<GitFile>> {
+        let output = self.git(&["ls-tree", "-r", "-z", "--full-tree", commit]).await?;
+        let mut files = Vec::new();
+        for record in output.split(|byte| *byte == 0).filter(|record| !record.is_empty()) {
+            // <mode> SP <type> SP <object> TAB <path>
+            let record = String::from_utf8_lossy(record);
+            let Some((info, path)) = record.split_once('\t') else {
+                continue;
+            };
+            let mut fields = info.split(' ');
+            let (Some(_mode), Some("blob"), Some(blob)) = (fields.next(), fields.next(), fields.next()) else {
+                continue;
+            };
+            if !self.paths.is_empty() && !self.paths.iter().any(|pattern| matches_glob(pattern, path)) {
+                continue;
+            }
+            let extension = path.rsplit_once('.').map(|(_, extension)| extension.to_ascii_lowercase());
+            let document_type = match extension.as_deref() {
+                Some("html" | "htm") => None,
+                Some(extension) => match DocumentType::from_extension(extension) {
+                    Some(document_type @ (DocumentType::Markdown | DocumentType::Rst | DocumentType::AsciiDoc)) => {
+                        Some(document_type)
+                    }
+                    _ => continue,
+                },
+                None => continue,
+            };
+            files.push(GitFile {
+                path: path.to_string(),
+                blob: blob.to_string(),
+                document_type,
+            });
+        }
+        debug!("{} documentation files in {} at {}", files.len(), self.repository.display(), commit);
+        Ok(files)
+    }
+
+    /// Content of a blob
+    ///
+    /// # Errors
+    /// Returns an error if git fails
+    pub async fn read_blob(&self, blob: &str) -> Result<Vec<u8>> {
+        self.git(&["cat-file", "blob", blob]).await
+    }
+
+    /// Run git in the repository and return its standard output
+    async fn git(&self, args: &[&str]) -> Result<Vec<u8>> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(&self.repository)
+            .args(args)
+            .output()
+            .await
+            .map_err(|e| DoclingError::config_with("Failed to run git", e))?;
+        if !output.status.success() {
+            return Err(DoclingError::config(format!(
+                "git {} failed in {}: {}",
+                args.first().copied().unwrap_or_default(),
+                self.repository.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(output.stdout)
+    }
+}
+
+/// Whether a repository path matches a glob
+///
+/// `*` and `?` stay within a path segment, `**` spans any number of
+/// segments, and a pattern without wildcards also matches everything below
+/// the directory it names.
+pub fn matches_glob(pattern: &str, path: &str) -> bool {
+    let pattern = pattern.trim_matches('/');
+    if !pattern.contains(['*', '?']) {
+        return path == pattern || path.strip_prefix(pattern).is_some_and(|rest| rest.starts_with('/'));
+    }
+    let pattern: Vec<&str> = pattern.split('/').collect();
+    let path: Vec<&str> = path.split('/').collect();
+    matches_segments(&pattern, &path)
+}
+
+/// Match path segments against pattern segments
+fn matches_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| matches_segments(rest, &path[skip..])),
+        Some((segment, rest)) => path
+            .split_first()
+            .is_some_and(|(name, path)| matches_segment(segment, name) && matches_segments(rest, path)),
+    }
+}
+
+/// Match a single segment against a pattern with `*` and `?`
+fn matches_segment(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    // Positions after the last `*` for backtracking
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p + 1, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globs_match_repository_paths() {
+        assert!(matches_glob("docs", "docs/guide/intro.md"));
+        assert!(matches_glob("docs/", "docs/index.rst"));
+        assert!(!matches_glob("docs", "docs-old/index.rst"));
+        assert!(matches_glob("docs/**/*.md", "docs/intro.md"));
+        assert!(matches_glob("docs/**/*.md", "docs/a/b/intro.md"));
+        assert!(!matches_glob("docs/*.md", "docs/a/intro.md"));
+        assert!(matches_glob("**/README.m?", "crates/core/README.md"));
+        assert!(!matches_glob("*.adoc", "guide.md"));
+    }
+
+    #[tokio::test]
+    async fn files_are_read_at_a_reference() {
+        let dir = std::env::temp_dir().join(format!("docling-git-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("docs")).unwrap();
+        let git = |args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .arg("-C")
+                .arg(&dir)
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {:?} failed", args);
+        };
+        git(&["init", "-q"]);
+        std::fs::write(dir.join("docs/intro.md"), "# Intro\n").unwrap();
+        std::fs::write(dir.join("docs/setup.adoc"), "= Setup\n").unwrap();
+        std::fs::write(dir.join("docs/logo.png"), [0x89, b'P']).unwrap();
+        std::fs::write(dir.join("README.md"), "# Project\n").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "-q", "-m", "docs"]);
+        git(&["tag", "v1"]);
+        // Uncommitted changes are not read
+        std::fs::write(dir.join("docs/intro.md"), "# Draft\n").unwrap();
+
+        let source = GitSource::new(&dir, Some("v1"), vec!["docs".to_string()]).unwrap();
+        let commit = source.resolve_commit().await.unwrap();
+        assert_eq!(commit.len(), 40);
+        let files = source.list_files(&commit).await.unwrap();
+        let paths: Vec<_> = files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(paths, ["docs/intro.md", "docs/setup.adoc"]);
+        assert_eq!(files[1].document_type, Some(DocumentType::AsciiDoc));
+        assert_eq!(source.read_blob(&files[0].blob).await.unwrap(), b"# Intro\n");
+
+        let missing = GitSource::new(&dir, Some("no-such-branch"), Vec::new()).unwrap();
+        assert!(missing.resolve_commit().await.is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
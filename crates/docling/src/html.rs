@@ -0,0 +1,566 @@
+//! HTML parsing helpers shared by the crawler, converter and processor
+//!
+//! Built on the `scraper` crate so attributes are read from a real DOM
+//! instead of scanning lines of markup.
+
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+use url::Url;
+
+use crate::MediaKind;
+
+/// Attributes that lazy-loading scripts use instead of `src`, in order of preference
+const LAZY_SRC_ATTRIBUTES: [&str; 4] = ["data-src", "data-lazy-src", "data-original", "src"];
+
+/// Attributes that lazy-loading scripts use instead of `srcset`
+const LAZY_SRCSET_ATTRIBUTES: [&str; 2] = ["data-srcset", "srcset"];
+
+/// `url(...)` of a `background` or `background-image` declaration
+static CSS_BACKGROUND_URL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)background(?:-image)?\s*:[^;{}]*?url\(\s*['"]?([^'")]+?)['"]?\s*\)"#)
+        .expect("valid background regex")
+});
+
+/// A hyperlink found in a page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    /// Absolute URL of the link target, without fragment
+    pub url: Url,
+    /// Visible text of the link, with whitespace collapsed
+    pub text: String,
+}
+
+/// Where the content of a media reference comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MediaSource {
+    /// A file to download
+    Url(Url),
+    /// Markup embedded in the page, e.g. an inline `<svg>`
+    Inline(String),
+}
+
+/// A media file referenced by a page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MediaRef {
+    /// What kind of media it is
+    pub kind: MediaKind,
+    /// Where to get the content
+    pub source: MediaSource,
+    /// Alternative text or title, if the page gives one
+    pub alt: Option<String>,
+}
+
+/// Extract all `<a href>` links from a page, resolved against `base`
+///
+/// Fragments are removed and only `http`, `https` and `file` links are
+/// returned. A `<base href>` element in the page overrides `base`.
+pub fn extract_links(base: &Url, html: &str) -> Vec<Link> {
+    let document = Html::parse_document(html);
+    let base = document_base(&document, base);
+    let selector = Selector::parse("a[href]").expect("valid selector");
+
+    document
+        .select(&selector)
+        .filter_map(|element| {
+            let href = element.value().attr("href")?.trim();
+            let url = resolve_link(&base, href)?;
+            let text = collapse_whitespace(&element.text().collect::<String>());
+            Some(Link { url, text })
+        })
+        .collect()
+}
+
+/// Resolve a link relative to `base`, dropping fragments and unsupported schemes
+pub fn resolve_link(base: &Url, href: &str) -> Option<Url> {
+    if href.is_empty() || href.starts_with('#') {
+        return None;
+    }
+
+    let mut url = base.join(href).ok()?;
+    url.set_fragment(None);
+
+    match url.scheme() {
+        "http" | "https" | "file" => Some(url),
+        _ => None,
+    }
+}
+
+/// Base URL of a document, honouring `<base href>` if present
+pub(crate) fn document_base(document: &Html, fallback: &Url) -> Url {
+    let selector = Selector::parse("base[href]").expect("valid selector");
+    document
+        .select(&selector)
+        .next()
+        .and_then(|element| element.value().attr("href"))
+        .and_then(|href| fallback.join(href.trim()).ok())
+        .unwrap_or_else(|| fallback.clone())
+}
+
+/// Collapse runs of whitespace into single spaces and trim the result
+pub(crate) fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The `rel=canonical` URL a page declares, resolved against `base`
+///
+/// A `<base href>` element in the page overrides `base`.
+pub fn extract_canonical(base: &Url, html: &str) -> Option<Url> {
+    let document = Html::parse_document(html);
+    let base = document_base(&document, base);
+    let selector = Selector::parse("link[rel][href]").expect("valid selector");
+
+    document
+        .select(&selector)
+        .find(|element| {
+            let rel = element.value().attr("rel").unwrap_or_default();
+            rel.split_whitespace().any(|token| token.eq_ignore_ascii_case("canonical"))
+        })
+        .and_then(|element| resolve_link(&base, element.value().attr("href")?.trim()))
+}
+
+/// The `hreflang` alternates a page declares, as language tags and URLs
+///
+/// Alternates come from `<link rel="alternate" hreflang>` and `<a hreflang>`
+/// elements; `x-default` is left out.
+pub fn extract_alternates(base: &Url, html: &str) -> Vec<(String, Url)> {
+    let document = Html::parse_document(html);
+    let base = document_base(&document, base);
+    let selector = Selector::parse("link[hreflang][href], a[hreflang][href]").expect("valid selector");
+
+    document
+        .select(&selector)
+        .filter(|element| {
+            element.value().name() == "a"
+                || element
+                    .value()
+                    .attr("rel")
+                    .is_some_and(|rel| rel.split_whitespace().any(|token| token.eq_ignore_ascii_case("alternate")))
+        })
+        .filter_map(|element| {
+            let language = element.value().attr("hreflang")?.trim();
+            if language.is_empty() || language.eq_ignore_ascii_case("x-default") {
+                return None;
+            }
+            let url = resolve_link(&base, element.value().attr("href")?.trim())?;
+            Some((language.to_string(), url))
+        })
+        .collect()
+}
+
+/// The `lang` (or `xml:lang`) attribute of a page's `<html>` element
+pub(crate) fn document_language(html: &str) -> Option<String> {
+    let document = Html::parse_document(html);
+    let root = document.root_element();
+    root.value()
+        .attr("lang")
+        .or_else(|| root.value().attr("xml:lang"))
+        .map(str::trim)
+        .filter(|lang| !lang.is_empty())
+        .map(str::to_string)
+}
+
+/// URLs offered by the `<select>` version or language switchers of a page
+///
+/// Only option values that look like paths or URLs are returned.
+pub fn extract_switcher_options(base: &Url, html: &str) -> Vec<Url> {
+    let document = Html::parse_document(html);
+    let base = document_base(&document, base);
+    let selector = Selector::parse("select option[value]").expect("valid selector");
+
+    document
+        .select(&selector)
+        .filter_map(|element| element.value().attr("value"))
+        .map(str::trim)
+        .filter(|value| value.contains('/'))
+        .filter_map(|value| resolve_link(&base, value))
+        .collect()
+}
+
+/// Documentation version a page declares in its `<meta>` tags, if any
+///
+/// Docusaurus and other generators name it in `docsearch:version`; some
+/// sites use a plain `version` or `docs-version` meta tag.
+pub(crate) fn declared_version(html: &str) -> Option<String> {
+    const NAMES: [&str; 3] = ["docsearch:version", "docs-version", "version"];
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("meta[name][content]").expect("valid selector");
+
+    let metas: Vec<_> = document.select(&selector).collect();
+    NAMES.iter().find_map(|name| {
+        metas
+            .iter()
+            .find(|element| element.value().attr("name").is_some_and(|value| value.eq_ignore_ascii_case(name)))
+            .and_then(|element| element.value().attr("content"))
+            .map(str::trim)
+            // docsearch:version may list several versions separated by commas
+            .and_then(|content| content.split(',').next())
+            .map(str::trim)
+            .filter(|content| !content.is_empty())
+            .map(str::to_string)
+    })
+}
+
+/// Text a reader sees on a page, with whitespace collapsed
+///
+/// The head and the content of scripts, styles and templates are left out.
+pub(crate) fn visible_text(html: &str) -> String {
+    const HIDDEN: [&str; 5] = ["head", "script", "style", "noscript", "template"];
+    let document = Html::parse_document(html);
+    let text: String = document
+        .root_element()
+        .descendants()
+        .filter_map(|node| {
+            let text = node.value().as_text()?;
+            let hidden = node.ancestors().any(|ancestor| {
+                ancestor
+                    .value()
+                    .as_element()
+                    .is_some_and(|element| HIDDEN.contains(&element.name()))
+            });
+            (!hidden).then(|| format!("{} ", &**text))
+        })
+        .collect();
+    collapse_whitespace(&text)
+}
+
+/// Extract the media of the given kinds referenced by a page
+///
+/// Images are read from `<img>` and `<picture>` elements, preferring the
+/// largest `srcset` candidate and lazy-loading attributes such as `data-src`
+/// over placeholder `src` values. References are resolved against `base` (or
+/// the page's `<base href>`) and each URL is returned only once.
+pub fn extract_media(base: &Url, html: &str, kinds: &[MediaKind]) -> Vec<MediaRef> {
+    let document = Html::parse_document(html);
+    let base = document_base(&document, base);
+    let mut collector = MediaCollector {
+        base: &base,
+        seen: HashSet::new(),
+        media: Vec::new(),
+    };
+
+    if kinds.contains(&MediaKind::Image) {
+        collect_images(&document, &mut collector);
+    }
+    if kinds.contains(&MediaKind::Svg) {
+        collect_inline_svgs(&document, &mut collector);
+    }
+    if kinds.contains(&MediaKind::Icon) {
+        let selector = Selector::parse("link[rel][href]").expect("valid selector");
+        for element in document.select(&selector) {
+            let rel = element.value().attr("rel").unwrap_or_default().to_ascii_lowercase();
+            if rel.split_whitespace().any(|token| token.ends_with("icon")) {
+                collector.add_url(MediaKind::Icon, element.value().attr("href"), None);
+            }
+        }
+    }
+    if kinds.contains(&MediaKind::CssBackground) {
+        collect_css_backgrounds(&document, &mut collector);
+    }
+    if kinds.contains(&MediaKind::Video) {
+        let selector = Selector::parse("video").expect("valid selector");
+        let source_selector = Selector::parse("source[src]").expect("valid selector");
+        for video in document.select(&selector) {
+            let title = video.value().attr("title").map(collapse_whitespace);
+            collector.add_url(MediaKind::Video, video.value().attr("poster"), title.clone());
+            collector.add_url(MediaKind::Video, video.value().attr("src"), title.clone());
+            for source in video.select(&source_selector) {
+                collector.add_url(MediaKind::Video, source.value().attr("src"), title.clone());
+            }
+        }
+    }
+
+    collector.media
+}
+
+/// Pick the largest candidate of a `srcset` attribute
+///
+/// Width descriptors (`480w`) are preferred over density descriptors (`2x`);
+/// a candidate without a descriptor counts as `1x`.
+pub fn best_srcset_candidate(srcset: &str) -> Option<&str> {
+    parse_srcset(srcset)
+        .into_iter()
+        .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(url, _)| url)
+}
+
+/// Collects media references, resolving and deduplicating them
+struct MediaCollector<'a> {
+    /// Base URL references are resolved against
+    base: &'a Url,
+    /// URLs (or inline markup) already collected
+    seen: HashSet<String>,
+    /// Collected references in document order
+    media: Vec<MediaRef>,
+}
+
+impl MediaCollector<'_> {
+    /// Add a reference to a file, if it resolves to a fetchable URL
+    fn add_url(&mut self, kind: MediaKind, href: Option<&str>, alt: Option<String>) {
+        let Some(url) = href.and_then(|href| resolve_media(self.base, href)) else {
+            return;
+        };
+        if self.seen.insert(url.to_string()) {
+            self.media.push(MediaRef {
+                kind,
+                source: MediaSource::Url(url),
+                alt: alt.filter(|alt| !alt.is_empty()),
+            });
+        }
+    }
+
+    /// Add media embedded in the page
+    fn add_inline(&mut self, kind: MediaKind, markup: String, alt: Option<String>) {
+        if self.seen.insert(markup.clone()) {
+            self.media.push(MediaRef {
+                kind,
+                source: MediaSource::Inline(markup),
+                alt: alt.filter(|alt| !alt.is_empty()),
+            });
+        }
+    }
+}
+
+/// Collect `<picture>` and `<img>` images
+fn collect_images(document: &Html, collector: &mut MediaCollector<'_>) {
+    let picture_selector = Selector::parse("picture").expect("valid selector");
+    let source_selector = Selector::parse("source").expect("valid selector");
+    let img_selector = Selector::parse("img").expect("valid selector");
+    let mut in_picture = HashSet::new();
+
+    // A picture is one image: take the largest candidate of all its sources
+    for picture in document.select(&picture_selector) {
+        let img = picture.select(&img_selector).next();
+        let alt = img.and_then(|img| img.value().attr("alt")).map(collapse_whitespace);
+        let mut srcsets: Vec<&str> = picture
+            .select(&source_selector)
+            .filter_map(|source| first_attr(source, &LAZY_SRCSET_ATTRIBUTES))
+            .collect();
+        if let Some(img) = img {
+            in_picture.insert(img.id());
+            srcsets.extend(first_attr(img, &LAZY_SRCSET_ATTRIBUTES));
+        }
+
+        let best = srcsets
+            .iter()
+            .flat_map(|srcset| parse_srcset(srcset))
+            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(url, _)| url)
+            .or_else(|| img.and_then(lazy_src));
+        collector.add_url(MediaKind::Image, best, alt);
+    }
+
+    for img in document.select(&img_selector) {
+        if in_picture.contains(&img.id()) {
+            continue;
+        }
+        let alt = img.value().attr("alt").map(collapse_whitespace);
+        let src = first_attr(img, &LAZY_SRCSET_ATTRIBUTES)
+            .and_then(best_srcset_candidate)
+            .or_else(|| lazy_src(img));
+        collector.add_url(MediaKind::Image, src, alt);
+    }
+}
+
+/// Collect top-level inline `<svg>` elements as standalone documents
+fn collect_inline_svgs(document: &Html, collector: &mut MediaCollector<'_>) {
+    let selector = Selector::parse("svg").expect("valid selector");
+    let title_selector = Selector::parse("title").expect("valid selector");
+
+    for svg in document.select(&selector) {
+        // Nested <svg> elements are part of their outer one
+        let nested = svg
+            .ancestors()
+            .filter_map(ElementRef::wrap)
+            .any(|ancestor| ancestor.value().name() == "svg");
+        if nested {
+            continue;
+        }
+
+        let alt = svg
+            .value()
+            .attr("aria-label")
+            .map(collapse_whitespace)
+            .or_else(|| {
+                svg.select(&title_selector)
+                    .next()
+                    .map(|title| collapse_whitespace(&title.text().collect::<String>()))
+            });
+
+        let mut markup = svg.html();
+        // Standalone SVG files need the namespace declaration
+        if !markup.contains("xmlns=") {
+            markup = markup.replacen("<svg", "<svg xmlns=\"http://www.w3.org/2000/svg\"", 1);
+        }
+        collector.add_inline(MediaKind::Svg, markup, alt);
+    }
+}
+
+/// Collect background images from `style` attributes and `<style>` elements
+fn collect_css_backgrounds(document: &Html, collector: &mut MediaCollector<'_>) {
+    let styled_selector = Selector::parse("[style]").expect("valid selector");
+    let style_selector = Selector::parse("style").expect("valid selector");
+
+    let inline_styles = document
+        .select(&styled_selector)
+        .filter_map(|element| element.value().attr("style"))
+        .map(str::to_string);
+    let stylesheets = document
+        .select(&style_selector)
+        .map(|element| element.text().collect::<String>());
+
+    for css in inline_styles.chain(stylesheets).collect::<Vec<_>>() {
+        for captures in CSS_BACKGROUND_URL.captures_iter(&css) {
+            collector.add_url(MediaKind::CssBackground, captures.get(1).map(|m| m.as_str()), None);
+        }
+    }
+}
+
+/// First non-empty value of the given attributes
+fn first_attr<'a>(element: ElementRef<'a>, names: &[&str]) -> Option<&'a str> {
+    names
+        .iter()
+        .filter_map(|name| element.value().attr(name))
+        .map(str::trim)
+        .find(|value| !value.is_empty())
+}
+
+/// Real source of an image, skipping `data:` placeholders used by lazy loading
+fn lazy_src(img: ElementRef<'_>) -> Option<&str> {
+    LAZY_SRC_ATTRIBUTES
+        .iter()
+        .filter_map(|name| img.value().attr(name))
+        .map(str::trim)
+        .find(|value| !value.is_empty() && !value.starts_with("data:"))
+}
+
+/// Resolve a media reference, dropping fragments, `data:` URIs and unsupported schemes
+fn resolve_media(base: &Url, href: &str) -> Option<Url> {
+    let href = href.trim();
+    if href.is_empty() || href.starts_with("data:") {
+        return None;
+    }
+    resolve_link(base, href)
+}
+
+/// Parse a `srcset` attribute into candidates and their sizes
+///
+/// Widths are ranked above densities by offsetting them, since the two
+/// cannot be compared directly.
+fn parse_srcset(srcset: &str) -> Vec<(&str, f64)> {
+    const WIDTH_RANK: f64 = 1_000_000.0;
+    let mut candidates = Vec::new();
+    let mut rest = srcset;
+
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        if rest.is_empty() {
+            break;
+        }
+
+        let url_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let mut url = &rest[..url_end];
+        rest = &rest[url_end..];
+
+        // A URL ending in a comma has no descriptor
+        let descriptor = if url.ends_with(',') {
+            url = url.trim_end_matches(',');
+            ""
+        } else {
+            let descriptor_end = rest.find(',').unwrap_or(rest.len());
+            let descriptor = rest[..descriptor_end].trim();
+            rest = &rest[descriptor_end..];
+            descriptor
+        };
+
+        let size = if let Some(width) = descriptor.strip_suffix('w') {
+            width.parse::<f64>().map(|w| WIDTH_RANK + w).ok()
+        } else if let Some(density) = descriptor.strip_suffix('x') {
+            density.parse::<f64>().ok()
+        } else {
+            Some(1.0)
+        };
+
+        if let Some(size) = size.filter(|_| !url.is_empty()) {
+            candidates.push((url, size));
+        }
+    }
+
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn urls(media: &[MediaRef]) -> Vec<String> {
+        media
+            .iter()
+            .filter_map(|m| match &m.source {
+                MediaSource::Url(url) => Some(url.path().to_string()),
+                MediaSource::Inline(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn picks_best_srcset_candidate() {
+        assert_eq!(best_srcset_candidate("a.png 1x, b.png 2x"), Some("b.png"));
+        assert_eq!(best_srcset_candidate("a.png 480w, b,c.png 960w"), Some("b,c.png"));
+        assert_eq!(best_srcset_candidate("a.png, b.png 1.5x"), Some("b.png"));
+    }
+
+    #[test]
+    fn extracts_lazy_picture_svg_and_background_media() {
+        let base = Url::parse("https://example.com/docs/page").unwrap();
+        let html = r#"<html><head><style>.hero { background-image: url("/img/hero.jpg"); }</style>
+<link rel="icon" href="/favicon.ico"></head><body>
+<img src="data:image/gif;base64,R0lGOD" data-src="diagram.png" alt="Architecture"><img src="a.png"><img src="b.png">
+<picture><source srcset="/img/p-800.webp 800w, /img/p-1600.webp 1600w"><img src="/img/p.jpg" alt="Pic"></picture>
+<div style="background: #fff url('/img/bg.png') no-repeat"></div>
+<svg viewBox="0 0 10 10"><title>Flow</title><svg><rect/></svg></svg>
+</body></html>"#;
+
+        let media = extract_media(
+            &base,
+            html,
+            &[MediaKind::Image, MediaKind::Svg, MediaKind::CssBackground],
+        );
+        assert_eq!(
+            urls(&media),
+            [
+                "/img/p-1600.webp",
+                "/docs/diagram.png",
+                "/docs/a.png",
+                "/docs/b.png",
+                "/img/bg.png",
+                "/img/hero.jpg",
+            ]
+        );
+        assert_eq!(media[1].alt.as_deref(), Some("Architecture"));
+
+        let svgs: Vec<_> = media.iter().filter(|m| m.kind == MediaKind::Svg).collect();
+        assert_eq!(svgs.len(), 1);
+        assert_eq!(svgs[0].alt.as_deref(), Some("Flow"));
+
+        let icons = extract_media(&base, html, &[MediaKind::Icon]);
+        assert_eq!(urls(&icons), ["/favicon.ico"]);
+    }
+
+    #[test]
+    fn reads_canonical_url_and_visible_text() {
+        let base = Url::parse("https://example.com/docs/page?ref=nav").unwrap();
+        let html = r#"<html><head><title>Page</title><link rel="Canonical" href="/docs/page#top">
+<script>var hidden = 1;</script></head><body><h1>Hello</h1>
+<p>visible   <b>text</b></p><style>p { color: red }</style></body></html>"#;
+
+        assert_eq!(
+            extract_canonical(&base, html).map(String::from),
+            Some("https://example.com/docs/page".to_string())
+        );
+        assert_eq!(extract_canonical(&base, "<p>No canonical</p>"), None);
+        assert_eq!(visible_text(html), "Hello visible text");
+    }
+}
@@ -0,0 +1,249 @@
+//! Languages of multilingual documentation
+//!
+//! Many documentation sites publish every page in several languages, under
+//! `/en/` and `/de/`, with `?lang=fr` or as `hreflang` alternates. The
+//! [`LanguageSettings`] of an entry name the languages to keep: pages in
+//! other languages are neither saved nor followed, and links that are known
+//! to lead to them (from their `hreflang` or URL) are not fetched at all.
+//! Alternatively every kept language gets its own combined result file.
+//!
+//! The language of a page is taken from its `<html lang>`, then from the
+//! `hreflang` of the links to it, then from its URL, and last guessed from
+//! the common words of its text. Languages are compared by their primary
+//! ISO 639-1 code, so `en-GB` counts as `en`.
+
+use std::cmp::Reverse;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::html::{document_language, visible_text};
+
+/// Language codes recognised as path segments, like `/de/` or `/pt-br/`
+///
+/// Codes that are common words in paths (`id`, `is`, `no`) are left out.
+const PATH_LANGUAGES: [&str; 38] = [
+    "ar", "bg", "ca", "cs", "da", "de", "el", "en", "es", "et", "fa", "fi", "fr", "he", "hi", "hr", "hu", "it",
+    "ja", "ko", "lt", "lv", "nb", "nl", "pl", "pt", "ro", "ru", "sk", "sl", "sr", "sv", "th", "tr", "uk", "vi",
+    "zh", "nn",
+];
+
+/// Query parameters naming the language of a page
+const LANGUAGE_PARAMS: [&str; 4] = ["lang", "hl", "locale", "language"];
+
+/// Common words of the languages the text detector knows
+const STOPWORDS: [(&str, &[&str]); 8] = [
+    ("en", &["the", "and", "is", "of", "to", "in", "that", "it", "for", "with", "you", "this", "are", "be", "can", "or"]),
+    ("de", &["der", "die", "und", "das", "ist", "nicht", "mit", "sie", "ein", "eine", "den", "auf", "für", "wird", "werden", "oder"]),
+    ("fr", &["le", "la", "les", "et", "est", "des", "une", "pour", "dans", "pas", "vous", "sur", "avec", "qui", "sont", "du"]),
+    ("es", &["el", "los", "las", "y", "es", "una", "para", "por", "con", "que", "del", "se", "como", "su", "está", "puede"]),
+    ("it", &["il", "gli", "e", "di", "che", "è", "una", "per", "non", "sono", "con", "della", "del", "questo", "anche", "può"]),
+    ("pt", &["o", "os", "as", "e", "é", "uma", "para", "com", "não", "que", "do", "da", "em", "você", "são", "pode"]),
+    ("nl", &["de", "het", "een", "en", "is", "van", "niet", "met", "op", "voor", "dat", "zijn", "je", "wordt", "kan", "ook"]),
+    ("pl", &["i", "w", "jest", "się", "nie", "na", "z", "do", "że", "to", "jak", "dla", "są", "można", "oraz", "lub"]),
+];
+
+/// Stopwords the most frequent language needs before the guess is trusted
+const MIN_STOPWORD_HITS: usize = 8;
+
+/// Helper for `#[serde(default = ...)]` of switches that are on by default
+fn default_true() -> bool {
+    true
+}
+
+/// Languages kept from an entry
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LanguageSettings {
+    /// Languages to keep (ISO 639-1 codes such as `en`) in order of
+    /// preference; empty keeps every language
+    #[serde(default)]
+    pub keep: Vec<String>,
+    /// Write one combined result per language: the result file holds the
+    /// first kept language (and pages of unknown language), every other
+    /// language is written to `<entry>.<language>.md`
+    #[serde(default)]
+    pub split_results: bool,
+    /// Guess the language of pages that declare none from their text
+    #[serde(default = "default_true")]
+    pub detect_text: bool,
+}
+
+impl Default for LanguageSettings {
+    fn default() -> Self {
+        Self {
+            keep: Vec::new(),
+            split_results: false,
+            detect_text: true,
+        }
+    }
+}
+
+impl LanguageSettings {
+    /// Settings for an entry: the entry's own settings replace the global ones
+    pub fn for_entry(&self, entry: Option<&LanguageSettings>) -> LanguageSettings {
+        entry.unwrap_or(self).clone()
+    }
+
+    /// Whether pages in a language are kept
+    pub fn accepts(&self, language: &str) -> bool {
+        self.keep.is_empty()
+            || self
+                .keep
+                .iter()
+                .any(|keep| primary_subtag(keep).as_deref() == Some(language))
+    }
+
+    /// Whether pages of some languages are left out
+    pub fn filters(&self) -> bool {
+        !self.keep.is_empty()
+    }
+}
+
+/// Where the language of a page was taken from
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LanguageSource {
+    /// The `lang` attribute of the `<html>` element
+    HtmlLang,
+    /// The `hreflang` of a link to the page
+    Hreflang,
+    /// A path segment or query parameter of the URL
+    Url,
+    /// The common words of the text
+    Text,
+}
+
+impl fmt::Display for LanguageSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            LanguageSource::HtmlLang => "html lang",
+            LanguageSource::Hreflang => "hreflang",
+            LanguageSource::Url => "URL",
+            LanguageSource::Text => "text",
+        };
+        f.write_str(description)
+    }
+}
+
+/// Language of a page and where it was taken from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageLanguage {
+    /// Primary ISO 639-1 code of the language
+    pub code: String,
+    /// Where the language was taken from
+    pub source: LanguageSource,
+}
+
+/// Language of a fetched page, `None` if nothing tells
+///
+/// `hreflang` is the language links to the page declared for it, if any.
+pub fn page_language(url: &Url, html: &str, hreflang: Option<&str>, detect_text: bool) -> Option<PageLanguage> {
+    let found = |code: Option<String>, source| code.map(|code| PageLanguage { code, source });
+    found(document_language(html).as_deref().and_then(primary_subtag), LanguageSource::HtmlLang)
+        .or_else(|| found(hreflang.and_then(primary_subtag), LanguageSource::Hreflang))
+        .or_else(|| found(url_language(url), LanguageSource::Url))
+        .or_else(|| {
+            if !detect_text {
+                return None;
+            }
+            found(detect_text_language(&visible_text(html)), LanguageSource::Text)
+        })
+}
+
+/// Language named by a URL: a language query parameter, or a language code
+/// as one of the first two path segments
+pub fn url_language(url: &Url) -> Option<String> {
+    let from_query = url
+        .query_pairs()
+        .find(|(name, _)| LANGUAGE_PARAMS.contains(&name.to_ascii_lowercase().as_str()))
+        .and_then(|(_, value)| primary_subtag(&value));
+    if from_query.is_some() {
+        return from_query;
+    }
+
+    url.path_segments()?.take(2).find_map(|segment| {
+        let (language, region) = match segment.split_once(['-', '_']) {
+            Some((language, region)) => (language, Some(region)),
+            None => (segment, None),
+        };
+        let language = language.to_ascii_lowercase();
+        let is_region = region.is_none_or(|region| region.len() == 2 && region.chars().all(|c| c.is_ascii_alphabetic()));
+        (is_region && PATH_LANGUAGES.contains(&language.as_str())).then_some(language)
+    })
+}
+
+/// Guess the language of a text from its most common words
+///
+/// Only the languages of [`STOPWORDS`] are known; `None` if the text is too
+/// short or no language clearly wins.
+pub fn detect_text_language(text: &str) -> Option<String> {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    let mut scores: Vec<(usize, &str)> = STOPWORDS
+        .iter()
+        .map(|(language, stopwords)| {
+            let hits = words.iter().filter(|word| stopwords.contains(&word.as_str())).count();
+            (hits, *language)
+        })
+        .collect();
+    scores.sort_by_key(|score| Reverse(score.0));
+
+    let (best, language) = scores[0];
+    let second = scores[1].0;
+    (best >= MIN_STOPWORD_HITS && best * 2 > second * 3).then(|| language.to_string())
+}
+
+/// Primary language subtag of a language tag (`en-US` is `en`), if it is one
+pub fn primary_subtag(tag: &str) -> Option<String> {
+    let primary = tag.trim().split(['-', '_']).next()?.to_ascii_lowercase();
+    (matches!(primary.len(), 2 | 3) && primary.chars().all(|c| c.is_ascii_alphabetic())).then_some(primary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn languages_are_read_from_urls_and_markup() {
+        let url = |url: &str| Url::parse(url).unwrap();
+        assert_eq!(url_language(&url("https://example.com/de/guide/")), Some("de".to_string()));
+        assert_eq!(url_language(&url("https://example.com/docs/pt-BR/intro")), Some("pt".to_string()));
+        assert_eq!(url_language(&url("https://example.com/guide?lang=fr_CA")), Some("fr".to_string()));
+        assert_eq!(url_language(&url("https://example.com/api/id/users")), None);
+        assert_eq!(url_language(&url("https://example.com/docs/guide/de/")), None);
+
+        let page = url("https://example.com/pl/start");
+        let declared = page_language(&page, r#"<html lang="en-GB"><body>Hi</body></html>"#, Some("pl"), true);
+        assert_eq!(
+            declared,
+            Some(PageLanguage {
+                code: "en".to_string(),
+                source: LanguageSource::HtmlLang
+            })
+        );
+        let hinted = page_language(&page, "<p>Hi</p>", Some("pl"), true).unwrap();
+        assert_eq!((hinted.code.as_str(), hinted.source), ("pl", LanguageSource::Hreflang));
+
+        let settings = LanguageSettings {
+            keep: vec!["en-US".to_string(), "pl".to_string()],
+            ..LanguageSettings::default()
+        };
+        assert!(settings.accepts("en") && settings.accepts("pl") && !settings.accepts("de"));
+        assert!(LanguageSettings::default().accepts("de"));
+    }
+
+    #[test]
+    fn text_language_is_guessed_from_common_words() {
+        let english = "The crawler downloads the pages of a site and converts them to Markdown. \
+            It is easy to use and you can configure it for every entry, with limits that are kept in the settings.";
+        let polish = "Ta strona jest dostępna w języku polskim i można ją przeczytać na telefonie lub komputerze. \
+            Nie jest to trudne, a instrukcja jest dla każdego, kto się uczy, oraz dla tych, którzy nie mają czasu.";
+        assert_eq!(detect_text_language(english).as_deref(), Some("en"));
+        assert_eq!(detect_text_language(polish).as_deref(), Some("pl"));
+        assert_eq!(detect_text_language("Hello world"), None);
+    }
+}
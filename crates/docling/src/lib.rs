@@ -9,37 +9,80 @@
 //! - Converts HTML to Markdown using configurable methods
 //! - Processes and combines content
 //! - Robust error handling and retry logic
+//!
+//! ## Usage
+//! Create a [`Docling`] handle once for a workspace root and use its methods
+//! to manage entries and run the pipeline.
 
 // Public modules
+pub mod auth;
+pub mod budget;
+pub mod canonical;
+pub mod charset;
 pub mod crawler;
 pub mod converter;
+pub mod documents;
+pub mod error;
+pub mod events;
+pub mod extract;
+pub mod fetch;
+pub mod git;
+pub mod html;
+pub mod language;
+pub mod links;
+pub mod local;
+pub mod manifest;
+pub mod media;
+pub mod network;
+pub mod plan;
 pub mod processor;
+pub mod rate_limit;
+pub mod report;
+pub mod robots;
+pub mod scope;
+pub mod sitemap;
+pub mod store;
+pub mod version;
+pub mod warc;
+
+#[cfg(test)]
+mod test_server;
 
-use std::collections::HashMap;
-use std::path::PathBuf;
-use std::time::{Duration, SystemTime};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
 use std::fs::create_dir_all;
 
-use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use mi4ulings_config::{Config, Configuration};
 use serde::{Deserialize, Serialize};
-use tracing::{debug, error, info, warn};
+use tracing::{error, info, warn};
 use url::Url;
 
+pub use auth::SecretStore;
+pub use error::{DoclingError, IoContext, Result};
+pub use events::{ProgressEvent, ProgressObserver, ProgressReporter, ProgressUpdate};
+pub use report::{RunMode, RunReport};
+pub use store::EntryStore;
+
 // Constants
 const DEFAULT_RETRY_COUNT: u32 = 3;
 const DEFAULT_DELAY_BETWEEN_REQUESTS_MS: u64 = 500;
 const DEFAULT_MAX_CONCURRENT_REQUESTS: u32 = 1;
+const DEFAULT_RATE_LIMIT_BURST: u32 = 1;
 const DEFAULT_USER_AGENT: &str = "mi4uling-docling-bot";
 const DEFAULT_REFETCH_DAYS: u32 = 100;
 const DEFAULT_CRAWL_DEPTH: u32 = 1;
+const DEFAULT_MAX_MEDIA_FILE_BYTES: u64 = 50 * 1024 * 1024;
+const DEFAULT_MAX_MEDIA_BYTES_PER_ENTRY: u64 = 1024 * 1024 * 1024;
 
 /// HTML to Markdown transformation method
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum TransformMethod {
     /// Use htmd library (default)
+    #[default]
     Htmd,
     /// Use fast_html2md library
     FastHtml2md,
@@ -47,17 +90,153 @@ pub enum TransformMethod {
     JinaReader,
 }
 
-impl Default for TransformMethod {
-    fn default() -> Self {
-        TransformMethod::Htmd
+/// Kind of media downloaded from crawled pages
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaKind {
+    /// `<img>` and `<picture>` images, including `srcset` and lazy-loaded sources
+    Image,
+    /// Inline `<svg>` elements
+    Svg,
+    /// Favicons and touch icons from `<link rel="icon">`
+    Icon,
+    /// `background-image` URLs in `style` attributes and `<style>` elements
+    CssBackground,
+    /// `<video>` sources and poster images
+    Video,
+}
+
+/// Media kinds downloaded when the configuration does not list any
+fn default_media_kinds() -> Vec<MediaKind> {
+    vec![MediaKind::Image, MediaKind::Svg, MediaKind::CssBackground]
+}
+
+/// Request burst per host when the configuration does not set one
+fn default_rate_limit_burst() -> u32 {
+    DEFAULT_RATE_LIMIT_BURST
+}
+
+/// Directory suffix for linked documents when the configuration does not set one
+fn default_output_parts_docs_suffix() -> String {
+    "parts_docs".to_string()
+}
+
+/// Directory suffix for WARC archives when the configuration does not set one
+fn default_output_parts_warc_suffix() -> String {
+    "parts_warc".to_string()
+}
+
+/// Directory suffix for extracted HTML when the configuration does not set one
+fn default_output_parts_extracted_suffix() -> String {
+    "parts_extracted".to_string()
+}
+
+/// Size limit of a single media file when the configuration does not set one
+fn default_max_media_file_bytes() -> u64 {
+    DEFAULT_MAX_MEDIA_FILE_BYTES
+}
+
+/// Media budget of an entry when the configuration does not set one
+fn default_max_media_bytes_per_entry() -> u64 {
+    DEFAULT_MAX_MEDIA_BYTES_PER_ENTRY
+}
+
+/// Type of linked document downloaded alongside the HTML pages
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentType {
+    /// PDF files, converted by text extraction (`pdf` feature)
+    Pdf,
+    /// Word documents, converted to Markdown (`docx` feature)
+    Docx,
+    /// Markdown files, kept as they are
+    Markdown,
+    /// Plain text files, kept as they are
+    Text,
+    /// reStructuredText files, kept as they are
+    Rst,
+    /// AsciiDoc files, converted to Markdown
+    #[serde(rename = "asciidoc")]
+    AsciiDoc,
+}
+
+impl DocumentType {
+    /// Extension of saved documents of this type
+    pub fn extension(self) -> &'static str {
+        match self {
+            DocumentType::Pdf => "pdf",
+            DocumentType::Docx => "docx",
+            DocumentType::Markdown => "md",
+            DocumentType::Text => "txt",
+            DocumentType::Rst => "rst",
+            DocumentType::AsciiDoc => "adoc",
+        }
+    }
+
+    /// Document type for a file extension
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "pdf" => Some(DocumentType::Pdf),
+            "docx" => Some(DocumentType::Docx),
+            "md" | "markdown" => Some(DocumentType::Markdown),
+            "txt" | "text" => Some(DocumentType::Text),
+            "rst" => Some(DocumentType::Rst),
+            "adoc" | "asciidoc" => Some(DocumentType::AsciiDoc),
+            _ => None,
+        }
+    }
+
+    /// Document type named by the extension of a URL's last path segment
+    pub fn from_url(url: &Url) -> Option<Self> {
+        let segment = url.path_segments()?.next_back()?;
+        let (_, extension) = segment.rsplit_once('.')?;
+        Self::from_extension(extension)
+    }
+
+    /// Document type of a response
+    ///
+    /// Specific MIME types decide on their own; generic ones such as
+    /// `text/plain` or `application/octet-stream` (and a missing
+    /// `Content-Type`) fall back to the extension of the URL.
+    ///
+    /// # Arguments
+    /// * `mime_type` - MIME type of the response without parameters, if any
+    /// * `url` - URL the document was requested from
+    pub fn detect(mime_type: Option<&str>, url: &Url) -> Option<Self> {
+        match mime_type {
+            Some("application/pdf" | "application/x-pdf") => Some(DocumentType::Pdf),
+            Some("application/vnd.openxmlformats-officedocument.wordprocessingml.document") => {
+                Some(DocumentType::Docx)
+            }
+            Some("text/markdown" | "text/x-markdown") => Some(DocumentType::Markdown),
+            Some("text/x-rst" | "text/prs.fallenstein.rst") => Some(DocumentType::Rst),
+            Some("text/asciidoc" | "text/x-asciidoc") => Some(DocumentType::AsciiDoc),
+            Some("text/plain") => Some(Self::from_url(url).unwrap_or(DocumentType::Text)),
+            None | Some("application/octet-stream" | "binary/octet-stream") => Self::from_url(url),
+            Some(_) => None,
+        }
+    }
+}
+
+impl std::str::FromStr for DocumentType {
+    type Err = DoclingError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::from_extension(s.trim()).ok_or_else(|| {
+            DoclingError::config(format!(
+                "Unknown document type '{}' (expected pdf, docx, md, txt, rst or adoc)",
+                s
+            ))
+        })
     }
 }
 
 /// Status of a crawl task
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum CrawlStatus {
     /// Task is enabled and ready to run
+    #[default]
     Enabled,
     /// Task is disabled by user
     Disabled,
@@ -65,9 +244,28 @@ pub enum CrawlStatus {
     Failed,
 }
 
-impl Default for CrawlStatus {
-    fn default() -> Self {
-        CrawlStatus::Enabled
+/// How the crawler discovers the pages of an entry
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscoveryMode {
+    /// Follow links up to the crawl depth and seed the crawl from sitemaps (default)
+    #[default]
+    LinksAndSitemaps,
+    /// Only follow links from the entry URL
+    Links,
+    /// Only fetch the entry URL and the pages listed in its sitemaps
+    SitemapOnly,
+}
+
+impl DiscoveryMode {
+    /// Whether links found on pages are followed
+    pub fn follows_links(self) -> bool {
+        !matches!(self, DiscoveryMode::SitemapOnly)
+    }
+
+    /// Whether the crawl is seeded from sitemaps
+    pub fn uses_sitemaps(self) -> bool {
+        !matches!(self, DiscoveryMode::Links)
     }
 }
 
@@ -84,16 +282,28 @@ pub struct DoclingConfig {
     pub output_parts_html_suffix: String,
     /// Suffix for media output directories
     pub output_parts_media_suffix: String,
+    /// Suffix for linked document output directories
+    #[serde(default = "default_output_parts_docs_suffix")]
+    pub output_parts_docs_suffix: String,
+    /// Suffix for WARC archive directories
+    #[serde(default = "default_output_parts_warc_suffix")]
+    pub output_parts_warc_suffix: String,
+    /// Suffix for directories of extracted main content, saved when asked to
+    #[serde(default = "default_output_parts_extracted_suffix")]
+    pub output_parts_extracted_suffix: String,
     /// Suffix for Markdown output directories
     pub output_parts_markdown_suffix: String,
     /// Suffix for final Markdown result directories
     pub output_parts_markdown_results_suffix: String,
     /// Number of retry attempts for failed downloads
     pub retry_count: u32,
-    /// Delay between HTTP requests in milliseconds
+    /// Minimum delay between HTTP requests to the same host in milliseconds
     pub delay_between_request_in_ms: u64,
     /// Maximum number of concurrent requests
     pub max_concurrent_requests: u32,
+    /// Number of requests a host may receive back to back before the delay applies
+    #[serde(default = "default_rate_limit_burst")]
+    pub rate_limit_burst: u32,
     /// User agent string for HTTP requests
     pub user_agent: String,
     /// Whether to respect robots.txt
@@ -106,6 +316,36 @@ pub struct DoclingConfig {
     pub refetch_after_days: u32,
     /// Default crawl depth
     pub default_deep: u32,
+    /// Kinds of media downloaded from crawled pages
+    #[serde(default = "default_media_kinds")]
+    pub media_kinds: Vec<MediaKind>,
+    /// Largest media file that is downloaded, in bytes
+    #[serde(default = "default_max_media_file_bytes")]
+    pub max_media_file_bytes: u64,
+    /// Total size of the media kept for one entry, in bytes
+    #[serde(default = "default_max_media_bytes_per_entry")]
+    pub max_media_bytes_per_entry: u64,
+    /// Proxy and TLS settings for all HTTP clients
+    #[serde(default)]
+    pub network: network::NetworkSettings,
+    /// Limits of every crawl, unlimited unless set
+    #[serde(default)]
+    pub budget: budget::CrawlBudget,
+    /// Also archive every crawl as a WARC file
+    #[serde(default)]
+    pub write_warc: bool,
+    /// When URLs and pages count as the same page
+    #[serde(default)]
+    pub normalization: canonical::UrlNormalization,
+    /// Languages kept from multilingual sites, every language unless set
+    #[serde(default)]
+    pub languages: language::LanguageSettings,
+    /// Version of versioned documentation to crawl, every version unless set
+    #[serde(default)]
+    pub versions: version::VersionSettings,
+    /// Main-content extraction before conversion
+    #[serde(default)]
+    pub extraction: extract::ExtractionSettings,
 }
 
 impl Default for DoclingConfig {
@@ -116,17 +356,31 @@ impl Default for DoclingConfig {
             logs_path: PathBuf::from("logs"),
             output_parts_html_suffix: "parts_html".to_string(),
             output_parts_media_suffix: "parts_media".to_string(),
+            output_parts_docs_suffix: default_output_parts_docs_suffix(),
+            output_parts_warc_suffix: default_output_parts_warc_suffix(),
+            output_parts_extracted_suffix: default_output_parts_extracted_suffix(),
             output_parts_markdown_suffix: "parts_md".to_string(),
             output_parts_markdown_results_suffix: "results_md".to_string(),
             retry_count: DEFAULT_RETRY_COUNT,
             delay_between_request_in_ms: DEFAULT_DELAY_BETWEEN_REQUESTS_MS,
             max_concurrent_requests: DEFAULT_MAX_CONCURRENT_REQUESTS,
+            rate_limit_burst: DEFAULT_RATE_LIMIT_BURST,
             user_agent: DEFAULT_USER_AGENT.to_string(),
             respect_robots_txt: true,
             transform_md_using: TransformMethod::default(),
             retry_delay: vec![10, 40, 200],
             refetch_after_days: DEFAULT_REFETCH_DAYS,
             default_deep: DEFAULT_CRAWL_DEPTH,
+            media_kinds: default_media_kinds(),
+            max_media_file_bytes: DEFAULT_MAX_MEDIA_FILE_BYTES,
+            max_media_bytes_per_entry: DEFAULT_MAX_MEDIA_BYTES_PER_ENTRY,
+            network: network::NetworkSettings::default(),
+            budget: budget::CrawlBudget::default(),
+            write_warc: false,
+            normalization: canonical::UrlNormalization::default(),
+            languages: language::LanguageSettings::default(),
+            versions: version::VersionSettings::default(),
+            extraction: extract::ExtractionSettings::default(),
         }
     }
 }
@@ -156,27 +410,54 @@ pub struct UrlEntry {
     pub status: CrawlStatus,
     /// Version of the entry
     pub version: u32,
+    /// How pages are discovered
+    #[serde(default)]
+    pub discovery: DiscoveryMode,
+    /// Types of linked documents downloaded and converted with the pages
+    #[serde(default)]
+    pub document_types: Vec<DocumentType>,
+    /// Proxy and TLS settings added to the global ones for this entry
+    #[serde(default)]
+    pub network: Option<network::NetworkSettings>,
+    /// Crawl limits replacing the global ones for this entry
+    #[serde(default)]
+    pub budget: Option<budget::CrawlBudget>,
+    /// Git repository read instead of crawling the URL
+    #[serde(default)]
+    pub git: Option<git::GitSource>,
+    /// Revision of the source the last run read (the commit of a git source)
+    #[serde(default)]
+    pub revision: Option<String>,
+    /// Archive the crawls of this entry as WARC files even if not configured globally
+    #[serde(default)]
+    pub warc: bool,
+    /// Languages kept from this entry, replacing the global language settings
+    #[serde(default)]
+    pub languages: Option<language::LanguageSettings>,
+    /// Documentation version crawled from this entry, replacing the global version settings
+    #[serde(default)]
+    pub versions: Option<version::VersionSettings>,
+    /// Main-content extraction of this entry, replacing the global extraction settings
+    #[serde(default)]
+    pub extraction: Option<extract::ExtractionSettings>,
 }
 
 impl UrlEntry {
     /// Create a new URL entry
     /// 
     /// # Arguments
-    /// * `url` - The URL to crawl
+    /// * `url` - The URL to crawl, or a path to a local directory or file
     /// * `name` - Name for this entry (used for file naming)
-    /// * `crawl_depth` - How deep to crawl (number of link levels), or None to use default_deep
+    /// * `crawl_depth` - How deep to crawl (number of link levels)
     ///
     /// # Returns
     /// A new UrlEntry if successful
     ///
     /// # Errors
-    /// Returns an error if the URL is invalid
-    pub fn new(url: &str, name: &str, crawl_depth: Option<u32>) -> Result<Self> {
-        // Load config to get default_deep
-        let config = Config::<DoclingConfig>::load_or_default()?;
-        
-        // Validate URL
-        let parsed_url = Url::parse(url).context("Invalid URL format")?;
+    /// Returns an error if the URL is invalid and not an existing path
+    pub fn new(url: &str, name: &str, crawl_depth: u32) -> Result<Self> {
+        // Validate URL, local paths become file:// URLs
+        let parsed_url = local::source_url(url)?;
         
         Ok(Self {
             url: parsed_url.to_string(),
@@ -184,9 +465,19 @@ impl UrlEntry {
             last_download: None,
             last_try: None,
             last_fail: None,
-            crawl_depth: crawl_depth.unwrap_or(config.data.default_deep),
+            crawl_depth,
             status: CrawlStatus::Enabled,
             version: 1,
+            discovery: DiscoveryMode::default(),
+            document_types: Vec::new(),
+            network: None,
+            budget: None,
+            git: None,
+            revision: None,
+            warc: false,
+            languages: None,
+            versions: None,
+            extraction: None,
         })
     }
     
@@ -201,344 +492,716 @@ impl UrlEntry {
             true
         }
     }
+
+    /// Copy the fields updated by a run (timestamps, status, version) from `other`
+    fn copy_run_state(&mut self, other: &UrlEntry) {
+        self.last_download = other.last_download;
+        self.last_try = other.last_try;
+        self.last_fail = other.last_fail;
+        self.status = other.status.clone();
+        self.version = other.version;
+        self.revision = other.revision.clone();
+    }
 }
 
 /// Collection of URL entries
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct UrlEntries {
     /// Map of URL entries by name
     pub entries: HashMap<String, UrlEntry>,
 }
 
-impl Default for UrlEntries {
-    fn default() -> Self {
-        Self {
-            entries: HashMap::new(),
-        }
+impl DoclingConfig {
+    /// Return a copy of the configuration with relative paths resolved against `root`
+    ///
+    /// Absolute paths are left untouched.
+    pub fn resolved_against(&self, root: &Path) -> Self {
+        let mut config = self.clone();
+        config.inputs_path = root.join(&self.inputs_path);
+        config.outputs_path = root.join(&self.outputs_path);
+        config.logs_path = root.join(&self.logs_path);
+        config.network.ca_certificates = self
+            .network
+            .ca_certificates
+            .iter()
+            .map(|path| root.join(path))
+            .collect();
+        config
     }
 }
 
-/// Store URL entries in a TOML file
-pub fn save_entries(entries: &UrlEntries) -> Result<()> {
-    let config = Config::<DoclingConfig>::load_or_default()?;
-    let inputs_path = config.data.inputs_path.clone();
-    
-    std::fs::create_dir_all(&inputs_path)
-        .context("Failed to create inputs directory")?;
-    
-    let entries_path = inputs_path.join("entries.toml");
-    let toml_string = toml::to_string(entries)
-        .context("Failed to serialize URL entries to TOML")?;
-    
-    std::fs::write(&entries_path, toml_string)
-        .context("Failed to write URL entries to file")?;
-    
-    Ok(())
+/// Handle to a docling workspace
+///
+/// Holds the workspace root, a snapshot of the configuration (with all paths
+/// resolved against the root) and the entry store. Construct it once and
+/// share it; it is cheap to clone and all methods take `&self`, so it can be
+/// used concurrently, e.g. from web request handlers.
+#[derive(Debug, Clone)]
+pub struct Docling {
+    /// Workspace root all relative paths are resolved against
+    root: PathBuf,
+    /// Configuration snapshot with resolved paths
+    config: Arc<DoclingConfig>,
+    /// Store of URL entries
+    store: Arc<EntryStore>,
+    /// Store of entry credentials
+    secrets: Arc<SecretStore>,
 }
 
-/// Load URL entries from a TOML file
-pub fn load_entries() -> Result<UrlEntries> {
-    let config = Config::<DoclingConfig>::load_or_default()?;
-    let inputs_path = config.data.inputs_path.clone();
-    let entries_path = inputs_path.join("entries.toml");
-    
-    if !entries_path.exists() {
-        return Ok(UrlEntries::default());
+impl Docling {
+    /// Create a handle for the workspace at `root` using the given configuration
+    ///
+    /// # Arguments
+    /// * `root` - Workspace root; relative paths in `config` are resolved against it
+    /// * `config` - Configuration to use for all operations
+    pub fn new(root: impl Into<PathBuf>, config: DoclingConfig) -> Self {
+        let root = root.into();
+        let config = config.resolved_against(&root);
+        let store = EntryStore::new(&config.inputs_path);
+        let secrets = SecretStore::new(&config.inputs_path);
+
+        Self {
+            root,
+            config: Arc::new(config),
+            store: Arc::new(store),
+            secrets: Arc::new(secrets),
+        }
     }
-    
-    let toml_string = std::fs::read_to_string(&entries_path)
-        .context("Failed to read URL entries from file")?;
-    
-    let entries: UrlEntries = toml::from_str(&toml_string)
-        .context("Failed to parse URL entries from TOML")?;
-    
-    Ok(entries)
-}
 
-/// Add a new URL entry
-///
-/// Creates a new URL entry with the specified URL, name, and crawl depth.
-/// If crawl_depth is not provided, uses the default_deep from config.
-/// If name is not provided, uses the host part of the URL.
-///
-/// # Arguments
-/// * `url` - The URL to crawl
-/// * `name_opt` - Optional name for the entry (defaults to host name)
-/// * `crawl_depth` - Optional crawl depth (defaults to config.default_deep)
-///
-/// # Returns
-/// Ok(()) if successful
-///
-/// # Errors
-/// Returns an error if:
-/// - The URL is invalid
-/// - An entry with the same name already exists
-/// - The entries cannot be saved
-pub fn add_url(url: &str, name_opt: Option<&str>, crawl_depth: Option<u32>) -> Result<()> {
-    // Load configuration to get default_deep
-    let config = Config::<DoclingConfig>::load_or_default()?;
-    let default_deep = config.data.default_deep;
-    
-    // Generate name from URL if not provided
-    let name = match name_opt {
-        Some(n) => n.to_string(),
-        None => {
-            let parsed_url = Url::parse(url).context("Invalid URL format")?;
-            parsed_url.host_str()
-                .map(|h| h.to_string())
-                .unwrap_or_else(|| "unnamed".to_string())
+    /// Create a handle for the current directory, loading (or creating) the stored configuration
+    ///
+    /// # Errors
+    /// Returns an error if the current directory or the configuration cannot be read
+    pub fn from_current_dir() -> Result<Self> {
+        let root = std::env::current_dir().io_context("Failed to determine current directory")?;
+        let config = Config::<DoclingConfig>::load_or_default()
+            .map_err(|e| DoclingError::config_with("Failed to load docling configuration", e))?;
+
+        Ok(Self::new(root, config.data))
+    }
+
+    /// Workspace root
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Configuration snapshot with resolved paths
+    pub fn config(&self) -> &DoclingConfig {
+        &self.config
+    }
+
+    /// Store of URL entries
+    pub fn store(&self) -> &EntryStore {
+        &self.store
+    }
+
+    /// Store of entry credentials
+    pub fn secrets(&self) -> &SecretStore {
+        &self.secrets
+    }
+
+    /// Directory the final result of each entry is copied to
+    ///
+    /// This is `docs/docling_output` in the parent of the workspace root, as
+    /// the CLI runs from its crate directory inside the repository; a root
+    /// without a parent holds the directory itself.
+    pub fn docling_output_dir(&self) -> PathBuf {
+        self.root.parent().unwrap_or(&self.root).join("docs").join("docling_output")
+    }
+
+    /// Get a single entry by name
+    pub fn entry(&self, name: &str) -> Result<UrlEntry> {
+        self.store.get(name)
+    }
+
+    /// Add a new URL entry
+    ///
+    /// Creates a new URL entry with the specified URL, name, and crawl depth.
+    /// If crawl_depth is not provided, uses the default_deep from config.
+    /// If name is not provided, uses the host part of the URL, or the name of
+    /// the directory or file of a local source.
+    ///
+    /// # Arguments
+    /// * `url` - The URL to crawl, or a path to a local directory or file
+    /// * `name_opt` - Optional name for the entry (defaults to host name)
+    /// * `crawl_depth` - Optional crawl depth (defaults to config.default_deep)
+    ///
+    /// # Returns
+    /// The newly created entry
+    ///
+    /// # Errors
+    /// Returns an error if:
+    /// - The URL is invalid
+    /// - An entry with the same name already exists
+    /// - The entries cannot be saved
+    pub fn add_url(&self, url: &str, name_opt: Option<&str>, crawl_depth: Option<u32>) -> Result<UrlEntry> {
+        // Generate name from URL if not provided
+        let name = match name_opt {
+            Some(n) => n.to_string(),
+            None => {
+                let parsed_url = local::source_url(url)?;
+                // Local sources are named after their directory or file
+                parsed_url.host_str()
+                    .or_else(|| parsed_url.path_segments().and_then(|segments| segments.rev().find(|s| !s.is_empty())))
+                    .map(|h| h.to_string())
+                    .unwrap_or_else(|| "unnamed".to_string())
+            }
+        };
+        
+        // Create new entry
+        let entry = UrlEntry::new(url, &name, crawl_depth.unwrap_or(self.config.default_deep))?;
+        self.insert_entry(&entry)?;
+        
+        info!("Added URL entry: {} ({}) with depth {}", name, url, entry.crawl_depth);
+        Ok(entry)
+    }
+
+    /// Add an entry that reads documentation from a local git repository
+    ///
+    /// Markdown, reStructuredText, AsciiDoc and HTML files matching the
+    /// source's path globs are read at its reference and converted like
+    /// crawled pages.
+    ///
+    /// # Arguments
+    /// * `source` - Repository, reference and path globs to read
+    /// * `name_opt` - Optional name for the entry (defaults to the repository directory name)
+    ///
+    /// # Returns
+    /// The newly created entry
+    ///
+    /// # Errors
+    /// Returns an error if the repository path is invalid, an entry with the
+    /// same name already exists or the entries cannot be saved
+    pub fn add_git(&self, source: git::GitSource, name_opt: Option<&str>) -> Result<UrlEntry> {
+        let url = source.url()?;
+        let name = match name_opt {
+            Some(n) => n.to_string(),
+            None => source
+                .repository
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "unnamed".to_string()),
+        };
+
+        let mut entry = UrlEntry::new(url.as_str(), &name, 0)?;
+        entry.git = Some(source);
+        self.insert_entry(&entry)?;
+
+        info!("Added git entry: {} ({})", name, url);
+        Ok(entry)
+    }
+
+    /// Store a new entry, refusing to replace one with the same name
+    fn insert_entry(&self, entry: &UrlEntry) -> Result<()> {
+        self.store.update(|entries| {
+            // Check if name already exists
+            if entries.entries.contains_key(&entry.name) {
+                return Err(DoclingError::AlreadyExists { name: entry.name.clone() });
+            }
+            
+            entries.entries.insert(entry.name.clone(), entry.clone());
+            Ok(())
+        })
+    }
+
+    /// Remove a URL entry
+    pub fn remove_url(&self, name: &str) -> Result<()> {
+        self.store.update(|entries| {
+            entries.entries.remove(name)
+                .map(|_| ())
+                .ok_or_else(|| DoclingError::NotFound { name: name.to_string() })
+        })?;
+        self.secrets.remove(name)?;
+        
+        info!("Removed URL entry: {}", name);
+        Ok(())
+    }
+
+    /// Stop a URL entry (disable it)
+    pub fn stop_url(&self, name: &str) -> Result<()> {
+        self.store.update_entry(name, |entry| entry.status = CrawlStatus::Disabled)?;
+        
+        info!("Stopped URL entry: {}", name);
+        Ok(())
+    }
+
+    /// Start a URL entry (enable it)
+    pub fn start_url(&self, name: &str) -> Result<()> {
+        self.store.update_entry(name, |entry| entry.status = CrawlStatus::Enabled)?;
+        
+        info!("Started URL entry: {}", name);
+        Ok(())
+    }
+
+    /// List all URL entries
+    pub fn list_urls(&self) -> Result<Vec<UrlEntry>> {
+        let entries = self.store.load()?;
+        Ok(entries.entries.values().cloned().collect())
+    }
+
+    /// Link graph recorded by the last crawl of an entry
+    ///
+    /// # Errors
+    /// Returns an error if the entry does not exist, was never crawled or
+    /// the graph cannot be read
+    pub fn link_graph(&self, name: &str) -> Result<links::LinkGraph> {
+        self.store.get(name)?;
+        let entry_dir = self.config.outputs_path.join(name);
+        if !links::LinkGraph::path_in(&entry_dir).exists() {
+            return Err(DoclingError::config(format!("No link graph for entry '{}', run it first", name)));
         }
-    };
-    
-    // Create new entry
-    let entry = UrlEntry::new(url, &name, crawl_depth)?;
-    
-    // Store crawl_depth for logging before the entry is moved
-    let depth = entry.crawl_depth;
-    
-    // Load existing entries
-    let mut entries = load_entries()?;
-    
-    // Check if name already exists
-    if entries.entries.contains_key(&name) {
-        return Err(anyhow::anyhow!("Entry with name '{}' already exists", name));
+        links::LinkGraph::load(&entry_dir)
     }
-    
-    // Add new entry
-    entries.entries.insert(name.clone(), entry);
-    
-    // Save entries
-    save_entries(&entries)?;
-    
-    info!("Added URL entry: {} ({}) with depth {}", name, url, depth);
-    Ok(())
-}
 
-/// Remove a URL entry
-pub fn remove_url(name: &str) -> Result<()> {
-    // Load existing entries
-    let mut entries = load_entries()?;
-    
-    // Check if entry exists
-    if !entries.entries.contains_key(name) {
-        return Err(anyhow::anyhow!("Entry with name '{}' does not exist", name));
+    /// Discovers the pages of an entry without crawling it
+    ///
+    /// A dry run of the crawl: robots.txt, sitemaps and links are followed
+    /// within the entry's scope, depth and budget, but nothing is saved and
+    /// the entry's state does not change. Disabled entries can be planned too.
+    ///
+    /// # Errors
+    /// Returns an error if the entry does not exist, its credentials cannot
+    /// be resolved or its URL cannot be read
+    pub async fn plan_entry(&self, name: &str) -> Result<plan::CrawlPlan> {
+        let entry = self.store.get(name)?;
+        let (config, auth) = entry_settings(&entry, self.config.as_ref(), &self.secrets)?;
+        let mut crawler = crawler::Crawler::new(config)?.with_auth(auth);
+        crawler.plan_entry(&entry).await
     }
-    
-    // Remove entry
-    entries.entries.remove(name);
-    
-    // Save entries
-    save_entries(&entries)?;
-    
-    info!("Removed URL entry: {}", name);
-    Ok(())
-}
 
-/// Stop a URL entry (disable it)
-pub fn stop_url(name: &str) -> Result<()> {
-    // Load existing entries
-    let mut entries = load_entries()?;
-    
-    // Check if entry exists
-    let entry = entries.entries.get_mut(name)
-        .ok_or_else(|| anyhow::anyhow!("Entry with name '{}' does not exist", name))?;
-    
-    // Update status
-    entry.status = CrawlStatus::Disabled;
-    
-    // Save entries
-    save_entries(&entries)?;
-    
-    info!("Stopped URL entry: {}", name);
-    Ok(())
-}
+    /// Runs the crawling, conversion, and processing for a URL entry
+    /// 
+    /// This is the main function that orchestrates the entire process:
+    /// 1. Crawls the website to the specified depth
+    /// 2. Downloads HTML and media files
+    /// 3. Converts HTML to Markdown
+    /// 4. Processes and combines Markdown files
+    /// 5. Creates the final output file
+    /// 6. Copies the result to docs/docling_output directory
+    ///
+    /// Returns a [`RunReport`] describing every stage, which is also saved
+    /// as JSON next to the result file.
+    pub async fn run_entry(&self, name: &str) -> Result<RunReport> {
+        self.run_entry_with_progress(name, Arc::new(events::NoopObserver)).await
+    }
 
-/// Start a URL entry (enable it)
-pub fn start_url(name: &str) -> Result<()> {
-    // Load existing entries
-    let mut entries = load_entries()?;
-    
-    // Check if entry exists
-    let entry = entries.entries.get_mut(name)
-        .ok_or_else(|| anyhow::anyhow!("Entry with name '{}' does not exist", name))?;
-    
-    // Update status
-    entry.status = CrawlStatus::Enabled;
-    
-    // Save entries
-    save_entries(&entries)?;
-    
-    info!("Started URL entry: {}", name);
-    Ok(())
-}
+    /// Same as [`Docling::run_entry`], reporting progress events to `observer`
+    pub async fn run_entry_with_progress(
+        &self,
+        name: &str,
+        observer: Arc<dyn ProgressObserver>,
+    ) -> Result<RunReport> {
+        self.run_entry_in_mode(name, RunMode::Crawl, observer).await
+    }
 
-/// List all URL entries
-pub fn list_urls() -> Result<Vec<UrlEntry>> {
-    let entries = load_entries()?;
-    Ok(entries.entries.values().cloned().collect())
-}
+    /// Converts and processes the pages saved by earlier runs again, without
+    /// crawling
+    ///
+    /// Every saved page and document is converted again, offline, so changes
+    /// to the converter or processor settings can be checked quickly. The
+    /// entry's version and download times are left alone.
+    ///
+    /// # Errors
+    /// Returns an error if the entry was never crawled or a stage fails
+    pub async fn rebuild_entry(&self, name: &str) -> Result<RunReport> {
+        self.run_entry_in_mode(name, RunMode::Rebuild, Arc::new(events::NoopObserver)).await
+    }
 
-/// Runs the crawling, conversion, and processing for a URL entry
-/// 
-/// This is the main function that orchestrates the entire process:
-/// 1. Crawls the website to the specified depth
-/// 2. Downloads HTML and media files
-/// 3. Converts HTML to Markdown
-/// 4. Processes and combines Markdown files
-/// 5. Creates the final output file
-/// 6. Copies the result to docs/docling_output directory
-pub async fn run_entry(name: &str) -> Result<PathBuf> {
-    // Load configuration
-    let config = Config::<DoclingConfig>::load_or_default()?;
-    let config_data = config.data.clone();
-    
-    // Load entries
-    let mut entries = load_entries()?;
-    
-    // Check if entry exists and is enabled before processing
-    {
-        let entry = entries.entries.get(name)
-            .ok_or_else(|| anyhow::anyhow!("Entry with name '{}' does not exist", name))?;
+    /// Crawls an entry again from its WARC archives, without the network
+    ///
+    /// The crawler answers every request with the archived response, so the
+    /// run is deterministic; pages missing from the archives are answered
+    /// with 404. Like [`Docling::rebuild_entry`], every file is converted
+    /// again and the entry's version and download times are left alone.
+    ///
+    /// # Errors
+    /// Returns an error if the entry has no WARC archive or a stage fails
+    pub async fn replay_entry(&self, name: &str) -> Result<RunReport> {
+        self.run_entry_in_mode(name, RunMode::Replay, Arc::new(events::NoopObserver)).await
+    }
+
+    /// Runs an entry in the given mode, reporting progress events to `observer`
+    ///
+    /// Crawls are retried as configured; replays and rebuilds do not depend
+    /// on the source, so they are attempted once and a failure does not
+    /// change the state of the entry.
+    pub async fn run_entry_in_mode(
+        &self,
+        name: &str,
+        mode: RunMode,
+        observer: Arc<dyn ProgressObserver>,
+    ) -> Result<RunReport> {
+        let config_data = self.config.as_ref();
+        let progress = ProgressReporter::new(name, observer);
         
+        // Check if entry exists and is enabled before processing
+        let mut entry = self.store.get(name)?;
         if entry.status == CrawlStatus::Disabled {
-            return Err(anyhow::anyhow!("Entry '{}' is disabled", name));
+            return Err(DoclingError::Disabled { name: name.to_string() });
         }
-    }
-    
-    // Create directories (not dependent on entry borrow)
-    let base_dir = config_data.outputs_path.join(name);
-    create_dir_all(&base_dir).context("Failed to create output directory")?;
-    
-    // Create error directory 
-    let error_dir = base_dir.join("ERRORS");
-    create_dir_all(&error_dir).context("Failed to create error directory")?;
-    
-    // Prepare the docs/docling_output directory
-    let workspace_root = std::env::current_dir()?
-        .parent() // Go up from crates/docling
-        .ok_or_else(|| anyhow::anyhow!("Failed to determine workspace root"))?
-        .to_path_buf();
-    let docling_output_dir = workspace_root.join("docs").join("docling_output");
-    create_dir_all(&docling_output_dir)
-        .context("Failed to create docs/docling_output directory")?;
-    
-    // Crawl and download
-    let mut retry_count = 0;
-    let mut success = false;
-    let mut last_error = None;
-    
-    while retry_count < config_data.retry_count && !success {
-        // Clone the name since we'll use it in multiple places
-        let name_clone = name.to_string();
         
-        // Process with isolated scope to limit borrow duration
-        let process_result = {
-            // Get mutable reference to the entry within this scope
-            let entry = entries.entries.get_mut(&name_clone)
-                .ok_or_else(|| anyhow::anyhow!("Entry with name '{}' does not exist", name_clone))?;
-            
-            // Process entry without holding the borrow outside this scope
-            process_with_retry(entry, &config_data).await
-        };
+        // Create directories
+        let base_dir = config_data.outputs_path.join(name);
+        create_dir_all(&base_dir).io_path_context("Failed to create output directory", &base_dir)?;
+        
+        // Create error directory 
+        let error_dir = base_dir.join("ERRORS");
+        create_dir_all(&error_dir).io_path_context("Failed to create error directory", &error_dir)?;
         
-        match process_result {
-            Ok(result_file) => {
-                // Update entry in a separate scope
-                {
-                    let entry = entries.entries.get_mut(name)
-                        .ok_or_else(|| anyhow::anyhow!("Entry with name '{}' does not exist", name))?;
+        // Prepare the docs/docling_output directory
+        let docling_output_dir = self.docling_output_dir();
+        create_dir_all(&docling_output_dir)
+            .io_path_context("Failed to create docs/docling_output directory", &docling_output_dir)?;
+        
+        let result_dir = base_dir.join(&config_data.output_parts_markdown_results_suffix);
+        let report_path = RunReport::path_in(&result_dir, name);
+        let mut report = RunReport::new(&entry);
+        report.mode = mode;
+        
+        // Crawl and download
+        let mut retry_count = 0;
+        let mut last_error = None;
+        let max_attempts = if mode == RunMode::Crawl { config_data.retry_count } else { 1 };
+        
+        while retry_count < max_attempts {
+            report.start_attempt();
+            match process_with_retry(&mut entry, config_data, &self.secrets, mode, &progress, &mut report).await {
+                Ok(result_file) => {
+                    if mode == RunMode::Crawl {
+                        entry.last_download = Some(Utc::now());
+                        entry.status = CrawlStatus::Enabled;
+                        entry.version += 1;
+                        self.store.update_entry(name, |stored| stored.copy_run_state(&entry))?;
+                    }
+                    
+                    // Copy result to docs/docling_output directory
+                    let target_file = docling_output_dir.join(format!("{}.md", name));
+                    std::fs::copy(&result_file, &target_file)
+                        .io_path_context(format!(
+                            "Failed to copy result file from {} to {}",
+                            result_file.display(),
+                            target_file.display()
+                        ), &target_file)?;
                     
-                    entry.last_download = Some(Utc::now());
-                    entry.status = CrawlStatus::Enabled;
-                    entry.version += 1;
+                    info!("Successfully copied result to {}", target_file.display());
+                    report.output_files.push(target_file);
+
+                    // Results of languages written separately are copied alongside
+                    let language_files = report
+                        .processing
+                        .as_ref()
+                        .map(|processing| processing.language_files.clone())
+                        .unwrap_or_default();
+                    for (language, language_file) in &language_files {
+                        let target_file = docling_output_dir.join(format!("{}.{}.md", name, language));
+                        std::fs::copy(language_file, &target_file)
+                            .io_path_context(format!(
+                                "Failed to copy result file from {} to {}",
+                                language_file.display(),
+                                target_file.display()
+                            ), &target_file)?;
+                        report.output_files.push(target_file);
+                    }
+                    // Copies of languages without pages go, but not the results of entries named like them
+                    let entry_names: HashSet<String> = self.list_urls()?.into_iter().map(|entry| entry.name).collect();
+                    processor::remove_stale_language_files(&docling_output_dir, name, |language| {
+                        language_files.contains_key(language) || entry_names.contains(&format!("{}.{}", name, language))
+                    });
+                    
+                    report.finish();
+                    report.save(&report_path)?;
+                    info!("Saved run report to {}", report_path.display());
+                    
+                    return Ok(report);
                 }
-                
-                // Now save entries after the borrow is released
-                save_entries(&entries)?;
-                
-                // Copy result to docs/docling_output directory
-                let target_file = docling_output_dir.join(format!("{}.md", name));
-                std::fs::copy(&result_file, &target_file)
-                    .with_context(|| format!(
-                        "Failed to copy result file from {} to {}",
-                        result_file.display(),
-                        target_file.display()
-                    ))?;
-                
-                info!("Successfully copied result to {}", target_file.display());
-                
-                success = true;
-                return Ok(result_file);
-            }
-            Err(e) => {
-                // Log error
-                let error_message = format!("Error processing '{}': {}", name, e);
-                error!("{}", error_message);
-                
-                // Save error to file
-                let now = Utc::now();
-                let error_file = error_dir.join(format!("error_{}.txt", now.format("%Y%m%d_%H%M%S")));
-                std::fs::write(&error_file, error_message)
-                    .context("Failed to write error file")?;
-                
-                // Update entry in a separate scope
-                {
-                    let entry = entries.entries.get_mut(name)
-                        .ok_or_else(|| anyhow::anyhow!("Entry with name '{}' does not exist", name))?;
+                Err(e) => {
+                    // Log error
+                    let error_message = format!("Error processing '{}': {}", name, e);
+                    error!("{}", error_message);
                     
+                    // Save error to file
+                    let now = Utc::now();
+                    let error_file = error_dir.join(format!("error_{}.txt", now.format("%Y%m%d_%H%M%S")));
+                    std::fs::write(&error_file, error_message)
+                        .io_path_context("Failed to write error file", &error_file)?;
+                    
+                    // Keep the report of the failed attempt for auditing
+                    report.error = Some(e.to_string());
+                    report.finish();
+                    if let Err(save_error) = report.save(&report_path) {
+                        warn!("Failed to save run report: {}", save_error);
+                    }
+                    
+                    // Offline runs fail the same way every time
+                    if mode != RunMode::Crawl {
+                        return Err(e);
+                    }
                     entry.last_fail = Some(now);
+                    self.store.update_entry(name, |stored| stored.copy_run_state(&entry))?;
+                    
+                    // Wait before retrying, unless that was the last attempt
+                    if retry_count + 1 < max_attempts {
+                        let delay = if retry_count < config_data.retry_delay.len() as u32 {
+                            config_data.retry_delay[retry_count as usize]
+                        } else {
+                            60 // Default to 60 seconds if no specific delay is configured
+                        };
+                        
+                        progress.emit(ProgressEvent::RetryScheduled {
+                            attempt: retry_count + 1,
+                            delay_secs: delay,
+                            reason: e.to_string(),
+                        });
+                        tokio::time::sleep(tokio::time::Duration::from_secs(delay)).await;
+                    }
+                    
+                    retry_count += 1;
+                    last_error = Some(e);
                 }
-                
-                // Now save entries after the borrow is released
-                save_entries(&entries)?;
-                
-                // Get retry delay
-                let delay = if retry_count < config_data.retry_delay.len() as u32 {
-                    config_data.retry_delay[retry_count as usize]
-                } else {
-                    60 // Default to 60 seconds if no specific delay is configured
-                };
-                
-                // Wait before retrying
-                tokio::time::sleep(tokio::time::Duration::from_secs(delay)).await;
-                
-                retry_count += 1;
-                last_error = Some(e);
             }
         }
-    }
-    
-    // If we get here, all retries failed
-    {
-        // Get entry in a new scope to limit borrow duration
-        let entry = entries.entries.get_mut(name)
-            .ok_or_else(|| anyhow::anyhow!("Entry with name '{}' does not exist", name))?;
+        
+        // If we get here, all retries failed
         entry.status = CrawlStatus::Failed;
+        self.store.update_entry(name, |stored| stored.copy_run_state(&entry))?;
+        
+        // Surface the last error so callers can still match on its kind
+        Err(last_error.unwrap_or_else(|| {
+            DoclingError::config(format!("Failed to process entry after {} retries", retry_count))
+        }))
     }
-    
-    // Save entries after the borrow is released
-    save_entries(&entries)?;
-    
-    Err(anyhow::anyhow!("Failed to process entry after {} retries: {}", 
-                      retry_count, 
-                      last_error.unwrap_or_else(|| anyhow::anyhow!("Unknown error"))))
+}
+
+/// Configuration and credentials for the components working on an entry
+///
+/// The entry's own proxy, TLS settings, limits, languages, docs version and
+/// extraction settings are applied to the configuration, and its credentials
+/// in `secrets` are resolved.
+fn entry_settings(
+    entry: &UrlEntry,
+    config: &DoclingConfig,
+    secrets: &SecretStore,
+) -> Result<(DoclingConfig, Option<auth::RequestAuth>)> {
+    let auth = match secrets.get(&entry.name)? {
+        Some(credentials) => {
+            let root = Url::parse(&entry.url)
+                .map_err(|e| DoclingError::config_with(format!("Invalid URL format: {}", entry.url), e))?;
+            Some(auth::RequestAuth::resolve(&credentials, &root, &config.inputs_path)?)
+        }
+        None => None,
+    };
+
+    let mut config = config.clone();
+    config.network = config.network.for_entry(entry.network.as_ref());
+    config.budget = config.budget.for_entry(entry.budget.as_ref());
+    config.languages = config.languages.for_entry(entry.languages.as_ref());
+    config.versions = config.versions.for_entry(entry.versions.as_ref());
+    config.extraction = config.extraction.for_entry(entry.extraction.as_ref());
+    Ok((config, auth))
+}
+
+/// Crawl an entry and record the pages and crawl warnings in `report`
+///
+/// # Returns
+/// Whether the pages left out as duplicates, for their language or for their
+/// docs version changed
+async fn crawl_stage(crawler: &mut crawler::Crawler, entry: &mut UrlEntry, report: &mut RunReport) -> Result<bool> {
+    let stage_start = Instant::now();
+    let crawl = crawler.process_entry(entry).await?;
+    report.pages = crawl.pages;
+    report.revision = entry.revision.clone();
+    report.warc_file = crawl.warc_file;
+    report.docs_version = crawl.docs_version;
+    report.timings.crawl_ms = report::millis(stage_start.elapsed());
+    if let Some(limit) = crawl.truncated {
+        report.truncated = Some(limit);
+        report.warnings.push(format!("Crawl stopped early: {}", limit));
+    }
+    for page in report.pages.iter().filter(|p| p.outcome == report::PageOutcome::Failed) {
+        report.warnings.push(format!(
+            "Page {} failed: {}",
+            page.url,
+            page.error.as_deref().unwrap_or("unknown error")
+        ));
+    }
+    for page in report.pages.iter().filter(|p| p.encoding_source == Some(charset::EncodingSource::Detected)) {
+        report.warnings.push(format!(
+            "Encoding of page {} was not declared, detected {}",
+            page.url,
+            page.encoding.as_deref().unwrap_or("unknown")
+        ));
+    }
+    Ok(crawl.selection_changed)
 }
 
 /// Process a URL entry with retry logic
-async fn process_with_retry(entry: &mut UrlEntry, config: &DoclingConfig) -> Result<PathBuf> {
+///
+/// Fills `report` with the outcome of every stage as it goes, so a partial
+/// report is available even if a later stage fails. `mode` decides where the
+/// pages come from; offline modes convert every file again.
+async fn process_with_retry(
+    entry: &mut UrlEntry,
+    config: &DoclingConfig,
+    secrets: &SecretStore,
+    mode: RunMode,
+    progress: &ProgressReporter,
+    report: &mut RunReport,
+) -> Result<PathBuf> {
+    // Credentials are resolved on every attempt so rotated secrets are picked up
+    let (config, auth) = entry_settings(entry, config, secrets)?;
+
+    // Replays answer the crawler's requests from the entry's archives
+    let entry_dir = config.outputs_path.join(&entry.name);
+    let replay = match mode {
+        RunMode::Replay => Some(warc::WarcReplay::open(&entry_dir.join(&config.output_parts_warc_suffix))?),
+        RunMode::Crawl | RunMode::Rebuild => None,
+    };
+
     // Initialize components
-    let mut crawler = crawler::Crawler::new(config.clone())?;
-    let converter = converter::Converter::new(config.clone())?;
-    let processor = processor::Processor::new(config.clone());
+    let mut crawler = crawler::Crawler::new(config.clone())?
+        .with_progress(progress.clone())
+        .with_auth(auth.clone())
+        .with_replay(replay);
+    let converter = converter::Converter::new(config.clone())?
+        .with_progress(progress.clone())
+        .with_auth(auth)
+        .with_reconvert(mode != RunMode::Crawl);
+    let processor = processor::Processor::new(config.clone()).with_progress(progress.clone());
     
-    // Step 1: Crawl and download
-    crawler.process_entry(entry).await?;
+    // Step 1: Crawl and download, unless rebuilding from the saved pages
+    let mut selection_changed = false;
+    if mode == RunMode::Rebuild {
+        let html_dir = entry_dir.join(&config.output_parts_html_suffix);
+        if !html_dir.is_dir() {
+            return Err(DoclingError::config(format!(
+                "No saved pages for entry '{}', run it before rebuilding",
+                entry.name
+            )));
+        }
+    } else {
+        selection_changed = crawl_stage(&mut crawler, entry, report).await?;
+    }
     
     // Step 2: Convert HTML to Markdown
-    let md_files = converter.convert_directory(&entry.name).await?;
+    let stage_start = Instant::now();
+    report.conversions = converter.convert_directory(&entry.name).await?;
+    report.timings.convert_ms = report::millis(stage_start.elapsed());
+    for conversion in &report.conversions {
+        if let Some(error) = &conversion.error {
+            report.warnings.push(format!(
+                "Conversion of {} failed: {}",
+                conversion.source.display(),
+                error
+            ));
+        } else if let Some(reason) = &conversion.fallback_reason {
+            let method = conversion.method.map(|method| format!("{:?}", method)).unwrap_or_default();
+            report.warnings.push(format!(
+                "Conversion of {} fell back to {}: {}",
+                conversion.source.display(),
+                method,
+                reason
+            ));
+        }
+    }
+    report.output_files.extend(report.conversions.iter().filter_map(|c| c.output.clone()));
     
-    // Step 3: Process and combine Markdown files
-    let result_file = processor.process_entry(&entry.name, &entry.url)?;
+    // Step 3: Process and combine Markdown files, unless nothing changed since the last run
+    let result_file = processor.result_file(&entry.name);
+    let unchanged = mode == RunMode::Crawl
+        && report.pages_saved() == 0
+        && report.conversions.is_empty()
+        && !selection_changed;
+    if unchanged && result_file.exists() {
+        info!("No changes for entry '{}', keeping {}", entry.name, result_file.display());
+        report.result_file = Some(result_file.clone());
+        return Ok(result_file);
+    }
+
+    let stage_start = Instant::now();
+    let processing = processor.process_entry(&entry.name, &entry.url)?;
+    report.timings.process_ms = report::millis(stage_start.elapsed());
+    let result_file = processing.output_file.clone();
+    report.output_files.push(result_file.clone());
+    report.output_files.extend(processing.language_files.values().cloned());
+    report.result_file = Some(result_file.clone());
+    report.processing = Some(processing);
     
     Ok(result_file)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A workspace in a fresh temporary directory, next to a small local
+    /// site with a home page linking to a guide
+    ///
+    /// # Returns
+    /// The temporary directory and a handle for the `workspace` directory in it
+    pub(crate) fn test_workspace(name: &str) -> (PathBuf, Docling) {
+        let dir = std::env::temp_dir().join(format!("docling-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        create_dir_all(dir.join("site")).unwrap();
+        std::fs::write(
+            dir.join("site/index.html"),
+            "<html><head><title>Home</title></head><body><h1>Home</h1>\
+             <p>Welcome to the manual of the tool.</p><a href=\"guide.html\">Guide</a></body></html>",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("site/guide.html"),
+            "<html><head><title>Guide</title></head><body><h1>Guide</h1>\
+             <p>Install the tool with cargo and run it from the workspace.</p></body></html>",
+        )
+        .unwrap();
+
+        let config = DoclingConfig {
+            delay_between_request_in_ms: 0,
+            retry_delay: vec![0; DEFAULT_RETRY_COUNT as usize],
+            ..DoclingConfig::default()
+        };
+        let docling = Docling::new(dir.join("workspace"), config);
+        (dir, docling)
+    }
+
+    #[tokio::test]
+    async fn runs_an_entry_in_a_temporary_workspace() {
+        let (dir, docling) = test_workspace("workspace");
+        docling
+            .add_url(dir.join("site").to_str().unwrap(), Some("site"), Some(1))
+            .unwrap();
+        assert!(docling.store().path().starts_with(dir.join("workspace")));
+        assert!(docling.config().outputs_path.starts_with(dir.join("workspace")));
+
+        let report = docling.run_entry("site").await.unwrap();
+        assert_eq!(report.pages_saved(), 2);
+        let result = std::fs::read_to_string(dir.join("docs/docling_output/site.md")).unwrap();
+        assert!(result.contains("Install the tool with cargo"));
+        assert!(docling.entry("site").unwrap().last_download.is_some());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn retries_are_only_announced_when_another_attempt_follows() {
+        let (dir, docling) = test_workspace("retries");
+        docling
+            .add_url(dir.join("site").to_str().unwrap(), Some("site"), Some(1))
+            .unwrap();
+        // Every attempt fails to convert because a file is where the Markdown goes
+        let entry_dir = docling.config().outputs_path.join("site");
+        create_dir_all(&entry_dir).unwrap();
+        std::fs::write(entry_dir.join(&docling.config().output_parts_markdown_suffix), "").unwrap();
+
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        assert!(docling.run_entry_in_mode("site", RunMode::Crawl, Arc::new(sender)).await.is_err());
+        let mut retries = Vec::new();
+        while let Ok(update) = receiver.try_recv() {
+            if let ProgressEvent::RetryScheduled { attempt, .. } = update.event {
+                retries.push(attempt);
+            }
+        }
+        assert_eq!(retries, [1, 2]);
+        assert_eq!(docling.entry("site").unwrap().status, CrawlStatus::Failed);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
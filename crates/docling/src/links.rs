@@ -0,0 +1,431 @@
+//! Link graph of a crawl
+//!
+//! While crawling, every fetched page is recorded with its status, the
+//! redirects it went through and its outgoing links (anchor text, whether the
+//! target is inside the entry's scope). The graph is saved next to the
+//! manifest after each crawl and can be exported as JSON, CSV or Graphviz
+//! DOT, or summarised as a report of broken links and redirect chains.
+//!
+//! External links are recorded but never fetched, so only the targets the
+//! crawler requested have a status.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::fetch::{FetchResponse, Redirect};
+use crate::html::Link;
+use crate::{DoclingError, IoContext, Result};
+
+/// Name of the link graph file in an entry's output directory
+pub const LINK_GRAPH_FILE_NAME: &str = "link_graph.json";
+
+/// Whether a link stays within the entry
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkScope {
+    /// The target is in the entry's scope
+    Internal,
+    /// The target is outside the entry's scope
+    External,
+}
+
+/// A link from a page
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PageLink {
+    /// URL the link points to, without fragment
+    pub target: String,
+    /// Anchor text of the link (the first non-empty one if the page links
+    /// to the target several times)
+    pub text: String,
+    /// Whether the target is in the entry's scope
+    pub scope: LinkScope,
+    /// Status of the final response for the target, if it was fetched
+    pub status: Option<u16>,
+}
+
+/// A page the crawler requested
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkedPage {
+    /// URL that was requested
+    pub url: String,
+    /// Number of links between the entry URL and the page
+    pub depth: u32,
+    /// Status of the final response, `None` if the fetch failed
+    pub status: Option<u16>,
+    /// Redirects followed to reach the final response
+    pub redirects: Vec<Redirect>,
+    /// Why the fetch failed, if it did
+    pub error: Option<String>,
+    /// Outgoing links, in the order they appear in the page
+    pub links: Vec<PageLink>,
+}
+
+impl LinkedPage {
+    /// Whether the page could not be fetched or answered with an error status
+    pub fn is_broken(&self) -> bool {
+        self.error.is_some() || self.status.is_some_and(|status| status >= 400)
+    }
+}
+
+/// A link whose target is missing or failed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrokenLink {
+    /// Page containing the link
+    pub source: String,
+    /// URL the link points to
+    pub target: String,
+    /// Anchor text of the link
+    pub text: String,
+    /// Status the target was answered with, if any
+    pub status: Option<u16>,
+    /// Why fetching the target failed, if it did
+    pub error: Option<String>,
+}
+
+/// A URL that is only reached through redirects
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedirectChain {
+    /// URL that was requested
+    pub url: String,
+    /// Redirects followed, in order
+    pub hops: Vec<Redirect>,
+    /// Status of the final response
+    pub status: Option<u16>,
+    /// Pages linking to the requested URL
+    pub linked_from: Vec<String>,
+}
+
+/// Broken links and redirect chains found in a link graph
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LinkReport {
+    /// Links to pages that failed or answered with an error status
+    pub broken: Vec<BrokenLink>,
+    /// Requested URLs that redirected elsewhere
+    pub redirects: Vec<RedirectChain>,
+}
+
+impl LinkReport {
+    /// Whether nothing needs attention
+    pub fn is_empty(&self) -> bool {
+        self.broken.is_empty() && self.redirects.is_empty()
+    }
+}
+
+/// Directed graph of the pages of a crawl and the links between them
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LinkGraph {
+    /// URL the crawl started from
+    pub root: String,
+    /// Pages in navigation order: breadth first from the root, following
+    /// links in page order; pages not reached through links come last
+    pub pages: Vec<LinkedPage>,
+}
+
+impl LinkGraph {
+    /// Start the graph of a crawl from `root`
+    pub fn new(root: &str) -> Self {
+        Self {
+            root: root.to_string(),
+            pages: Vec::new(),
+        }
+    }
+
+    /// Location of the link graph inside an entry's output directory
+    pub fn path_in(entry_dir: &Path) -> PathBuf {
+        entry_dir.join(LINK_GRAPH_FILE_NAME)
+    }
+
+    /// Record a fetched page, before its links are known
+    pub fn add_page(&mut self, url: &str, depth: u32, response: std::result::Result<&FetchResponse, String>) {
+        let (status, redirects, error) = match response {
+            Ok(response) => (Some(response.status), response.redirects.clone(), None),
+            Err(error) => (None, Vec::new(), Some(error)),
+        };
+        self.pages.push(LinkedPage {
+            url: url.to_string(),
+            depth,
+            status,
+            redirects,
+            error,
+            links: Vec::new(),
+        });
+    }
+
+    /// Record the outgoing links of a page added with [`LinkGraph::add_page`]
+    ///
+    /// `is_internal` tells whether a target is in the entry's scope.
+    pub fn add_links(&mut self, url: &str, links: &[Link], is_internal: impl Fn(&Link) -> bool) {
+        let Some(page) = self.pages.iter_mut().rev().find(|page| page.url == url) else {
+            return;
+        };
+        for link in links {
+            let target = link.url.to_string();
+            if let Some(existing) = page.links.iter_mut().find(|existing| existing.target == target) {
+                if existing.text.is_empty() {
+                    existing.text = link.text.clone();
+                }
+                continue;
+            }
+            page.links.push(PageLink {
+                target,
+                text: link.text.clone(),
+                scope: if is_internal(link) { LinkScope::Internal } else { LinkScope::External },
+                status: None,
+            });
+        }
+    }
+
+    /// Fill in the status of the link targets and put the pages in navigation order
+    pub fn finish(&mut self) {
+        let statuses: HashMap<String, Option<u16>> = self
+            .pages
+            .iter()
+            .map(|page| (page.url.clone(), page.status))
+            .collect();
+        for link in self.pages.iter_mut().flat_map(|page| page.links.iter_mut()) {
+            link.status = statuses.get(&link.target).copied().flatten();
+        }
+
+        // Breadth first from the root over internal links
+        let positions: HashMap<&str, usize> = self
+            .pages
+            .iter()
+            .enumerate()
+            .map(|(position, page)| (page.url.as_str(), position))
+            .collect();
+        let mut order = Vec::with_capacity(self.pages.len());
+        let mut visited = HashSet::new();
+        let mut queue: VecDeque<usize> = positions.get(self.root.as_str()).copied().into_iter().collect();
+        while let Some(position) = queue.pop_front() {
+            if !visited.insert(position) {
+                continue;
+            }
+            order.push(position);
+            for link in self.pages[position].links.iter().filter(|link| link.scope == LinkScope::Internal) {
+                if let Some(&next) = positions.get(link.target.as_str()) {
+                    queue.push_back(next);
+                }
+            }
+        }
+        let mut unreached: Vec<usize> = (0..self.pages.len()).filter(|position| !visited.contains(position)).collect();
+        unreached.sort_by(|a, b| self.pages[*a].url.cmp(&self.pages[*b].url));
+        order.extend(unreached);
+
+        let mut pages: Vec<Option<LinkedPage>> = std::mem::take(&mut self.pages).into_iter().map(Some).collect();
+        self.pages = order.into_iter().filter_map(|position| pages[position].take()).collect();
+    }
+
+    /// Broken links and redirect chains of the crawl
+    pub fn report(&self) -> LinkReport {
+        let pages: HashMap<&str, &LinkedPage> = self.pages.iter().map(|page| (page.url.as_str(), page)).collect();
+        let mut linked_from: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        let mut report = LinkReport::default();
+
+        for page in &self.pages {
+            for link in &page.links {
+                linked_from.entry(link.target.as_str()).or_default().push(page.url.clone());
+                let Some(target) = pages.get(link.target.as_str()).filter(|target| target.is_broken()) else {
+                    continue;
+                };
+                report.broken.push(BrokenLink {
+                    source: page.url.clone(),
+                    target: link.target.clone(),
+                    text: link.text.clone(),
+                    status: target.status,
+                    error: target.error.clone(),
+                });
+            }
+        }
+
+        for page in self.pages.iter().filter(|page| !page.redirects.is_empty()) {
+            report.redirects.push(RedirectChain {
+                url: page.url.clone(),
+                hops: page.redirects.clone(),
+                status: page.status,
+                linked_from: linked_from.remove(page.url.as_str()).unwrap_or_default(),
+            });
+        }
+        report
+    }
+
+    /// The links as CSV: one row per link with its source, target, anchor
+    /// text, scope and target status
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("source,target,text,scope,status\n");
+        for page in &self.pages {
+            for link in &page.links {
+                let scope = match link.scope {
+                    LinkScope::Internal => "internal",
+                    LinkScope::External => "external",
+                };
+                let status = link.status.map(|status| status.to_string()).unwrap_or_default();
+                let _ = writeln!(
+                    csv,
+                    "{},{},{},{},{}",
+                    csv_field(&page.url),
+                    csv_field(&link.target),
+                    csv_field(&link.text),
+                    scope,
+                    status
+                );
+            }
+        }
+        csv
+    }
+
+    /// The graph in Graphviz DOT format
+    ///
+    /// Broken pages are drawn in red, external targets dashed, and edges
+    /// are labelled with their anchor text.
+    pub fn to_dot(&self, name: &str) -> String {
+        let mut dot = format!("digraph {} {{\n  rankdir=LR;\n  node [shape=box];\n", dot_id(name));
+        let mut external = BTreeSet::new();
+        for page in &self.pages {
+            let style = if page.is_broken() { " color=red" } else { "" };
+            let _ = writeln!(dot, "  {} [label={}{}];", dot_id(&page.url), dot_id(&page.url), style);
+            for link in page.links.iter().filter(|link| link.scope == LinkScope::External) {
+                external.insert(link.target.as_str());
+            }
+        }
+        for target in external {
+            let _ = writeln!(dot, "  {} [label={} style=dashed];", dot_id(target), dot_id(target));
+        }
+        for page in &self.pages {
+            for link in &page.links {
+                let _ = writeln!(
+                    dot,
+                    "  {} -> {} [label={}];",
+                    dot_id(&page.url),
+                    dot_id(&link.target),
+                    dot_id(&link.text)
+                );
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Load the link graph of an entry, returning an empty one if none was saved yet
+    pub fn load(entry_dir: &Path) -> Result<Self> {
+        let path = Self::path_in(entry_dir);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let json = std::fs::read_to_string(&path).io_path_context("Failed to read link graph", &path)?;
+        serde_json::from_str(&json)
+            .map_err(|e| DoclingError::config_with(format!("Failed to parse link graph {}", path.display()), e))
+    }
+
+    /// Save the link graph into an entry's output directory
+    pub fn save(&self, entry_dir: &Path) -> Result<()> {
+        let path = Self::path_in(entry_dir);
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| DoclingError::config_with("Failed to serialize link graph", e))?;
+        std::fs::write(&path, json).io_path_context("Failed to write link graph", &path)
+    }
+}
+
+/// A CSV field, quoted if it contains separators, quotes or line breaks
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// A quoted DOT identifier
+fn dot_id(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderMap;
+    use url::Url;
+
+    use super::*;
+
+    fn response(url: &str, status: u16, redirects: Vec<Redirect>) -> FetchResponse {
+        FetchResponse {
+            url: Url::parse(url).unwrap(),
+            final_url: Url::parse(url).unwrap(),
+            status,
+            headers: HeaderMap::new(),
+            body: Vec::new(),
+            redirects,
+        }
+    }
+
+    fn link(url: &str, text: &str) -> Link {
+        Link {
+            url: Url::parse(url).unwrap(),
+            text: text.to_string(),
+        }
+    }
+
+    fn graph() -> LinkGraph {
+        let mut graph = LinkGraph::new("https://example.com/docs/");
+        let internal = |link: &Link| link.url.as_str().starts_with("https://example.com/docs/");
+        // Pages complete out of order when fetched concurrently
+        graph.add_page("https://example.com/docs/b", 1, Ok(&response("https://example.com/docs/b", 404, Vec::new())));
+        let moved = vec![Redirect {
+            from: "https://example.com/docs/a".to_string(),
+            to: "https://example.com/docs/a/".to_string(),
+            status: 301,
+        }];
+        graph.add_page("https://example.com/docs/a", 1, Ok(&response("https://example.com/docs/a", 200, moved)));
+        graph.add_links("https://example.com/docs/a", &[link("https://example.com/docs/", "Home")], internal);
+        graph.add_page("https://example.com/docs/", 0, Ok(&response("https://example.com/docs/", 200, Vec::new())));
+        graph.add_links(
+            "https://example.com/docs/",
+            &[
+                link("https://example.com/docs/a", ""),
+                link("https://example.com/docs/a", "Guide, part \"A\""),
+                link("https://example.com/docs/b", "Reference"),
+                link("https://rust-lang.org/", "Rust"),
+            ],
+            internal,
+        );
+        graph.finish();
+        graph
+    }
+
+    #[test]
+    fn pages_are_ordered_and_targets_resolved() {
+        let graph = graph();
+        let order: Vec<_> = graph.pages.iter().map(|page| page.url.as_str()).collect();
+        assert_eq!(order, ["https://example.com/docs/", "https://example.com/docs/a", "https://example.com/docs/b"]);
+        let links = &graph.pages[0].links;
+        assert_eq!(links.len(), 3);
+        assert_eq!(links[0].text, "Guide, part \"A\"");
+        assert_eq!(links[1].status, Some(404));
+        assert_eq!(links[2].scope, LinkScope::External);
+        assert_eq!(links[2].status, None);
+
+        let report = graph.report();
+        assert_eq!(report.broken.len(), 1);
+        assert_eq!(report.broken[0].target, "https://example.com/docs/b");
+        assert_eq!(report.redirects.len(), 1);
+        assert_eq!(report.redirects[0].linked_from, ["https://example.com/docs/"]);
+    }
+
+    #[test]
+    fn exports_escape_values() {
+        let graph = graph();
+        let csv = graph.to_csv();
+        assert!(csv.starts_with("source,target,text,scope,status\n"));
+        assert!(csv.contains("https://example.com/docs/,https://example.com/docs/a,\"Guide, part \"\"A\"\"\",internal,200\n"));
+        assert!(csv.contains(",Rust,external,\n"));
+
+        let dot = graph.to_dot("docs");
+        assert!(dot.starts_with("digraph \"docs\" {"));
+        assert!(dot.contains("\"https://example.com/docs/b\" [label=\"https://example.com/docs/b\" color=red];"));
+        assert!(dot.contains("[label=\"Guide, part \\\"A\\\"\"];"));
+        assert!(dot.contains("\"https://rust-lang.org/\" [label=\"https://rust-lang.org/\" style=dashed];"));
+    }
+}
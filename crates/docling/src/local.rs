@@ -0,0 +1,325 @@
+//! Local sources: `file://` URLs and directories
+//!
+//! Entries can point at a directory or file on disk, such as a built static
+//! documentation site or an unpacked vendor archive. The [`crate::fetch::Fetcher`]
+//! answers `file://` requests from here like a simple web server would: files
+//! are served with a content type derived from their extension and validators
+//! derived from their size and modification time, a directory is served by
+//! its `index.html`, and a directory without one gets a generated listing
+//! that links to its contents so the crawler can walk the whole tree.
+
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use chrono::{DateTime, Utc};
+use reqwest::header::{self, HeaderMap, HeaderValue};
+use url::Url;
+
+use crate::fetch::{Redirect, Validators};
+use crate::{DoclingError, IoContext, Result};
+
+/// Header marking a generated directory listing; its links are followed but
+/// the listing itself is not saved
+pub const LISTING_HEADER: &str = "x-docling-directory-listing";
+
+/// Files served for a directory, in order of preference
+const INDEX_FILES: [&str; 2] = ["index.html", "index.htm"];
+
+/// Parse the source of an entry: a URL, or a path to a local directory or file
+///
+/// Paths are made absolute and turned into `file://` URLs; directories get a
+/// trailing slash so relative links resolve inside them.
+///
+/// # Errors
+/// Returns an error if the source is neither a URL nor an existing path
+pub fn source_url(source: &str) -> Result<Url> {
+    // A single letter scheme is a Windows drive, not a URL
+    if let Ok(url) = Url::parse(source)
+        && url.scheme().len() > 1
+    {
+        if url.scheme() == "file"
+            && let Ok(path) = url.to_file_path()
+            && path.is_dir()
+        {
+            return Ok(directory_url(url));
+        }
+        return Ok(url);
+    }
+
+    let path = Path::new(source);
+    let path = path
+        .canonicalize()
+        .map_err(|e| DoclingError::config_with(format!("Invalid URL or path: {}", source), e))?;
+    let url = if path.is_dir() {
+        Url::from_directory_path(&path)
+    } else {
+        Url::from_file_path(&path)
+    };
+    url.map_err(|()| DoclingError::config(format!("Cannot use path as a source: {}", path.display())))
+}
+
+/// Whether the URL points at the local file system
+pub fn is_local(url: &Url) -> bool {
+    url.scheme() == "file"
+}
+
+/// The URL with a trailing slash on its path
+fn directory_url(mut url: Url) -> Url {
+    if !url.path().ends_with('/') {
+        let path = format!("{}/", url.path());
+        url.set_path(&path);
+    }
+    url
+}
+
+/// Content of a local response
+#[derive(Debug)]
+pub(crate) enum LocalContent {
+    /// A file read from disk as the body is consumed
+    File {
+        /// The open file
+        file: tokio::fs::File,
+        /// Size of the file in bytes
+        length: u64,
+    },
+    /// A body generated in memory (listings and empty bodies)
+    Generated(Vec<u8>),
+}
+
+/// Answer to a request for a `file://` URL
+pub(crate) struct LocalResponse {
+    /// URL of the final response after redirects
+    pub final_url: Url,
+    /// HTTP status equivalent of the outcome
+    pub status: u16,
+    /// Headers describing the content
+    pub headers: HeaderMap,
+    /// Redirects followed, in order
+    pub redirects: Vec<Redirect>,
+    /// Body of the response
+    pub content: LocalContent,
+}
+
+/// Open a `file://` URL like a web server would
+///
+/// Missing files are answered with status 404. A directory URL without a
+/// trailing slash is redirected to one with it.
+///
+/// # Errors
+/// Returns an error if the URL is not a local path or the file exists but
+/// cannot be read
+pub(crate) async fn open(url: &Url, validators: Option<&Validators>) -> Result<LocalResponse> {
+    let mut path = url
+        .to_file_path()
+        .map_err(|()| DoclingError::config(format!("Not a local path: {}", url)))?;
+    let mut final_url = url.clone();
+    final_url.set_query(None);
+    final_url.set_fragment(None);
+    let mut redirects = Vec::new();
+
+    let Ok(mut metadata) = tokio::fs::metadata(&path).await else {
+        return Ok(LocalResponse {
+            final_url,
+            status: 404,
+            headers: HeaderMap::new(),
+            redirects,
+            content: LocalContent::Generated(Vec::new()),
+        });
+    };
+
+    if metadata.is_dir() {
+        if !final_url.path().ends_with('/') {
+            let next = directory_url(final_url.clone());
+            redirects.push(Redirect {
+                from: final_url.to_string(),
+                to: next.to_string(),
+                status: 301,
+            });
+            final_url = next;
+        }
+        let index = INDEX_FILES.iter().map(|name| path.join(name)).find(|index| index.is_file());
+        match index {
+            Some(index) => {
+                metadata = tokio::fs::metadata(&index)
+                    .await
+                    .io_path_context("Failed to read file metadata", &index)?;
+                path = index;
+            }
+            None => {
+                let listing = directory_listing(&path, &final_url)?;
+                let mut headers = HeaderMap::new();
+                headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("text/html; charset=utf-8"));
+                headers.insert(LISTING_HEADER, HeaderValue::from_static("1"));
+                return Ok(LocalResponse {
+                    final_url,
+                    status: 200,
+                    headers,
+                    redirects,
+                    content: LocalContent::Generated(listing.into_bytes()),
+                });
+            }
+        }
+    }
+
+    let mut headers = HeaderMap::new();
+    if let Some(content_type) = content_type(&path) {
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+    }
+    let length = metadata.len();
+    if let Ok(modified) = metadata.modified() {
+        let nanos = modified.duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or_default();
+        let etag = format!("\"{:x}-{:x}\"", length, nanos);
+        let last_modified = DateTime::<Utc>::from(modified)
+            .format("%a, %d %b %Y %H:%M:%S GMT")
+            .to_string();
+        let not_modified = validators.is_some_and(|validators| validators.etag.as_deref() == Some(&etag));
+        if let Ok(value) = HeaderValue::from_str(&etag) {
+            headers.insert(header::ETAG, value);
+        }
+        if let Ok(value) = HeaderValue::from_str(&last_modified) {
+            headers.insert(header::LAST_MODIFIED, value);
+        }
+        if not_modified {
+            return Ok(LocalResponse {
+                final_url,
+                status: 304,
+                headers,
+                redirects,
+                content: LocalContent::Generated(Vec::new()),
+            });
+        }
+    }
+
+    let file = tokio::fs::File::open(&path)
+        .await
+        .io_path_context("Failed to open file", &path)?;
+    Ok(LocalResponse {
+        final_url,
+        status: 200,
+        headers,
+        redirects,
+        content: LocalContent::File { file, length },
+    })
+}
+
+/// Content type of a local file, from its extension
+fn content_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    let content_type = match extension.as_str() {
+        "html" | "htm" => "text/html",
+        "xhtml" => "application/xhtml+xml",
+        "pdf" => "application/pdf",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "md" | "markdown" => "text/markdown",
+        "rst" => "text/x-rst",
+        "adoc" | "asciidoc" => "text/asciidoc",
+        "txt" => "text/plain",
+        "xml" => "application/xml",
+        "css" => "text/css",
+        "js" => "text/javascript",
+        "json" => "application/json",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "svg" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        "bmp" => "image/bmp",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "ogv" => "video/ogg",
+        _ => "application/octet-stream",
+    };
+    Some(content_type)
+}
+
+/// HTML page linking to the entries of a directory, hidden entries excluded
+fn directory_listing(dir: &Path, dir_url: &Url) -> Result<String> {
+    let mut entries = Vec::new();
+    for entry in std::fs::read_dir(dir).io_path_context("Failed to read directory", dir)? {
+        let entry = entry.io_path_context("Failed to read directory", dir)?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') {
+            continue;
+        }
+        let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+        entries.push((name, is_dir));
+    }
+    entries.sort();
+
+    let title = html_escape::encode_text(dir_url.path()).into_owned();
+    let mut html = format!("<!DOCTYPE html>\n<html><head><title>Index of {0}</title></head><body>\n<h1>Index of {0}</h1>\n<ul>\n", title);
+    for (name, is_dir) in entries {
+        let mut url = dir_url.clone();
+        if let Ok(mut segments) = url.path_segments_mut() {
+            segments.pop_if_empty().push(&name);
+            if is_dir {
+                segments.push("");
+            }
+        }
+        html.push_str(&format!(
+            "<li><a href=\"{}\">{}{}</a></li>\n",
+            html_escape::encode_double_quoted_attribute(url.as_str()),
+            html_escape::encode_text(&name),
+            if is_dir { "/" } else { "" }
+        ));
+    }
+    html.push_str("</ul>\n</body></html>\n");
+    Ok(html)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_become_file_urls() {
+        let dir = std::env::temp_dir().join(format!("docling-local-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("guide")).unwrap();
+        std::fs::write(dir.join("guide/intro.html"), "<p>Intro</p>").unwrap();
+
+        let url = source_url(dir.to_str().unwrap()).unwrap();
+        assert!(is_local(&url));
+        assert!(url.path().ends_with('/'));
+        let file = source_url(dir.join("guide/intro.html").to_str().unwrap()).unwrap();
+        assert!(file.path().ends_with("/guide/intro.html"));
+        assert_eq!(
+            source_url("https://example.com/docs").unwrap().as_str(),
+            "https://example.com/docs"
+        );
+        assert!(source_url("no/such/directory").is_err());
+
+        let listing = directory_listing(&dir, &url).unwrap();
+        assert!(listing.contains(&format!("href=\"{}guide/\"", url)));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn directories_are_served_like_a_web_server() {
+        let dir = std::env::temp_dir().join(format!("docling-serve-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("site")).unwrap();
+        std::fs::write(dir.join("site/index.html"), "<h1>Home</h1>").unwrap();
+        let root = Url::from_directory_path(&dir).unwrap();
+
+        let listing = open(&root, None).await.unwrap();
+        assert_eq!(listing.status, 200);
+        assert!(listing.headers.contains_key(LISTING_HEADER));
+
+        let site = open(&root.join("site").unwrap(), None).await.unwrap();
+        assert_eq!(site.status, 200);
+        assert_eq!(site.final_url, root.join("site/").unwrap());
+        assert_eq!(site.redirects.len(), 1);
+        assert_eq!(site.headers[header::CONTENT_TYPE], "text/html");
+        let validators = Validators {
+            etag: site.headers[header::ETAG].to_str().ok().map(str::to_string),
+            last_modified: None,
+        };
+        let cached = open(&root.join("site/").unwrap(), Some(&validators)).await.unwrap();
+        assert_eq!(cached.status, 304);
+
+        let missing = open(&root.join("missing.html").unwrap(), None).await.unwrap();
+        assert_eq!(missing.status, 404);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
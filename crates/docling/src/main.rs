@@ -2,10 +2,19 @@
 //! 
 //! Provides commands for managing web crawling tasks:
 //! - add [url] - Add a new URL to crawl
+//! - add-git [repository] - Add documentation kept in a local git repository
 //! - stop [name] - Disable a URL entry
 //! - list - List all URL entries
 //! - remove [name] - Remove a URL entry
-//! - start [name] - Enable and process a URL entry
+//! - start [name] - Enable and process a URL entry, or only plan its crawl with --dry-run
+//! - auth [name] - Set the credentials used to crawl an entry
+//! - network [name] - Set the proxy and TLS settings of an entry
+//! - budget [name] - Set the crawl limits of an entry
+//! - rebuild [name] - Convert an entry again from its saved pages or archives
+//! - links [name] - Export the link graph of an entry or report its broken links
+//! - languages [name] - Set the languages kept from an entry
+//! - docs-version [name] [version] - Confine an entry to one version of its documentation
+//! - extraction [name] - Set how the main content of an entry's pages is extracted
 
 use std::io::Write;
 use std::path::PathBuf;
@@ -13,17 +22,21 @@ use std::process;
 use std::fs::create_dir_all;
 
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use mi4ulings_config::Config;
-use tokio::runtime::Runtime;
-use tracing::{debug, error, info, warn, Level};
+use tracing::{debug, error, info};
 use tracing_subscriber::{
     fmt::{format::FmtSpan, time::UtcTime}, 
     EnvFilter, 
     prelude::*
 };
 
-use mi4ulings_docling::{self, DoclingConfig};
+use mi4ulings_docling::git::GitSource;
+use mi4ulings_docling::plan::CrawlPlan;
+use mi4ulings_docling::version::VersionSettings;
+use mi4ulings_docling::{
+    self, DiscoveryMode, Docling, DocumentType, DoclingConfig, ProgressEvent, ProgressUpdate, RunMode,
+};
 
 /// Docling - Web crawler and document processor
 #[derive(Parser)]
@@ -39,17 +52,55 @@ struct Cli {
 enum Commands {
     /// Add a new URL to crawl
     Add {
-        /// URL to crawl
+        /// URL to crawl, or a local directory or file (also as file:// URL)
         #[clap(required = true)]
         url: String,
         
-        /// Name for this entry (defaults to domain name)
+        /// Name for this entry (defaults to domain name, or directory name for local sources)
         #[clap(short, long)]
         name: Option<String>,
         
         /// Crawl depth (how many levels of links to follow)
         #[clap(short, long)]
         depth: Option<u32>,
+
+        /// Only crawl the entry URL and the pages listed in its sitemaps
+        #[clap(long, conflicts_with = "no_sitemaps")]
+        sitemap_only: bool,
+
+        /// Do not use sitemaps, only follow links
+        #[clap(long)]
+        no_sitemaps: bool,
+
+        /// Linked documents to download and convert (pdf, docx, md, txt, rst, adoc)
+        #[clap(long, value_delimiter = ',')]
+        documents: Vec<DocumentType>,
+
+        /// Also archive every crawl of this entry as a WARC file
+        #[clap(long)]
+        warc: bool,
+    },
+
+    /// Add documentation kept in a local git repository
+    ///
+    /// Markdown, reStructuredText, AsciiDoc and HTML files are read at the
+    /// given reference, so uncommitted changes are ignored.
+    AddGit {
+        /// Path of the repository
+        #[clap(required = true)]
+        repository: PathBuf,
+
+        /// Name for this entry (defaults to the repository directory name)
+        #[clap(short, long)]
+        name: Option<String>,
+
+        /// Branch, tag or commit to read
+        #[clap(short, long, default_value = "HEAD")]
+        reference: String,
+
+        /// Paths to read, as globs relative to the repository root (e.g. docs or docs/**/*.md)
+        #[clap(short, long)]
+        paths: Vec<String>,
     },
     
     /// Stop (disable) a URL entry
@@ -74,6 +125,206 @@ enum Commands {
         /// Name of the entry to start
         #[clap(required = true)]
         name: String,
+
+        /// Only discover the pages and print the URLs a crawl would fetch and leave out
+        #[clap(long)]
+        dry_run: bool,
+
+        /// Print the plan of a dry run as JSON
+        #[clap(long, requires = "dry_run")]
+        json: bool,
+    },
+
+    /// Convert and process an entry again without the network
+    ///
+    /// Every page saved by earlier runs is converted again, so converter and
+    /// processor settings can be tried out without crawling the site.
+    Rebuild {
+        /// Name of the entry to rebuild
+        #[clap(required = true)]
+        name: String,
+
+        /// Crawl the entry again from its WARC archives instead of using the saved pages
+        #[clap(long)]
+        replay: bool,
+    },
+
+    /// Export the link graph of the last crawl of an entry
+    Links {
+        /// Name of the entry
+        #[clap(required = true)]
+        name: String,
+
+        /// Output format; report lists broken links and redirect chains
+        #[clap(short, long, value_enum, default_value = "report")]
+        format: LinkFormat,
+
+        /// File to write to instead of standard output
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Set the credentials used to crawl an entry
+    ///
+    /// Credentials are kept in secrets.toml next to entries.toml, readable
+    /// only by the owner. Any value may be given as env:NAME to read it from
+    /// an environment variable when the entry runs.
+    Auth {
+        /// Name of the entry
+        #[clap(required = true)]
+        name: String,
+
+        /// Header sent with every request to the entry's origin, as "Name: value"
+        #[clap(long = "header", value_name = "NAME: VALUE")]
+        headers: Vec<String>,
+
+        /// HTTP basic auth as "user:password"
+        #[clap(long, value_name = "USER:PASSWORD")]
+        basic: Option<String>,
+
+        /// Bearer token for the Authorization header
+        #[clap(long, value_name = "TOKEN", conflicts_with = "basic")]
+        bearer: Option<String>,
+
+        /// Netscape cookies.txt file exported from a browser or curl
+        #[clap(long, value_name = "FILE")]
+        cookies: Option<PathBuf>,
+
+        /// Remove all credentials of the entry
+        #[clap(long, conflicts_with_all = ["headers", "basic", "bearer", "cookies"])]
+        clear: bool,
+    },
+
+    /// Set the proxy and TLS settings of an entry
+    ///
+    /// They are added to the [network] settings of the configuration: the
+    /// entry's proxy replaces the global one, no-proxy hosts and certificates
+    /// are added to the global lists.
+    Network {
+        /// Name of the entry
+        #[clap(required = true)]
+        name: String,
+
+        /// Proxy URL (http://, https://, socks5:// or socks5h://), or env:NAME
+        #[clap(long, value_name = "URL")]
+        proxy: Option<String>,
+
+        /// Hosts reached without the proxy (comma-separated)
+        #[clap(long, value_delimiter = ',', value_name = "HOSTS")]
+        no_proxy: Vec<String>,
+
+        /// PEM file with additional trusted root certificates
+        #[clap(long = "ca-cert", value_name = "FILE")]
+        ca_certificates: Vec<PathBuf>,
+
+        /// Accept invalid TLS certificates for this entry
+        #[clap(long)]
+        accept_invalid_certs: bool,
+
+        /// Remove the entry's own settings
+        #[clap(long, conflicts_with_all = ["proxy", "no_proxy", "ca_certificates", "accept_invalid_certs"])]
+        clear: bool,
+    },
+
+    /// Set the crawl limits of an entry
+    ///
+    /// Limits set here replace the ones of the [budget] settings of the
+    /// configuration. A crawl that reaches a limit stops early and still
+    /// converts what it collected.
+    Budget {
+        /// Name of the entry
+        #[clap(required = true)]
+        name: String,
+
+        /// Maximum number of pages and documents requested
+        #[clap(long, value_name = "COUNT")]
+        max_pages: Option<u64>,
+
+        /// Maximum bytes of pages and documents downloaded
+        #[clap(long, value_name = "BYTES")]
+        max_bytes: Option<u64>,
+
+        /// Maximum bytes of media kept
+        #[clap(long, value_name = "BYTES")]
+        max_media_bytes: Option<u64>,
+
+        /// Maximum crawl time in seconds
+        #[clap(long, value_name = "SECONDS")]
+        max_duration: Option<u64>,
+
+        /// Remove the entry's own limits
+        #[clap(long, conflicts_with_all = ["max_pages", "max_bytes", "max_media_bytes", "max_duration"])]
+        clear: bool,
+    },
+
+    /// Set the languages kept from an entry
+    ///
+    /// Settings made here replace the [languages] settings of the
+    /// configuration. Pages in other languages are neither saved nor
+    /// followed.
+    Languages {
+        /// Name of the entry
+        #[clap(required = true)]
+        name: String,
+
+        /// Languages to keep, like `en,pl`, in order of preference
+        #[clap(long, value_name = "LANGUAGES", value_delimiter = ',')]
+        keep: Option<Vec<String>>,
+
+        /// Write every language other than the first kept one to its own result file
+        #[clap(long, value_name = "BOOL")]
+        split_results: Option<bool>,
+
+        /// Guess the language of pages that declare none from their text
+        #[clap(long, value_name = "BOOL")]
+        detect_text: Option<bool>,
+
+        /// Remove the entry's own settings
+        #[clap(long, conflicts_with_all = ["keep", "split_results", "detect_text"])]
+        clear: bool,
+    },
+
+    /// Set the documentation version crawled from an entry
+    ///
+    /// Sites hosting several versions of their documentation (under paths
+    /// like /v2/, /en/stable/ or /docs/1.x/) are confined to this version.
+    /// The setting replaces the [versions] settings of the configuration.
+    DocsVersion {
+        /// Name of the entry
+        #[clap(required = true)]
+        name: String,
+
+        /// Version to crawl: `latest` or a version such as `2.1` or `v3`
+        #[clap(required_unless_present = "clear")]
+        target: Option<String>,
+
+        /// Remove the entry's own setting
+        #[clap(long, conflicts_with = "target")]
+        clear: bool,
+    },
+
+    /// Set how the main content of an entry's pages is extracted
+    ///
+    /// Extraction keeps only the main content of each page (its <main> or
+    /// <article>, or the block the text points to) for conversion. Settings
+    /// made here replace the [extraction] settings of the configuration;
+    /// rebuild the entry to convert its saved pages with them.
+    Extraction {
+        /// Name of the entry
+        #[clap(required = true)]
+        name: String,
+
+        /// Convert only the main content of pages instead of whole pages
+        #[clap(long, value_name = "BOOL")]
+        enabled: Option<bool>,
+
+        /// Save the extracted HTML of every page, to check what was kept
+        #[clap(long, value_name = "BOOL")]
+        save_extracted: Option<bool>,
+
+        /// Remove the entry's own settings
+        #[clap(long, conflicts_with_all = ["enabled", "save_extracted"])]
+        clear: bool,
     },
 }
 
@@ -103,8 +354,8 @@ fn init_logging() -> Result<()> {
     let log_file_name = format!("docling_{}.log", now.format("%Y%m%d_%H%M%S"));
     let log_file_path = log_dir.join(log_file_name);
     
-    // Log info about the log file
-    println!("Logs will be written to: {}", log_file_path.display());
+    // Log info about the log file; stdout is left to the output of the command
+    eprintln!("Logs will be written to: {}", log_file_path.display());
     
     // Create file appender
     let file_appender = tracing_appender::rolling::daily(
@@ -113,8 +364,9 @@ fn init_logging() -> Result<()> {
     );
     let (file_writer, _guard) = tracing_appender::non_blocking(file_appender);
     
-    // Also create a non-blocking stdout writer
-    let (stdout_writer, _guard_stdout) = tracing_appender::non_blocking(std::io::stdout());
+    // Also create a non-blocking stderr writer, so JSON, CSV and DOT output
+    // written to stdout can be piped or redirected as is
+    let (stderr_writer, _guard_stderr) = tracing_appender::non_blocking(std::io::stderr());
     
     // Configure and install the tracing subscriber
     tracing_subscriber::registry()
@@ -122,7 +374,7 @@ fn init_logging() -> Result<()> {
         // Add console logger with minimal formatting
         .with(
             tracing_subscriber::fmt::layer()
-                .with_writer(stdout_writer)
+                .with_writer(stderr_writer)
                 .with_ansi(true)
                 .with_span_events(FmtSpan::CLOSE)
         )
@@ -147,6 +399,147 @@ fn init_logging() -> Result<()> {
     Ok(())
 }
 
+/// Formats the link graph can be exported in
+#[derive(Clone, Copy, ValueEnum)]
+enum LinkFormat {
+    /// The whole graph as JSON
+    Json,
+    /// One row per link
+    Csv,
+    /// Graphviz DOT
+    Dot,
+    /// Broken links and redirect chains
+    Report,
+}
+
+/// Run an entry, rendering its progress, and print a summary of the run
+///
+/// Exits the process if the run fails.
+async fn run_and_report(docling: &Docling, name: &str, mode: RunMode) {
+    // Render progress events while the entry runs
+    let (progress_tx, mut progress_rx) = tokio::sync::mpsc::unbounded_channel();
+    let progress_task = tokio::spawn(async move {
+        while let Some(update) = progress_rx.recv().await {
+            render_progress(&update);
+        }
+    });
+    
+    // Run the entry
+    let result = docling
+        .run_entry_in_mode(name, mode, std::sync::Arc::new(progress_tx))
+        .await;
+    let _ = progress_task.await;
+    eprintln!();
+    
+    match result {
+        Ok(report) => {
+            println!("Successfully processed entry: {}", name);
+            println!(
+                "Pages saved: {}/{} ({} unchanged), conversions: {} ({} used fallback), warnings: {}",
+                report.pages_saved(),
+                report.pages.len(),
+                report.pages_unchanged(),
+                report.conversions.len(),
+                report.fallback_conversions(),
+                report.warnings.len()
+            );
+            if let Some(limit) = report.truncated {
+                println!("Crawl stopped early: {}", limit);
+            }
+            if let Some(warc_file) = &report.warc_file {
+                println!("WARC archive: {}", warc_file.display());
+            }
+            if let Some(result_file) = &report.result_file {
+                println!("Result file: {}", result_file.display());
+            }
+        }
+        Err(e) => {
+            error!("Failed to process entry: {}", e);
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Print the URLs of a crawl plan grouped by depth and path prefix, and the
+/// URLs it leaves out grouped by rule
+fn print_plan(plan: &CrawlPlan) {
+    println!("Crawl plan for {} ({})", plan.entry, plan.root);
+    if let Some(version) = &plan.docs_version {
+        println!("Docs version: {} (requested {})", version.resolved, version.requested);
+        if !version.available.is_empty() {
+            println!("Versions offered: {}", version.available.join(", "));
+        }
+    }
+    println!("Would fetch {} pages and leave out {} URLs", plan.pages.len(), plan.excluded.len());
+    for (depth, prefixes) in plan.pages_by_depth() {
+        let count: usize = prefixes.values().map(Vec::len).sum();
+        println!("\nDepth {}: {} pages", depth, count);
+        for (prefix, pages) in prefixes {
+            println!("  {} ({})", prefix, pages.len());
+            for page in pages {
+                match (&page.error, page.status) {
+                    (Some(error), _) => println!("    {} [failed: {}]", page.url, error),
+                    (None, Some(status)) if !(200..300).contains(&status) => {
+                        println!("    {} [{}]", page.url, status)
+                    }
+                    _ => println!("    {}", page.url),
+                }
+            }
+        }
+    }
+
+    let excluded = plan.excluded_by_rule();
+    if !excluded.is_empty() {
+        println!("\nLeft out:");
+    }
+    for (rule, urls) in excluded {
+        println!("  {} ({})", rule, urls.len());
+        for url in urls {
+            match &url.found_on {
+                Some(found_on) => println!("    {} (found on {})", url.url, found_on),
+                None => println!("    {}", url.url),
+            }
+        }
+    }
+    if let Some(limit) = plan.truncated {
+        println!("\nThe crawl would stop early: {}", limit);
+    }
+}
+
+/// Render a progress update as a single status line on stderr
+fn render_progress(update: &ProgressUpdate) {
+    let totals = &update.totals;
+    match &update.event {
+        ProgressEvent::ConversionStarted { total } => {
+            eprint!("\r\x1b[2KConverting {} files...", total);
+        }
+        ProgressEvent::ConversionFinished { converted, failed } => {
+            eprint!("\r\x1b[2KConverted {} files ({} failed)", converted, failed);
+        }
+        ProgressEvent::ProcessingDone { output, bytes } => {
+            eprint!("\r\x1b[2KWrote {} ({} KiB)", output.display(), bytes / 1024);
+        }
+        ProgressEvent::RetryScheduled { attempt, delay_secs, reason } => {
+            eprintln!("\r\x1b[2KAttempt {} failed: {}. Retrying in {}s", attempt, reason, delay_secs);
+        }
+        ProgressEvent::CrawlTruncated { limit } => {
+            eprintln!("\r\x1b[2KCrawl stopped early: {}", limit);
+        }
+        _ => {
+            eprint!(
+                "\r\x1b[2KPages: {} saved, {} failed ({} KiB) | Media: {} ({} KiB)",
+                totals.pages_saved,
+                totals.pages_failed,
+                totals.page_bytes / 1024,
+                totals.media_saved,
+                totals.media_bytes / 1024
+            );
+        }
+    }
+    let _ = std::io::stderr().flush();
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     // Load configuration first (needed for logging)
@@ -155,6 +548,9 @@ async fn main() -> Result<()> {
     // Initialize logging
     init_logging()?;
     
+    // Open the workspace in the current directory
+    let docling = Docling::new(std::env::current_dir()?, config.data.clone());
+    
     // Parse command line arguments (only once)
     let cli = Cli::parse();
     
@@ -164,23 +560,40 @@ async fn main() -> Result<()> {
     
     // Execute command
     match cli.command {
-        Commands::Add { url, name, depth } => {
+        Commands::Add { url, name, depth, sitemap_only, no_sitemaps, documents, warc } => {
             let name_str = name.as_deref();
-            mi4ulings_docling::add_url(&url, name_str, depth)?;
+            let entry = docling.add_url(&url, name_str, depth)?;
+            let discovery = if sitemap_only {
+                DiscoveryMode::SitemapOnly
+            } else if no_sitemaps {
+                DiscoveryMode::Links
+            } else {
+                DiscoveryMode::default()
+            };
+            if discovery != entry.discovery || !documents.is_empty() || warc {
+                docling.store().update_entry(&entry.name, |entry| {
+                    entry.discovery = discovery;
+                    entry.document_types = documents;
+                    entry.warc = warc;
+                })?;
+            }
             println!("Added URL: {}", url);
         }
+
+        Commands::AddGit { repository, name, reference, paths } => {
+            let source = GitSource::new(&repository, Some(&reference), paths)?;
+            let entry = docling.add_git(source, name.as_deref())?;
+            println!("Added git repository: {} as {}", repository.display(), entry.name);
+        }
         
         Commands::Stop { name } => {
-            mi4ulings_docling::stop_url(&name)?;
+            docling.stop_url(&name)?;
             println!("Stopped entry: {}", name);
         }
         
         Commands::List => {
-            let entries = mi4ulings_docling::list_urls()?;
-            
-            // Load config to get retry count
-            let config = Config::<DoclingConfig>::load_or_default()?;
-            let retry_count = config.data.retry_count;
+            let entries = docling.list_urls()?;
+            let retry_count = docling.config().retry_count;
             
             if entries.is_empty() {
                 println!("No entries found");
@@ -233,27 +646,214 @@ async fn main() -> Result<()> {
             }
         }
         
+        Commands::Auth { name, headers, basic, bearer, cookies, clear } => {
+            // Fail early for unknown entries
+            docling.entry(&name)?;
+            if clear {
+                docling.secrets().remove(&name)?;
+                println!("Removed credentials of entry: {}", name);
+                return Ok(());
+            }
+
+            let mut parsed_headers = Vec::new();
+            for header in headers {
+                let (header_name, value) = header
+                    .split_once(':')
+                    .with_context(|| format!("Header must be given as \"Name: value\": {}", header))?;
+                parsed_headers.push((header_name.trim().to_string(), value.trim().to_string()));
+            }
+            let basic = basic.map(|basic| match basic.split_once(':') {
+                Some((username, password)) => mi4ulings_docling::auth::BasicCredentials {
+                    username: username.to_string(),
+                    password: Some(password.to_string()),
+                },
+                None => mi4ulings_docling::auth::BasicCredentials {
+                    username: basic,
+                    password: None,
+                },
+            });
+            let cookies = cookies
+                .map(|path| std::path::absolute(&path).with_context(|| format!("Invalid path: {}", path.display())))
+                .transpose()?;
+
+            let summary = docling.secrets().update(&name, |credentials| {
+                credentials.headers.extend(parsed_headers);
+                if basic.is_some() {
+                    credentials.basic = basic;
+                    credentials.bearer_token = None;
+                }
+                if bearer.is_some() {
+                    credentials.bearer_token = bearer;
+                    credentials.basic = None;
+                }
+                if cookies.is_some() {
+                    credentials.cookies_file = cookies;
+                }
+                format!("{:?}", credentials)
+            })?;
+            println!("Updated credentials of entry {}: {}", name, summary);
+            println!("Stored in {}", docling.secrets().path().display());
+        }
+
+        Commands::Network { name, proxy, no_proxy, ca_certificates, accept_invalid_certs, clear } => {
+            let ca_certificates = ca_certificates
+                .into_iter()
+                .map(|path| std::path::absolute(&path).with_context(|| format!("Invalid path: {}", path.display())))
+                .collect::<Result<Vec<_>>>()?;
+            let network = docling.store().update_entry(&name, |entry| {
+                if clear {
+                    entry.network = None;
+                    return None;
+                }
+                let network = entry.network.get_or_insert_with(Default::default);
+                if proxy.is_some() {
+                    network.proxy = proxy;
+                }
+                network.no_proxy.extend(no_proxy);
+                network.ca_certificates.extend(ca_certificates);
+                network.accept_invalid_certs |= accept_invalid_certs;
+                Some(network.clone())
+            })?;
+            match network {
+                Some(network) => println!("Network settings of entry {}: {:?}", name, network),
+                None => println!("Removed network settings of entry: {}", name),
+            }
+        }
+
+        Commands::Budget { name, max_pages, max_bytes, max_media_bytes, max_duration, clear } => {
+            let budget = docling.store().update_entry(&name, |entry| {
+                if clear {
+                    entry.budget = None;
+                    return None;
+                }
+                let budget = entry.budget.get_or_insert_with(Default::default);
+                budget.max_pages = max_pages.or(budget.max_pages);
+                budget.max_bytes = max_bytes.or(budget.max_bytes);
+                budget.max_media_bytes = max_media_bytes.or(budget.max_media_bytes);
+                budget.max_duration_secs = max_duration.or(budget.max_duration_secs);
+                Some(budget.clone())
+            })?;
+            match budget {
+                Some(budget) => println!("Crawl limits of entry {}: {:?}", name, budget),
+                None => println!("Removed crawl limits of entry: {}", name),
+            }
+        }
+
+        Commands::Languages { name, keep, split_results, detect_text, clear } => {
+            let languages = docling.store().update_entry(&name, |entry| {
+                if clear {
+                    entry.languages = None;
+                    return None;
+                }
+                let languages = entry.languages.get_or_insert_with(Default::default);
+                if let Some(keep) = keep {
+                    languages.keep = keep.into_iter().filter(|language| !language.trim().is_empty()).collect();
+                }
+                languages.split_results = split_results.unwrap_or(languages.split_results);
+                languages.detect_text = detect_text.unwrap_or(languages.detect_text);
+                Some(languages.clone())
+            })?;
+            match languages {
+                Some(languages) => println!("Language settings of entry {}: {:?}", name, languages),
+                None => println!("Removed language settings of entry: {}", name),
+            }
+        }
+
+        Commands::Extraction { name, enabled, save_extracted, clear } => {
+            let extraction = docling.store().update_entry(&name, |entry| {
+                if clear {
+                    entry.extraction = None;
+                    return None;
+                }
+                let extraction = entry.extraction.get_or_insert_with(Default::default);
+                extraction.enabled = enabled.unwrap_or(extraction.enabled);
+                extraction.save_extracted = save_extracted.unwrap_or(extraction.save_extracted);
+                Some(extraction.clone())
+            })?;
+            match extraction {
+                Some(extraction) => println!("Extraction settings of entry {}: {:?}", name, extraction),
+                None => println!("Removed extraction settings of entry: {}", name),
+            }
+        }
+
+        Commands::DocsVersion { name, target, clear } => {
+            let versions = docling.store().update_entry(&name, |entry| {
+                entry.versions = (!clear).then_some(VersionSettings { target });
+                entry.versions.clone()
+            })?;
+            match versions.and_then(|versions| versions.target) {
+                Some(target) => println!("Docs version of entry {}: {}", name, target),
+                None => println!("Removed docs version of entry: {}", name),
+            }
+        }
+
         Commands::Remove { name } => {
-            mi4ulings_docling::remove_url(&name)?;
+            docling.remove_url(&name)?;
             println!("Removed entry: {}", name);
         }
         
-        Commands::Start { name } => {
+        Commands::Start { name, dry_run: true, json } => {
+            let plan = docling.plan_entry(&name).await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&plan)?);
+            } else {
+                print_plan(&plan);
+            }
+        }
+
+        Commands::Start { name, .. } => {
             // First enable the entry
-            mi4ulings_docling::start_url(&name)?;
+            docling.start_url(&name)?;
             println!("Starting entry: {}", name);
             
-            // Then run the entry
-            match mi4ulings_docling::run_entry(&name).await {
-                Ok(result_file) => {
-                    println!("Successfully processed entry: {}", name);
-                    println!("Result file: {}", result_file.display());
+            run_and_report(&docling, &name, RunMode::Crawl).await;
+        }
+
+        Commands::Rebuild { name, replay } => {
+            let mode = if replay { RunMode::Replay } else { RunMode::Rebuild };
+            println!("Rebuilding entry: {}", name);
+            run_and_report(&docling, &name, mode).await;
+        }
+
+        Commands::Links { name, format, output } => {
+            let graph = docling.link_graph(&name)?;
+            let content = match format {
+                LinkFormat::Json => serde_json::to_string_pretty(&graph)? + "\n",
+                LinkFormat::Csv => graph.to_csv(),
+                LinkFormat::Dot => graph.to_dot(&name),
+                LinkFormat::Report => {
+                    let report = graph.report();
+                    let mut text = format!(
+                        "{} pages, {} broken links, {} redirect chains\n",
+                        graph.pages.len(),
+                        report.broken.len(),
+                        report.redirects.len()
+                    );
+                    for link in &report.broken {
+                        let status = link
+                            .status
+                            .map(|status| status.to_string())
+                            .or_else(|| link.error.clone())
+                            .unwrap_or_default();
+                        text.push_str(&format!("BROKEN {} -> {} ({}) \"{}\"\n", link.source, link.target, status, link.text));
+                    }
+                    for chain in &report.redirects {
+                        let mut hops = chain.url.clone();
+                        for hop in &chain.hops {
+                            hops.push_str(&format!(" -{}-> {}", hop.status, hop.to));
+                        }
+                        text.push_str(&format!("REDIRECT {} (linked from {} pages)\n", hops, chain.linked_from.len()));
+                    }
+                    text
                 }
-                Err(e) => {
-                    error!("Failed to process entry: {}", e);
-                    eprintln!("Error: {}", e);
-                    process::exit(1);
+            };
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, content)
+                        .with_context(|| format!("Failed to write {}", path.display()))?;
+                    println!("Wrote {}", path.display());
                 }
+                None => print!("{}", content),
             }
         }
     }
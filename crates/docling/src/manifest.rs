@@ -0,0 +1,332 @@
+//! Per-entry manifest mapping crawled URLs to saved files
+//!
+//! File names produced by [`url_to_filename`] cannot be turned back into
+//! URLs, so the crawler records every saved page in `manifest.json` inside
+//! the entry's output directory. Later stages (converter, processor) look
+//! the original URL of a file up here instead of guessing it from the name.
+
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use crate::fetch::Validators;
+use crate::{DoclingError, DocumentType, IoContext, MediaKind, Result};
+
+/// File name of the manifest inside an entry's output directory
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+/// Maximum length of the readable part of a generated file name
+const MAX_FILENAME_STEM_LEN: usize = 150;
+
+/// Number of hex characters of the URL hash appended to file names
+const FILENAME_HASH_LEN: usize = 12;
+
+/// Everything known about a single saved page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageRecord {
+    /// URL the page was requested from
+    pub url: String,
+    /// URL the page was served from after redirects
+    pub final_url: String,
+    /// Link depth from the entry URL (0 for the entry URL itself), if known
+    pub depth: Option<u32>,
+    /// URL of the page the link to this page was first found on
+    pub parent: Option<String>,
+    /// HTTP status code of the response
+    pub status: Option<u16>,
+    /// Content type of the response
+    pub content_type: Option<String>,
+    /// When the page was fetched
+    pub fetched_at: DateTime<Utc>,
+    /// SHA-256 of the saved content, hex encoded
+    pub content_hash: String,
+    /// Saved file, relative to the entry's output directory
+    pub saved_path: PathBuf,
+    /// Cache validators sent when the page is refreshed
+    #[serde(default, flatten)]
+    pub validators: Validators,
+    /// Encoding the page was served in (the saved copy is always UTF-8)
+    #[serde(default)]
+    pub encoding: Option<String>,
+    /// Content hash the current Markdown file was converted from
+    #[serde(default)]
+    pub converted_hash: Option<String>,
+    /// Type of the saved document, if it is a linked document rather than an HTML page
+    #[serde(default)]
+    pub document: Option<DocumentType>,
+    /// URL of the page this one duplicates; duplicates are not converted
+    #[serde(default)]
+    pub duplicate_of: Option<String>,
+    /// Language of the page (primary ISO 639-1 code), if known
+    #[serde(default)]
+    pub language: Option<String>,
+}
+
+impl PageRecord {
+    /// Whether the Markdown file is up to date with the saved content
+    pub fn is_converted(&self) -> bool {
+        self.converted_hash.as_deref() == Some(self.content_hash.as_str())
+    }
+}
+
+/// Everything known about a single saved media file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MediaRecord {
+    /// URL the file was downloaded from, or `inline:<hash>` for media embedded in a page
+    pub url: String,
+    /// Kind of media, if known
+    #[serde(default)]
+    pub kind: Option<MediaKind>,
+    /// Saved file, relative to the entry's output directory
+    pub saved_path: PathBuf,
+    /// SHA-256 of the file, hex encoded
+    pub content_hash: String,
+    /// MIME type of the file, if known
+    #[serde(default)]
+    pub content_type: Option<String>,
+    /// Size of the file in bytes
+    #[serde(default)]
+    pub bytes: u64,
+    /// When the file was fetched
+    pub fetched_at: DateTime<Utc>,
+    /// Cache validators sent when the file is refreshed
+    #[serde(default, flatten)]
+    pub validators: Validators,
+}
+
+/// Manifest of all pages saved for an entry, keyed by URL
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Page records by requested URL
+    pub pages: BTreeMap<String, PageRecord>,
+    /// Media records by URL
+    #[serde(default)]
+    pub media: BTreeMap<String, MediaRecord>,
+}
+
+impl Manifest {
+    /// Location of the manifest inside an entry's output directory
+    pub fn path_in(entry_dir: &Path) -> PathBuf {
+        entry_dir.join(MANIFEST_FILE_NAME)
+    }
+
+    /// Load the manifest of an entry, returning an empty one if none was saved yet
+    pub fn load(entry_dir: &Path) -> Result<Self> {
+        let path = Self::path_in(entry_dir);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let json = std::fs::read_to_string(&path).io_path_context("Failed to read manifest", &path)?;
+        serde_json::from_str(&json)
+            .map_err(|e| DoclingError::config_with(format!("Failed to parse manifest {}", path.display()), e))
+    }
+
+    /// Save the manifest into an entry's output directory
+    pub fn save(&self, entry_dir: &Path) -> Result<()> {
+        let path = Self::path_in(entry_dir);
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| DoclingError::config_with("Failed to serialize manifest", e))?;
+        std::fs::write(&path, json).io_path_context("Failed to write manifest", &path)
+    }
+
+    /// Add or replace the record for a page
+    pub fn insert(&mut self, record: PageRecord) {
+        self.pages.insert(record.url.clone(), record);
+    }
+
+    /// Record of a page by URL
+    pub fn get(&self, url: &str) -> Option<&PageRecord> {
+        self.pages.get(url)
+    }
+
+    /// Index of the saved pages by file name, for looking up many files
+    pub fn file_index(&self) -> FileIndex {
+        let urls = self
+            .pages
+            .values()
+            .filter_map(|record| Some((record.saved_path.file_stem()?.to_os_string(), record.url.clone())))
+            .collect();
+        FileIndex { urls }
+    }
+
+    /// Record of the page saved under the given file name (ignoring the extension)
+    pub fn find_by_file(&self, index: &FileIndex, file: &Path) -> Option<&PageRecord> {
+        self.pages.get(index.url(file)?)
+    }
+
+    /// Mutable record of the page saved under the given file name (ignoring the extension)
+    pub fn find_by_file_mut(&mut self, index: &FileIndex, file: &Path) -> Option<&mut PageRecord> {
+        self.pages.get_mut(index.url(file)?)
+    }
+}
+
+/// URLs of the saved pages by file name (without extension)
+///
+/// Built once per run with [`Manifest::file_index`], so looking up the page
+/// of every file does not scan the whole manifest each time. The index does
+/// not follow later changes to the manifest's file names.
+#[derive(Debug, Clone, Default)]
+pub struct FileIndex {
+    urls: HashMap<OsString, String>,
+}
+
+impl FileIndex {
+    /// URL of the page saved under the given file name (ignoring the extension)
+    pub fn url(&self, file: &Path) -> Option<&str> {
+        self.urls.get(file.file_stem()?).map(String::as_str)
+    }
+}
+
+/// SHA-256 of some content, hex encoded
+pub fn content_hash(content: &[u8]) -> String {
+    to_hex(&Sha256::digest(content))
+}
+
+/// Converts a URL to a valid, collision-safe filename, attempting to preserve structure.
+///
+/// The readable part is built from the host and path; a short hash of the
+/// full URL (including the query) is always appended, so URLs that only differ
+/// in characters that get replaced, in the query or beyond the length limit
+/// still map to different files.
+///
+/// # Arguments
+/// * `url` - The URL to convert
+///
+/// # Returns
+/// A string that can be used as a filename (without extension)
+pub fn url_to_filename(url: &Url) -> String {
+    let host = url.host_str().unwrap_or("local");
+    // Get path segments, filter out empty ones, join with underscores
+    let path = url
+        .path_segments()
+        .map(|segments| {
+            segments
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>()
+                .join("_")
+        })
+        .unwrap_or_default();
+
+    // Combine host and path
+    let mut stem = if path.is_empty() {
+        host.to_string()
+    } else {
+        format!("{}_{}", host, path)
+    };
+
+    // Keep only characters that are safe on every filesystem
+    stem = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' })
+        .collect();
+
+    // Handle potential trailing characters or excessive underscores
+    while stem.contains("__") {
+        stem = stem.replace("__", "_");
+    }
+    stem = stem.trim_matches(['_', '.']).to_string();
+
+    // Limit length to avoid filesystem limits; the hash keeps names unique
+    if stem.len() > MAX_FILENAME_STEM_LEN {
+        stem.truncate(MAX_FILENAME_STEM_LEN);
+        stem = stem.trim_end_matches(['_', '.']).to_string();
+    }
+
+    // Handle case where filename might become empty after cleaning (e.g. URL was just "/")
+    if stem.is_empty() {
+        stem = "index".to_string();
+    }
+
+    let hash = content_hash(url.as_str().as_bytes());
+    format!("{}-{}", stem, &hash[..FILENAME_HASH_LEN])
+}
+
+/// Whether a file name (without extension) was made by [`url_to_filename`]
+///
+/// Pages saved before file names carried a URL hash have names without it.
+pub fn is_hashed_filename(stem: &str) -> bool {
+    stem.rsplit_once('-').is_some_and(|(readable, hash)| {
+        !readable.is_empty()
+            && hash.len() == FILENAME_HASH_LEN
+            && hash.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+    })
+}
+
+/// Path relative to `base`, or the path itself if it is outside of `base`
+///
+/// Saved paths in the manifest, media and WARC indexes are relative to the
+/// entry's output directory.
+pub(crate) fn relative_to(path: &Path, base: &Path) -> PathBuf {
+    path.strip_prefix(base).unwrap_or(path).to_path_buf()
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filenames_differ_for_query_and_long_urls() {
+        let a = Url::parse("https://example.com/docs?page=1").unwrap();
+        let b = Url::parse("https://example.com/docs?page=2").unwrap();
+        assert_ne!(url_to_filename(&a), url_to_filename(&b));
+
+        let long = "x".repeat(300);
+        let c = Url::parse(&format!("https://example.com/{}/a", long)).unwrap();
+        let d = Url::parse(&format!("https://example.com/{}/b", long)).unwrap();
+        assert_ne!(url_to_filename(&c), url_to_filename(&d));
+        assert!(url_to_filename(&c).len() <= MAX_FILENAME_STEM_LEN + FILENAME_HASH_LEN + 1);
+    }
+
+    #[test]
+    fn filenames_are_readable_and_safe() {
+        let url = Url::parse("https://example.com/docs/getting-started/").unwrap();
+        let name = url_to_filename(&url);
+        assert!(name.starts_with("example.com_docs_getting-started-"));
+        assert!(name.chars().all(|c| c.is_ascii_alphanumeric() || "._-".contains(c)));
+        assert!(is_hashed_filename(&name));
+        assert!(!is_hashed_filename("example.com_docs_getting-started"));
+    }
+
+    #[test]
+    fn files_are_found_through_the_index() {
+        let mut manifest = Manifest::default();
+        for url in ["https://example.com/a", "https://example.com/b"] {
+            let url = Url::parse(url).unwrap();
+            manifest.insert(PageRecord {
+                url: url.to_string(),
+                final_url: url.to_string(),
+                depth: None,
+                parent: None,
+                status: Some(200),
+                content_type: None,
+                fetched_at: Utc::now(),
+                content_hash: content_hash(url.as_str().as_bytes()),
+                saved_path: PathBuf::from("parts_html").join(format!("{}.html", url_to_filename(&url))),
+                validators: Validators::default(),
+                encoding: None,
+                converted_hash: None,
+                document: None,
+                duplicate_of: None,
+                language: None,
+            });
+        }
+
+        let index = manifest.file_index();
+        let b = Url::parse("https://example.com/b").unwrap();
+        let md_file = PathBuf::from("parts_md").join(format!("{}.md", url_to_filename(&b)));
+        assert_eq!(index.url(&md_file), Some("https://example.com/b"));
+        manifest.find_by_file_mut(&index, &md_file).unwrap().converted_hash = Some("x".to_string());
+        assert_eq!(manifest.get("https://example.com/b").unwrap().converted_hash.as_deref(), Some("x"));
+        assert!(manifest.find_by_file(&index, Path::new("example.com_b.html")).is_none());
+    }
+}
@@ -0,0 +1,713 @@
+//! Content-addressed media storage
+//!
+//! Media files are stored once per content hash in a store shared by all
+//! entries (`<outputs>/_media_store/ab/abcdef….png`) and hard-linked (or
+//! copied where links are not supported) into each entry's media directory
+//! as `<hash>.<ext>`. Downloads are streamed to disk with a per-file size cap
+//! and a per-entry budget, and the extension is derived from the content
+//! rather than the URL. Every entry keeps a media index recording where each
+//! file came from, which pages referenced it and with which alt texts.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
+use tracing::{debug, warn};
+use url::Url;
+
+use crate::events::{ProgressEvent, ProgressReporter};
+use crate::fetch::{FetchResponse, Fetcher, StreamingResponse, Validators};
+use crate::html::{MediaRef, MediaSource};
+use crate::manifest::{content_hash, relative_to, to_hex, MediaRecord};
+use crate::warc::WarcWriter;
+use crate::{DoclingError, IoContext, MediaKind, Result};
+
+/// Directory of the shared media store inside the outputs directory
+pub const MEDIA_STORE_DIR_NAME: &str = "_media_store";
+
+/// File name of the media index inside an entry's output directory
+pub const MEDIA_INDEX_FILE_NAME: &str = "media_index.json";
+
+/// Number of leading bytes kept to recognise the file type
+const SNIFF_BYTES: usize = 64;
+
+/// Counter making temporary file names unique within the process
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Key under which a media reference is recorded
+///
+/// The URL for downloaded files, `inline:<hash>` for embedded media.
+pub fn media_key(media: &MediaRef) -> String {
+    match &media.source {
+        MediaSource::Url(url) => url.to_string(),
+        MediaSource::Inline(markup) => format!("inline:{}", content_hash(markup.as_bytes())),
+    }
+}
+
+/// Whether content recognised as `extension` and served as `content_type` is media of `kind`
+///
+/// Content of an unknown type is accepted if it was served as an image (or
+/// a video, for videos).
+pub fn is_media(kind: MediaKind, extension: &str, content_type: &str) -> bool {
+    let video = kind == MediaKind::Video;
+    match extension {
+        "mp4" | "webm" | "ogv" => video,
+        "bin" => content_type.starts_with("image/") || (video && content_type.starts_with("video/")),
+        _ => true,
+    }
+}
+
+/// File extension for media, from its magic bytes or else its MIME type
+pub fn media_extension(content_type: Option<&str>, head: &[u8]) -> &'static str {
+    let sniffed = if head.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("png")
+    } else if head.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("jpg")
+    } else if head.starts_with(b"GIF87a") || head.starts_with(b"GIF89a") {
+        Some("gif")
+    } else if head.len() >= 12 && &head[..4] == b"RIFF" && &head[8..12] == b"WEBP" {
+        Some("webp")
+    } else if head.len() >= 12 && &head[4..8] == b"ftyp" {
+        match &head[8..12] {
+            b"avif" | b"avis" => Some("avif"),
+            b"heic" | b"heix" | b"mif1" => Some("heic"),
+            _ => Some("mp4"),
+        }
+    } else if head.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+        Some("webm")
+    } else if head.starts_with(&[0x00, 0x00, 0x01, 0x00]) {
+        Some("ico")
+    } else if head.starts_with(b"BM") {
+        Some("bmp")
+    } else {
+        let text = String::from_utf8_lossy(head);
+        let text = text.trim_start_matches('\u{feff}').trim_start();
+        (text.starts_with("<svg") || (text.starts_with("<?xml") && text.contains("<svg"))).then_some("svg")
+    };
+
+    sniffed.unwrap_or_else(|| {
+        let mime = content_type
+            .and_then(|value| value.split(';').next())
+            .map(|mime| mime.trim().to_ascii_lowercase());
+        match mime.as_deref() {
+            Some("image/png") => "png",
+            Some("image/jpeg") | Some("image/jpg") => "jpg",
+            Some("image/gif") => "gif",
+            Some("image/webp") => "webp",
+            Some("image/avif") => "avif",
+            Some("image/svg+xml") => "svg",
+            Some("image/x-icon") | Some("image/vnd.microsoft.icon") => "ico",
+            Some("image/bmp") => "bmp",
+            Some("image/tiff") => "tiff",
+            Some("video/mp4") => "mp4",
+            Some("video/webm") => "webm",
+            Some("video/ogg") => "ogv",
+            _ => "bin",
+        }
+    })
+}
+
+/// Size limits for the media of one entry
+#[derive(Debug)]
+pub struct MediaBudget {
+    /// Largest single file in bytes
+    max_file_bytes: u64,
+    /// Total budget of the entry in bytes
+    max_total_bytes: u64,
+    /// Bytes used so far
+    used: AtomicU64,
+    /// Whether a reservation was refused for lack of budget
+    exhausted: AtomicBool,
+}
+
+impl MediaBudget {
+    /// Create a budget with the given limits
+    pub fn new(max_file_bytes: u64, max_total_bytes: u64) -> Self {
+        Self {
+            max_file_bytes,
+            max_total_bytes,
+            used: AtomicU64::new(0),
+            exhausted: AtomicBool::new(false),
+        }
+    }
+
+    /// Largest single file in bytes
+    pub fn max_file_bytes(&self) -> u64 {
+        self.max_file_bytes
+    }
+
+    /// Bytes used so far
+    pub fn used(&self) -> u64 {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    /// Whether a download was refused because the total budget ran out
+    pub fn is_exhausted(&self) -> bool {
+        self.exhausted.load(Ordering::Relaxed)
+    }
+
+    /// Reserve `bytes` of the total budget
+    ///
+    /// # Errors
+    /// Returns [`DoclingError::LimitExceeded`] if the budget would be exceeded
+    pub fn reserve(&self, bytes: u64) -> Result<()> {
+        self.used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                used.checked_add(bytes).filter(|total| *total <= self.max_total_bytes)
+            })
+            .map(|_| ())
+            .map_err(|_| {
+                self.exhausted.store(true, Ordering::Relaxed);
+                DoclingError::LimitExceeded {
+                    what: "Media of the entry".to_string(),
+                    limit: self.max_total_bytes,
+                }
+            })
+    }
+
+    /// Give back bytes reserved for a download that was abandoned
+    pub fn release(&self, bytes: u64) {
+        let _ = self
+            .used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| Some(used.saturating_sub(bytes)));
+    }
+}
+
+/// A file in the media store
+#[derive(Debug, Clone)]
+pub struct StoredMedia {
+    /// SHA-256 of the content, hex encoded
+    pub hash: String,
+    /// File extension derived from the content
+    pub extension: &'static str,
+    /// Size in bytes
+    pub bytes: u64,
+    /// Location in the store
+    pub path: PathBuf,
+}
+
+impl StoredMedia {
+    /// File name used for the media (`<hash>.<ext>`)
+    pub fn file_name(&self) -> String {
+        format!("{}.{}", self.hash, self.extension)
+    }
+}
+
+/// Media store shared by all entries
+#[derive(Debug, Clone)]
+pub struct MediaStore {
+    /// Root directory of the store
+    root: PathBuf,
+}
+
+impl MediaStore {
+    /// Media store inside the given outputs directory
+    pub fn new(outputs_path: &Path) -> Self {
+        Self {
+            root: outputs_path.join(MEDIA_STORE_DIR_NAME),
+        }
+    }
+
+    /// Root directory of the store
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Location of the file with the given hash and extension
+    pub fn path_for(&self, hash: &str, extension: &str) -> PathBuf {
+        self.root
+            .join(&hash[..2.min(hash.len())])
+            .join(format!("{}.{}", hash, extension))
+    }
+
+    /// Store content that is already in memory
+    pub async fn put_bytes(&self, content: &[u8], extension: &'static str) -> Result<StoredMedia> {
+        let hash = content_hash(content);
+        let path = self.path_for(&hash, extension);
+        if !path.exists() {
+            create_parent(&path).await?;
+            let temp = self.temp_path().await?;
+            tokio::fs::write(&temp, content)
+                .await
+                .io_path_context("Failed to write media file", &temp)?;
+            self.commit(&temp, &path).await?;
+        }
+
+        Ok(StoredMedia {
+            hash,
+            extension,
+            bytes: content.len() as u64,
+            path,
+        })
+    }
+
+    /// Stream the body of a response into the store
+    ///
+    /// Bytes are charged against `budget` while they arrive; the download is
+    /// abandoned (and its reservation released) as soon as the file exceeds
+    /// the per-file limit or the entry runs out of budget. Once the first
+    /// bytes are in, the file type is recognised (see [`media_extension`])
+    /// and the download is dropped if `accept` rejects its extension.
+    ///
+    /// # Returns
+    /// The stored file, or `None` if `accept` rejected its type
+    pub async fn put_stream(
+        &self,
+        response: &mut StreamingResponse,
+        budget: &MediaBudget,
+        accept: impl Fn(&'static str) -> bool,
+    ) -> Result<Option<StoredMedia>> {
+        let url = response.head.url.to_string();
+        let content_type = response.head.content_type().map(str::to_string);
+        let temp = self.temp_path().await?;
+        let mut reserved = 0u64;
+        let mut hasher = Sha256::new();
+        let mut head = Vec::with_capacity(SNIFF_BYTES);
+        let mut extension = None;
+
+        let written: Result<bool> = async {
+            let mut file = tokio::fs::File::create(&temp)
+                .await
+                .io_path_context("Failed to create media file", &temp)?;
+            while let Some(chunk) = response.chunk().await? {
+                let size = chunk.len() as u64;
+                if reserved + size > budget.max_file_bytes() {
+                    return Err(DoclingError::LimitExceeded {
+                        what: format!("Media file {}", url),
+                        limit: budget.max_file_bytes(),
+                    });
+                }
+                budget.reserve(size)?;
+                reserved += size;
+
+                hasher.update(&chunk);
+                if head.len() < SNIFF_BYTES {
+                    let take = (SNIFF_BYTES - head.len()).min(chunk.len());
+                    head.extend_from_slice(&chunk[..take]);
+                    if head.len() == SNIFF_BYTES {
+                        let sniffed = media_extension(content_type.as_deref(), &head);
+                        if !accept(sniffed) {
+                            return Ok(false);
+                        }
+                        extension = Some(sniffed);
+                    }
+                }
+                file.write_all(&chunk)
+                    .await
+                    .io_path_context("Failed to write media file", &temp)?;
+            }
+            file.flush().await.io_path_context("Failed to write media file", &temp)?;
+            Ok(true)
+        }
+        .await;
+
+        // Files shorter than the sniffed prefix are recognised at the end
+        let extension = extension.unwrap_or_else(|| media_extension(content_type.as_deref(), &head));
+        let result = written.map(|accepted| accepted && accept(extension));
+        if !matches!(result, Ok(true)) {
+            budget.release(reserved);
+            let _ = tokio::fs::remove_file(&temp).await;
+            if result.is_ok() {
+                debug!("Skipping {} of type '{}'", url, content_type.as_deref().unwrap_or_default());
+            }
+            return result.map(|_| None);
+        }
+
+        let hash = to_hex(&hasher.finalize());
+        let path = self.path_for(&hash, extension);
+        if path.exists() {
+            // Already stored for another page or entry
+            let _ = tokio::fs::remove_file(&temp).await;
+        } else {
+            create_parent(&path).await?;
+            self.commit(&temp, &path).await?;
+        }
+
+        Ok(Some(StoredMedia {
+            hash,
+            extension,
+            bytes: reserved,
+            path,
+        }))
+    }
+
+    /// Make a stored file available in `dir` as `<hash>.<ext>`
+    ///
+    /// The file is hard-linked so it is only kept once on disk, and copied if
+    /// the filesystem does not support links.
+    pub async fn link_into(&self, media: &StoredMedia, dir: &Path) -> Result<PathBuf> {
+        let target = dir.join(media.file_name());
+        if target.exists() {
+            return Ok(target);
+        }
+
+        if let Err(e) = tokio::fs::hard_link(&media.path, &target).await {
+            debug!("Hard link to {} failed ({}), copying instead", target.display(), e);
+            tokio::fs::copy(&media.path, &target)
+                .await
+                .io_path_context("Failed to copy media file", &target)?;
+        }
+        Ok(target)
+    }
+
+    /// A fresh temporary file path inside the store
+    async fn temp_path(&self) -> Result<PathBuf> {
+        let dir = self.root.join("tmp");
+        tokio::fs::create_dir_all(&dir)
+            .await
+            .io_path_context("Failed to create media store directory", &dir)?;
+        let id = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        Ok(dir.join(format!("{}-{}.part", std::process::id(), id)))
+    }
+
+    /// Move a finished temporary file to its final location
+    async fn commit(&self, temp: &Path, path: &Path) -> Result<()> {
+        tokio::fs::rename(temp, path)
+            .await
+            .io_path_context("Failed to move media file into the store", path)
+    }
+}
+
+/// Create the parent directory of a path
+async fn create_parent(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .io_path_context("Failed to create media store directory", parent)?;
+    }
+    Ok(())
+}
+
+/// A page referencing a media file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MediaReference {
+    /// URL of the referring page
+    pub page: String,
+    /// Alternative text given by the page, if any
+    pub alt: Option<String>,
+}
+
+/// Everything known about one media file of an entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MediaIndexEntry {
+    /// File in the entry's media directory, relative to the entry's output directory
+    pub file: PathBuf,
+    /// Kind of media, if known
+    pub kind: Option<MediaKind>,
+    /// MIME type of the file, if known
+    pub content_type: Option<String>,
+    /// Size in bytes
+    pub bytes: u64,
+    /// URLs the file was served from
+    pub sources: BTreeSet<String>,
+    /// Pages that reference the file
+    pub pages: BTreeSet<String>,
+    /// Alternative texts the pages give for the file
+    pub alt_texts: BTreeSet<String>,
+}
+
+/// Index of the media of an entry, keyed by content hash
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MediaIndex {
+    /// Media files by content hash
+    pub files: BTreeMap<String, MediaIndexEntry>,
+}
+
+impl MediaIndex {
+    /// Location of the media index inside an entry's output directory
+    pub fn path_in(entry_dir: &Path) -> PathBuf {
+        entry_dir.join(MEDIA_INDEX_FILE_NAME)
+    }
+
+    /// Build the index from the media kept in a run and the pages referencing them
+    pub fn build<'a>(
+        records: impl IntoIterator<Item = &'a MediaRecord>,
+        references: &BTreeMap<String, Vec<MediaReference>>,
+    ) -> Self {
+        let mut index = Self::default();
+        for record in records {
+            let entry = index
+                .files
+                .entry(record.content_hash.clone())
+                .or_insert_with(|| MediaIndexEntry {
+                    file: record.saved_path.clone(),
+                    kind: record.kind,
+                    content_type: record.content_type.clone(),
+                    bytes: record.bytes,
+                    sources: BTreeSet::new(),
+                    pages: BTreeSet::new(),
+                    alt_texts: BTreeSet::new(),
+                });
+            if !record.url.starts_with("inline:") {
+                entry.sources.insert(record.url.clone());
+            }
+            for reference in references.get(&record.url).into_iter().flatten() {
+                entry.pages.insert(reference.page.clone());
+                entry.alt_texts.extend(reference.alt.clone());
+            }
+        }
+        index
+    }
+
+    /// Load the media index of an entry, returning an empty one if none was saved yet
+    pub fn load(entry_dir: &Path) -> Result<Self> {
+        let path = Self::path_in(entry_dir);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let json = std::fs::read_to_string(&path).io_path_context("Failed to read media index", &path)?;
+        serde_json::from_str(&json)
+            .map_err(|e| DoclingError::config_with(format!("Failed to parse media index {}", path.display()), e))
+    }
+
+    /// Save the media index into an entry's output directory
+    pub fn save(&self, entry_dir: &Path) -> Result<()> {
+        let path = Self::path_in(entry_dir);
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| DoclingError::config_with("Failed to serialize media index", e))?;
+        std::fs::write(&path, json).io_path_context("Failed to write media index", &path)
+    }
+}
+
+/// Downloads the media referenced by crawled pages into the store
+///
+/// Media kept by a previous run is re-fetched conditionally if validators
+/// were recorded for it, and downloaded again otherwise; either way it is
+/// only reported as saved if its content hash changed.
+#[derive(Debug, Clone)]
+pub(crate) struct MediaDownloader {
+    /// HTTP fetcher to use
+    pub fetcher: Fetcher,
+    /// Shared media store
+    pub store: MediaStore,
+    /// Entry's media directory the stored files are linked into
+    pub media_dir: PathBuf,
+    /// Output directory of the entry, saved paths are relative to it
+    pub entry_dir: PathBuf,
+    /// Media records from the previous run
+    pub known_media: Arc<BTreeMap<String, MediaRecord>>,
+    /// Size limits of the entry
+    pub budget: Arc<MediaBudget>,
+    /// Reporter notified about every saved file
+    pub progress: ProgressReporter,
+    /// Archive the exchanges are written to, if any
+    pub warc: Option<WarcWriter>,
+}
+
+impl MediaDownloader {
+    /// Download or save all media referenced by a page
+    ///
+    /// # Returns
+    /// Records for the media that were saved or kept from the previous run
+    pub async fn download(&self, media: Vec<MediaRef>) -> Vec<MediaRecord> {
+        let mut records = Vec::new();
+        for media_ref in media {
+            let key = media_key(&media_ref);
+            let result = match &media_ref.source {
+                MediaSource::Url(url) => self.fetch(url, media_ref.kind).await,
+                MediaSource::Inline(markup) => self.save_inline(key.clone(), media_ref.kind, markup).await,
+            };
+            match result {
+                Ok(record) => records.extend(record),
+                Err(e) => warn!("Skipping media {}: {}", key, e),
+            }
+        }
+        records
+    }
+
+    /// Fetch a media file into the store, reusing it if it did not change
+    async fn fetch(&self, url: &Url, kind: MediaKind) -> Result<Option<MediaRecord>> {
+        let previous = self
+            .known_media
+            .get(url.as_str())
+            .filter(|record| self.entry_dir.join(&record.saved_path).exists());
+        let validators = previous
+            .map(|record| record.validators.clone())
+            .filter(|validators| !validators.is_empty());
+
+        let mut response = self.fetcher.fetch_streaming(url, validators.as_ref()).await?;
+
+        if response.head.is_not_modified() {
+            self.archive(&response.head, &[], validators.as_ref());
+            debug!("Media not modified: {}", url);
+            let Some(record) = previous else {
+                return Ok(None);
+            };
+            self.budget.reserve(record.bytes)?;
+            return Ok(Some(MediaRecord {
+                fetched_at: Utc::now(),
+                ..record.clone()
+            }));
+        }
+
+        if !response.head.is_success() {
+            return Err(DoclingError::http_response(response.head.status, url.as_str()));
+        }
+
+        if let Some(length) = response.content_length()
+            && length > self.budget.max_file_bytes()
+        {
+            return Err(DoclingError::LimitExceeded {
+                what: format!("Media file {} ({} bytes)", url, length),
+                limit: self.budget.max_file_bytes(),
+            });
+        }
+
+        // Check that the content is what the page referenced, by its magic
+        // bytes first, so media served as `application/octet-stream` is kept
+        let content_type = response.head.mime_type().unwrap_or_default();
+        let accept = |extension| is_media(kind, extension, &content_type);
+        let Some(stored) = self.store.put_stream(&mut response, &self.budget, accept).await? else {
+            return Ok(None);
+        };
+        if self.warc.is_some() {
+            match tokio::fs::read(&stored.path).await {
+                Ok(body) => self.archive(&response.head, &body, validators.as_ref()),
+                Err(e) => warn!("Failed to read {} for the archive: {}", stored.path.display(), e),
+            }
+        }
+        let file_path = self.store.link_into(&stored, &self.media_dir).await?;
+        if previous.is_none_or(|record| record.content_hash != stored.hash) {
+            self.report_saved(url.as_str(), &file_path, stored.bytes);
+        }
+
+        Ok(Some(MediaRecord {
+            url: url.to_string(),
+            kind: Some(kind),
+            saved_path: relative_to(&file_path, &self.entry_dir),
+            content_hash: stored.hash,
+            content_type: Some(content_type),
+            bytes: stored.bytes,
+            fetched_at: Utc::now(),
+            validators: response.head.validators(),
+        }))
+    }
+
+    /// Write an exchange to the archive, if there is one
+    fn archive(&self, head: &FetchResponse, body: &[u8], validators: Option<&Validators>) {
+        if let Some(warc) = &self.warc
+            && let Err(e) = warc.write_exchange(head, body, validators)
+        {
+            warn!("Failed to archive {}: {}", head.url, e);
+        }
+    }
+
+    /// Save media embedded in a page, such as an inline `<svg>`
+    async fn save_inline(&self, key: String, kind: MediaKind, markup: &str) -> Result<Option<MediaRecord>> {
+        self.budget.reserve(markup.len() as u64)?;
+        let stored = self.store.put_bytes(markup.as_bytes(), "svg").await?;
+        let file_path = self.store.link_into(&stored, &self.media_dir).await?;
+        if !self.known_media.contains_key(&key) {
+            self.report_saved(&key, &file_path, stored.bytes);
+        }
+
+        Ok(Some(MediaRecord {
+            url: key,
+            kind: Some(kind),
+            saved_path: relative_to(&file_path, &self.entry_dir),
+            content_hash: stored.hash,
+            content_type: Some("image/svg+xml".to_string()),
+            bytes: stored.bytes,
+            fetched_at: Utc::now(),
+            validators: Validators::default(),
+        }))
+    }
+
+    /// Report a newly saved media file
+    fn report_saved(&self, url: &str, path: &Path, bytes: u64) {
+        debug!("Saved media: {}", url);
+        self.progress.emit(ProgressEvent::MediaSaved {
+            url: url.to_string(),
+            path: path.to_path_buf(),
+            bytes,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extension_prefers_magic_bytes_over_content_type() {
+        assert_eq!(media_extension(Some("image/jpeg"), b"\x89PNG\r\n\x1a\n...."), "png");
+        assert_eq!(media_extension(Some("text/plain"), b"<?xml version=\"1.0\"?><svg>"), "svg");
+        assert_eq!(media_extension(Some("image/webp; q=1"), b"????"), "webp");
+        assert_eq!(media_extension(None, b"????"), "bin");
+    }
+
+    #[test]
+    fn budget_rejects_and_releases() {
+        let budget = MediaBudget::new(10, 15);
+        budget.reserve(10).unwrap();
+        assert!(matches!(budget.reserve(6), Err(DoclingError::LimitExceeded { limit: 15, .. })));
+        assert!(budget.is_exhausted());
+        budget.release(10);
+        budget.reserve(15).unwrap();
+        assert_eq!(budget.used(), 15);
+    }
+
+    #[tokio::test]
+    async fn media_is_sniffed_before_its_content_type_is_checked() {
+        use crate::test_server::{request_path, response, TestServer};
+        use crate::DoclingConfig;
+
+        // The logo has no validators and changes between the two runs
+        let runs = Arc::new(AtomicU64::new(0));
+        let served = Arc::clone(&runs);
+        let server = TestServer::start(move |head| match request_path(head) {
+            "/logo" => {
+                let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+                png.extend_from_slice(&[served.load(Ordering::Relaxed) as u8; 80]);
+                response("200 OK", &[("Content-Type", "application/octet-stream")], &png)
+            }
+            _ => response("200 OK", &[("Content-Type", "text/html")], b"<html><body>Not found</body></html>"),
+        })
+        .await;
+        let dir = std::env::temp_dir().join(format!("docling-media-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let media_dir = dir.join("site").join("parts_media");
+        std::fs::create_dir_all(&media_dir).unwrap();
+        let config = DoclingConfig {
+            delay_between_request_in_ms: 0,
+            ..DoclingConfig::default()
+        };
+        let downloader = |known_media| MediaDownloader {
+            fetcher: Fetcher::new(&config).unwrap(),
+            store: MediaStore::new(&dir),
+            media_dir: media_dir.clone(),
+            entry_dir: dir.join("site"),
+            known_media: Arc::new(known_media),
+            budget: Arc::new(MediaBudget::new(1 << 20, 1 << 20)),
+            progress: ProgressReporter::noop(),
+            warc: None,
+        };
+
+        let logo = server.url("/logo");
+        let first = downloader(BTreeMap::new()).fetch(&logo, MediaKind::Image).await.unwrap().unwrap();
+        assert_eq!(first.saved_path.extension().unwrap(), "png");
+        assert!(downloader(BTreeMap::new()).fetch(&server.url("/missing.png"), MediaKind::Image).await.unwrap().is_none());
+
+        runs.store(1, Ordering::Relaxed);
+        let known = BTreeMap::from([(logo.to_string(), first.clone())]);
+        let second = downloader(known).fetch(&logo, MediaKind::Image).await.unwrap().unwrap();
+        assert_ne!(second.content_hash, first.content_hash);
+        assert!(dir.join("site").join(&second.saved_path).exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn unknown_content_is_media_only_if_served_as_media() {
+        assert!(is_media(MediaKind::Image, "png", "application/octet-stream"));
+        assert!(is_media(MediaKind::Image, "bin", "image/x-portable-pixmap"));
+        assert!(!is_media(MediaKind::Image, "bin", "text/html"));
+        assert!(!is_media(MediaKind::Image, "mp4", "video/mp4"));
+        assert!(is_media(MediaKind::Video, "mp4", "application/octet-stream"));
+    }
+}
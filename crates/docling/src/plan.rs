@@ -0,0 +1,217 @@
+//! Crawl plans of dry runs
+//!
+//! A dry run discovers the pages of an entry the way a crawl does (robots.txt,
+//! sitemaps and links within scope, depth and budget) but saves no pages,
+//! media or state. The resulting [`CrawlPlan`] lists the URLs a crawl would
+//! fetch and every URL that was left out together with the rule that excluded
+//! it, so scope settings can be tuned without running full crawls.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use serde::Serialize;
+use url::Url;
+
+use crate::budget::BudgetLimit;
+use crate::scope::ExclusionRule;
+use crate::version::DocsVersion;
+
+/// Why a discovered URL would not be fetched
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum Exclusion {
+    /// The URL is outside the entry's scope
+    Scope(ExclusionRule),
+    /// robots.txt disallows the URL
+    Robots,
+    /// The URL was found beyond the crawl depth
+    Depth {
+        /// Crawl depth of the entry
+        max_depth: u32,
+    },
+    /// The page is in a language that is not kept
+    Language {
+        /// Language of the page
+        language: String,
+    },
+    /// The URL belongs to another version of the documentation
+    Version {
+        /// Version segment of the URL
+        version: String,
+    },
+    /// The crawl budget ran out before the URL was fetched
+    Budget {
+        /// Limit that was reached
+        limit: BudgetLimit,
+    },
+}
+
+impl fmt::Display for Exclusion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Exclusion::Scope(rule) => write!(f, "{}", rule),
+            Exclusion::Robots => f.write_str("disallowed by robots.txt"),
+            Exclusion::Depth { max_depth } => write!(f, "beyond crawl depth {}", max_depth),
+            Exclusion::Language { language } => write!(f, "language '{}' is not kept", language),
+            Exclusion::Version { version } => write!(f, "other docs version '{}'", version),
+            Exclusion::Budget { limit } => write!(f, "crawl budget, {}", limit),
+        }
+    }
+}
+
+/// A URL a crawl would fetch
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedPage {
+    /// URL of the page
+    pub url: String,
+    /// Link depth from the entry URL
+    pub depth: u32,
+    /// URL of the page or sitemap the URL was first found on
+    pub found_on: Option<String>,
+    /// HTTP status code the dry run received, if it got a response
+    pub status: Option<u16>,
+    /// Content type of the response, if known
+    pub content_type: Option<String>,
+    /// Language of the page, if known
+    pub language: Option<String>,
+    /// Why the dry run could not fetch the page, if it could not
+    pub error: Option<String>,
+}
+
+/// A URL a crawl would leave out
+#[derive(Debug, Clone, Serialize)]
+pub struct ExcludedUrl {
+    /// URL that was found
+    pub url: String,
+    /// Depth the page would have had
+    pub depth: u32,
+    /// URL of the page or sitemap the URL was first found on
+    pub found_on: Option<String>,
+    /// Rule that excluded the URL
+    #[serde(flatten)]
+    pub exclusion: Exclusion,
+}
+
+/// URLs a crawl of an entry would fetch and leave out
+#[derive(Debug, Clone, Serialize)]
+pub struct CrawlPlan {
+    /// Name of the entry
+    pub entry: String,
+    /// URL the crawl starts from
+    pub root: String,
+    /// Pages that would be fetched, in crawl order
+    pub pages: Vec<PlannedPage>,
+    /// URLs that would be left out, each once with the first rule that excluded it
+    pub excluded: Vec<ExcludedUrl>,
+    /// Limit of the crawl budget that cut the crawl short, if any
+    pub truncated: Option<BudgetLimit>,
+    /// Documentation version the crawl is confined to, if the entry names one
+    pub docs_version: Option<DocsVersion>,
+    /// Excluded URLs already recorded
+    #[serde(skip)]
+    excluded_urls: HashSet<String>,
+}
+
+impl CrawlPlan {
+    /// Start an empty plan for an entry
+    pub fn new(entry: &str, root: &str) -> Self {
+        Self {
+            entry: entry.to_string(),
+            root: root.to_string(),
+            pages: Vec::new(),
+            excluded: Vec::new(),
+            truncated: None,
+            docs_version: None,
+            excluded_urls: HashSet::new(),
+        }
+    }
+
+    /// Record a URL that would be left out, unless it already was
+    pub fn exclude(&mut self, url: &Url, depth: u32, found_on: Option<&str>, exclusion: Exclusion) {
+        if self.excluded_urls.insert(url.to_string()) {
+            self.excluded.push(ExcludedUrl {
+                url: url.to_string(),
+                depth,
+                found_on: found_on.map(str::to_string),
+                exclusion,
+            });
+        }
+    }
+
+    /// Pages by depth, then by path prefix (see [`path_prefix`])
+    pub fn pages_by_depth(&self) -> BTreeMap<u32, BTreeMap<String, Vec<&PlannedPage>>> {
+        let mut groups: BTreeMap<u32, BTreeMap<String, Vec<&PlannedPage>>> = BTreeMap::new();
+        for page in &self.pages {
+            groups
+                .entry(page.depth)
+                .or_default()
+                .entry(path_prefix(&page.url))
+                .or_default()
+                .push(page);
+        }
+        groups
+    }
+
+    /// Excluded URLs by the rule that excluded them
+    pub fn excluded_by_rule(&self) -> BTreeMap<String, Vec<&ExcludedUrl>> {
+        let mut groups: BTreeMap<String, Vec<&ExcludedUrl>> = BTreeMap::new();
+        for excluded in &self.excluded {
+            groups.entry(excluded.exclusion.to_string()).or_default().push(excluded);
+        }
+        groups
+    }
+}
+
+/// Top-level directory of a URL's path, `/` for pages at the top
+///
+/// `https://example.com/docs/guide/intro` belongs to `/docs/`, and both
+/// `https://example.com/` and `https://example.com/about.html` to `/`.
+pub fn path_prefix(url: &str) -> String {
+    let Ok(url) = Url::parse(url) else {
+        return "/".to_string();
+    };
+    let path = url.path().trim_start_matches('/');
+    match path.split_once('/') {
+        Some((directory, _)) => format!("/{}/", directory),
+        None => "/".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_are_grouped_by_depth_and_prefix() {
+        let page = |url: &str, depth| PlannedPage {
+            url: url.to_string(),
+            depth,
+            found_on: None,
+            status: Some(200),
+            content_type: None,
+            language: None,
+            error: None,
+        };
+        let mut plan = CrawlPlan::new("docs", "https://example.com/");
+        plan.pages = vec![
+            page("https://example.com/", 0),
+            page("https://example.com/docs/", 1),
+            page("https://example.com/docs/guide/intro", 1),
+            page("https://example.com/about.html", 1),
+        ];
+        let groups = plan.pages_by_depth();
+        assert_eq!(groups[&0]["/"].len(), 1);
+        assert_eq!(groups[&1]["/docs/"].len(), 2);
+        assert_eq!(groups[&1]["/"].len(), 1);
+
+        let external = Url::parse("https://other.org/").unwrap();
+        let rule = Exclusion::Scope(ExclusionRule::DifferentHost {
+            host: "other.org".to_string(),
+        });
+        plan.exclude(&external, 1, Some("https://example.com/"), rule.clone());
+        plan.exclude(&external, 2, Some("https://example.com/docs/"), Exclusion::Depth { max_depth: 1 });
+        assert_eq!(plan.excluded.len(), 1);
+        assert_eq!(plan.excluded[0].exclusion, rule);
+        assert_eq!(plan.excluded_by_rule()["different host 'other.org'"].len(), 1);
+    }
+}
@@ -3,18 +3,25 @@
 
 use std::fs::{self, create_dir_all, read_to_string, write};
 use std::path::{Path, PathBuf};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Instant;
 
-use anyhow::{Context, Result};
-use tracing::{debug, error, info, warn};
+use tracing::{debug, info, warn};
 use url::Url;
 
-use crate::DoclingConfig;
+use crate::events::{ProgressEvent, ProgressReporter};
+use crate::language::primary_subtag;
+use crate::local;
+use crate::manifest::{FileIndex, Manifest};
+use crate::report::{millis, ProcessingReport};
+use crate::{DoclingConfig, DoclingError, IoContext, Result};
 
 /// Processor for Markdown content
 pub struct Processor {
     /// Configuration for the processor
     config: DoclingConfig,
+    /// Receives an event when the result file is written
+    progress: ProgressReporter,
 }
 
 impl Processor {
@@ -22,65 +29,145 @@ impl Processor {
     pub fn new(config: DoclingConfig) -> Self {
         Self {
             config,
+            progress: ProgressReporter::noop(),
         }
     }
+
+    /// Report progress events to the given reporter
+    pub fn with_progress(mut self, progress: ProgressReporter) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Location of the combined result file of an entry
+    pub fn result_file(&self, entry_name: &str) -> PathBuf {
+        self.config
+            .outputs_path
+            .join(entry_name)
+            .join(&self.config.output_parts_markdown_results_suffix)
+            .join(format!("{}.md", entry_name))
+    }
+
+    /// Location of the result file of one language of an entry
+    pub fn language_result_file(&self, entry_name: &str, language: &str) -> PathBuf {
+        self.result_file(entry_name)
+            .with_file_name(format!("{}.{}.md", entry_name, language))
+    }
     
     /// Process Markdown files for an entry
-    pub fn process_entry(&self, entry_name: &str, base_url: &str) -> Result<PathBuf> {
+    pub fn process_entry(&self, entry_name: &str, base_url: &str) -> Result<ProcessingReport> {
+        let start_time = Instant::now();
         let base_dir = self.config.outputs_path.join(entry_name);
         let md_dir = base_dir.join(&self.config.output_parts_markdown_suffix);
         let result_dir = base_dir.join(&self.config.output_parts_markdown_results_suffix);
         
         // Create result directory if it doesn't exist
-        create_dir_all(&result_dir).context("Failed to create result directory")?;
+        create_dir_all(&result_dir)
+            .io_path_context("Failed to create result directory", &result_dir)?;
         
         // Get all Markdown files
-        let md_files = fs::read_dir(&md_dir)
-            .context("Failed to read Markdown directory")?
-            .filter_map(Result::ok)
+        let mut md_files = fs::read_dir(&md_dir)
+            .io_path_context("Failed to read Markdown directory", &md_dir)?
+            .filter_map(std::result::Result::ok)
             .filter(|entry| {
                 entry.file_type().map(|ft| ft.is_file()).unwrap_or(false) &&
                 entry.path().extension().map(|ext| ext == "md").unwrap_or(false)
             })
             .map(|entry| entry.path())
             .collect::<Vec<_>>();
+        md_files.sort();
         
+        // Leave out pages in languages that are not kept
+        let manifest = Manifest::load(&base_dir)?;
+        let languages = &self.config.languages;
+        let file_index = manifest.file_index();
+        let page_language = |file: &PathBuf| manifest.find_by_file(&file_index, file).and_then(|record| record.language.clone());
+        md_files.retain(|file| page_language(file).is_none_or(|language| languages.accepts(&language)));
+
+        // Split the pages by language if every language gets its own result
+        let mut by_language: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+        if languages.split_results {
+            let primary = languages
+                .keep
+                .first()
+                .and_then(|language| primary_subtag(language))
+                .or_else(|| most_common_language(md_files.iter().filter_map(page_language)));
+            md_files.retain(|file| match page_language(file) {
+                Some(language) if Some(&language) != primary.as_ref() => {
+                    by_language.entry(language).or_default().push(file.clone());
+                    false
+                }
+                _ => true,
+            });
+        }
+
         // Combine and clean Markdown files
-        let combined_content = self.combine_files(&md_files, base_url)?;
-        let output_file = result_dir.join(format!("{}.md", entry_name));
+        let combined_content = self.combine_files(&md_files, base_url, &manifest, &file_index)?;
+        let output_file = self.result_file(entry_name);
         
         // Write result
-        write(&output_file, combined_content).context("Failed to write result file")?;
+        let bytes = combined_content.len() as u64;
+        write(&output_file, combined_content)
+            .io_path_context("Failed to write result file", &output_file)?;
+        self.progress.emit(ProgressEvent::ProcessingDone {
+            output: output_file.clone(),
+            bytes,
+        });
         
         info!(
             "Created combined and cleaned Markdown file: {}",
             output_file.display()
         );
+
+        let mut language_files = BTreeMap::new();
+        for (language, files) in &by_language {
+            let content = self.combine_files(files, base_url, &manifest, &file_index)?;
+            let language_file = self.language_result_file(entry_name, language);
+            write(&language_file, &content)
+                .io_path_context("Failed to write language result file", &language_file)?;
+            info!("Created result file for language '{}': {}", language, language_file.display());
+            language_files.insert(language.clone(), language_file);
+        }
+        remove_stale_language_files(&result_dir, entry_name, |language| language_files.contains_key(language));
+        md_files.extend(by_language.into_values().flatten());
         
-        Ok(output_file)
+        Ok(ProcessingReport {
+            input_files: md_files,
+            output_file,
+            bytes,
+            duration_ms: millis(start_time.elapsed()),
+            language_files,
+        })
     }
-    
+
     /// Combine multiple Markdown files into one
-    fn combine_files(&self, files: &[PathBuf], base_url: &str) -> Result<String> {
+    fn combine_files(&self, files: &[PathBuf], base_url: &str, manifest: &Manifest, index: &FileIndex) -> Result<String> {
         let mut combined = String::new();
         
         // Try to parse the base URL
-        let parsed_base_url = Url::parse(base_url).context("Invalid base URL")?;
-        let base_domain = parsed_base_url.host_str()
-            .ok_or_else(|| anyhow::anyhow!("URL has no host"))?
-            .to_string();
+        let parsed_base_url = Url::parse(base_url)
+            .map_err(|e| DoclingError::config_with(format!("Invalid base URL: {}", base_url), e))?;
+        // Local sources have no host of their own, so their web links are kept
+        let base_domain = match parsed_base_url.host_str() {
+            Some(host) => host.to_string(),
+            None if local::is_local(&parsed_base_url) => String::new(),
+            None => return Err(DoclingError::config(format!("URL has no host: {}", base_url))),
+        };
         
         // Process each file
         for file in files {
             let content = read_to_string(file)
-                .with_context(|| format!("Failed to read file: {}", file.display()))?;
+                .io_path_context(format!("Failed to read file: {}", file.display()), file)?;
             
             // Clean the content
             let cleaned = self.clean_content(&content, &base_domain);
             
-            // Add section header based on filename
-            let filename = file.file_stem().unwrap().to_string_lossy();
-            combined.push_str(&format!("\n\n## {}\n\n", filename));
+            // Add section header based on the source URL, or the filename if unknown
+            let header = match manifest.find_by_file(index, file) {
+                Some(record) => record.url.clone(),
+                None => file.file_stem().unwrap().to_string_lossy().to_string(),
+            };
+            combined.push_str(&format!("\n\n## {}\n\n", header));
             combined.push_str(&cleaned);
         }
         
@@ -112,7 +199,7 @@ impl Processor {
                 let mut in_tag = false;
                 let mut tag_content = String::new();
                 
-                for (i, c) in line.chars().enumerate() {
+                for c in line.chars() {
                     if c == '<' {
                         in_tag = true;
                         if !tag_content.is_empty() {
@@ -173,18 +260,17 @@ impl Processor {
                 let url = &result[url_start..url_end];
                 
                 // Check if URL is from the base domain
-                if let Ok(parsed_url) = Url::parse(url) {
-                    if let Some(host) = parsed_url.host_str() {
-                        if !host.contains(base_domain) {
-                            // Replace with just the text
-                            let text = &result[text_start + 1..real_pos];
-                            let link = format!("[{}]({})", text, url);
-                            result = result.replace(&link, text);
-                            // Reset position because the string changed
-                            link_start = 0;
-                            continue;
-                        }
-                    }
+                if let Ok(parsed_url) = Url::parse(url)
+                    && let Some(host) = parsed_url.host_str()
+                    && !host.contains(base_domain)
+                {
+                    // Replace with just the text
+                    let text = &result[text_start + 1..real_pos];
+                    let link = format!("[{}]({})", text, url);
+                    result = result.replace(&link, text);
+                    // Reset position because the string changed
+                    link_start = 0;
+                    continue;
                 }
             }
             
@@ -258,4 +344,101 @@ impl Processor {
         
         result
     }
-}
\ No newline at end of file
+}
+
+/// Remove the result files of an entry's languages that no longer have pages
+///
+/// Files in `dir` named `<entry>.<language>.md` are removed unless `current`
+/// holds for their language.
+pub(crate) fn remove_stale_language_files(dir: &Path, entry_name: &str, current: impl Fn(&str) -> bool) {
+    let Ok(files) = fs::read_dir(dir) else {
+        return;
+    };
+    let prefix = format!("{}.", entry_name);
+    for file in files.filter_map(std::result::Result::ok) {
+        let name = file.file_name().to_string_lossy().to_string();
+        let Some(language) = name.strip_prefix(&prefix).and_then(|rest| rest.strip_suffix(".md")) else {
+            continue;
+        };
+        if primary_subtag(language).as_deref() == Some(language) && !current(language) {
+            match fs::remove_file(file.path()) {
+                Ok(()) => debug!("Removed stale result file {}", file.path().display()),
+                Err(e) => warn!("Failed to remove stale result file {}: {}", file.path().display(), e),
+            }
+        }
+    }
+}
+
+/// Language most pages are in, the first in alphabetical order on ties
+fn most_common_language(languages: impl Iterator<Item = String>) -> Option<String> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for language in languages {
+        *counts.entry(language).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+        .map(|(language, _)| language)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::LanguageSettings;
+    use crate::tests::test_workspace;
+
+    #[tokio::test]
+    async fn languages_get_their_own_result_files() {
+        let (dir, docling) = test_workspace("languages");
+        std::fs::write(
+            dir.join("site/index.html"),
+            "<html lang=\"en\"><head><title>Home</title></head><body><h1>Home</h1>\
+             <p>Welcome to the manual of the tool.</p><a href=\"guide.html\">Anleitung</a></body></html>",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("site/guide.html"),
+            "<html lang=\"de\"><head><title>Anleitung</title></head><body><h1>Anleitung</h1>\
+             <p>Installiere das Werkzeug mit cargo und starte es im Arbeitsbereich.</p></body></html>",
+        )
+        .unwrap();
+        docling
+            .add_url(dir.join("site").to_str().unwrap(), Some("site"), Some(1))
+            .unwrap();
+        let set_languages = |keep: &[&str]| {
+            docling
+                .store()
+                .update_entry("site", |entry| {
+                    entry.languages = Some(LanguageSettings {
+                        keep: keep.iter().map(|language| language.to_string()).collect(),
+                        split_results: true,
+                        ..LanguageSettings::default()
+                    })
+                })
+                .unwrap();
+        };
+        let processor = Processor::new(docling.config().clone());
+        let result_file = processor.result_file("site");
+        let german_file = processor.language_result_file("site", "de");
+        let german_copy = docling.docling_output_dir().join("site.de.md");
+
+        set_languages(&["en", "de"]);
+        let report = docling.run_entry("site").await.unwrap();
+        let result = std::fs::read_to_string(&result_file).unwrap();
+        assert!(result.contains("Welcome to the manual"));
+        assert!(!result.contains("Installiere das Werkzeug"));
+        let german = std::fs::read_to_string(&german_file).unwrap();
+        assert!(german.contains("Installiere das Werkzeug"));
+        assert!(!german.contains("Welcome to the manual"));
+        assert_eq!(report.processing.unwrap().language_files, BTreeMap::from([("de".to_string(), german_file.clone())]));
+        assert!(german_copy.exists());
+
+        // German pages are no longer kept, so their result files go
+        set_languages(&["en"]);
+        docling.rebuild_entry("site").await.unwrap();
+        assert!(std::fs::read_to_string(&result_file).unwrap().contains("Welcome to the manual"));
+        assert!(!german_file.exists());
+        assert!(!german_copy.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,244 @@
+//! Per-host rate limiting shared by all requests of a crawl
+//!
+//! Every request made through a [`Fetcher`](crate::fetch::Fetcher) first
+//! takes a slot from the bucket of its host, so pages, media, robots.txt and
+//! sitemaps on the same host are spaced out together. Each bucket refills at
+//! one request per interval and holds up to `burst` requests; the interval
+//! starts at the configured delay, is raised to the robots.txt `Crawl-delay`,
+//! doubles whenever the host answers 429 or 503 and recovers gradually after
+//! successful responses. `Retry-After` pauses the host until the given time.
+//! Neither a `Crawl-delay` nor a pause holds a host back for longer than
+//! [`MAX_INTERVAL`] and [`MAX_RETRY_AFTER`].
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tokio::time::{sleep_until, Instant};
+use tracing::{debug, warn};
+use url::Url;
+
+/// Largest interval the adaptive slowdown or a `Crawl-delay` raises a host to
+pub const MAX_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Longest pause a `Retry-After` imposes on a host; fetches give up on
+/// longer ones
+pub const MAX_RETRY_AFTER: Duration = Duration::from_secs(300);
+
+/// Interval used when slowing down a host that had no delay at all
+const MIN_BACKOFF_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Rate limiting state of a single host
+#[derive(Debug, Clone)]
+struct HostState {
+    /// Interval configured or requested by robots.txt
+    base_interval: Duration,
+    /// Interval currently applied, at least `base_interval`
+    interval: Duration,
+    /// Theoretical time at which the bucket is full again
+    full_at: Instant,
+    /// No requests before this time (from `Retry-After`)
+    paused_until: Option<Instant>,
+}
+
+/// Per-host token bucket rate limiter
+///
+/// Cheap to clone; clones share the same buckets.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    /// Interval for hosts without a specific one
+    default_interval: Duration,
+    /// Number of requests a host may receive back to back
+    burst: u32,
+    /// Buckets by host (with port)
+    hosts: Arc<Mutex<HashMap<String, HostState>>>,
+}
+
+impl RateLimiter {
+    /// Create a rate limiter
+    ///
+    /// # Arguments
+    /// * `default_interval` - Minimum time between requests to the same host
+    /// * `burst` - Number of requests allowed back to back before spacing applies
+    pub fn new(default_interval: Duration, burst: u32) -> Self {
+        Self {
+            default_interval,
+            burst: burst.max(1),
+            hosts: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Raise the interval of the host of `url`, e.g. to honour `Crawl-delay`
+    ///
+    /// Intervals shorter than the configured default are ignored, longer
+    /// ones than [`MAX_INTERVAL`] are cut to it.
+    pub fn set_min_interval(&self, url: &Url, interval: Duration) {
+        let interval = interval.min(MAX_INTERVAL);
+        let now = Instant::now();
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        let state = self.state(&mut hosts, url, now);
+        if interval > state.base_interval {
+            debug!("Using an interval of {:?} for {}", interval, host_key(url));
+            state.base_interval = interval;
+            state.interval = state.interval.max(interval);
+        }
+    }
+
+    /// Wait until a request to the host of `url` may be made
+    pub async fn acquire(&self, url: &Url) {
+        let start = self.reserve(url, Instant::now());
+        if start > Instant::now() {
+            sleep_until(start).await;
+        }
+    }
+
+    /// Adjust the host of `url` to the status of a response
+    ///
+    /// 429 and 503 double the interval of the host and pause it for
+    /// `retry_after` (or one interval), at most [`MAX_RETRY_AFTER`]; other
+    /// responses let the interval
+    /// recover towards its base value.
+    pub fn observe(&self, url: &Url, status: u16, retry_after: Option<Duration>) {
+        let now = Instant::now();
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        let state = self.state(&mut hosts, url, now);
+
+        if is_throttling_status(status) {
+            state.interval = (state.interval * 2).max(MIN_BACKOFF_INTERVAL).min(MAX_INTERVAL);
+            let pause = retry_after.unwrap_or(state.interval).min(MAX_RETRY_AFTER);
+            state.paused_until = Some(now + pause);
+            warn!(
+                "{} answered {}, pausing for {:?} and slowing down to one request per {:?}",
+                host_key(url),
+                status,
+                pause,
+                state.interval
+            );
+        } else if state.interval > state.base_interval {
+            state.interval = (state.interval * 3 / 4).max(state.base_interval);
+        }
+    }
+
+    /// Take a slot from the bucket of the host of `url`
+    ///
+    /// # Returns
+    /// The time at which the request may be made
+    fn reserve(&self, url: &Url, now: Instant) -> Instant {
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        let burst = self.burst;
+        let state = self.state(&mut hosts, url, now);
+
+        // Generic cell rate algorithm: the bucket is full at `full_at`, a
+        // request may start once the bucket has at least one token left
+        let tolerance = state.interval * (burst - 1);
+        let full_at = state.full_at.max(now);
+        let mut start = full_at.checked_sub(tolerance).unwrap_or(now).max(now);
+        if let Some(paused_until) = state.paused_until {
+            if paused_until > start {
+                start = paused_until;
+            } else {
+                state.paused_until = None;
+            }
+        }
+        state.full_at = full_at.max(start) + state.interval;
+        start
+    }
+
+    /// State of the host of `url`, created with the default interval
+    fn state<'a>(&self, hosts: &'a mut HashMap<String, HostState>, url: &Url, now: Instant) -> &'a mut HostState {
+        hosts.entry(host_key(url)).or_insert_with(|| HostState {
+            base_interval: self.default_interval,
+            interval: self.default_interval,
+            full_at: now,
+            paused_until: None,
+        })
+    }
+}
+
+/// Whether a status asks the client to slow down
+pub fn is_throttling_status(status: u16) -> bool {
+    status == 429 || status == 503
+}
+
+/// Parse a `Retry-After` header value (seconds or an HTTP date)
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = date.with_timezone(&Utc) - Utc::now();
+    Some(wait.to_std().unwrap_or_default())
+}
+
+/// Key of the bucket a URL belongs to
+fn host_key(url: &Url) -> String {
+    match (url.host_str(), url.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_string(),
+        (None, _) => url.scheme().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spaces_requests_per_host_and_allows_bursts() {
+        let url = Url::parse("https://example.com/a").unwrap();
+        let other = Url::parse("https://other.example/a").unwrap();
+        let now = Instant::now();
+        let second = Duration::from_secs(1);
+
+        let limiter = RateLimiter::new(second, 1);
+        assert_eq!(limiter.reserve(&url, now), now);
+        assert_eq!(limiter.reserve(&url, now), now + second);
+        assert_eq!(limiter.reserve(&url, now), now + second * 2);
+        assert_eq!(limiter.reserve(&other, now), now);
+
+        let limiter = RateLimiter::new(second, 3);
+        assert_eq!(limiter.reserve(&url, now), now);
+        assert_eq!(limiter.reserve(&url, now), now);
+        assert_eq!(limiter.reserve(&url, now), now);
+        assert_eq!(limiter.reserve(&url, now), now + second);
+    }
+
+    #[test]
+    fn throttling_slows_down_and_pauses_the_host() {
+        let url = Url::parse("https://example.com/a").unwrap();
+        let limiter = RateLimiter::new(Duration::ZERO, 1);
+        limiter.set_min_interval(&url, Duration::from_secs(2));
+
+        limiter.observe(&url, 429, Some(Duration::from_secs(30)));
+        let now = Instant::now();
+        let start = limiter.reserve(&url, now);
+        assert!(start >= now + Duration::from_secs(29));
+        assert_eq!(limiter.reserve(&url, now), start + Duration::from_secs(4));
+
+        // Successful responses recover towards the Crawl-delay, not below it
+        for _ in 0..10 {
+            limiter.observe(&url, 200, None);
+        }
+        let hosts = limiter.hosts.lock().unwrap();
+        assert_eq!(hosts["example.com"].interval, Duration::from_secs(2));
+
+        drop(hosts);
+
+        // Day-long pauses and delays are cut to the limits
+        let huge = Duration::from_secs(86_400);
+        limiter.set_min_interval(&url, huge);
+        limiter.observe(&url, 503, Some(huge));
+        let now = Instant::now();
+        assert!(limiter.reserve(&url, now) <= now + MAX_RETRY_AFTER);
+        assert_eq!(limiter.hosts.lock().unwrap()["example.com"].base_interval, MAX_INTERVAL);
+
+        assert_eq!(parse_retry_after(" 120 "), Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+}
@@ -0,0 +1,389 @@
+//! Structured report of a single run of an entry
+//!
+//! A [`RunReport`] records what went into a generated document: every page
+//! the crawler received, how each file was converted (including fallbacks),
+//! what the processor produced, warnings and timings. It is returned from
+//! [`crate::Docling::run_entry`] and saved as JSON next to the result file.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::budget::BudgetLimit;
+use crate::charset::EncodingSource;
+use crate::extract::ContentExtraction;
+use crate::version::DocsVersion;
+use crate::{DoclingError, DocumentType, IoContext, Result, TransformMethod, UrlEntry};
+
+/// Where a run took the pages it converted from
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RunMode {
+    /// The source was crawled
+    #[default]
+    Crawl,
+    /// The source was crawled again from the entry's WARC archives, offline
+    Replay,
+    /// The pages saved by earlier runs were converted and processed again, offline
+    Rebuild,
+}
+
+/// Outcome of fetching a single page
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PageOutcome {
+    /// The page was saved to disk
+    Saved,
+    /// The page was not modified since the last run and was kept as is
+    Unchanged,
+    /// The page was not saved because of its content type
+    Skipped,
+    /// The page could not be fetched, decoded or saved
+    Failed,
+}
+
+/// Report for a single crawled page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageReport {
+    /// URL of the page
+    pub url: String,
+    /// Whether the page was saved
+    pub outcome: PageOutcome,
+    /// HTTP status code of the response, if known
+    pub http_status: Option<u16>,
+    /// Content type of the response, if known
+    pub content_type: Option<String>,
+    /// Size of the content in bytes
+    pub bytes: u64,
+    /// Where the page was saved
+    pub saved_path: Option<PathBuf>,
+    /// Encoding the page was served in, if it was decoded
+    #[serde(default)]
+    pub encoding: Option<String>,
+    /// Where the encoding was determined from
+    #[serde(default)]
+    pub encoding_source: Option<EncodingSource>,
+    /// Why the page failed, if it did
+    pub error: Option<String>,
+    /// URL of the page this one duplicates, if it was left out for it
+    #[serde(default)]
+    pub duplicate_of: Option<String>,
+    /// Language of the page, if known
+    #[serde(default)]
+    pub language: Option<String>,
+}
+
+/// Method actually used to convert a file to Markdown
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConversionMethod {
+    /// htmd library
+    Htmd,
+    /// fast_html2md library
+    FastHtml2md,
+    /// Jina AI reader service
+    JinaReader,
+    /// Built-in tag stripping fallback
+    SimpleFallback,
+    /// Text extraction from a PDF document
+    PdfText,
+    /// DOCX document converted to Markdown
+    Docx,
+    /// AsciiDoc document converted to Markdown
+    #[serde(rename = "asciidoc")]
+    AsciiDoc,
+    /// Text document used as it is
+    Passthrough,
+}
+
+impl From<DocumentType> for ConversionMethod {
+    fn from(document_type: DocumentType) -> Self {
+        match document_type {
+            DocumentType::Pdf => ConversionMethod::PdfText,
+            DocumentType::Docx => ConversionMethod::Docx,
+            DocumentType::AsciiDoc => ConversionMethod::AsciiDoc,
+            DocumentType::Markdown | DocumentType::Text | DocumentType::Rst => ConversionMethod::Passthrough,
+        }
+    }
+}
+
+impl From<&TransformMethod> for ConversionMethod {
+    fn from(method: &TransformMethod) -> Self {
+        match method {
+            TransformMethod::Htmd => ConversionMethod::Htmd,
+            TransformMethod::FastHtml2md => ConversionMethod::FastHtml2md,
+            TransformMethod::JinaReader => ConversionMethod::JinaReader,
+        }
+    }
+}
+
+/// Report for a single converted file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversionReport {
+    /// Source file that was converted
+    pub source: PathBuf,
+    /// Markdown file written, if conversion succeeded
+    pub output: Option<PathBuf>,
+    /// Method configured for the conversion
+    pub requested_method: TransformMethod,
+    /// Method that produced the output, if any
+    pub method: Option<ConversionMethod>,
+    /// Why the configured method was not used, if a fallback happened
+    pub fallback_reason: Option<String>,
+    /// Size of the source in bytes
+    pub input_bytes: u64,
+    /// Size of the Markdown output in bytes
+    pub output_bytes: u64,
+    /// Time spent converting in milliseconds
+    pub duration_ms: u64,
+    /// Why the conversion failed, if it did
+    pub error: Option<String>,
+    /// What main-content extraction kept of the page, if it ran
+    #[serde(default)]
+    pub extraction: Option<ContentExtraction>,
+}
+
+/// Report for the processing (cleanup and combine) stage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessingReport {
+    /// Markdown files that were combined, in order
+    pub input_files: Vec<PathBuf>,
+    /// Combined result file
+    pub output_file: PathBuf,
+    /// Size of the result file in bytes
+    pub bytes: u64,
+    /// Time spent processing in milliseconds
+    pub duration_ms: u64,
+    /// Result files of languages written separately, by language
+    #[serde(default)]
+    pub language_files: BTreeMap<String, PathBuf>,
+}
+
+/// Time spent in each pipeline stage in milliseconds
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StageTimings {
+    /// Crawling and downloading
+    pub crawl_ms: u64,
+    /// Converting to Markdown
+    pub convert_ms: u64,
+    /// Cleaning and combining
+    pub process_ms: u64,
+}
+
+/// Report of a single run of an entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunReport {
+    /// Name of the entry
+    pub entry: String,
+    /// URL of the entry
+    pub url: String,
+    /// Version of the entry the run started from
+    pub version: u32,
+    /// Revision of the source that was read (the commit of a git source)
+    #[serde(default)]
+    pub revision: Option<String>,
+    /// Where the pages came from
+    #[serde(default)]
+    pub mode: RunMode,
+    /// When the run started
+    pub started_at: DateTime<Utc>,
+    /// When the run finished
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Number of attempts made (including retries)
+    pub attempts: u32,
+    /// Pages received from the crawler
+    pub pages: Vec<PageReport>,
+    /// Limit that stopped the crawl before every page was fetched
+    #[serde(default)]
+    pub truncated: Option<BudgetLimit>,
+    /// WARC archive of the crawl, if one was written
+    #[serde(default)]
+    pub warc_file: Option<PathBuf>,
+    /// Documentation version the crawl was confined to, if the entry names one
+    #[serde(default)]
+    pub docs_version: Option<DocsVersion>,
+    /// Files converted to Markdown
+    pub conversions: Vec<ConversionReport>,
+    /// Result of the processing stage
+    pub processing: Option<ProcessingReport>,
+    /// Time spent in each stage
+    pub timings: StageTimings,
+    /// Non-fatal problems noticed during the run
+    pub warnings: Vec<String>,
+    /// Files written by the run (Markdown parts and results)
+    pub output_files: Vec<PathBuf>,
+    /// Final result file, if the run succeeded
+    pub result_file: Option<PathBuf>,
+    /// Error that ended the run, if it failed
+    pub error: Option<String>,
+}
+
+impl RunReport {
+    /// Start a new report for the given entry
+    pub fn new(entry: &UrlEntry) -> Self {
+        Self {
+            entry: entry.name.clone(),
+            url: entry.url.clone(),
+            version: entry.version,
+            revision: None,
+            mode: RunMode::Crawl,
+            started_at: Utc::now(),
+            finished_at: None,
+            attempts: 0,
+            pages: Vec::new(),
+            truncated: None,
+            warc_file: None,
+            docs_version: None,
+            conversions: Vec::new(),
+            processing: None,
+            timings: StageTimings::default(),
+            warnings: Vec::new(),
+            output_files: Vec::new(),
+            result_file: None,
+            error: None,
+        }
+    }
+
+    /// Reset the per-attempt data before a retry, keeping the start time
+    pub(crate) fn start_attempt(&mut self) {
+        self.attempts += 1;
+        self.pages.clear();
+        self.truncated = None;
+        self.warc_file = None;
+        self.docs_version = None;
+        self.conversions.clear();
+        self.processing = None;
+        self.timings = StageTimings::default();
+        self.warnings.clear();
+        self.output_files.clear();
+        self.result_file = None;
+        self.error = None;
+    }
+
+    /// Mark the report as finished
+    pub(crate) fn finish(&mut self) {
+        self.finished_at = Some(Utc::now());
+    }
+
+    /// Number of pages that were saved
+    pub fn pages_saved(&self) -> usize {
+        self.pages
+            .iter()
+            .filter(|page| page.outcome == PageOutcome::Saved)
+            .count()
+    }
+
+    /// Number of pages that were unchanged since the last run
+    pub fn pages_unchanged(&self) -> usize {
+        self.pages
+            .iter()
+            .filter(|page| page.outcome == PageOutcome::Unchanged)
+            .count()
+    }
+
+    /// Number of conversions that used a fallback method
+    pub fn fallback_conversions(&self) -> usize {
+        self.conversions
+            .iter()
+            .filter(|conversion| conversion.fallback_reason.is_some())
+            .count()
+    }
+
+    /// Location of the report file for an entry inside its results directory
+    pub fn path_in(result_dir: &Path, entry_name: &str) -> PathBuf {
+        result_dir.join(format!("{}.report.json", entry_name))
+    }
+
+    /// Save the report as pretty-printed JSON
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .io_path_context("Failed to create report directory", parent)?;
+        }
+
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| DoclingError::config_with("Failed to serialize run report", e))?;
+
+        std::fs::write(path, json).io_path_context("Failed to write run report", path)
+    }
+
+    /// Load a previously saved report
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path).io_path_context("Failed to read run report", path)?;
+
+        serde_json::from_str(&json)
+            .map_err(|e| DoclingError::config_with("Failed to parse run report", e))
+    }
+}
+
+/// Convert a duration to whole milliseconds for reports
+pub(crate) fn millis(duration: Duration) -> u64 {
+    duration.as_millis().min(u64::MAX as u128) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(url: &str, outcome: PageOutcome) -> PageReport {
+        PageReport {
+            url: url.to_string(),
+            outcome,
+            http_status: Some(200),
+            content_type: Some("text/html".to_string()),
+            bytes: 120,
+            saved_path: None,
+            encoding: None,
+            encoding_source: None,
+            error: None,
+            duplicate_of: None,
+            language: None,
+        }
+    }
+
+    #[test]
+    fn reports_are_saved_and_loaded() {
+        let entry = UrlEntry::new("https://example.com/docs/", "docs", 1).unwrap();
+        let mut report = RunReport::new(&entry);
+        report.mode = RunMode::Replay;
+        report.start_attempt();
+        report.pages.push(page("https://example.com/docs/", PageOutcome::Saved));
+        report.truncated = Some(BudgetLimit::Pages);
+        report.warnings.push("Conversion fell back".to_string());
+        report.finish();
+
+        let dir = std::env::temp_dir().join(format!("docling-report-{}", std::process::id()));
+        let path = RunReport::path_in(&dir.join("results"), "docs");
+        report.save(&path).unwrap();
+        let loaded = RunReport::load(&path).unwrap();
+        assert_eq!(loaded.entry, "docs");
+        assert_eq!(loaded.mode, RunMode::Replay);
+        assert_eq!(loaded.attempts, 1);
+        assert_eq!(loaded.pages_saved(), 1);
+        assert_eq!(loaded.truncated, Some(BudgetLimit::Pages));
+        assert_eq!(loaded.warnings, report.warnings);
+        assert_eq!(loaded.finished_at, report.finished_at);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn retries_start_from_a_clean_attempt() {
+        let entry = UrlEntry::new("https://example.com/docs/", "docs", 1).unwrap();
+        let mut report = RunReport::new(&entry);
+        let started_at = report.started_at;
+        report.start_attempt();
+        report.pages.push(page("https://example.com/docs/", PageOutcome::Saved));
+        report.pages.push(page("https://example.com/docs/old", PageOutcome::Unchanged));
+        report.error = Some("HTTP 503 for 'https://example.com/docs/'".to_string());
+
+        report.start_attempt();
+        assert_eq!(report.attempts, 2);
+        assert_eq!(report.started_at, started_at);
+        assert_eq!(report.pages_saved(), 0);
+        assert_eq!(report.pages_unchanged(), 0);
+        assert!(report.error.is_none());
+    }
+}
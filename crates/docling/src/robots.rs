@@ -0,0 +1,308 @@
+//! robots.txt parsing and matching
+//!
+//! Implements the parts of the Robots Exclusion Protocol (RFC 9309) the
+//! crawler needs: user-agent groups, `Allow`/`Disallow` rules with `*` and
+//! `$` wildcards (longest match wins, `Allow` wins ties), plus the widely
+//! used `Crawl-delay` and `Sitemap` extensions.
+
+use std::time::Duration;
+
+use regex::Regex;
+use tracing::{debug, warn};
+use url::Url;
+
+use crate::fetch::Fetcher;
+
+/// Longest `Crawl-delay` that is honoured; longer delays are cut to it
+pub const MAX_CRAWL_DELAY: Duration = Duration::from_secs(60);
+
+/// A single `Allow` or `Disallow` rule
+#[derive(Debug, Clone)]
+struct Rule {
+    /// Whether the rule allows matching paths
+    allow: bool,
+    /// Length of the original pattern, used to pick the most specific rule
+    specificity: usize,
+    /// Compiled pattern
+    pattern: Regex,
+}
+
+/// Rules of one user-agent group
+#[derive(Debug, Clone, Default)]
+struct Group {
+    /// User agents the group applies to (lowercase)
+    agents: Vec<String>,
+    /// Allow/Disallow rules
+    rules: Vec<Rule>,
+    /// Crawl-delay in seconds, if given
+    crawl_delay: Option<f64>,
+}
+
+/// Parsed robots.txt, already narrowed to the rules for one user agent
+#[derive(Debug, Clone, Default)]
+pub struct Robots {
+    /// Rules that apply to our user agent
+    rules: Vec<Rule>,
+    /// Crawl-delay that applies to our user agent
+    crawl_delay: Option<Duration>,
+    /// Sitemap URLs listed in the file
+    sitemaps: Vec<String>,
+}
+
+impl Robots {
+    /// Robots rules that allow everything (used when robots.txt is missing)
+    pub fn allow_all() -> Self {
+        Self::default()
+    }
+
+    /// Parse a robots.txt file and select the group matching `user_agent`
+    pub fn parse(content: &str, user_agent: &str) -> Self {
+        let mut groups: Vec<Group> = Vec::new();
+        let mut sitemaps = Vec::new();
+        // Whether the previous line was a user-agent line (consecutive ones share a group)
+        let mut in_agent_lines = false;
+
+        for raw_line in content.lines() {
+            let line = raw_line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_ascii_lowercase();
+            let value = value.trim();
+
+            match key.as_str() {
+                "user-agent" => {
+                    if !in_agent_lines {
+                        groups.push(Group::default());
+                    }
+                    if let Some(group) = groups.last_mut() {
+                        group.agents.push(value.to_ascii_lowercase());
+                    }
+                    in_agent_lines = true;
+                }
+                "allow" | "disallow" => {
+                    in_agent_lines = false;
+                    let Some(group) = groups.last_mut() else {
+                        continue;
+                    };
+                    // An empty Disallow means "allow everything" and adds no rule
+                    if value.is_empty() {
+                        continue;
+                    }
+                    if let Some(pattern) = compile_pattern(value) {
+                        group.rules.push(Rule {
+                            allow: key == "allow",
+                            specificity: value.len(),
+                            pattern,
+                        });
+                    }
+                }
+                "crawl-delay" => {
+                    in_agent_lines = false;
+                    if let Some(group) = groups.last_mut() {
+                        group.crawl_delay = value.parse::<f64>().ok().filter(|d| *d >= 0.0);
+                    }
+                }
+                "sitemap" => {
+                    // Sitemap lines are global and do not end a user-agent block
+                    if !value.is_empty() {
+                        sitemaps.push(value.to_string());
+                    }
+                }
+                _ => {
+                    in_agent_lines = false;
+                }
+            }
+        }
+
+        let selected = select_group(&groups, user_agent);
+        Self {
+            rules: selected.map(|g| g.rules.clone()).unwrap_or_default(),
+            crawl_delay: selected.and_then(|g| g.crawl_delay).map(clamp_crawl_delay),
+            sitemaps,
+        }
+    }
+
+    /// Fetch and parse robots.txt for the host of `url`
+    ///
+    /// A missing or unreadable robots.txt allows everything.
+    pub async fn fetch(fetcher: &Fetcher, url: &Url, user_agent: &str) -> Self {
+        let Some(robots_url) = robots_url(url) else {
+            return Self::allow_all();
+        };
+
+        match fetcher.get(&robots_url).await {
+            Ok(response) if response.is_success() => {
+                debug!("Loaded robots.txt from {}", robots_url);
+                Self::parse(&String::from_utf8_lossy(&response.body), user_agent)
+            }
+            Ok(response) => {
+                debug!(
+                    "No usable robots.txt at {} (status {}), allowing everything",
+                    robots_url, response.status
+                );
+                Self::allow_all()
+            }
+            Err(e) => {
+                warn!("Failed to fetch {}: {}, allowing everything", robots_url, e);
+                Self::allow_all()
+            }
+        }
+    }
+
+    /// Whether the URL may be fetched
+    pub fn is_allowed(&self, url: &Url) -> bool {
+        let mut target = url.path().to_string();
+        if let Some(query) = url.query() {
+            target.push('?');
+            target.push_str(query);
+        }
+
+        // robots.txt itself is always allowed
+        if target == "/robots.txt" {
+            return true;
+        }
+
+        self.rules
+            .iter()
+            .filter(|rule| rule.pattern.is_match(&target))
+            .max_by_key(|rule| (rule.specificity, rule.allow))
+            .map(|rule| rule.allow)
+            .unwrap_or(true)
+    }
+
+    /// Crawl-delay requested for our user agent
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+
+    /// Sitemap URLs listed in robots.txt
+    pub fn sitemaps(&self) -> &[String] {
+        &self.sitemaps
+    }
+}
+
+/// URL of the robots.txt file for the host of `url`
+pub fn robots_url(url: &Url) -> Option<Url> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+    url.join("/robots.txt").ok()
+}
+
+/// Pick the group for our user agent: the longest matching agent token, else `*`
+fn select_group<'a>(groups: &'a [Group], user_agent: &str) -> Option<&'a Group> {
+    let user_agent = user_agent.to_ascii_lowercase();
+    // Match against the product token (the part before any '/' version)
+    let product = user_agent.split('/').next().unwrap_or(&user_agent).trim();
+
+    groups
+        .iter()
+        .filter_map(|group| {
+            group
+                .agents
+                .iter()
+                .filter(|agent| agent.as_str() != "*" && product.contains(agent.as_str()))
+                .map(|agent| agent.len())
+                .max()
+                .map(|len| (len, group))
+        })
+        .max_by_key(|(len, _)| *len)
+        .map(|(_, group)| group)
+        .or_else(|| groups.iter().find(|g| g.agents.iter().any(|a| a == "*")))
+}
+
+/// Crawl-delay in seconds as a duration of at most [`MAX_CRAWL_DELAY`]
+///
+/// Values too large for a duration, like `inf` or `1e30`, are cut as well.
+fn clamp_crawl_delay(seconds: f64) -> Duration {
+    Duration::try_from_secs_f64(seconds)
+        .unwrap_or(MAX_CRAWL_DELAY)
+        .min(MAX_CRAWL_DELAY)
+}
+
+/// Compile a robots.txt path pattern into an anchored regex
+fn compile_pattern(pattern: &str) -> Option<Regex> {
+    let (body, anchored_end) = match pattern.strip_suffix('$') {
+        Some(body) => (body, true),
+        None => (pattern, false),
+    };
+
+    let mut regex = String::from("^");
+    for (i, part) in body.split('*').enumerate() {
+        if i > 0 {
+            regex.push_str(".*");
+        }
+        regex.push_str(&regex::escape(part));
+    }
+    if anchored_end {
+        regex.push('$');
+    }
+
+    Regex::new(&regex).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROBOTS: &str = "\
+User-agent: *
+Disallow: /private/
+Allow: /private/public$
+Crawl-delay: 2
+
+User-agent: mi4uling-docling-bot
+User-agent: other-bot
+Disallow: /search
+Allow: /search/help
+Crawl-delay: 5
+
+Sitemap: https://example.com/sitemap.xml
+";
+
+    fn url(path: &str) -> Url {
+        Url::parse("https://example.com").unwrap().join(path).unwrap()
+    }
+
+    #[test]
+    fn selects_specific_group_over_wildcard() {
+        let robots = Robots::parse(ROBOTS, "mi4uling-docling-bot");
+        assert!(!robots.is_allowed(&url("/search?q=x")));
+        assert!(robots.is_allowed(&url("/search/help")));
+        assert!(robots.is_allowed(&url("/private/secret")));
+        assert_eq!(robots.crawl_delay(), Some(Duration::from_secs(5)));
+        assert_eq!(robots.sitemaps(), ["https://example.com/sitemap.xml"]);
+    }
+
+    #[test]
+    fn falls_back_to_wildcard_group() {
+        let robots = Robots::parse(ROBOTS, "SomeOtherCrawler/1.0");
+        assert!(!robots.is_allowed(&url("/private/secret")));
+        assert!(robots.is_allowed(&url("/private/public")));
+        assert!(!robots.is_allowed(&url("/private/public/more")));
+        assert!(robots.is_allowed(&url("/search")));
+        assert_eq!(robots.crawl_delay(), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn huge_crawl_delays_are_clamped() {
+        for delay in ["inf", "1e30", "86400"] {
+            let robots = Robots::parse(&format!("User-agent: *\nCrawl-delay: {}\n", delay), "bot");
+            assert_eq!(robots.crawl_delay(), Some(MAX_CRAWL_DELAY));
+        }
+        for delay in ["NaN", "-1", "soon"] {
+            let robots = Robots::parse(&format!("User-agent: *\nCrawl-delay: {}\n", delay), "bot");
+            assert_eq!(robots.crawl_delay(), None);
+        }
+        let robots = Robots::parse("User-agent: *\nCrawl-delay: 0.5\n", "bot");
+        assert_eq!(robots.crawl_delay(), Some(Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn wildcards_match_anywhere() {
+        let robots = Robots::parse("User-agent: *\nDisallow: /*.pdf$\n", "bot");
+        assert!(!robots.is_allowed(&url("/files/spec.pdf")));
+        assert!(robots.is_allowed(&url("/files/spec.pdf.html")));
+    }
+}
@@ -0,0 +1,129 @@
+//! Crawl scope rules
+//!
+//! Decides which discovered URLs belong to an entry. Every decision names the
+//! rule that made it, so excluded URLs can be reported together with the
+//! reason they were left out. Local sources are confined to the directory of
+//! the entry instead of its host.
+
+use std::fmt;
+
+use serde::Serialize;
+use url::Url;
+
+use crate::{DoclingError, Result, UrlEntry};
+
+/// Rule that excluded a URL from the crawl
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum ExclusionRule {
+    /// The URL uses a scheme the crawler cannot fetch
+    UnsupportedScheme {
+        /// Scheme of the URL
+        scheme: String,
+    },
+    /// The URL is on a different host than the entry
+    DifferentHost {
+        /// Host of the URL
+        host: String,
+    },
+    /// The local path is outside the directory of the entry
+    OutsideDirectory {
+        /// Path of the URL
+        path: String,
+    },
+}
+
+impl fmt::Display for ExclusionRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExclusionRule::UnsupportedScheme { scheme } => write!(f, "unsupported scheme '{}'", scheme),
+            ExclusionRule::DifferentHost { host } => write!(f, "different host '{}'", host),
+            ExclusionRule::OutsideDirectory { path } => write!(f, "outside the entry directory '{}'", path),
+        }
+    }
+}
+
+/// Outcome of checking a URL against the scope
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScopeDecision {
+    /// The URL belongs to the entry
+    Allowed,
+    /// The URL is outside the entry's scope
+    Excluded(ExclusionRule),
+}
+
+impl ScopeDecision {
+    /// Whether the URL is allowed
+    pub fn is_allowed(&self) -> bool {
+        matches!(self, ScopeDecision::Allowed)
+    }
+}
+
+/// Scope of a single entry's crawl
+#[derive(Debug, Clone)]
+pub struct Scope {
+    /// The entry URL the crawl starts from
+    root: Url,
+}
+
+impl Scope {
+    /// Build the scope for an entry
+    ///
+    /// # Errors
+    /// Returns an error if the entry URL is invalid
+    pub fn for_entry(entry: &UrlEntry) -> Result<Self> {
+        let root = Url::parse(&entry.url)
+            .map_err(|e| DoclingError::config_with(format!("Invalid URL: {}", entry.url), e))?;
+        Ok(Self { root })
+    }
+
+    /// The same scope for a crawl starting from another URL of the entry
+    pub fn with_root(self, root: Url) -> Self {
+        Self { root }
+    }
+
+    /// The entry URL the crawl starts from
+    pub fn root(&self) -> &Url {
+        &self.root
+    }
+
+    /// Check whether a page URL belongs to the entry
+    pub fn check(&self, url: &Url) -> ScopeDecision {
+        if self.root.scheme() == "file" {
+            return self.check_local(url);
+        }
+
+        if !matches!(url.scheme(), "http" | "https") {
+            return ScopeDecision::Excluded(ExclusionRule::UnsupportedScheme {
+                scheme: url.scheme().to_string(),
+            });
+        }
+
+        if url.host_str() != self.root.host_str() {
+            return ScopeDecision::Excluded(ExclusionRule::DifferentHost {
+                host: url.host_str().unwrap_or_default().to_string(),
+            });
+        }
+
+        ScopeDecision::Allowed
+    }
+
+    /// Local sources include everything below the directory of the entry
+    fn check_local(&self, url: &Url) -> ScopeDecision {
+        if url.scheme() != "file" {
+            return ScopeDecision::Excluded(ExclusionRule::UnsupportedScheme {
+                scheme: url.scheme().to_string(),
+            });
+        }
+
+        let root_path = self.root.path();
+        let directory = &root_path[..root_path.rfind('/').map_or(0, |i| i + 1)];
+        if url.host_str() != self.root.host_str() || !url.path().starts_with(directory) {
+            return ScopeDecision::Excluded(ExclusionRule::OutsideDirectory {
+                path: url.path().to_string(),
+            });
+        }
+
+        ScopeDecision::Allowed
+    }
+}
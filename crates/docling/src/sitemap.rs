@@ -0,0 +1,360 @@
+//! Sitemap discovery and parsing
+//!
+//! Locates the sitemaps of a site (robots.txt `Sitemap:` lines, falling back
+//! to `sitemap.xml` next to the entry URL and at the host root), follows
+//! sitemap indexes and gzip-compressed files, and collects the page URLs they
+//! list. Plain-text sitemaps (one URL per line) are accepted as well.
+
+use std::borrow::Cow;
+use std::collections::{HashSet, VecDeque};
+use std::io::Read;
+
+use flate2::read::GzDecoder;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::fetch::Fetcher;
+use crate::local;
+use crate::robots::Robots;
+use crate::{DoclingError, Result};
+
+/// Maximum number of sitemap files fetched for one entry (including indexes)
+const MAX_SITEMAPS: usize = 100;
+
+/// Maximum number of page URLs collected from all sitemaps of one entry
+const MAX_SITEMAP_URLS: usize = 100_000;
+
+/// Maximum size of a decompressed sitemap (the protocol allows 50 MiB)
+const MAX_SITEMAP_BYTES: u64 = 50 * 1024 * 1024;
+
+/// Magic bytes at the start of a gzip stream
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// A page listed in a sitemap
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SitemapUrl {
+    /// URL of the page
+    pub loc: String,
+    /// Last modification date as given in the sitemap, if any
+    pub lastmod: Option<String>,
+}
+
+/// Contents of a single sitemap file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Sitemap {
+    /// A `<urlset>` listing pages
+    UrlSet(Vec<SitemapUrl>),
+    /// A `<sitemapindex>` listing further sitemaps
+    Index(Vec<String>),
+}
+
+/// A page found through sitemap discovery
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SitemapPage {
+    /// Absolute URL of the page
+    pub url: Url,
+    /// Sitemap the page was listed in
+    pub sitemap: Url,
+    /// Last modification date as given in the sitemap, if any
+    pub lastmod: Option<String>,
+}
+
+/// Result of looking for the sitemaps of a site
+#[derive(Debug, Clone, Default)]
+pub struct SitemapDiscovery {
+    /// Sitemaps that listed pages, in the order they were read
+    pub sitemaps: Vec<Url>,
+    /// Pages listed in the sitemaps
+    pub pages: Vec<SitemapPage>,
+}
+
+impl SitemapDiscovery {
+    /// Whether no pages were found
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+}
+
+/// Find and read the sitemaps for an entry URL
+///
+/// Sitemaps listed in robots.txt are used if there are any; otherwise
+/// `sitemap.xml` is tried next to the entry URL and at the root of the host.
+/// Missing or invalid sitemaps are logged and skipped, so the result may be
+/// empty but discovery never fails.
+///
+/// # Arguments
+/// * `fetcher` - The HTTP fetcher to use
+/// * `root` - The entry URL
+/// * `robots` - robots.txt rules of the entry's host
+pub async fn discover(fetcher: &Fetcher, root: &Url, robots: &Robots) -> SitemapDiscovery {
+    let mut discovery = SitemapDiscovery::default();
+    let mut queue: VecDeque<Url> = candidate_urls(root, robots).into();
+    let mut visited = HashSet::new();
+
+    while let Some(sitemap_url) = queue.pop_front() {
+        if !visited.insert(sitemap_url.to_string()) {
+            continue;
+        }
+        if visited.len() > MAX_SITEMAPS {
+            warn!("Stopping sitemap discovery after {} sitemaps", MAX_SITEMAPS);
+            break;
+        }
+
+        let response = match fetcher.get(&sitemap_url).await {
+            Ok(response) if response.is_success() => response,
+            Ok(response) => {
+                debug!("No sitemap at {} (status {})", sitemap_url, response.status);
+                continue;
+            }
+            Err(e) => {
+                warn!("Failed to fetch sitemap {}: {}", sitemap_url, e);
+                continue;
+            }
+        };
+
+        let sitemap = decompress(&response.body)
+            .map_err(|e| DoclingError::conversion_with(format!("Invalid gzip data in {}", sitemap_url), e))
+            .and_then(|body| parse(&body));
+
+        match sitemap {
+            Ok(Sitemap::Index(locations)) => {
+                debug!("Sitemap index {} lists {} sitemaps", sitemap_url, locations.len());
+                queue.extend(locations.iter().filter_map(|loc| response.final_url.join(loc).ok()));
+            }
+            Ok(Sitemap::UrlSet(urls)) => {
+                debug!("Sitemap {} lists {} pages", sitemap_url, urls.len());
+                for entry in urls {
+                    if discovery.pages.len() >= MAX_SITEMAP_URLS {
+                        warn!("Ignoring sitemap pages beyond the first {}", MAX_SITEMAP_URLS);
+                        break;
+                    }
+                    if let Ok(url) = response.final_url.join(&entry.loc) {
+                        discovery.pages.push(SitemapPage {
+                            url,
+                            sitemap: sitemap_url.clone(),
+                            lastmod: entry.lastmod,
+                        });
+                    }
+                }
+                discovery.sitemaps.push(sitemap_url);
+            }
+            Err(e) => warn!("Skipping sitemap {}: {}", sitemap_url, e),
+        }
+    }
+
+    info!(
+        "Found {} pages in {} sitemaps for {}",
+        discovery.pages.len(),
+        discovery.sitemaps.len(),
+        root
+    );
+    discovery
+}
+
+/// Sitemap URLs to try for an entry
+fn candidate_urls(root: &Url, robots: &Robots) -> Vec<Url> {
+    let listed: Vec<Url> = robots
+        .sitemaps()
+        .iter()
+        .filter_map(|sitemap| Url::parse(sitemap).ok())
+        .collect();
+    if !listed.is_empty() {
+        return listed;
+    }
+
+    // Documentation often lives below the host root with its own sitemap;
+    // the root of a local source is the root of the file system
+    let paths: &[&str] = if local::is_local(root) {
+        &["sitemap.xml"]
+    } else {
+        &["sitemap.xml", "/sitemap.xml"]
+    };
+    let mut candidates = Vec::new();
+    for url in paths
+        .iter()
+        .filter_map(|path| root.join(path).ok())
+    {
+        if !candidates.contains(&url) {
+            candidates.push(url);
+        }
+    }
+    candidates
+}
+
+/// Decompress a gzip-compressed sitemap, leaving other content untouched
+pub fn decompress(body: &[u8]) -> std::io::Result<Cow<'_, [u8]>> {
+    if !body.starts_with(&GZIP_MAGIC) {
+        return Ok(Cow::Borrowed(body));
+    }
+
+    let mut decompressed = Vec::new();
+    GzDecoder::new(body)
+        .take(MAX_SITEMAP_BYTES)
+        .read_to_end(&mut decompressed)?;
+    Ok(Cow::Owned(decompressed))
+}
+
+/// Parse an XML or plain-text sitemap
+///
+/// # Errors
+/// Returns an error if the content is neither a `<urlset>`, a
+/// `<sitemapindex>` nor a list of URLs
+pub fn parse(content: &[u8]) -> Result<Sitemap> {
+    let text = String::from_utf8_lossy(content);
+    let trimmed = text.trim_start_matches('\u{feff}').trim_start();
+
+    if !trimmed.starts_with('<') {
+        // Plain-text sitemap: one absolute URL per line
+        let urls: Vec<SitemapUrl> = trimmed
+            .lines()
+            .map(str::trim)
+            .filter(|line| Url::parse(line).is_ok())
+            .map(|line| SitemapUrl {
+                loc: line.to_string(),
+                lastmod: None,
+            })
+            .collect();
+        if urls.is_empty() {
+            return Err(DoclingError::conversion("Sitemap is neither XML nor a list of URLs"));
+        }
+        return Ok(Sitemap::UrlSet(urls));
+    }
+
+    let mut reader = Reader::from_str(trimmed);
+    reader.config_mut().trim_text(true);
+
+    let mut is_index = None;
+    // Element whose text is being read (`loc` or `lastmod`)
+    let mut field: Option<Vec<u8>> = None;
+    let mut current = SitemapUrl {
+        loc: String::new(),
+        lastmod: None,
+    };
+    let mut urls = Vec::new();
+    let mut sitemaps = Vec::new();
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(element)) => {
+                let name = element.local_name().as_ref().to_vec();
+                match name.as_slice() {
+                    b"urlset" if is_index.is_none() => is_index = Some(false),
+                    b"sitemapindex" if is_index.is_none() => is_index = Some(true),
+                    b"loc" | b"lastmod" => field = Some(name),
+                    _ => {}
+                }
+            }
+            Ok(Event::Text(text)) => {
+                if let Some(name) = &field {
+                    let value = text
+                        .unescape()
+                        .map_err(|e| DoclingError::conversion_with("Invalid text in sitemap", e))?;
+                    set_field(&mut current, name, value.trim());
+                }
+            }
+            Ok(Event::CData(data)) => {
+                if let Some(name) = &field {
+                    let value = String::from_utf8_lossy(&data);
+                    set_field(&mut current, name, value.trim());
+                }
+            }
+            Ok(Event::End(element)) => match element.local_name().as_ref() {
+                b"loc" | b"lastmod" => field = None,
+                b"url" | b"sitemap" => {
+                    let entry = std::mem::replace(
+                        &mut current,
+                        SitemapUrl {
+                            loc: String::new(),
+                            lastmod: None,
+                        },
+                    );
+                    if entry.loc.is_empty() {
+                        continue;
+                    }
+                    if element.local_name().as_ref() == b"sitemap" {
+                        sitemaps.push(entry.loc);
+                    } else {
+                        urls.push(entry);
+                    }
+                }
+                _ => {}
+            },
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+            Err(e) => return Err(DoclingError::conversion_with("Invalid sitemap XML", e)),
+        }
+    }
+
+    match is_index {
+        Some(true) => Ok(Sitemap::Index(sitemaps)),
+        Some(false) => Ok(Sitemap::UrlSet(urls)),
+        None => Err(DoclingError::conversion(
+            "XML document is neither a <urlset> nor a <sitemapindex>",
+        )),
+    }
+}
+
+/// Store the text of a `<loc>` or `<lastmod>` element
+fn set_field(entry: &mut SitemapUrl, name: &[u8], value: &str) {
+    match name {
+        b"loc" => entry.loc.push_str(value),
+        b"lastmod" => entry.lastmod = Some(value.to_string()),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_urlset_and_index() {
+        let urlset = br#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <url><loc>https://example.com/docs/a?x=1&amp;y=2</loc><lastmod>2024-01-01</lastmod></url>
+  <url><loc><![CDATA[https://example.com/docs/b]]></loc></url>
+</urlset>"#;
+        assert_eq!(
+            parse(urlset).unwrap(),
+            Sitemap::UrlSet(vec![
+                SitemapUrl {
+                    loc: "https://example.com/docs/a?x=1&y=2".to_string(),
+                    lastmod: Some("2024-01-01".to_string()),
+                },
+                SitemapUrl {
+                    loc: "https://example.com/docs/b".to_string(),
+                    lastmod: None,
+                },
+            ])
+        );
+
+        let index = br#"<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <sitemap><loc>https://example.com/sitemap-1.xml.gz</loc></sitemap>
+</sitemapindex>"#;
+        assert_eq!(
+            parse(index).unwrap(),
+            Sitemap::Index(vec!["https://example.com/sitemap-1.xml.gz".to_string()])
+        );
+    }
+
+    #[test]
+    fn parses_plain_text_and_gzip() {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        let text = "https://example.com/a\nhttps://example.com/b\n";
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(text.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let body = decompress(&compressed).unwrap();
+        match parse(&body).unwrap() {
+            Sitemap::UrlSet(urls) => assert_eq!(urls.len(), 2),
+            other => panic!("unexpected sitemap {:?}", other),
+        }
+        assert!(parse(b"<html><body>Not found</body></html>").is_err());
+    }
+}
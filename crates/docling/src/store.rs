@@ -0,0 +1,154 @@
+//! Persistent storage for URL entries
+//!
+//! Entries are kept in a single `entries.toml` file inside the configured
+//! inputs directory. All read-modify-write cycles go through [`EntryStore::update`],
+//! which holds a lock for the duration of the cycle so concurrent callers
+//! (e.g. several web requests) cannot overwrite each other's changes. The
+//! file is replaced by renaming a fully written temporary file over it.
+
+use std::fs::create_dir_all;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use crate::{DoclingError, IoContext, Result, UrlEntries, UrlEntry};
+
+/// File name of the entry store inside the inputs directory
+pub const ENTRIES_FILE_NAME: &str = "entries.toml";
+
+/// Store of URL entries backed by a TOML file
+#[derive(Debug)]
+pub struct EntryStore {
+    /// Path of the `entries.toml` file
+    path: PathBuf,
+    /// Serialises read-modify-write cycles on the file
+    lock: Mutex<()>,
+}
+
+impl EntryStore {
+    /// Create a store for the `entries.toml` file in the given inputs directory
+    pub fn new(inputs_path: impl AsRef<Path>) -> Self {
+        Self {
+            path: inputs_path.as_ref().join(ENTRIES_FILE_NAME),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Path of the backing `entries.toml` file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Load all entries, returning an empty collection if the file does not exist
+    pub fn load(&self) -> Result<UrlEntries> {
+        let _guard = self.guard();
+        self.read()
+    }
+
+    /// Replace all entries with the given collection
+    pub fn save(&self, entries: &UrlEntries) -> Result<()> {
+        let _guard = self.guard();
+        self.write(entries)
+    }
+
+    /// Get a copy of a single entry by name
+    pub fn get(&self, name: &str) -> Result<UrlEntry> {
+        self.load()?
+            .entries
+            .get(name)
+            .cloned()
+            .ok_or_else(|| DoclingError::NotFound {
+                name: name.to_string(),
+            })
+    }
+
+    /// Load the entries, apply `f` and save the result atomically
+    ///
+    /// Nothing is written if `f` returns an error.
+    pub fn update<R>(&self, f: impl FnOnce(&mut UrlEntries) -> Result<R>) -> Result<R> {
+        let _guard = self.guard();
+        let mut entries = self.read()?;
+        let result = f(&mut entries)?;
+        self.write(&entries)?;
+        Ok(result)
+    }
+
+    /// Apply `f` to a single entry and save the result atomically
+    pub fn update_entry<R>(&self, name: &str, f: impl FnOnce(&mut UrlEntry) -> R) -> Result<R> {
+        self.update(|entries| {
+            let entry = entries
+                .entries
+                .get_mut(name)
+                .ok_or_else(|| DoclingError::NotFound {
+                    name: name.to_string(),
+                })?;
+            Ok(f(entry))
+        })
+    }
+
+    fn guard(&self) -> MutexGuard<'_, ()> {
+        // A panic while holding the lock cannot leave the file half-written,
+        // so a poisoned lock is safe to reuse
+        self.lock.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn read(&self) -> Result<UrlEntries> {
+        if !self.path.exists() {
+            return Ok(UrlEntries::default());
+        }
+
+        let toml_string = std::fs::read_to_string(&self.path)
+            .io_path_context("Failed to read URL entries from file", &self.path)?;
+
+        toml::from_str(&toml_string)
+            .map_err(|e| DoclingError::config_with("Failed to parse URL entries from TOML", e))
+    }
+
+    fn write(&self, entries: &UrlEntries) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            create_dir_all(parent).io_path_context("Failed to create inputs directory", parent)?;
+        }
+
+        let toml_string = toml::to_string(entries)
+            .map_err(|e| DoclingError::config_with("Failed to serialize URL entries to TOML", e))?;
+
+        // Write a temporary file and rename it into place, so readers never
+        // see a half-written file
+        let temp_path = self.path.with_extension("toml.tmp");
+        std::fs::write(&temp_path, toml_string)
+            .io_path_context("Failed to write URL entries to file", &temp_path)?;
+        std::fs::rename(&temp_path, &self.path).io_path_context("Failed to replace URL entries file", &self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn updates_are_saved_and_failed_updates_are_not() {
+        let dir = std::env::temp_dir().join(format!("docling-store-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let store = EntryStore::new(dir.join("inputs"));
+        assert!(store.load().unwrap().entries.is_empty());
+
+        let entry = UrlEntry::new("https://example.com/docs", "example", 2).unwrap();
+        store
+            .update(|entries| {
+                entries.entries.insert(entry.name.clone(), entry.clone());
+                Ok(())
+            })
+            .unwrap();
+        store.update_entry("example", |entry| entry.crawl_depth = 3).unwrap();
+        let failed: Result<()> = store.update(|entries| {
+            entries.entries.clear();
+            Err(DoclingError::config("rejected"))
+        });
+        assert!(failed.is_err());
+
+        assert_eq!(store.get("example").unwrap().crawl_depth, 3);
+        assert!(matches!(store.get("missing"), Err(DoclingError::NotFound { .. })));
+        assert!(store.path().exists());
+        assert!(!store.path().with_extension("toml.tmp").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
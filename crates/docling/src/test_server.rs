@@ -0,0 +1,90 @@
+//! Minimal HTTP server for tests
+//!
+//! Answers every request on a local port with the response a handler builds
+//! from the request head, and remembers the request heads so tests can check
+//! the headers that were sent. Every connection serves one request.
+
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use url::Url;
+
+/// Builds the raw response to a request from its head
+type Handler = dyn Fn(&str) -> Vec<u8> + Send + Sync;
+
+/// HTTP server answering requests on a local port
+pub(crate) struct TestServer {
+    /// Base URL of the server
+    pub url: Url,
+    /// Heads of the requests received, in order
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl TestServer {
+    /// Start a server answering with `handler` until the runtime stops
+    pub async fn start(handler: impl Fn(&str) -> Vec<u8> + Send + Sync + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind test server");
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().expect("local address"))).expect("valid URL");
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let handler: Arc<Handler> = Arc::new(handler);
+
+        let received = Arc::clone(&requests);
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let handler = Arc::clone(&handler);
+                let received = Arc::clone(&received);
+                tokio::spawn(async move {
+                    let mut head = Vec::new();
+                    let mut buffer = [0; 1024];
+                    while !head.ends_with(b"\r\n\r\n") {
+                        match stream.read(&mut buffer).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(read) => head.extend_from_slice(&buffer[..read]),
+                        }
+                    }
+                    let head = String::from_utf8_lossy(&head).into_owned();
+                    let response = handler(&head);
+                    received.lock().unwrap_or_else(|e| e.into_inner()).push(head);
+                    // The client may hang up before reading everything
+                    let _ = stream.write_all(&response).await;
+                    let _ = stream.shutdown().await;
+                });
+            }
+        });
+
+        Self { url, requests }
+    }
+
+    /// URL of a path on the server
+    pub fn url(&self, path: &str) -> Url {
+        self.url.join(path).expect("valid path")
+    }
+
+    /// Heads of the requests received so far, lowercase
+    pub fn requests(&self) -> Vec<String> {
+        self.requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|head| head.to_ascii_lowercase())
+            .collect()
+    }
+}
+
+/// Raw response with the given status line, headers and body
+pub(crate) fn response(status: &str, headers: &[(&str, &str)], body: &[u8]) -> Vec<u8> {
+    let mut raw = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n", status, body.len());
+    for (name, value) in headers {
+        raw.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    raw.push_str("\r\n");
+    let mut raw = raw.into_bytes();
+    raw.extend_from_slice(body);
+    raw
+}
+
+/// Path requested in a request head
+pub(crate) fn request_path(head: &str) -> &str {
+    head.split_whitespace().nth(1).unwrap_or("/")
+}
@@ -0,0 +1,398 @@
+//! Versions of versioned documentation
+//!
+//! Documentation built with Docusaurus, Read the Docs, docs.rs or mike keeps
+//! its releases side by side, under paths like `/en/stable/`,
+//! `/serde/1.0.1/`, `/docs/2.0/` or `/v3/`. An entry with a target version in
+//! its [`VersionSettings`] is confined to one of them: the path segment that
+//! holds the version (the version slot) is found from the entry URL and the
+//! links of its version switcher, the target is resolved against the versions
+//! the site offers, and pages of every other version are left out of the
+//! crawl. The version that was crawled is recorded as a [`DocsVersion`].
+
+use std::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{DoclingError, Result};
+
+/// Target that follows the newest version of the documentation
+pub const LATEST: &str = "latest";
+
+/// Path segments naming moving versions rather than releases
+const VERSION_ALIASES: [&str; 6] = ["latest", "stable", "current", "next", "dev", "nightly"];
+
+/// Aliases of the newest release, in order of preference
+///
+/// `stable` comes first because Read the Docs builds `latest` from the
+/// development branch.
+const LATEST_ALIASES: [&str; 3] = ["stable", "latest", "current"];
+
+/// Number of leading path segments searched for the version slot
+const MAX_SLOT: usize = 3;
+
+/// Version of the documentation kept from an entry
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VersionSettings {
+    /// Version to crawl: `latest` or a version such as `2.1` or `v3`;
+    /// none crawls every version
+    #[serde(default)]
+    pub target: Option<String>,
+}
+
+impl VersionSettings {
+    /// Settings for an entry: the entry's own settings replace the global ones
+    pub fn for_entry(&self, entry: Option<&VersionSettings>) -> VersionSettings {
+        entry.unwrap_or(self).clone()
+    }
+}
+
+/// Documentation version a crawl was confined to
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DocsVersion {
+    /// Version the entry asked for
+    pub requested: String,
+    /// Version that was crawled, as the pages declare it or their URLs name it
+    pub resolved: String,
+    /// Version segment of the crawled URLs; `None` if they have none
+    pub path_segment: Option<String>,
+    /// URL the crawl started from
+    pub root: String,
+    /// Versions the site offers, as its URLs name them
+    #[serde(default)]
+    pub available: Vec<String>,
+}
+
+/// Whether a path segment names a documentation version
+///
+/// Versions are aliases like `stable` or numbers with a dot or a `v` prefix
+/// (`2.0`, `1.x`, `v3`, `v1.2.0-rc1`); bare numbers are too often years or
+/// page numbers.
+pub fn is_version_segment(segment: &str) -> bool {
+    let segment = segment.to_ascii_lowercase();
+    if VERSION_ALIASES.contains(&segment.as_str()) {
+        return true;
+    }
+    let (prefixed, number) = match segment.strip_prefix('v') {
+        Some(number) => (true, number),
+        None => (false, segment.as_str()),
+    };
+    let release = number.split(['-', '+']).next().unwrap_or_default();
+    let parts: Vec<&str> = release.split('.').collect();
+    let is_number = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+    (prefixed || parts.len() > 1)
+        && is_number(parts[0])
+        && parts[1..].iter().all(|part| *part == "x" || is_number(part))
+}
+
+/// Whether two version names are the same version (`v2.0` is `2.0`)
+pub fn same_version(a: &str, b: &str) -> bool {
+    normalize(a) == normalize(b)
+}
+
+/// Lowercase version name without a `v` prefix
+fn normalize(version: &str) -> String {
+    let version = version.trim().to_ascii_lowercase();
+    match version.strip_prefix('v') {
+        Some(number) if number.starts_with(|c: char| c.is_ascii_digit()) => number.to_string(),
+        _ => version,
+    }
+}
+
+/// Numbers of a release for ordering; `None` for aliases and pre-releases
+///
+/// A wildcard like the `x` of `1.x` sorts above every number.
+fn release_key(version: &str) -> Option<Vec<u64>> {
+    let version = normalize(version);
+    if version.contains(['-', '+']) {
+        return None;
+    }
+    version
+        .split('.')
+        .map(|part| if part == "x" { Some(u64::MAX) } else { part.parse().ok() })
+        .collect()
+}
+
+/// Non-empty path segments of a URL
+fn path_segments(url: &Url) -> Vec<String> {
+    url.path_segments()
+        .map(|segments| segments.filter(|segment| !segment.is_empty()).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Where the version sits in the URLs of a site
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionScheme {
+    /// Host of the site
+    host: Option<String>,
+    /// Path segments before the version
+    prefix: Vec<String>,
+}
+
+impl VersionScheme {
+    /// Detect the version slot from the entry URL and the links of its page
+    ///
+    /// The slot is the first leading path segment of the entry URL that
+    /// names a version. If none does (the newest version of a Docusaurus
+    /// site has no segment), it is where the links of the page name the most
+    /// different versions, if they name at least two.
+    pub fn detect(root: &Url, links: &[Url]) -> Option<Self> {
+        let segments = path_segments(root);
+        let host = root.host_str().map(str::to_string);
+        if let Some(slot) = segments.iter().take(MAX_SLOT).position(|segment| is_version_segment(segment)) {
+            return Some(Self {
+                host,
+                prefix: segments[..slot].to_vec(),
+            });
+        }
+
+        (0..=segments.len().min(MAX_SLOT - 1))
+            .map(|slot| {
+                let scheme = Self {
+                    host: host.clone(),
+                    prefix: segments[..slot].to_vec(),
+                };
+                let mut versions: Vec<String> = links.iter().filter_map(|link| scheme.version_of(link)).collect();
+                versions.sort();
+                versions.dedup();
+                (versions.len(), scheme)
+            })
+            .filter(|(count, _)| *count >= 2)
+            // The shorter prefix wins ties
+            .max_by(|a, b| a.0.cmp(&b.0).then_with(|| b.1.prefix.len().cmp(&a.1.prefix.len())))
+            .map(|(_, scheme)| scheme)
+    }
+
+    /// Version segment of a URL, if it is on the site and has one
+    pub fn version_of(&self, url: &Url) -> Option<String> {
+        if url.host_str() != self.host.as_deref() {
+            return None;
+        }
+        let segments = path_segments(url);
+        if segments.len() <= self.prefix.len() || segments[..self.prefix.len()] != self.prefix[..] {
+            return None;
+        }
+        Some(segments[self.prefix.len()].clone()).filter(|segment| is_version_segment(segment))
+    }
+
+    /// The same page in another version, if the URL has a version segment
+    pub fn with_version(&self, url: &Url, version: &str) -> Option<Url> {
+        self.version_of(url)?;
+        let mut segments: Vec<String> = url.path_segments()?.map(str::to_string).collect();
+        let slot = segments
+            .iter()
+            .enumerate()
+            .filter(|(_, segment)| !segment.is_empty())
+            .nth(self.prefix.len())
+            .map(|(index, _)| index)?;
+        segments[slot] = version.to_string();
+        let mut versioned = url.clone();
+        versioned.path_segments_mut().ok()?.clear().extend(&segments);
+        Some(versioned)
+    }
+}
+
+/// Version of the documentation a crawl is confined to
+#[derive(Debug, Clone)]
+pub struct VersionFilter {
+    /// Where the version sits in the URLs of the site
+    scheme: VersionScheme,
+    /// Version segment of the kept pages; `None` keeps the pages without one
+    segment: Option<String>,
+}
+
+impl VersionFilter {
+    /// Version segment of a URL in another version than the kept one, if it is
+    pub fn other_version(&self, url: &Url) -> Option<String> {
+        let version = self.scheme.version_of(url)?;
+        match &self.segment {
+            Some(kept) if same_version(kept, &version) => None,
+            _ => Some(version),
+        }
+    }
+}
+
+/// A target version resolved against the versions a site offers
+#[derive(Debug, Clone)]
+pub struct Resolution {
+    /// URL the crawl starts from: the entry URL, or the same page in the target version
+    pub root: Url,
+    /// Leaves out the pages of other versions; `None` if the site shows no versions
+    pub filter: Option<VersionFilter>,
+    /// Versions the site offers, as its URLs name them
+    pub available: Vec<String>,
+}
+
+impl Resolution {
+    /// Record of the crawled version
+    ///
+    /// `declared` is the version the page at the new root declares, and
+    /// `served` the URL it was served from; docs.rs, for one, redirects
+    /// `latest` to the release it stands for.
+    pub fn docs_version(&self, requested: &str, declared: Option<String>, served: &Url) -> DocsVersion {
+        let path_segment = self.filter.as_ref().and_then(|filter| filter.segment.clone());
+        let served_release = self
+            .filter
+            .as_ref()
+            .and_then(|filter| filter.scheme.version_of(served))
+            .filter(|version| release_key(version).is_some());
+        let resolved = declared
+            .or(served_release)
+            .or_else(|| path_segment.clone())
+            .unwrap_or_else(|| requested.to_string());
+        DocsVersion {
+            requested: requested.to_string(),
+            resolved,
+            path_segment,
+            root: self.root.to_string(),
+            available: self.available.clone(),
+        }
+    }
+}
+
+/// Resolve a target version (see [`VersionSettings::target`]) for an entry URL
+///
+/// `links` are the links and switcher options of the page at the entry URL.
+/// `latest` keeps the entry URL's version if it is an alias of the newest
+/// release or if the entry URL has no version, and otherwise picks the
+/// newest version the site offers.
+///
+/// # Errors
+/// Returns an error if a named version is not offered by the site and the
+/// entry URL has no version segment to replace
+pub fn resolve(target: &str, root: &Url, links: &[Url]) -> Result<Resolution> {
+    let Some(scheme) = VersionScheme::detect(root, links) else {
+        return Ok(Resolution {
+            root: root.clone(),
+            filter: None,
+            available: Vec::new(),
+        });
+    };
+    let current = scheme.version_of(root);
+    let mut available: Vec<String> = Vec::new();
+    for version in links.iter().chain([root]).filter_map(|link| scheme.version_of(link)) {
+        if !available.iter().any(|known| same_version(known, &version)) {
+            available.push(version);
+        }
+    }
+    available.sort_by(|a, b| compare_versions(a, b));
+
+    let segment = if target.eq_ignore_ascii_case(LATEST) {
+        match &current {
+            // Pages without a version are the newest ones
+            None => None,
+            Some(version) if LATEST_ALIASES.contains(&version.to_ascii_lowercase().as_str()) => Some(version.clone()),
+            Some(version) => Some(newest(&available).unwrap_or_else(|| version.clone())),
+        }
+    } else {
+        match available.iter().find(|version| same_version(version, target)) {
+            Some(version) => Some(version.clone()),
+            None if current.is_some() => Some(target.to_string()),
+            None => {
+                return Err(DoclingError::config(format!(
+                    "Version '{}' not found, the site offers: {}",
+                    target,
+                    available.join(", ")
+                )));
+            }
+        }
+    };
+
+    // Start from the same page in the target version
+    let start = match (&segment, &current) {
+        (Some(segment), Some(current)) if !same_version(segment, current) => scheme.with_version(root, segment),
+        (Some(segment), None) => links
+            .iter()
+            .find(|link| scheme.version_of(link).is_some_and(|version| same_version(&version, segment)))
+            .cloned(),
+        _ => None,
+    };
+    Ok(Resolution {
+        root: start.unwrap_or_else(|| root.clone()),
+        filter: Some(VersionFilter { scheme, segment }),
+        available,
+    })
+}
+
+/// The newest version: an alias of the newest release, else the highest release
+fn newest(versions: &[String]) -> Option<String> {
+    LATEST_ALIASES
+        .iter()
+        .find_map(|alias| versions.iter().find(|version| version.eq_ignore_ascii_case(alias)))
+        .or_else(|| {
+            versions
+                .iter()
+                .filter_map(|version| Some((release_key(version)?, version)))
+                .max_by(|a, b| a.0.cmp(&b.0))
+                .map(|(_, version)| version)
+        })
+        .cloned()
+}
+
+/// Order of versions: releases from the newest down, then aliases and pre-releases by name
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    match (release_key(a), release_key(b)) {
+        (Some(a), Some(b)) => b.cmp(&a),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => a.cmp(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn urls(urls: &[&str]) -> Vec<Url> {
+        urls.iter().map(|url| Url::parse(url).unwrap()).collect()
+    }
+
+    #[test]
+    fn version_segments_are_recognised() {
+        for segment in ["v3", "2.0", "1.x", "v1.2.0-rc1", "stable", "Latest", "0.10.2"] {
+            assert!(is_version_segment(segment), "{}", segment);
+        }
+        for segment in ["2024", "docs", "intro", "v", "version", "1.", "main"] {
+            assert!(!is_version_segment(segment), "{}", segment);
+        }
+        assert!(same_version("v2.0", "2.0"));
+        assert_eq!(compare_versions("1.10", "1.9"), Ordering::Less);
+    }
+
+    #[test]
+    fn targets_resolve_against_the_offered_versions() {
+        // Read the Docs: the version is the second segment of the entry URL
+        let root = Url::parse("https://proj.readthedocs.io/en/v1.0/intro.html").unwrap();
+        let links = urls(&[
+            "https://proj.readthedocs.io/en/stable/",
+            "https://proj.readthedocs.io/en/v2.0/",
+            "https://proj.readthedocs.io/en/latest/",
+        ]);
+        let latest = resolve(LATEST, &root, &links).unwrap();
+        assert_eq!(latest.root.as_str(), "https://proj.readthedocs.io/en/stable/intro.html");
+        let filter = latest.filter.unwrap();
+        assert_eq!(filter.other_version(&links[1]), Some("v2.0".to_string()));
+        assert_eq!(filter.other_version(&links[0]), None);
+        assert_eq!(filter.other_version(&Url::parse("https://proj.readthedocs.io/de/v2.0/").unwrap()), None);
+
+        // Docusaurus: the newest version has no segment, older ones are in the switcher
+        let root = Url::parse("https://example.com/docs/intro").unwrap();
+        let links = urls(&[
+            "https://example.com/docs/next/intro",
+            "https://example.com/docs/2.0/intro",
+            "https://example.com/docs/1.x/intro",
+            "https://example.com/blog/2024/release",
+        ]);
+        let latest = resolve(LATEST, &root, &links).unwrap();
+        assert_eq!(latest.root, root);
+        let filter = latest.filter.unwrap();
+        assert!(filter.other_version(&links[1]).is_some() && filter.other_version(&root).is_none());
+        assert_eq!(latest.available, vec!["2.0", "1.x", "next"]);
+        let named = resolve("v2.0", &root, &links).unwrap();
+        assert_eq!(named.root, links[1]);
+        assert!(resolve("3.0", &root, &links).is_err());
+
+        let unversioned = resolve(LATEST, &Url::parse("https://example.com/guide/").unwrap(), &[]).unwrap();
+        assert!(unversioned.filter.is_none());
+    }
+}
@@ -0,0 +1,730 @@
+//! WARC archives of crawls
+//!
+//! When enabled, every crawl of an entry is also written as a WARC 1.1 file
+//! (`<entry>-<timestamp>.warc.gz` in the entry's WARC directory): a
+//! `warcinfo` record followed by a `request` and a `response` record for
+//! every HTTP exchange, redirects and media included. Each record is its own
+//! gzip member, so standard tools can seek to a record directly.
+//!
+//! Requests are reconstructed from what the crawler sends, without any
+//! credentials of the entry. The [`WarcIndex`] maps every page URL to the
+//! record holding its last full response, so the saved copies can be traced
+//! back to the archive they came from.
+//!
+//! [`WarcReplay`] reads the archives back: the fetcher can answer requests
+//! with the archived responses instead of the network, which replays a crawl
+//! exactly as it was recorded.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use flate2::bufread::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use reqwest::header::{self, HeaderMap, HeaderName, HeaderValue};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::debug;
+use url::Url;
+
+use crate::fetch::{FetchResponse, Validators};
+use crate::manifest::{relative_to, to_hex, Manifest};
+use crate::{DoclingError, IoContext, Result};
+
+/// Name of the WARC index file in an entry's output directory
+pub const WARC_INDEX_FILE_NAME: &str = "warc_index.json";
+
+/// Counter making record ids unique within the process
+static RECORD_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Where a record is stored
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WarcLocation {
+    /// WARC file, relative to the entry's output directory
+    pub file: PathBuf,
+    /// Offset of the record's gzip member in the file
+    pub offset: u64,
+    /// Compressed length of the record
+    pub length: u64,
+    /// `WARC-Record-ID` of the record
+    pub record_id: String,
+    /// `WARC-Date` of the record
+    pub date: DateTime<Utc>,
+}
+
+/// A page in the WARC index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WarcIndexEntry {
+    /// Response record of the page
+    #[serde(flatten)]
+    pub location: WarcLocation,
+    /// HTTP status of the response
+    pub status: u16,
+    /// Copy of the page saved by the crawler, relative to the entry's output directory
+    pub saved_path: Option<PathBuf>,
+}
+
+/// Index of the WARC records of an entry's pages, keyed by page URL
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WarcIndex {
+    /// Latest full response of every page
+    pub pages: BTreeMap<String, WarcIndexEntry>,
+}
+
+impl WarcIndex {
+    /// Location of the WARC index inside an entry's output directory
+    pub fn path_in(entry_dir: &Path) -> PathBuf {
+        entry_dir.join(WARC_INDEX_FILE_NAME)
+    }
+
+    /// Load the WARC index of an entry, returning an empty one if none was saved yet
+    pub fn load(entry_dir: &Path) -> Result<Self> {
+        let path = Self::path_in(entry_dir);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let json = std::fs::read_to_string(&path).io_path_context("Failed to read WARC index", &path)?;
+        serde_json::from_str(&json)
+            .map_err(|e| DoclingError::config_with(format!("Failed to parse WARC index {}", path.display()), e))
+    }
+
+    /// Record the response record of a page
+    pub fn insert(&mut self, url: &str, location: WarcLocation, status: u16) {
+        self.pages.insert(
+            url.to_string(),
+            WarcIndexEntry {
+                location,
+                status,
+                saved_path: None,
+            },
+        );
+    }
+
+    /// Fill in the saved copies of the pages from the manifest
+    pub fn link_saved_paths(&mut self, manifest: &Manifest) {
+        for (url, entry) in &mut self.pages {
+            entry.saved_path = manifest.get(url).map(|record| record.saved_path.clone());
+        }
+    }
+
+    /// Save the WARC index into an entry's output directory
+    pub fn save(&self, entry_dir: &Path) -> Result<()> {
+        let path = Self::path_in(entry_dir);
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| DoclingError::config_with("Failed to serialize WARC index", e))?;
+        std::fs::write(&path, json).io_path_context("Failed to write WARC index", &path)
+    }
+}
+
+/// Open WARC file and the position of its end
+#[derive(Debug)]
+struct WarcFile {
+    /// The file being written
+    file: File,
+    /// Bytes written so far
+    offset: u64,
+}
+
+/// Writer for the WARC file of one crawl, shared by page and media downloads
+#[derive(Debug, Clone)]
+pub struct WarcWriter {
+    /// Location of the file
+    path: PathBuf,
+    /// Output directory of the entry, locations are relative to it
+    entry_dir: PathBuf,
+    /// User agent the requests were sent with
+    user_agent: String,
+    /// The open file
+    file: Arc<Mutex<WarcFile>>,
+}
+
+impl WarcWriter {
+    /// Start the WARC file of a crawl in `dir` and write its `warcinfo` record
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be created or written
+    pub fn create(dir: &Path, entry_dir: &Path, entry_name: &str, user_agent: &str) -> Result<Self> {
+        let started = Utc::now();
+        let path = dir.join(format!("{}-{}.warc.gz", entry_name, started.format("%Y%m%d%H%M%S")));
+        let file = File::create(&path).io_path_context("Failed to create WARC file", &path)?;
+        let writer = Self {
+            path,
+            entry_dir: entry_dir.to_path_buf(),
+            user_agent: user_agent.to_string(),
+            file: Arc::new(Mutex::new(WarcFile { file, offset: 0 })),
+        };
+
+        let info = format!(
+            "software: mi4ulings-docling/{}\r\nformat: WARC File Format 1.1\r\n\
+             conformsTo: http://iipc.github.io/warc-specifications/specifications/warc-format/warc-1.1/\r\n\
+             http-header-user-agent: {}\r\n",
+            env!("CARGO_PKG_VERSION"),
+            user_agent
+        );
+        let file_name = writer.path.file_name().map(|name| name.to_string_lossy().into_owned());
+        writer.write_record(
+            "warcinfo",
+            &[("WARC-Filename", file_name.unwrap_or_default())],
+            "application/warc-fields",
+            info.as_bytes(),
+            started,
+        )?;
+        Ok(writer)
+    }
+
+    /// Location of the WARC file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Write the requests and responses of a fetch, redirects included
+    ///
+    /// `body` is the body of the final response; the redirects are recorded
+    /// with their status and `Location` only.
+    ///
+    /// # Returns
+    /// The location of the final response record
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written
+    pub fn write_exchange(
+        &self,
+        response: &FetchResponse,
+        body: &[u8],
+        validators: Option<&Validators>,
+    ) -> Result<WarcLocation> {
+        let date = Utc::now();
+        for redirect in &response.redirects {
+            let (Ok(from), Ok(to)) = (Url::parse(&redirect.from), Url::parse(&redirect.to)) else {
+                continue;
+            };
+            let head = format!(
+                "{}{}: {}\r\n\r\n",
+                status_line(redirect.status),
+                header::LOCATION,
+                to
+            );
+            self.write_pair(&from, validators, head.as_bytes(), date, None)?;
+        }
+
+        let mut block = status_line(response.status).into_bytes();
+        for (name, value) in &response.headers {
+            // The body is stored de-chunked
+            if name == header::TRANSFER_ENCODING {
+                continue;
+            }
+            block.extend_from_slice(name.as_str().as_bytes());
+            block.extend_from_slice(b": ");
+            block.extend_from_slice(value.as_bytes());
+            block.extend_from_slice(b"\r\n");
+        }
+        block.extend_from_slice(b"\r\n");
+        let payload_start = block.len();
+        block.extend_from_slice(body);
+
+        let digest = format!("sha256:{}", base32(&Sha256::digest(&block[payload_start..])));
+        self.write_pair(&response.final_url, validators, &block, date, Some(digest))
+    }
+
+    /// Write a request record and the response record it belongs to
+    fn write_pair(
+        &self,
+        url: &Url,
+        validators: Option<&Validators>,
+        response_block: &[u8],
+        date: DateTime<Utc>,
+        payload_digest: Option<String>,
+    ) -> Result<WarcLocation> {
+        let response_id = record_id(url.as_str());
+        let mut response_headers = vec![
+            ("WARC-Record-ID", response_id.clone()),
+            ("WARC-Target-URI", url.to_string()),
+        ];
+        if let Some(digest) = payload_digest {
+            response_headers.push(("WARC-Payload-Digest", digest));
+        }
+        let location = self.write_record(
+            "response",
+            &response_headers,
+            "application/http;msgtype=response",
+            response_block,
+            date,
+        )?;
+
+        let request = self.request_block(url, validators);
+        self.write_record(
+            "request",
+            &[
+                ("WARC-Target-URI", url.to_string()),
+                ("WARC-Concurrent-To", response_id),
+            ],
+            "application/http;msgtype=request",
+            request.as_bytes(),
+            date,
+        )?;
+        Ok(location)
+    }
+
+    /// The request the crawler sent for a URL, without credentials
+    fn request_block(&self, url: &Url, validators: Option<&Validators>) -> String {
+        let mut target = url.path().to_string();
+        if let Some(query) = url.query() {
+            target.push('?');
+            target.push_str(query);
+        }
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => String::new(),
+        };
+        let mut request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: {}\r\nAccept: */*\r\n",
+            target, host, self.user_agent
+        );
+        if let Some(validators) = validators {
+            if let Some(etag) = &validators.etag {
+                request.push_str(&format!("If-None-Match: {}\r\n", etag));
+            }
+            if let Some(last_modified) = &validators.last_modified {
+                request.push_str(&format!("If-Modified-Since: {}\r\n", last_modified));
+            }
+        }
+        request.push_str("\r\n");
+        request
+    }
+
+    /// Append a record as its own gzip member
+    ///
+    /// A `WARC-Record-ID` among `headers` is used as is, otherwise one is generated.
+    fn write_record(
+        &self,
+        warc_type: &str,
+        headers: &[(&str, String)],
+        content_type: &str,
+        block: &[u8],
+        date: DateTime<Utc>,
+    ) -> Result<WarcLocation> {
+        let record_id = headers
+            .iter()
+            .find(|(name, _)| *name == "WARC-Record-ID")
+            .map(|(_, value)| value.clone())
+            .unwrap_or_else(|| record_id(warc_type));
+        let date_value = date.to_rfc3339_opts(SecondsFormat::Secs, true);
+
+        let mut record = format!(
+            "WARC/1.1\r\nWARC-Type: {}\r\nWARC-Record-ID: {}\r\nWARC-Date: {}\r\n",
+            warc_type, record_id, date_value
+        );
+        for (name, value) in headers.iter().filter(|(name, _)| *name != "WARC-Record-ID") {
+            record.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        record.push_str(&format!(
+            "Content-Type: {}\r\nContent-Length: {}\r\n\r\n",
+            content_type,
+            block.len()
+        ));
+
+        let io_error = |e| DoclingError::io("Failed to compress WARC record", &self.path, e);
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(record.as_bytes()).map_err(io_error)?;
+        encoder.write_all(block).map_err(io_error)?;
+        encoder.write_all(b"\r\n\r\n").map_err(io_error)?;
+        let compressed = encoder.finish().map_err(io_error)?;
+
+        let mut file = self
+            .file
+            .lock()
+            .map_err(|_| DoclingError::config("WARC writer lock poisoned"))?;
+        file.file
+            .write_all(&compressed)
+            .io_path_context("Failed to write WARC file", &self.path)?;
+        let offset = file.offset;
+        file.offset += compressed.len() as u64;
+
+        Ok(WarcLocation {
+            file: relative_to(&self.path, &self.entry_dir),
+            offset,
+            length: compressed.len() as u64,
+            record_id,
+            date,
+        })
+    }
+}
+
+/// A response read back from a WARC archive
+#[derive(Debug, Clone)]
+pub struct ArchivedResponse {
+    /// HTTP status of the response
+    pub status: u16,
+    /// Headers of the response
+    pub headers: HeaderMap,
+    /// Body of the response
+    pub body: Vec<u8>,
+}
+
+/// Where the archived response of a URL is stored
+#[derive(Debug, Clone)]
+struct ReplayRecord {
+    /// WARC file holding the record
+    file: PathBuf,
+    /// Offset of the record's gzip member in the file
+    offset: u64,
+    /// Compressed length of the record
+    length: u64,
+}
+
+/// Responses of an entry's WARC archives, served in place of the network
+///
+/// Only archives written by [`WarcWriter`], with one record per gzip member,
+/// are supported. When a URL was archived several times the latest response
+/// wins; `304 Not Modified` answers are skipped, so the last full response
+/// of every page and media file is served. Bodies are read from the archive
+/// when they are requested.
+#[derive(Debug, Clone, Default)]
+pub struct WarcReplay {
+    /// Latest response record of every archived URL
+    records: HashMap<String, ReplayRecord>,
+}
+
+impl WarcReplay {
+    /// Index the responses of all WARC files in `dir`, oldest first
+    ///
+    /// # Errors
+    /// Returns an error if the directory holds no WARC file or one cannot be read
+    pub fn open(dir: &Path) -> Result<Self> {
+        let mut files = Vec::new();
+        if dir.is_dir() {
+            for entry in std::fs::read_dir(dir).io_path_context("Failed to read WARC directory", dir)? {
+                let path = entry.io_path_context("Failed to read WARC directory", dir)?.path();
+                if path.is_file() && path.to_string_lossy().ends_with(".warc.gz") {
+                    files.push(path);
+                }
+            }
+        }
+        if files.is_empty() {
+            return Err(DoclingError::config(format!("No WARC archive found in {}", dir.display())));
+        }
+        // File names start with the entry name and the time the crawl started
+        files.sort();
+
+        let mut replay = Self::default();
+        for path in files {
+            replay.index_file(&path)?;
+        }
+        debug!("Indexed {} archived responses in {}", replay.records.len(), dir.display());
+        Ok(replay)
+    }
+
+    /// Number of URLs with an archived response
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Whether no response was archived
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Archived response of a URL, `None` if it was never archived
+    ///
+    /// # Errors
+    /// Returns an error if the record cannot be read back
+    pub fn response(&self, url: &Url) -> Result<Option<ArchivedResponse>> {
+        let Some(record) = self.records.get(url.as_str()) else {
+            return Ok(None);
+        };
+        let mut file = File::open(&record.file).io_path_context("Failed to open WARC file", &record.file)?;
+        file.seek(SeekFrom::Start(record.offset))
+            .io_path_context("Failed to read WARC file", &record.file)?;
+        let mut member = BufReader::new(file.take(record.length));
+        let mut data = Vec::new();
+        GzDecoder::new(&mut member)
+            .read_to_end(&mut data)
+            .io_path_context("Failed to decompress WARC record", &record.file)?;
+        let response = parse_response_record(&data)
+            .and_then(|(_, block)| parse_http_response(block))
+            .ok_or_else(|| {
+                DoclingError::conversion(format!(
+                    "Invalid WARC record at offset {} of {}",
+                    record.offset,
+                    record.file.display()
+                ))
+            })?;
+        Ok(Some(response))
+    }
+
+    /// Add the response records of a WARC file
+    fn index_file(&mut self, path: &Path) -> Result<()> {
+        let file = File::open(path).io_path_context("Failed to open WARC file", path)?;
+        let mut reader = CountingReader {
+            inner: BufReader::new(file),
+            position: 0,
+        };
+        loop {
+            if reader.fill_buf().io_path_context("Failed to read WARC file", path)?.is_empty() {
+                break;
+            }
+            let offset = reader.position;
+            let mut data = Vec::new();
+            GzDecoder::new(&mut reader)
+                .read_to_end(&mut data)
+                .io_path_context("Failed to decompress WARC record", path)?;
+            let Some((target, block)) = parse_response_record(&data) else {
+                continue;
+            };
+            let status = parse_http_response(block).map(|response| response.status);
+            if status.is_none_or(|status| status == StatusCode::NOT_MODIFIED.as_u16()) {
+                continue;
+            }
+            self.records.insert(
+                target,
+                ReplayRecord {
+                    file: path.to_path_buf(),
+                    offset,
+                    length: reader.position - offset,
+                },
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Buffered reader keeping track of the bytes consumed, to find where gzip members end
+struct CountingReader<R> {
+    /// The underlying reader
+    inner: R,
+    /// Bytes consumed so far
+    position: u64,
+}
+
+impl<R: BufRead> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for CountingReader<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.position += amount as u64;
+        self.inner.consume(amount);
+    }
+}
+
+/// Target URI and block of a `response` record, `None` for other records
+fn parse_response_record(record: &[u8]) -> Option<(String, &[u8])> {
+    let (head, rest) = split_head(record)?;
+    let head = std::str::from_utf8(head).ok()?;
+    let mut lines = head.split("\r\n");
+    if !lines.next()?.starts_with("WARC/") {
+        return None;
+    }
+    let (mut warc_type, mut target, mut length) = (None, None, None);
+    for (name, value) in lines.filter_map(|line| line.split_once(':')) {
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "warc-type" => warc_type = Some(value),
+            "warc-target-uri" => target = Some(value.to_string()),
+            "content-length" => length = value.parse::<usize>().ok(),
+            _ => {}
+        }
+    }
+    if warc_type != Some("response") {
+        return None;
+    }
+    Some((target?, rest.get(..length?)?))
+}
+
+/// Status, headers and body of an HTTP response block
+fn parse_http_response(block: &[u8]) -> Option<ArchivedResponse> {
+    let (head, body) = split_head(block)?;
+    let head = std::str::from_utf8(head).ok()?;
+    let mut lines = head.split("\r\n");
+    let status = lines
+        .next()?
+        .strip_prefix("HTTP/")?
+        .split(' ')
+        .nth(1)?
+        .parse()
+        .ok()?;
+    let mut headers = HeaderMap::new();
+    for (name, value) in lines.filter_map(|line| line.split_once(':')) {
+        if let (Ok(name), Ok(value)) = (HeaderName::from_bytes(name.trim().as_bytes()), HeaderValue::from_str(value.trim())) {
+            headers.append(name, value);
+        }
+    }
+    Some(ArchivedResponse {
+        status,
+        headers,
+        body: body.to_vec(),
+    })
+}
+
+/// Split a message at the blank line ending its headers
+fn split_head(message: &[u8]) -> Option<(&[u8], &[u8])> {
+    let end = message.windows(4).position(|window| window == b"\r\n\r\n")?;
+    Some((&message[..end], &message[end + 4..]))
+}
+
+/// Status line of an HTTP/1.1 response
+fn status_line(status: u16) -> String {
+    let reason = StatusCode::from_u16(status)
+        .ok()
+        .and_then(|code| code.canonical_reason())
+        .unwrap_or("");
+    format!("HTTP/1.1 {} {}\r\n", status, reason)
+}
+
+/// A unique `urn:uuid:` record id, shaped like a version 4 UUID
+fn record_id(seed: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(seed.as_bytes());
+    hasher.update(RECORD_COUNTER.fetch_add(1, Ordering::Relaxed).to_le_bytes());
+    hasher.update(std::process::id().to_le_bytes());
+    hasher.update(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default()
+            .to_le_bytes(),
+    );
+    let mut bytes: [u8; 16] = hasher.finalize()[..16].try_into().expect("16 bytes of a SHA-256 digest");
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = to_hex(&bytes);
+    format!(
+        "<urn:uuid:{}-{}-{}-{}-{}>",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// RFC 4648 base32, the usual encoding of WARC digests
+fn base32(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    for chunk in bytes.chunks(5) {
+        let mut buffer = [0u8; 5];
+        buffer[..chunk.len()].copy_from_slice(chunk);
+        let bits = buffer.iter().fold(0u64, |bits, byte| (bits << 8) | u64::from(*byte));
+        let symbols = (chunk.len() * 8).div_ceil(5);
+        for i in 0..8 {
+            if i < symbols {
+                encoded.push(ALPHABET[((bits >> (35 - i * 5)) & 0x1f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::MultiGzDecoder;
+    use reqwest::header::{HeaderMap, HeaderValue};
+
+    use super::*;
+    use crate::fetch::Redirect;
+
+    #[test]
+    fn base32_matches_rfc_4648() {
+        assert_eq!(base32(b""), "");
+        assert_eq!(base32(b"f"), "MY======");
+        assert_eq!(base32(b"foob"), "MZXW6YQ=");
+        assert_eq!(base32(b"foobar"), "MZXW6YTBOI======");
+    }
+
+    #[test]
+    fn exchanges_are_written_as_separate_members() {
+        let dir = std::env::temp_dir().join(format!("docling-warc-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let writer = WarcWriter::create(&dir, &dir, "docs", "docling-test").unwrap();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("text/html"));
+        headers.insert(header::TRANSFER_ENCODING, HeaderValue::from_static("chunked"));
+        let response = FetchResponse {
+            url: Url::parse("https://example.com/docs").unwrap(),
+            final_url: Url::parse("https://example.com/docs/").unwrap(),
+            status: 200,
+            headers,
+            body: Vec::new(),
+            redirects: vec![Redirect {
+                from: "https://example.com/docs".to_string(),
+                to: "https://example.com/docs/".to_string(),
+                status: 301,
+            }],
+        };
+        let location = writer.write_exchange(&response, b"<h1>Docs</h1>", None).unwrap();
+
+        let compressed = std::fs::read(writer.path()).unwrap();
+        let member = &compressed[location.offset as usize..(location.offset + location.length) as usize];
+        let mut record = String::new();
+        MultiGzDecoder::new(member).read_to_string(&mut record).unwrap();
+        assert!(record.starts_with("WARC/1.1\r\nWARC-Type: response\r\n"));
+        assert!(record.contains(&format!("WARC-Record-ID: {}\r\n", location.record_id)));
+        assert!(record.contains("WARC-Target-URI: https://example.com/docs/\r\n"));
+        assert!(record.contains("\r\n\r\nHTTP/1.1 200 OK\r\ncontent-type: text/html\r\n\r\n<h1>Docs</h1>\r\n\r\n"));
+        assert!(!record.contains("transfer-encoding"));
+
+        let mut all = String::new();
+        MultiGzDecoder::new(&compressed[..]).read_to_string(&mut all).unwrap();
+        let types: Vec<_> = all.lines().filter_map(|line| line.strip_prefix("WARC-Type: ")).collect();
+        assert_eq!(types, ["warcinfo", "response", "request", "response", "request"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn archived_responses_are_replayed() {
+        let dir = std::env::temp_dir().join(format!("docling-replay-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let writer = WarcWriter::create(&dir, &dir, "docs", "docling-test").unwrap();
+        let page = Url::parse("https://example.com/docs/").unwrap();
+        let exchange = |status: u16, body: &[u8]| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::ETAG, HeaderValue::from_static("\"v1\""));
+            let response = FetchResponse {
+                url: page.clone(),
+                final_url: page.clone(),
+                status,
+                headers,
+                body: Vec::new(),
+                redirects: Vec::new(),
+            };
+            writer.write_exchange(&response, body, None).unwrap();
+        };
+        exchange(200, b"<h1>Old</h1>");
+        exchange(200, b"<h1>Docs</h1>");
+        // A later conditional request does not hide the full response
+        exchange(304, b"");
+
+        let replay = WarcReplay::open(&dir).unwrap();
+        assert_eq!(replay.len(), 1);
+        let response = replay.response(&page).unwrap().unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.headers[header::ETAG], "\"v1\"");
+        assert_eq!(response.body, b"<h1>Docs</h1>");
+        assert!(replay.response(&page.join("missing").unwrap()).unwrap().is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(WarcReplay::open(&dir).is_err());
+    }
+}
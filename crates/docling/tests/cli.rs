@@ -0,0 +1,79 @@
+//! Tests running the `docling` binary
+//!
+//! Machine-readable output goes to stdout and must parse as is, with logs
+//! kept on stderr.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A workspace in a fresh temporary directory, with an entry for a small
+/// local site with a home page linking to a guide
+fn workspace(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("docling-cli-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("site")).unwrap();
+    std::fs::create_dir_all(dir.join("workspace")).unwrap();
+    std::fs::write(
+        dir.join("site/index.html"),
+        "<html><head><title>Home</title></head><body><h1>Home</h1>\
+         <p>Welcome to the manual of the tool.</p><a href=\"guide.html\">Guide</a></body></html>",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("site/guide.html"),
+        "<html><head><title>Guide</title></head><body><h1>Guide</h1>\
+         <p>Install the tool with cargo and run it from the workspace.</p></body></html>",
+    )
+    .unwrap();
+
+    let site = dir.join("site");
+    docling(&dir, &["add", site.to_str().unwrap(), "--name", "site", "--depth", "1"]);
+    dir
+}
+
+/// Run `docling` in the workspace of `dir` and return its stdout
+fn docling(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_docling"))
+        .args(args)
+        .current_dir(dir.join("workspace"))
+        .output()
+        .expect("run docling");
+    assert!(
+        output.status.success(),
+        "docling {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).expect("UTF-8 output")
+}
+
+#[test]
+fn dry_run_json_is_the_only_output() {
+    let dir = workspace("plan");
+
+    let stdout = docling(&dir, &["start", "site", "--dry-run", "--json"]);
+    let plan: serde_json::Value = serde_json::from_str(&stdout).expect("stdout is the JSON plan");
+    assert_eq!(plan["entry"], "site");
+    assert_eq!(plan["pages"].as_array().unwrap().len(), 2);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn link_exports_are_the_only_output() {
+    let dir = workspace("links");
+    docling(&dir, &["start", "site"]);
+
+    let csv = docling(&dir, &["links", "site", "--format", "csv"]);
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("source,target,text,scope,status"));
+    let guide_link = lines.find(|line| line.contains("guide.html")).expect("link to the guide");
+    assert!(guide_link.ends_with(",Guide,internal,200"), "{}", guide_link);
+
+    let json = docling(&dir, &["links", "site", "--format", "json"]);
+    let graph: serde_json::Value = serde_json::from_str(&json).expect("stdout is the JSON graph");
+    assert_eq!(graph["pages"].as_array().unwrap().len(), 2);
+
+    let dot = docling(&dir, &["links", "site", "--format", "dot"]);
+    assert!(dot.starts_with("digraph"), "{}", dot);
+    std::fs::remove_dir_all(&dir).unwrap();
+}